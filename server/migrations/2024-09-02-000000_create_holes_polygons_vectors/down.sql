@@ -0,0 +1,3 @@
+DROP TABLE IF EXISTS vectors;
+DROP TABLE IF EXISTS polygons;
+DROP TABLE IF EXISTS holes;
@@ -0,0 +1,31 @@
+CREATE TABLE IF NOT EXISTS holes (
+    id SERIAL PRIMARY KEY,
+    hole_id INTEGER NOT NULL UNIQUE,
+    number INTEGER,
+    course_id INTEGER,
+    rotation DOUBLE PRECISION,
+    range_x_min DOUBLE PRECISION,
+    range_x_max DOUBLE PRECISION,
+    range_y_min DOUBLE PRECISION,
+    range_y_max DOUBLE PRECISION,
+    dimensions_width INTEGER,
+    dimensions_height INTEGER,
+    flag_lat DOUBLE PRECISION,
+    flag_long DOUBLE PRECISION
+);
+
+CREATE TABLE IF NOT EXISTS polygons (
+    id SERIAL PRIMARY KEY,
+    hole_id INTEGER,
+    surface_type VARCHAR(20),
+    lat DOUBLE PRECISION,
+    long DOUBLE PRECISION
+);
+
+CREATE TABLE IF NOT EXISTS vectors (
+    id SERIAL PRIMARY KEY,
+    hole_id INTEGER,
+    vector_type VARCHAR(10),
+    lat DOUBLE PRECISION,
+    long DOUBLE PRECISION
+);
@@ -0,0 +1,2 @@
+ALTER TABLE polygons
+DROP COLUMN shape_index;
@@ -0,0 +1,4 @@
+-- Each polygon row is a single vertex. shape_index groups the vertices of one
+-- polygon so that two bunkers on the same hole stay separate shapes.
+ALTER TABLE polygons
+ADD COLUMN shape_index INTEGER;
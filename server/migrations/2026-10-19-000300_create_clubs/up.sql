@@ -0,0 +1,16 @@
+CREATE TABLE clubs (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    club_type VARCHAR(20) NOT NULL,
+    name VARCHAR(40) NOT NULL,
+    loft DOUBLE PRECISION,
+    brand VARCHAR,
+    model VARCHAR,
+    carry_yards DOUBLE PRECISION,
+    total_yards DOUBLE PRECISION,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    UNIQUE (user_id, name)
+);
+
+SELECT diesel_manage_updated_at('clubs');
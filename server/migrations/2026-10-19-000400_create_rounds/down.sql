@@ -0,0 +1,6 @@
+DROP TABLE IF EXISTS hole_scores;
+DROP TABLE IF EXISTS round_players;
+DROP TABLE IF EXISTS rounds;
+
+ALTER TABLE holes
+DROP COLUMN par;
@@ -0,0 +1,44 @@
+ALTER TABLE holes
+ADD COLUMN par INTEGER;
+
+CREATE TABLE rounds (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    course_id INTEGER NOT NULL,
+    tee_set VARCHAR(20),
+    played_on DATE NOT NULL DEFAULT CURRENT_DATE,
+    status VARCHAR(20) NOT NULL DEFAULT 'in_progress',
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    finished_at TIMESTAMP
+);
+
+CREATE INDEX rounds_user_id_idx ON rounds (user_id);
+SELECT diesel_manage_updated_at('rounds');
+
+CREATE TABLE round_players (
+    id SERIAL PRIMARY KEY,
+    round_id INTEGER NOT NULL REFERENCES rounds(id) ON DELETE CASCADE,
+    user_id INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    name VARCHAR NOT NULL,
+    UNIQUE (round_id, name)
+);
+
+CREATE INDEX round_players_user_id_idx ON round_players (user_id);
+
+CREATE TABLE hole_scores (
+    id SERIAL PRIMARY KEY,
+    round_id INTEGER NOT NULL REFERENCES rounds(id) ON DELETE CASCADE,
+    player_id INTEGER NOT NULL REFERENCES round_players(id) ON DELETE CASCADE,
+    hole_id INTEGER NOT NULL,
+    strokes INTEGER NOT NULL,
+    putts INTEGER,
+    penalties INTEGER NOT NULL DEFAULT 0,
+    fairway_hit BOOLEAN,
+    green_in_regulation BOOLEAN,
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    UNIQUE (player_id, hole_id)
+);
+
+CREATE INDEX hole_scores_round_id_idx ON hole_scores (round_id);
+SELECT diesel_manage_updated_at('hole_scores');
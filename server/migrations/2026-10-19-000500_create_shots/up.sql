@@ -0,0 +1,24 @@
+CREATE TABLE shots (
+    id SERIAL PRIMARY KEY,
+    round_id INTEGER NOT NULL REFERENCES rounds(id) ON DELETE CASCADE,
+    player_id INTEGER NOT NULL REFERENCES round_players(id) ON DELETE CASCADE,
+    hole_id INTEGER NOT NULL,
+    shot_number INTEGER NOT NULL,
+    start_lat DOUBLE PRECISION NOT NULL,
+    start_long DOUBLE PRECISION NOT NULL,
+    end_lat DOUBLE PRECISION,
+    end_long DOUBLE PRECISION,
+    club_id INTEGER REFERENCES clubs(id) ON DELETE SET NULL,
+    club VARCHAR(40),
+    lie VARCHAR(20),
+    penalty_strokes INTEGER NOT NULL DEFAULT 0,
+    distance_yards DOUBLE PRECISION,
+    remaining_yards DOUBLE PRECISION,
+    start_lie VARCHAR(20),
+    end_lie VARCHAR(20),
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    UNIQUE (player_id, hole_id, shot_number)
+);
+
+CREATE INDEX shots_round_id_idx ON shots (round_id);
+CREATE INDEX shots_club_id_idx ON shots (club_id);
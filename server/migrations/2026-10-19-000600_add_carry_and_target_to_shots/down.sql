@@ -0,0 +1,4 @@
+ALTER TABLE shots
+    DROP COLUMN target_long,
+    DROP COLUMN target_lat,
+    DROP COLUMN carry_yards;
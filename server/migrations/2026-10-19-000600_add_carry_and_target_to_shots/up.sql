@@ -0,0 +1,4 @@
+ALTER TABLE shots
+    ADD COLUMN carry_yards DOUBLE PRECISION,
+    ADD COLUMN target_lat DOUBLE PRECISION,
+    ADD COLUMN target_long DOUBLE PRECISION;
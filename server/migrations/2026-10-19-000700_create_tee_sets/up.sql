@@ -0,0 +1,13 @@
+CREATE TABLE tee_sets (
+    id SERIAL PRIMARY KEY,
+    course_id INTEGER NOT NULL,
+    name VARCHAR(20) NOT NULL,
+    course_rating DOUBLE PRECISION NOT NULL,
+    slope_rating INTEGER NOT NULL,
+    par INTEGER NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    UNIQUE (course_id, name)
+);
+
+SELECT diesel_manage_updated_at('tee_sets');
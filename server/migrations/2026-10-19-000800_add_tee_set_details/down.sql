@@ -0,0 +1,10 @@
+ALTER TABLE rounds
+DROP COLUMN tee_set_id;
+
+DROP TABLE IF EXISTS tee_set_holes;
+
+ALTER TABLE tee_sets
+    DROP CONSTRAINT tee_sets_course_id_name_gender_key,
+    ADD CONSTRAINT tee_sets_course_id_name_key UNIQUE (course_id, name),
+    DROP COLUMN gender,
+    DROP COLUMN colour;
@@ -0,0 +1,17 @@
+ALTER TABLE tee_sets
+    ADD COLUMN colour VARCHAR(20),
+    ADD COLUMN gender VARCHAR(10) NOT NULL DEFAULT 'mixed',
+    DROP CONSTRAINT tee_sets_course_id_name_key,
+    ADD CONSTRAINT tee_sets_course_id_name_gender_key UNIQUE (course_id, name, gender);
+
+CREATE TABLE tee_set_holes (
+    tee_set_id INTEGER NOT NULL REFERENCES tee_sets(id) ON DELETE CASCADE,
+    hole_id INTEGER NOT NULL,
+    tee_lat DOUBLE PRECISION NOT NULL,
+    tee_long DOUBLE PRECISION NOT NULL,
+    yards INTEGER NOT NULL,
+    PRIMARY KEY (tee_set_id, hole_id)
+);
+
+ALTER TABLE rounds
+ADD COLUMN tee_set_id INTEGER REFERENCES tee_sets(id) ON DELETE SET NULL;
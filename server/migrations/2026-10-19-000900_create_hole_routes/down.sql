@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS hole_route_points;
+DROP TABLE IF EXISTS hole_routes;
@@ -0,0 +1,20 @@
+CREATE TABLE hole_routes (
+    hole_id INTEGER PRIMARY KEY,
+    yards DOUBLE PRECISION NOT NULL,
+    dogleg VARCHAR(10) NOT NULL,
+    dogleg_degrees DOUBLE PRECISION NOT NULL,
+    landing_width_200 DOUBLE PRECISION,
+    landing_width_250 DOUBLE PRECISION,
+    landing_width_300 DOUBLE PRECISION,
+    computed_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE hole_route_points (
+    id SERIAL PRIMARY KEY,
+    hole_id INTEGER NOT NULL REFERENCES hole_routes(hole_id) ON DELETE CASCADE,
+    point_index INTEGER NOT NULL,
+    lat DOUBLE PRECISION NOT NULL,
+    long DOUBLE PRECISION NOT NULL,
+    turn BOOLEAN NOT NULL DEFAULT FALSE,
+    UNIQUE (hole_id, point_index)
+);
@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS pin_positions;
+DROP TABLE IF EXISTS pin_sheets;
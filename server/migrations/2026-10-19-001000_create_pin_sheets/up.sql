@@ -0,0 +1,23 @@
+CREATE TABLE pin_sheets (
+    id SERIAL PRIMARY KEY,
+    course_id INTEGER NOT NULL,
+    played_on DATE NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    UNIQUE (course_id, played_on)
+);
+
+SELECT diesel_manage_updated_at('pin_sheets');
+
+CREATE TABLE pin_positions (
+    pin_sheet_id INTEGER NOT NULL REFERENCES pin_sheets(id) ON DELETE CASCADE,
+    hole_id INTEGER NOT NULL,
+    lat DOUBLE PRECISION NOT NULL,
+    long DOUBLE PRECISION NOT NULL,
+    front_paces INTEGER,
+    side_paces INTEGER,
+    side VARCHAR(5),
+    PRIMARY KEY (pin_sheet_id, hole_id)
+);
+
+CREATE INDEX pin_positions_hole_id_idx ON pin_positions (hole_id);
@@ -0,0 +1,5 @@
+DROP TABLE IF EXISTS hole_elevations;
+
+ALTER TABLE vectors DROP COLUMN elevation_ft;
+ALTER TABLE polygons DROP COLUMN elevation_ft;
+ALTER TABLE holes DROP COLUMN flag_elevation_ft;
@@ -0,0 +1,15 @@
+ALTER TABLE holes ADD COLUMN flag_elevation_ft DOUBLE PRECISION;
+ALTER TABLE polygons ADD COLUMN elevation_ft DOUBLE PRECISION;
+ALTER TABLE vectors ADD COLUMN elevation_ft DOUBLE PRECISION;
+
+CREATE TABLE hole_elevations (
+    hole_id INTEGER PRIMARY KEY,
+    source VARCHAR(255) NOT NULL,
+    origin_lat DOUBLE PRECISION NOT NULL,
+    origin_long DOUBLE PRECISION NOT NULL,
+    spacing_yards DOUBLE PRECISION NOT NULL,
+    grid_columns INTEGER NOT NULL,
+    grid_rows INTEGER NOT NULL,
+    elevations_ft DOUBLE PRECISION[] NOT NULL,
+    sampled_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,9 @@
+CREATE TABLE round_events (
+    id SERIAL PRIMARY KEY,
+    round_id INTEGER NOT NULL REFERENCES rounds(id) ON DELETE CASCADE,
+    sequence INTEGER NOT NULL,
+    kind VARCHAR(20) NOT NULL,
+    payload JSONB NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    UNIQUE (round_id, sequence)
+);
@@ -0,0 +1,14 @@
+CREATE TABLE round_games (
+    id SERIAL PRIMARY KEY,
+    round_id INTEGER NOT NULL REFERENCES rounds(id) ON DELETE CASCADE,
+    format VARCHAR(20) NOT NULL,
+    stake INTEGER NOT NULL DEFAULT 1,
+    allowance_percent DOUBLE PRECISION NOT NULL,
+    carry_overs BOOLEAN NOT NULL DEFAULT TRUE,
+    press_after INTEGER,
+    side_a INTEGER[] NOT NULL DEFAULT '{}',
+    side_b INTEGER[] NOT NULL DEFAULT '{}',
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX round_games_round_id_idx ON round_games (round_id);
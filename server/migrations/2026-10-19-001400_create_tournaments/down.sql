@@ -0,0 +1,6 @@
+DROP TABLE IF EXISTS tournament_group_entrants;
+DROP TABLE IF EXISTS tournament_groups;
+DROP TABLE IF EXISTS tournament_entrants;
+DROP TABLE IF EXISTS tournament_flights;
+DROP TABLE IF EXISTS tournament_rounds;
+DROP TABLE IF EXISTS tournaments;
@@ -0,0 +1,52 @@
+CREATE TABLE tournaments (
+    id SERIAL PRIMARY KEY,
+    name VARCHAR(100) NOT NULL,
+    organiser_id INTEGER NOT NULL REFERENCES users(id),
+    allowance_percent DOUBLE PRECISION NOT NULL DEFAULT 95,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+SELECT diesel_manage_updated_at('tournaments');
+
+CREATE TABLE tournament_rounds (
+    id SERIAL PRIMARY KEY,
+    tournament_id INTEGER NOT NULL REFERENCES tournaments(id) ON DELETE CASCADE,
+    round_number INTEGER NOT NULL,
+    course_id INTEGER NOT NULL,
+    tee_set_id INTEGER REFERENCES tee_sets(id) ON DELETE SET NULL,
+    played_on DATE NOT NULL,
+    UNIQUE (tournament_id, round_number)
+);
+
+CREATE TABLE tournament_flights (
+    id SERIAL PRIMARY KEY,
+    tournament_id INTEGER NOT NULL REFERENCES tournaments(id) ON DELETE CASCADE,
+    name VARCHAR(40) NOT NULL,
+    max_index DOUBLE PRECISION
+);
+
+CREATE TABLE tournament_entrants (
+    id SERIAL PRIMARY KEY,
+    tournament_id INTEGER NOT NULL REFERENCES tournaments(id) ON DELETE CASCADE,
+    user_id INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    name VARCHAR(100) NOT NULL,
+    handicap_index DOUBLE PRECISION,
+    flight_id INTEGER REFERENCES tournament_flights(id) ON DELETE SET NULL,
+    UNIQUE (tournament_id, user_id)
+);
+
+CREATE TABLE tournament_groups (
+    id SERIAL PRIMARY KEY,
+    tournament_round_id INTEGER NOT NULL REFERENCES tournament_rounds(id) ON DELETE CASCADE,
+    tee_time TIMESTAMP NOT NULL,
+    starting_hole INTEGER NOT NULL DEFAULT 1,
+    round_id INTEGER REFERENCES rounds(id) ON DELETE SET NULL
+);
+
+CREATE TABLE tournament_group_entrants (
+    group_id INTEGER NOT NULL REFERENCES tournament_groups(id) ON DELETE CASCADE,
+    entrant_id INTEGER NOT NULL REFERENCES tournament_entrants(id) ON DELETE CASCADE,
+    round_player_id INTEGER REFERENCES round_players(id) ON DELETE SET NULL,
+    PRIMARY KEY (group_id, entrant_id)
+);
@@ -0,0 +1,6 @@
+DROP TABLE IF EXISTS tee_set_hole_overrides;
+
+ALTER TABLE holes
+    DROP COLUMN stroke_index_women,
+    DROP COLUMN stroke_index,
+    DROP COLUMN name;
@@ -0,0 +1,13 @@
+ALTER TABLE holes
+    ADD COLUMN name VARCHAR(40),
+    ADD COLUMN stroke_index INTEGER,
+    ADD COLUMN stroke_index_women INTEGER;
+
+CREATE TABLE tee_set_hole_overrides (
+    tee_set_id INTEGER NOT NULL REFERENCES tee_sets(id) ON DELETE CASCADE,
+    hole_id INTEGER NOT NULL,
+    par INTEGER,
+    stroke_index INTEGER,
+    name VARCHAR(40),
+    PRIMARY KEY (tee_set_id, hole_id)
+);
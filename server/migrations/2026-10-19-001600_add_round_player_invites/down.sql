@@ -0,0 +1 @@
+ALTER TABLE round_players DROP COLUMN invite_hash;
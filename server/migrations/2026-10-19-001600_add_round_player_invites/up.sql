@@ -0,0 +1,3 @@
+-- Hash of the code a round's owner hands to whoever is playing in a slot,
+-- so they can link their account to it.
+ALTER TABLE round_players ADD COLUMN invite_hash VARCHAR(64) UNIQUE;
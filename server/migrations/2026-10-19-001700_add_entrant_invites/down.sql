@@ -0,0 +1 @@
+ALTER TABLE tournament_entrants DROP COLUMN invite_hash;
@@ -0,0 +1,3 @@
+-- Hash of the code an organiser hands to an entrant, so they can link their
+-- own account to the entry.
+ALTER TABLE tournament_entrants ADD COLUMN invite_hash VARCHAR(64) UNIQUE;
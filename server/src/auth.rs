@@ -0,0 +1,301 @@
+use std::future::{ready, Ready};
+
+use actix_web::dev::Payload;
+use actix_web::{http::header, web, FromRequest, HttpRequest};
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{Duration, Utc};
+use diesel::prelude::*;
+use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::config::{AuthConfig, Config};
+use crate::error::ApiError;
+use crate::models::{RefreshToken, User};
+use crate::schema::{refresh_tokens, users};
+
+pub const ROLE_PLAYER: &str = "player";
+pub const ROLE_ADMIN: &str = "admin";
+
+const MIN_PASSWORD_LENGTH: usize = 8;
+
+#[derive(Insertable)]
+#[diesel(table_name = users)]
+struct NewUser {
+    email: String,
+    password_hash: String,
+    display_name: Option<String>,
+    role: String,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = refresh_tokens)]
+struct NewRefreshToken {
+    user_id: i32,
+    token_hash: String,
+    expires_at: chrono::NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Claims {
+    sub: i32,
+    role: String,
+    iat: i64,
+    exp: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TokenPair {
+    pub access_token: String,
+    pub refresh_token: String,
+    pub token_type: &'static str,
+    pub expires_in: i64,
+}
+
+pub fn hash_password(password: &str) -> Result<String, ApiError> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| ApiError::Internal(format!("could not hash password: {}", e)))
+}
+
+fn verify_password(password: &str, password_hash: &str) -> bool {
+    PasswordHash::new(password_hash)
+        .map(|hash| Argon2::default().verify_password(password.as_bytes(), &hash).is_ok())
+        .unwrap_or(false)
+}
+
+/// Hex SHA-256 of a token, which is all that's stored of it.
+pub fn hash_token(token: &str) -> String {
+    Sha256::digest(token.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// 32 random bytes, URL-safe base64 encoded.
+pub fn random_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+fn issue_tokens(conn: &mut PgConnection, config: &AuthConfig, user: &User) -> Result<TokenPair, ApiError> {
+    let now = Utc::now();
+    let claims = Claims {
+        sub: user.id,
+        role: user.role.clone(),
+        iat: now.timestamp(),
+        exp: (now + Duration::seconds(config.access_token_ttl_secs)).timestamp(),
+    };
+    let access_token = encode(
+        &Header::default(),
+        &claims,
+        &EncodingKey::from_secret(config.jwt_secret.as_bytes()),
+    )
+    .map_err(|e| ApiError::Internal(format!("could not sign token: {}", e)))?;
+
+    let refresh_token = random_token();
+
+    diesel::insert_into(refresh_tokens::table)
+        .values(&NewRefreshToken {
+            user_id: user.id,
+            token_hash: hash_token(&refresh_token),
+            expires_at: (now + Duration::seconds(config.refresh_token_ttl_secs)).naive_utc(),
+        })
+        .execute(conn)?;
+
+    Ok(TokenPair {
+        access_token,
+        refresh_token,
+        token_type: "Bearer",
+        expires_in: config.access_token_ttl_secs,
+    })
+}
+
+fn normalize_email(email: &str) -> Result<String, ApiError> {
+    let email = email.trim().to_lowercase();
+    match email.split_once('@') {
+        Some((local, domain)) if !local.is_empty() && domain.contains('.') => Ok(email),
+        _ => Err(ApiError::BadRequest("a valid email address is required".to_string())),
+    }
+}
+
+pub fn register(
+    conn: &mut PgConnection,
+    config: &AuthConfig,
+    email: &str,
+    password: &str,
+    display_name: Option<String>,
+) -> Result<(User, TokenPair), ApiError> {
+    let email = normalize_email(email)?;
+    if password.len() < MIN_PASSWORD_LENGTH {
+        return Err(ApiError::BadRequest(format!(
+            "password must be at least {} characters",
+            MIN_PASSWORD_LENGTH
+        )));
+    }
+
+    let new_user = NewUser {
+        email,
+        password_hash: hash_password(password)?,
+        display_name,
+        role: ROLE_PLAYER.to_string(),
+    };
+
+    conn.transaction(|conn| {
+        let user = diesel::insert_into(users::table)
+            .values(&new_user)
+            .returning(User::as_returning())
+            .get_result(conn)
+            .map_err(|e| match ApiError::from(e) {
+                ApiError::Conflict(_) => ApiError::Conflict("an account with that email already exists".to_string()),
+                e => e,
+            })?;
+        let tokens = issue_tokens(conn, config, &user)?;
+        Ok((user, tokens))
+    })
+}
+
+pub fn login(conn: &mut PgConnection, config: &AuthConfig, email: &str, password: &str) -> Result<TokenPair, ApiError> {
+    let invalid = || ApiError::Unauthorized("invalid email or password".to_string());
+
+    let user = users::table
+        .filter(users::email.eq(email.trim().to_lowercase()))
+        .select(User::as_select())
+        .first(conn)
+        .optional()?
+        .ok_or_else(invalid)?;
+
+    if !verify_password(password, &user.password_hash) {
+        return Err(invalid());
+    }
+    issue_tokens(conn, config, &user)
+}
+
+/// Exchanges a refresh token for a new token pair. The old refresh token is
+/// revoked so each one can only be used once.
+pub fn refresh(conn: &mut PgConnection, config: &AuthConfig, refresh_token: &str) -> Result<TokenPair, ApiError> {
+    conn.transaction(|conn| {
+        let now = Utc::now().naive_utc();
+        let stored = refresh_tokens::table
+            .filter(refresh_tokens::token_hash.eq(hash_token(refresh_token)))
+            .select(RefreshToken::as_select())
+            .for_update()
+            .first(conn)
+            .optional()?
+            .filter(|token| token.revoked_at.is_none() && token.expires_at > now)
+            .ok_or_else(|| ApiError::Unauthorized("refresh token is invalid or expired".to_string()))?;
+
+        diesel::update(refresh_tokens::table.find(stored.id))
+            .set(refresh_tokens::revoked_at.eq(now))
+            .execute(conn)?;
+
+        let user = users::table.find(stored.user_id).select(User::as_select()).first(conn)?;
+        issue_tokens(conn, config, &user)
+    })
+}
+
+pub fn logout(conn: &mut PgConnection, refresh_token: &str) -> Result<(), ApiError> {
+    diesel::update(
+        refresh_tokens::table
+            .filter(refresh_tokens::token_hash.eq(hash_token(refresh_token)))
+            .filter(refresh_tokens::revoked_at.is_null()),
+    )
+    .set(refresh_tokens::revoked_at.eq(Utc::now().naive_utc()))
+    .execute(conn)?;
+    Ok(())
+}
+
+pub fn fetch_user(conn: &mut PgConnection, user_id: i32) -> Result<User, ApiError> {
+    Ok(users::table.find(user_id).select(User::as_select()).first(conn)?)
+}
+
+pub fn set_role(conn: &mut PgConnection, email: &str, role: &str) -> Result<User, ApiError> {
+    if role != ROLE_PLAYER && role != ROLE_ADMIN {
+        return Err(ApiError::BadRequest(format!("unknown role `{}`", role)));
+    }
+    Ok(diesel::update(users::table.filter(users::email.eq(email.trim().to_lowercase())))
+        .set(users::role.eq(role))
+        .returning(User::as_returning())
+        .get_result(conn)?)
+}
+
+/// The caller of a request, taken from a valid `Authorization: Bearer` access
+/// token. Add it as a handler argument to require a signed-in user.
+#[derive(Debug, Clone)]
+pub struct AuthenticatedUser {
+    pub id: i32,
+    pub role: String,
+}
+
+impl AuthenticatedUser {
+    pub fn is_admin(&self) -> bool {
+        self.role == ROLE_ADMIN
+    }
+}
+
+impl FromRequest for AuthenticatedUser {
+    type Error = ApiError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(authenticate(req))
+    }
+}
+
+fn authenticate(req: &HttpRequest) -> Result<AuthenticatedUser, ApiError> {
+    let config = req
+        .app_data::<web::Data<Config>>()
+        .ok_or_else(|| ApiError::Internal("configuration is not registered".to_string()))?;
+
+    let token = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .ok_or_else(|| ApiError::Unauthorized("missing bearer token".to_string()))?;
+
+    authenticate_token(&config.auth, token)
+}
+
+/// Validates an access token and returns the user it was issued to.
+pub fn authenticate_token(config: &AuthConfig, token: &str) -> Result<AuthenticatedUser, ApiError> {
+    let claims = decode::<Claims>(
+        token,
+        &DecodingKey::from_secret(config.jwt_secret.as_bytes()),
+        &Validation::default(),
+    )
+    .map_err(|_| ApiError::Unauthorized("invalid or expired access token".to_string()))?
+    .claims;
+
+    Ok(AuthenticatedUser {
+        id: claims.sub,
+        role: claims.role,
+    })
+}
+
+/// Like [`AuthenticatedUser`] but rejects anyone without the admin role.
+#[derive(Debug, Clone)]
+pub struct AdminUser(pub AuthenticatedUser);
+
+impl FromRequest for AdminUser {
+    type Error = ApiError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(authenticate(req).and_then(|user| {
+            if user.is_admin() {
+                Ok(AdminUser(user))
+            } else {
+                Err(ApiError::Forbidden)
+            }
+        }))
+    }
+}
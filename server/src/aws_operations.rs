@@ -1,10 +1,100 @@
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
 use std::process::Command;
 use serde_json::Value;
 use diesel::prelude::*;
 use diesel::pg::PgConnection;
-use dotenv::dotenv;
-use std::env;
-use crate::schema::{holes, vectors, polygons};
+use crate::config::GolfbertConfig;
+use crate::{course_operations, tee_set_operations};
+use crate::schema::{courses, holes, vectors, polygons};
+
+/// A source of course data. Responses use the Golfbert JSON shape, which is
+/// what the importer understands.
+pub trait CourseProvider {
+    /// `GET /v1/courses/{course_id}`
+    fn fetch_course(&self, course_id: &str) -> Result<Value, Box<dyn Error>>;
+    /// `GET /v1/courses/{course_id}/holes`
+    fn fetch_holes(&self, course_id: &str) -> Result<Value, Box<dyn Error>>;
+    /// `GET /v1/holes/{hole_id}/polygons`
+    fn fetch_polygons(&self, hole_id: i32) -> Result<Value, Box<dyn Error>>;
+}
+
+/// Fetches live data from the Golfbert API through `awscurl`.
+pub struct GolfbertProvider<'a> {
+    config: &'a GolfbertConfig,
+}
+
+impl<'a> GolfbertProvider<'a> {
+    pub fn new(config: &'a GolfbertConfig) -> Self {
+        GolfbertProvider { config }
+    }
+
+    fn get(&self, path: &str) -> Result<Value, Box<dyn Error>> {
+        let output = run_command("awscurl", &[
+            "--request", "GET",
+            &format!("https://api.golfbert.com/v1/{}", path),
+            "--header", &format!("x-api-key: {}", self.config.api_key),
+        ], &self.config.aws_access_key, &self.config.aws_secret_key)?;
+
+        Ok(serde_json::from_str(&output)?)
+    }
+}
+
+impl CourseProvider for GolfbertProvider<'_> {
+    fn fetch_course(&self, course_id: &str) -> Result<Value, Box<dyn Error>> {
+        self.get(&format!("courses/{}", course_id))
+    }
+
+    fn fetch_holes(&self, course_id: &str) -> Result<Value, Box<dyn Error>> {
+        self.get(&format!("courses/{}/holes", course_id))
+    }
+
+    fn fetch_polygons(&self, hole_id: i32) -> Result<Value, Box<dyn Error>> {
+        self.get(&format!("holes/{}/polygons", hole_id))
+    }
+}
+
+/// Reads saved Golfbert responses from a directory laid out like the API:
+/// `courses/{course_id}.json`, `courses/{course_id}/holes.json` and
+/// `holes/{hole_id}/polygons.json`.
+pub struct FileProvider {
+    root: PathBuf,
+}
+
+impl FileProvider {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        FileProvider { root: root.into() }
+    }
+
+    fn read(&self, path: &str) -> Result<Value, Box<dyn Error>> {
+        let path = self.root.join(path);
+        let contents = fs::read_to_string(&path)
+            .map_err(|e| format!("could not read {}: {}", path.display(), e))?;
+        Ok(serde_json::from_str(&contents)?)
+    }
+}
+
+impl CourseProvider for FileProvider {
+    fn fetch_course(&self, course_id: &str) -> Result<Value, Box<dyn Error>> {
+        self.read(&format!("courses/{}.json", course_id))
+    }
+
+    fn fetch_holes(&self, course_id: &str) -> Result<Value, Box<dyn Error>> {
+        self.read(&format!("courses/{}/holes.json", course_id))
+    }
+
+    fn fetch_polygons(&self, hole_id: i32) -> Result<Value, Box<dyn Error>> {
+        self.read(&format!("holes/{}/polygons.json", hole_id))
+    }
+}
+
+#[derive(Insertable, AsChangeset)]
+#[diesel(table_name = courses)]
+struct NewCourse {
+    course_id: i32,
+    course_name: String,
+}
 
 #[derive(Insertable, AsChangeset)]
 #[diesel(table_name = holes)]
@@ -21,6 +111,12 @@ struct NewHole {
     dimensions_height: Option<i32>,
     flag_lat: Option<f64>,
     flag_long: Option<f64>,
+    // Left unchanged on re-import when the provider has no value, so edits
+    // made through the admin endpoints survive.
+    par: Option<i32>,
+    stroke_index: Option<i32>,
+    stroke_index_women: Option<i32>,
+    name: Option<String>,
 }
 
 #[derive(Insertable)]
@@ -39,6 +135,30 @@ struct NewPolygon {
     surface_type: String,
     lat: f64,
     long: f64,
+    shape_index: i32,
+}
+
+/// Par and stroke indexes for a hole, from the hole itself or from its tee
+/// boxes when the provider includes them. The women's index is only kept
+/// where it differs.
+fn hole_scoring(hole: &Value) -> (Option<i32>, Option<i32>, Option<i32>) {
+    let teeboxes = hole["teeboxes"].as_array().map(Vec::as_slice).unwrap_or_default();
+    let is_women = |teebox: &&Value| {
+        teebox["teeboxtype"]
+            .as_str()
+            .is_some_and(|kind| kind.eq_ignore_ascii_case("women") || kind.eq_ignore_ascii_case("ladies"))
+    };
+    let int = |value: &Value| value.as_i64().map(|n| n as i32);
+
+    let par = int(&hole["par"]).or_else(|| teeboxes.iter().find_map(|teebox| int(&teebox["par"])));
+    let stroke_index = int(&hole["handicap"])
+        .or_else(|| teeboxes.iter().filter(|teebox| !is_women(teebox)).find_map(|teebox| int(&teebox["handicap"])));
+    let women = teeboxes
+        .iter()
+        .filter(is_women)
+        .find_map(|teebox| int(&teebox["handicap"]))
+        .filter(|women| Some(*women) != stroke_index);
+    (par, stroke_index, women)
 }
 
 fn run_command(command: &str, args: &[&str], access_key: &str, secret_key: &str) -> Result<String, std::io::Error> {
@@ -57,45 +177,55 @@ fn run_command(command: &str, args: &[&str], access_key: &str, secret_key: &str)
     if output.status.success() {
         Ok(String::from_utf8_lossy(&output.stdout).into_owned())
     } else {
-        Err(std::io::Error::new(
-            std::io::ErrorKind::Other,
-            format!("Command failed with status {}: {}",
-                    output.status,
-                    String::from_utf8_lossy(&output.stderr)),
-        ))
+        Err(std::io::Error::other(format!(
+            "Command failed with status {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr)
+        )))
     }
 }
 
-fn establish_connection() -> PgConnection {
-    dotenv().ok();
-
-    let database_url = env::var("DATABASE_URL")
-        .expect("DATABASE_URL must be set");
-    PgConnection::establish(&database_url)
-        .expect(&format!("Error connecting to {}", database_url))
+pub fn get_holes_from_golfbert(
+    course_id: String,
+    golfbert: &GolfbertConfig,
+    connection: &mut PgConnection,
+) -> Result<(), Box<dyn std::error::Error>> {
+    import_course(&GolfbertProvider::new(golfbert), &course_id, None, connection)
 }
 
-pub async fn  get_holes_from_golfbert(course_id: String) -> Result<(), Box<dyn std::error::Error>> {
+/// Imports a course with all of its holes, vectors and polygons, replacing
+/// whatever was stored for those holes before.
+pub fn import_course(
+    provider: &dyn CourseProvider,
+    course_id: &str,
+    course_name: Option<&str>,
+    connection: &mut PgConnection,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let numeric_course_id: i32 = course_id
+        .parse()
+        .map_err(|_| format!("course id `{}` is not a number", course_id))?;
 
-    match dotenv() {
-        Ok(_) => println!(".env file loaded successfully"),
-        Err(e) => eprintln!("Error loading .env file: {}", e),
-    }
+    let course_name = match course_name {
+        Some(name) => name.to_string(),
+        None => provider.fetch_course(course_id)?["name"]
+            .as_str()
+            .map(String::from)
+            .unwrap_or_else(|| format!("Course {}", course_id)),
+    };
 
-    let access_key = env::var("AWS_ACCESS_KEY").expect("AWS_ACCESS_KEY must be set");
-    let secret_key = env::var("AWS_SECRET_KEY").expect("AWS_SECRET_KEY must be set");
-    let api_key = env::var("GOLFBERT_API_KEY").expect("GOLFBERT_API_KEY must be set");
+    let new_course = NewCourse {
+        course_id: numeric_course_id,
+        course_name,
+    };
+    diesel::insert_into(courses::table)
+        .values(&new_course)
+        .on_conflict(courses::course_id)
+        .do_update()
+        .set(&new_course)
+        .execute(connection)?;
 
     println!("Fetching courses data...");
-    let courses_output = run_command("awscurl", &[
-        "--request", "GET",
-        &format!("https://api.golfbert.com/v1/courses/{}/holes", course_id),
-        "--header", &format!("x-api-key: {}", api_key),
-    ], &access_key, &secret_key)?;
-
-    let courses_data: Value = serde_json::from_str(&courses_output)?;
-
-    let mut connection = establish_connection();
+    let courses_data = provider.fetch_holes(course_id)?;
 
     if let Some(resources) = courses_data["resources"].as_array() {
         for hole in resources {
@@ -104,6 +234,7 @@ pub async fn  get_holes_from_golfbert(course_id: String) -> Result<(), Box<dyn s
                 hole["number"].as_i64(),
                 hole["courseid"].as_i64()
             ) {
+                let (par, stroke_index, stroke_index_women) = hole_scoring(hole);
                 let new_hole = NewHole {
                     hole_id: id as i32,
                     number: Some(number as i32),
@@ -117,6 +248,14 @@ pub async fn  get_holes_from_golfbert(course_id: String) -> Result<(), Box<dyn s
                     dimensions_height: hole["dimensions"]["height"].as_i64().map(|n| n as i32),
                     flag_lat: hole["flagcoords"]["lat"].as_f64(),
                     flag_long: hole["flagcoords"]["long"].as_f64(),
+                    par,
+                    stroke_index,
+                    stroke_index_women,
+                    name: hole["name"]
+                        .as_str()
+                        .map(str::trim)
+                        .filter(|name| !name.is_empty())
+                        .map(|name| name.chars().take(40).collect()),
                 };
 
                 diesel::insert_into(holes::table)
@@ -124,11 +263,11 @@ pub async fn  get_holes_from_golfbert(course_id: String) -> Result<(), Box<dyn s
                     .on_conflict(holes::hole_id)
                     .do_update()
                     .set(&new_hole)
-                    .execute(&mut connection)?;
+                    .execute(connection)?;
 
                 // Delete existing vectors for this hole
                 diesel::delete(vectors::table.filter(vectors::hole_id.eq(id as i32)))
-                    .execute(&mut connection)?;
+                    .execute(connection)?;
 
                 if let Some(vectors) = hole["vectors"].as_array() {
                     for vector in vectors {
@@ -146,27 +285,21 @@ pub async fn  get_holes_from_golfbert(course_id: String) -> Result<(), Box<dyn s
 
                             diesel::insert_into(vectors::table)
                                 .values(&new_vector)
-                                .execute(&mut connection)?;
+                                .execute(connection)?;
                         }
                     }
                 }
 
                 // Fetch polygons for this hole
                 println!("Fetching polygons for hole {}...", id);
-                let polygons_output = run_command("awscurl", &[
-                    "--request", "GET",
-                    &format!("https://api.golfbert.com/v1/holes/{}/polygons", id),
-                    "--header", &format!("x-api-key: {}", api_key),
-                ], &access_key, &secret_key)?;
-
-                let polygons_data: Value = serde_json::from_str(&polygons_output)?;
+                let polygons_data = provider.fetch_polygons(id as i32)?;
 
                 // Delete existing polygons for this hole
                 diesel::delete(polygons::table.filter(polygons::hole_id.eq(id as i32)))
-                    .execute(&mut connection)?;
+                    .execute(connection)?;
 
                 if let Some(resources) = polygons_data["resources"].as_array() {
-                    for polygon in resources {
+                    for (shape_index, polygon) in resources.iter().enumerate() {
                         if let (Some(surface_type), Some(polygon_coords)) = (
                             polygon["surfacetype"].as_str(),
                             polygon["polygon"].as_array()
@@ -178,11 +311,12 @@ pub async fn  get_holes_from_golfbert(course_id: String) -> Result<(), Box<dyn s
                                         surface_type: surface_type.to_string(),
                                         lat,
                                         long,
+                                        shape_index: shape_index as i32,
                                     };
 
                                     diesel::insert_into(polygons::table)
                                         .values(&new_polygon)
-                                        .execute(&mut connection)?;
+                                        .execute(connection)?;
                                 }
                             }
                         }
@@ -196,5 +330,9 @@ pub async fn  get_holes_from_golfbert(course_id: String) -> Result<(), Box<dyn s
 
     println!("Data successfully inserted into the database.");
 
+    let routed = course_operations::route_course(connection, numeric_course_id)?;
+    tee_set_operations::measure_course_tee_sets(connection, numeric_course_id)?;
+    println!("Routed {} holes.", routed);
+
     Ok(())
 }
\ No newline at end of file
@@ -0,0 +1,136 @@
+use diesel::prelude::*;
+use serde::Deserialize;
+
+use crate::error::ApiError;
+use crate::models::Club;
+use crate::schema::{clubs, users};
+
+/// The Rules of Golf allow at most 14 clubs in the bag.
+pub const MAX_CLUBS: i64 = 14;
+
+pub const CLUB_TYPES: &[&str] = &["driver", "wood", "hybrid", "iron", "wedge", "putter"];
+
+#[derive(Deserialize, Insertable, AsChangeset, Debug)]
+#[diesel(table_name = clubs, treat_none_as_null = true)]
+pub struct ClubInput {
+    pub club_type: String,
+    pub name: String,
+    pub loft: Option<f64>,
+    pub brand: Option<String>,
+    pub model: Option<String>,
+    pub carry_yards: Option<f64>,
+    pub total_yards: Option<f64>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = clubs)]
+struct NewClub {
+    user_id: i32,
+    #[diesel(embed)]
+    club: ClubInput,
+}
+
+impl ClubInput {
+    fn validate(&mut self) -> Result<(), ApiError> {
+        self.club_type = self.club_type.trim().to_lowercase();
+        self.name = self.name.trim().to_string();
+
+        if !CLUB_TYPES.contains(&self.club_type.as_str()) {
+            return Err(ApiError::BadRequest(format!(
+                "club_type must be one of {}",
+                CLUB_TYPES.join(", ")
+            )));
+        }
+        if self.name.is_empty() || self.name.len() > 40 {
+            return Err(ApiError::BadRequest("name must be 1 to 40 characters".to_string()));
+        }
+        if let Some(loft) = self.loft {
+            if !(0.0..=70.0).contains(&loft) {
+                return Err(ApiError::BadRequest("loft must be between 0 and 70 degrees".to_string()));
+            }
+        }
+        for (field, value) in [("carry_yards", self.carry_yards), ("total_yards", self.total_yards)] {
+            if let Some(yards) = value {
+                if !(0.0..=450.0).contains(&yards) {
+                    return Err(ApiError::BadRequest(format!("{} must be between 0 and 450", field)));
+                }
+            }
+        }
+        if let (Some(carry), Some(total)) = (self.carry_yards, self.total_yards) {
+            if carry > total {
+                return Err(ApiError::BadRequest("carry_yards cannot exceed total_yards".to_string()));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A user's clubs, longest first.
+pub fn fetch_bag(conn: &mut PgConnection, user_id: i32) -> Result<Vec<Club>, ApiError> {
+    Ok(clubs::table
+        .filter(clubs::user_id.eq(user_id))
+        .order((
+            clubs::total_yards.desc().nulls_last(),
+            clubs::loft.asc().nulls_last(),
+            clubs::id.asc(),
+        ))
+        .select(Club::as_select())
+        .load(conn)?)
+}
+
+pub fn fetch_club(conn: &mut PgConnection, user_id: i32, club_id: i32) -> Result<Club, ApiError> {
+    Ok(clubs::table
+        .filter(clubs::id.eq(club_id))
+        .filter(clubs::user_id.eq(user_id))
+        .select(Club::as_select())
+        .first(conn)?)
+}
+
+pub fn add_club(conn: &mut PgConnection, user_id: i32, mut input: ClubInput) -> Result<Club, ApiError> {
+    input.validate()?;
+
+    conn.transaction(|conn| {
+        // Lock the owner so two concurrent adds can't both pass the limit.
+        users::table.find(user_id).select(users::id).for_update().first::<i32>(conn)?;
+
+        let count: i64 = clubs::table
+            .filter(clubs::user_id.eq(user_id))
+            .count()
+            .get_result(conn)?;
+        if count >= MAX_CLUBS {
+            return Err(ApiError::Conflict(format!("a bag can hold at most {} clubs", MAX_CLUBS)));
+        }
+
+        Ok(diesel::insert_into(clubs::table)
+            .values(&NewClub { user_id, club: input })
+            .returning(Club::as_returning())
+            .get_result(conn)?)
+    })
+}
+
+pub fn update_club(conn: &mut PgConnection, user_id: i32, club_id: i32, mut input: ClubInput) -> Result<Club, ApiError> {
+    input.validate()?;
+
+    Ok(diesel::update(
+        clubs::table
+            .filter(clubs::id.eq(club_id))
+            .filter(clubs::user_id.eq(user_id)),
+    )
+    .set(&input)
+    .returning(Club::as_returning())
+    .get_result(conn)?)
+}
+
+pub fn delete_club(conn: &mut PgConnection, user_id: i32, club_id: i32) -> Result<(), ApiError> {
+    let deleted = diesel::delete(
+        clubs::table
+            .filter(clubs::id.eq(club_id))
+            .filter(clubs::user_id.eq(user_id)),
+    )
+    .execute(conn)?;
+
+    if deleted == 0 {
+        return Err(ApiError::NotFound);
+    }
+    Ok(())
+}
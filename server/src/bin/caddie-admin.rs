@@ -0,0 +1,209 @@
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+
+use clap::{Parser, Subcommand, ValueEnum};
+use diesel::{Connection, PgConnection};
+
+use ai_caddie_backend::aws_operations::{self, CourseProvider, FileProvider, GolfbertProvider};
+use ai_caddie_backend::config::Config;
+use ai_caddie_backend::course_operations::{self, Severity};
+use ai_caddie_backend::elevation::Terrain;
+use ai_caddie_backend::{auth, db_operations, run_migrations, tee_set_operations};
+
+/// Manage course data in the AI Caddie database.
+#[derive(Parser)]
+#[command(name = "caddie-admin")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Import a course and all of its holes from a provider.
+    Import {
+        course_id: String,
+        #[arg(long, value_enum, default_value_t = Provider::Golfbert)]
+        provider: Provider,
+        /// Directory of saved responses, required for `--provider file`.
+        #[arg(long)]
+        dir: Option<PathBuf>,
+        /// Overrides the course name reported by the provider.
+        #[arg(long)]
+        name: Option<String>,
+    },
+    /// List every course with its number of holes.
+    List,
+    /// Delete a course with all of its holes, geometry and tee sets, unless
+    /// rounds have been played on it.
+    Delete {
+        course_id: i32,
+        /// Skip the confirmation check.
+        #[arg(long)]
+        yes: bool,
+    },
+    /// Re-render the SVG map of every hole on a course.
+    Render {
+        course_id: i32,
+        #[arg(long, default_value = "assets")]
+        out: PathBuf,
+    },
+    /// Derive hole centerlines and re-measure the course's tee sets.
+    Route { course_id: i32 },
+    /// Sample terrain heights for a course from local SRTM `.hgt` tiles or
+    /// GeoTIFFs, given as files or directories.
+    Elevation {
+        course_id: i32,
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+    },
+    /// Check a course's geometry for missing or broken shapes.
+    Validate { course_id: i32 },
+    /// Export a course to GeoJSON.
+    Export {
+        course_id: i32,
+        /// Output file. Defaults to stdout.
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+    /// Run pending database migrations.
+    Migrate,
+    /// Change a user's role, e.g. to grant admin access.
+    SetRole {
+        email: String,
+        #[arg(value_parser = [auth::ROLE_PLAYER, auth::ROLE_ADMIN])]
+        role: String,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Provider {
+    Golfbert,
+    File,
+}
+
+fn main() {
+    let cli = Cli::parse();
+
+    let config = Config::load_database().unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(1);
+    });
+
+    let mut conn = PgConnection::establish(&config.database.url).unwrap_or_else(|e| {
+        eprintln!("Error connecting to database: {}", e);
+        process::exit(1);
+    });
+
+    if let Err(e) = run(cli.command, &config, &mut conn) {
+        eprintln!("Error: {}", e);
+        process::exit(1);
+    }
+}
+
+fn run(command: Command, config: &Config, conn: &mut PgConnection) -> Result<(), Box<dyn std::error::Error>> {
+    match command {
+        Command::Import { course_id, provider, dir, name } => {
+            let provider: Box<dyn CourseProvider> = match provider {
+                Provider::Golfbert => {
+                    let missing = config.providers.golfbert.missing_keys();
+                    if !missing.is_empty() {
+                        return Err(format!(
+                            "--provider golfbert needs providers.golfbert.{} to be set",
+                            missing.join(", providers.golfbert.")
+                        )
+                        .into());
+                    }
+                    Box::new(GolfbertProvider::new(&config.providers.golfbert))
+                }
+                Provider::File => {
+                    let dir = dir.ok_or("--dir is required with --provider file")?;
+                    Box::new(FileProvider::new(dir))
+                }
+            };
+            aws_operations::import_course(provider.as_ref(), &course_id, name.as_deref(), conn)?;
+            println!("Imported course {}", course_id);
+        }
+        Command::List => {
+            for course in db_operations::fetch_courses(conn)? {
+                println!(
+                    "{:>8}  {:<40}  {} holes",
+                    course.course.course_id,
+                    course.course.course_name,
+                    course.holes.len()
+                );
+            }
+        }
+        Command::Delete { course_id, yes } => {
+            let course = course_operations::fetch_course(conn, course_id)?;
+            if !yes {
+                return Err(format!(
+                    "this deletes `{}` and all of its holes; re-run with --yes to confirm",
+                    course.course_name
+                )
+                .into());
+            }
+            let deleted = course_operations::delete_course(conn, course_id)?;
+            println!("Deleted {} and {} holes", course.course_name, deleted);
+        }
+        Command::Render { course_id, out } => {
+            let holes = course_operations::fetch_course_hole_data(conn, course_id)?;
+            for path in course_operations::render_course_svgs(&holes, &out)? {
+                println!("Wrote {}", path.display());
+            }
+        }
+        Command::Route { course_id } => {
+            let routed = course_operations::route_course(conn, course_id)?;
+            let tee_sets = tee_set_operations::measure_course_tee_sets(conn, course_id)?;
+            println!("Routed {} holes and measured {} tee sets", routed, tee_sets);
+        }
+        Command::Elevation { course_id, paths } => {
+            let terrain = Terrain::load(&paths)?;
+            let sampled = course_operations::sample_course_elevations(conn, course_id, &terrain)?;
+            println!("Sampled elevations for {} holes from {}", sampled, terrain.source());
+        }
+        Command::Validate { course_id } => {
+            let holes = course_operations::fetch_course_hole_data(conn, course_id)?;
+            let issues = course_operations::validate_course(&holes);
+            for issue in &issues {
+                let severity = match issue.severity {
+                    Severity::Error => "error",
+                    Severity::Warning => "warning",
+                };
+                let number = issue.hole_number.map(|n| n.to_string()).unwrap_or_else(|| "?".to_string());
+                println!("{:<7} hole {:>2} ({}): {}", severity, number, issue.hole_id, issue.message);
+            }
+
+            let errors = issues.iter().filter(|issue| issue.severity == Severity::Error).count();
+            println!("{} holes checked, {} errors, {} warnings", holes.len(), errors, issues.len() - errors);
+            if errors > 0 {
+                process::exit(2);
+            }
+        }
+        Command::Export { course_id, out } => {
+            let course = course_operations::fetch_course(conn, course_id)?;
+            let holes = course_operations::fetch_course_hole_data(conn, course_id)?;
+            let geojson = serde_json::to_string_pretty(&course_operations::course_to_geojson(&course, &holes))?;
+            match out {
+                Some(path) => fs::write(path, geojson)?,
+                None => println!("{}", geojson),
+            }
+        }
+        Command::Migrate => {
+            let applied = run_migrations(conn).map_err(|e| e.to_string())?;
+            if applied.is_empty() {
+                println!("Database is up to date");
+            }
+            for version in applied {
+                println!("Applied migration {}", version);
+            }
+        }
+        Command::SetRole { email, role } => {
+            let user = auth::set_role(conn, &email, &role)?;
+            println!("{} is now {}", user.email, user.role);
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,88 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+type CachedBytes = (Instant, Arc<Vec<u8>>);
+
+/// Bytes built from course geometry, kept in memory until they expire or
+/// `max_entries` newer ones push them out.
+pub struct BuiltCache<K> {
+    ttl: Duration,
+    max_entries: usize,
+    entries: Mutex<HashMap<K, CachedBytes>>,
+}
+
+impl<K: Copy + Eq + Hash> BuiltCache<K> {
+    /// A `ttl` of zero or `max_entries` of 0 turns the cache off.
+    pub fn new(ttl: Duration, max_entries: usize) -> BuiltCache<K> {
+        BuiltCache {
+            ttl,
+            max_entries,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// The cached bytes, or freshly built ones. The lock isn't held while
+    /// building, so a slow build doesn't hold up the rest.
+    pub fn get_or_build<E>(&self, key: K, build: impl FnOnce() -> Result<Vec<u8>, E>) -> Result<Arc<Vec<u8>>, E> {
+        let now = Instant::now();
+        if let Ok(entries) = self.entries.lock() {
+            if let Some((built, bytes)) = entries.get(&key) {
+                if now.duration_since(*built) < self.ttl {
+                    return Ok(bytes.clone());
+                }
+            }
+        }
+
+        let bytes = Arc::new(build()?);
+        if self.ttl.is_zero() || self.max_entries == 0 {
+            return Ok(bytes);
+        }
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.retain(|_, (built, _)| now.duration_since(*built) < self.ttl);
+            if entries.len() >= self.max_entries {
+                let oldest = entries.iter().min_by_key(|(_, (built, _))| *built).map(|(key, _)| *key);
+                if let Some(oldest) = oldest {
+                    entries.remove(&oldest);
+                }
+            }
+            entries.insert(key, (now, bytes.clone()));
+        }
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build(calls: &mut u32) -> Result<Vec<u8>, ()> {
+        *calls += 1;
+        Ok(vec![*calls as u8])
+    }
+
+    #[test]
+    fn reuses_bytes_until_pushed_out() {
+        let cache = BuiltCache::new(Duration::from_secs(60), 1);
+        let mut calls = 0;
+        assert_eq!(*cache.get_or_build(1, || build(&mut calls)).unwrap(), vec![1]);
+        assert_eq!(*cache.get_or_build(1, || build(&mut calls)).unwrap(), vec![1]);
+        cache.get_or_build(2, || build(&mut calls)).unwrap();
+        assert_eq!(*cache.get_or_build(1, || build(&mut calls)).unwrap(), vec![3]);
+        assert_eq!(calls, 3);
+    }
+
+    #[test]
+    fn zero_ttl_always_builds() {
+        let cache = BuiltCache::new(Duration::ZERO, 10);
+        let mut calls = 0;
+        cache.get_or_build(1, || build(&mut calls)).unwrap();
+        cache.get_or_build(1, || build(&mut calls)).unwrap();
+        assert_eq!(calls, 2);
+    }
+}
@@ -0,0 +1,440 @@
+use std::env;
+use std::fmt;
+use std::fs;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+
+use dotenv::dotenv;
+use serde::Deserialize;
+
+const DEFAULT_CONFIG_PATH: &str = "config.toml";
+const CONFIG_PATH_VAR: &str = "AI_CADDIE_CONFIG";
+
+/// Every problem found while loading the configuration. Loading keeps going
+/// after the first problem so the operator can fix them all in one pass.
+#[derive(Debug)]
+pub struct ConfigError(pub Vec<String>);
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "invalid configuration:")?;
+        for error in &self.0 {
+            writeln!(f, "  - {}", error)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub database: DatabaseConfig,
+    pub cors: CorsConfig,
+    pub auth: AuthConfig,
+    pub providers: ProvidersConfig,
+    pub analytics: AnalyticsConfig,
+    pub tiles: TilesConfig,
+    pub meshes: MeshesConfig,
+    pub features: FeaturesConfig,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub bind_address: String,
+    pub tls: Option<TlsConfig>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            bind_address: "127.0.0.1:8080".to_string(),
+            tls: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TlsConfig {
+    pub cert_path: PathBuf,
+    pub key_path: PathBuf,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DatabaseConfig {
+    pub url: String,
+    pub pool_size: u32,
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        DatabaseConfig {
+            url: String::new(),
+            pool_size: 10,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CorsConfig {
+    /// Origins allowed to make cross-origin requests. `"*"` allows any origin.
+    pub allowed_origins: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthConfig {
+    /// HMAC key used to sign access tokens. At least 32 bytes.
+    pub jwt_secret: String,
+    pub access_token_ttl_secs: i64,
+    pub refresh_token_ttl_secs: i64,
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        AuthConfig {
+            jwt_secret: String::new(),
+            access_token_ttl_secs: 15 * 60,
+            refresh_token_ttl_secs: 30 * 24 * 60 * 60,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProvidersConfig {
+    pub golfbert: GolfbertConfig,
+    pub weather: WeatherConfig,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GolfbertConfig {
+    pub api_key: String,
+    pub aws_access_key: String,
+    pub aws_secret_key: String,
+}
+
+impl GolfbertConfig {
+    /// Names of the credentials that are still empty.
+    pub fn missing_keys(&self) -> Vec<&'static str> {
+        [
+            ("api_key", &self.api_key),
+            ("aws_access_key", &self.aws_access_key),
+            ("aws_secret_key", &self.aws_secret_key),
+        ]
+        .into_iter()
+        .filter(|(_, value)| value.is_empty())
+        .map(|(name, _)| name)
+        .collect()
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WeatherConfig {
+    /// Weather fixture to use instead of the one shipped with the server, in
+    /// the same JSON format as `weather/fixture.json`.
+    pub fixture_path: Option<PathBuf>,
+    /// How long a reading is reused for. 0 turns the cache off.
+    pub cache_ttl_secs: u64,
+}
+
+impl Default for WeatherConfig {
+    fn default() -> Self {
+        WeatherConfig {
+            fixture_path: None,
+            cache_ttl_secs: 10 * 60,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AnalyticsConfig {
+    /// Expected-strokes baseline to use instead of the one shipped with the
+    /// server, in the same JSON format as `baselines/pga_tour.json`.
+    pub strokes_baseline_path: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TilesConfig {
+    /// How long a built vector tile is served from memory. 0 turns the cache
+    /// off.
+    pub cache_ttl_secs: u64,
+    /// Most tiles kept at once; the oldest are dropped first.
+    pub cache_max_tiles: usize,
+}
+
+impl Default for TilesConfig {
+    fn default() -> Self {
+        TilesConfig {
+            cache_ttl_secs: 60 * 60,
+            cache_max_tiles: 5_000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MeshesConfig {
+    /// How long a built hole mesh is served from memory. 0 turns the cache
+    /// off.
+    pub cache_ttl_secs: u64,
+    /// Most meshes kept at once, counting each level of detail separately;
+    /// the oldest are dropped first.
+    pub cache_max_meshes: usize,
+}
+
+impl Default for MeshesConfig {
+    fn default() -> Self {
+        MeshesConfig {
+            cache_ttl_secs: 60 * 60,
+            cache_max_meshes: 200,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FeaturesConfig {
+    /// Exposes `POST /trigger_aws` for importing course data from Golfbert.
+    /// Off by default, since it needs Golfbert credentials.
+    pub golfbert_import: bool,
+}
+
+impl Config {
+    /// Loads the configuration for the API server from the TOML file named
+    /// by `AI_CADDIE_CONFIG` (or `config.toml` if present), then applies
+    /// environment overrides and validates everything the server uses.
+    pub fn load() -> Result<Config, ConfigError> {
+        Config::load_with(|config, errors| {
+            config.validate_database(errors);
+            config.validate_server(errors);
+        })
+    }
+
+    /// Loads the configuration like [`Config::load`], but only checks the
+    /// database settings. For tools such as `caddie-admin` that never serve
+    /// requests.
+    pub fn load_database() -> Result<Config, ConfigError> {
+        Config::load_with(Config::validate_database)
+    }
+
+    fn load_with(validate: impl FnOnce(&Config, &mut Vec<String>)) -> Result<Config, ConfigError> {
+        dotenv().ok();
+
+        let mut errors = Vec::new();
+
+        let mut config = match env::var(CONFIG_PATH_VAR) {
+            Ok(path) => Config::from_file(Path::new(&path), &mut errors),
+            Err(_) if Path::new(DEFAULT_CONFIG_PATH).exists() => {
+                Config::from_file(Path::new(DEFAULT_CONFIG_PATH), &mut errors)
+            }
+            Err(_) => Config::default(),
+        };
+
+        config.apply_env_overrides(&mut errors);
+        validate(&config, &mut errors);
+
+        if errors.is_empty() {
+            Ok(config)
+        } else {
+            Err(ConfigError(errors))
+        }
+    }
+
+    fn from_file(path: &Path, errors: &mut Vec<String>) -> Config {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) => {
+                errors.push(format!("could not read {}: {}", path.display(), e));
+                return Config::default();
+            }
+        };
+
+        match toml::from_str(&contents) {
+            Ok(config) => config,
+            Err(e) => {
+                errors.push(format!("could not parse {}: {}", path.display(), e));
+                Config::default()
+            }
+        }
+    }
+
+    fn apply_env_overrides(&mut self, errors: &mut Vec<String>) {
+        if let Ok(value) = env::var("BIND_ADDRESS") {
+            self.server.bind_address = value;
+        }
+        match (env::var("TLS_CERT_PATH"), env::var("TLS_KEY_PATH")) {
+            (Ok(cert_path), Ok(key_path)) => {
+                self.server.tls = Some(TlsConfig {
+                    cert_path: cert_path.into(),
+                    key_path: key_path.into(),
+                });
+            }
+            (Ok(_), Err(_)) | (Err(_), Ok(_)) => {
+                errors.push("TLS_CERT_PATH and TLS_KEY_PATH must be set together".to_string());
+            }
+            (Err(_), Err(_)) => {}
+        }
+        if let Ok(value) = env::var("DATABASE_URL") {
+            self.database.url = value;
+        }
+        if let Some(value) = parse_env("DATABASE_POOL_SIZE", errors) {
+            self.database.pool_size = value;
+        }
+        if let Ok(value) = env::var("CORS_ALLOWED_ORIGINS") {
+            self.cors.allowed_origins = value
+                .split(',')
+                .map(str::trim)
+                .filter(|origin| !origin.is_empty())
+                .map(String::from)
+                .collect();
+        }
+        if let Ok(value) = env::var("JWT_SECRET") {
+            self.auth.jwt_secret = value;
+        }
+        if let Ok(value) = env::var("GOLFBERT_API_KEY") {
+            self.providers.golfbert.api_key = value;
+        }
+        if let Ok(value) = env::var("AWS_ACCESS_KEY") {
+            self.providers.golfbert.aws_access_key = value;
+        }
+        if let Ok(value) = env::var("AWS_SECRET_KEY") {
+            self.providers.golfbert.aws_secret_key = value;
+        }
+        if let Ok(value) = env::var("WEATHER_FIXTURE_PATH") {
+            self.providers.weather.fixture_path = Some(value.into());
+        }
+        if let Some(value) = parse_env("WEATHER_CACHE_TTL_SECS", errors) {
+            self.providers.weather.cache_ttl_secs = value;
+        }
+        if let Ok(value) = env::var("STROKES_BASELINE_PATH") {
+            self.analytics.strokes_baseline_path = Some(value.into());
+        }
+        if let Some(value) = parse_env("TILES_CACHE_TTL_SECS", errors) {
+            self.tiles.cache_ttl_secs = value;
+        }
+        if let Some(value) = parse_env("TILES_CACHE_MAX_TILES", errors) {
+            self.tiles.cache_max_tiles = value;
+        }
+        if let Some(value) = parse_env("MESHES_CACHE_TTL_SECS", errors) {
+            self.meshes.cache_ttl_secs = value;
+        }
+        if let Some(value) = parse_env("MESHES_CACHE_MAX_MESHES", errors) {
+            self.meshes.cache_max_meshes = value;
+        }
+        if let Some(value) = parse_env("FEATURE_GOLFBERT_IMPORT", errors) {
+            self.features.golfbert_import = value;
+        }
+    }
+
+    fn validate_database(&self, errors: &mut Vec<String>) {
+        if self.database.url.is_empty() {
+            errors.push("database.url must be set (or DATABASE_URL)".to_string());
+        }
+        if self.database.pool_size == 0 {
+            errors.push("database.pool_size must be at least 1".to_string());
+        }
+    }
+
+    fn validate_server(&self, errors: &mut Vec<String>) {
+        if self.server.bind_address.parse::<SocketAddr>().is_err() {
+            errors.push(format!(
+                "server.bind_address `{}` is not a valid socket address",
+                self.server.bind_address
+            ));
+        }
+        if let Some(tls) = &self.server.tls {
+            for (name, path) in [("cert_path", &tls.cert_path), ("key_path", &tls.key_path)] {
+                if !path.is_file() {
+                    errors.push(format!("server.tls.{} {} does not exist", name, path.display()));
+                }
+            }
+        }
+
+        for origin in &self.cors.allowed_origins {
+            if origin != "*" && !origin.starts_with("http://") && !origin.starts_with("https://") {
+                errors.push(format!("cors.allowed_origins entry `{}` must be `*` or an http(s) origin", origin));
+            }
+        }
+
+        if self.auth.jwt_secret.len() < 32 {
+            errors.push("auth.jwt_secret must be at least 32 bytes (or JWT_SECRET)".to_string());
+        }
+        if self.auth.access_token_ttl_secs <= 0 || self.auth.refresh_token_ttl_secs <= 0 {
+            errors.push("auth token lifetimes must be positive".to_string());
+        }
+
+        if let Some(path) = &self.providers.weather.fixture_path {
+            if !path.is_file() {
+                errors.push(format!("providers.weather.fixture_path {} does not exist", path.display()));
+            }
+        }
+        if let Some(path) = &self.analytics.strokes_baseline_path {
+            if !path.is_file() {
+                errors.push(format!("analytics.strokes_baseline_path {} does not exist", path.display()));
+            }
+        }
+
+        if self.features.golfbert_import {
+            for name in self.providers.golfbert.missing_keys() {
+                errors.push(format!(
+                    "providers.golfbert.{} must be set when features.golfbert_import is enabled",
+                    name
+                ));
+            }
+        }
+    }
+}
+
+fn parse_env<T: std::str::FromStr>(name: &str, errors: &mut Vec<String>) -> Option<T> {
+    let value = env::var(name).ok()?;
+    match value.parse() {
+        Ok(parsed) => Some(parsed),
+        Err(_) => {
+            errors.push(format!("{} has invalid value `{}`", name, value));
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn errors(validate: impl FnOnce(&Config, &mut Vec<String>), config: &Config) -> Vec<String> {
+        let mut errors = Vec::new();
+        validate(config, &mut errors);
+        errors
+    }
+
+    #[test]
+    fn database_validation_ignores_server_settings() {
+        let mut config = Config::default();
+        config.database.url = "postgres://localhost/caddie".to_string();
+        config.features.golfbert_import = true;
+
+        assert!(errors(Config::validate_database, &config).is_empty());
+        let server = errors(Config::validate_server, &config);
+        assert!(server.iter().any(|error| error.starts_with("auth.jwt_secret")));
+        assert_eq!(server.iter().filter(|error| error.starts_with("providers.golfbert")).count(), 3);
+    }
+
+    #[test]
+    fn golfbert_import_is_off_by_default() {
+        let mut config = Config::default();
+        config.auth.jwt_secret = "x".repeat(32);
+
+        assert!(!config.features.golfbert_import);
+        assert!(errors(Config::validate_server, &config).is_empty());
+    }
+}
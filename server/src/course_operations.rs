@@ -0,0 +1,345 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use diesel::prelude::*;
+use diesel::result::Error as DieselError;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::db_operations::{fetch_hole_data, generate_svg};
+use crate::elevation::{ElevationGrid, Terrain};
+use crate::error::ApiError;
+use crate::geometry::{group_shapes, LatLong};
+use crate::models::{Course, Hole, HoleData};
+use crate::routing;
+use crate::schema::{
+    courses, hole_course_associations, hole_elevations, hole_route_points, hole_routes, holes, pin_sheets, polygons,
+    rounds, tee_set_hole_overrides, tee_set_holes, tee_sets, tournament_rounds, vectors,
+};
+
+/// Spacing of the elevation grid laid over each hole, and how far past the
+/// hole's outermost shapes it reaches.
+const ELEVATION_SPACING_YARDS: f64 = 10.0;
+const ELEVATION_MARGIN_YARDS: f64 = 20.0;
+/// Shapes smaller than this are almost certainly digitising mistakes.
+const MIN_SHAPE_AREA_SQ_YARDS: f64 = 1.0;
+
+pub fn fetch_course(conn: &mut PgConnection, course_id: i32) -> Result<Course, DieselError> {
+    courses::table
+        .filter(courses::course_id.eq(course_id))
+        .first::<Course>(conn)
+}
+
+/// Loads every hole of a course with its polygons and vectors, in hole order.
+pub fn fetch_course_hole_data(conn: &mut PgConnection, course_id: i32) -> Result<Vec<HoleData>, DieselError> {
+    let hole_ids = holes::table
+        .filter(holes::course_id.eq(Some(course_id)))
+        .order(holes::number.asc())
+        .select(holes::hole_id)
+        .load::<i32>(conn)?;
+
+    hole_ids
+        .into_iter()
+        .map(|hole_id| fetch_hole_data(conn, hole_id))
+        .collect()
+}
+
+/// Deletes a course with all of its holes, polygons and vectors, and what
+/// was derived from them: routes, elevations, pin sheets and tee sets.
+/// Refused while any round or tournament is played on the course, since
+/// their scores need its holes and ratings. Returns the number of holes
+/// removed.
+pub fn delete_course(conn: &mut PgConnection, course_id: i32) -> Result<usize, ApiError> {
+    conn.transaction(|conn| {
+        let course = fetch_course(conn, course_id)?;
+        let played: bool = diesel::select(diesel::dsl::exists(rounds::table.filter(rounds::course_id.eq(course_id))))
+            .get_result(conn)?;
+        let in_tournament: bool = diesel::select(diesel::dsl::exists(
+            tournament_rounds::table.filter(tournament_rounds::course_id.eq(course_id)),
+        ))
+        .get_result(conn)?;
+        if played || in_tournament {
+            return Err(ApiError::Conflict(format!(
+                "`{}` has rounds or tournaments played on it",
+                course.course_name
+            )));
+        }
+        let course_holes = holes::table
+            .filter(holes::course_id.eq(Some(course_id)))
+            .load::<Hole>(conn)?;
+
+        let hole_ids: Vec<i32> = course_holes.iter().map(|hole| hole.hole_id).collect();
+        let hole_row_ids: Vec<i32> = course_holes.iter().map(|hole| hole.id).collect();
+
+        diesel::delete(polygons::table.filter(polygons::hole_id.eq_any(&hole_ids))).execute(conn)?;
+        diesel::delete(vectors::table.filter(vectors::hole_id.eq_any(&hole_ids))).execute(conn)?;
+        // Route points and pin positions go with their rows by cascade.
+        diesel::delete(hole_routes::table.filter(hole_routes::hole_id.eq_any(&hole_ids))).execute(conn)?;
+        diesel::delete(hole_elevations::table.filter(hole_elevations::hole_id.eq_any(&hole_ids))).execute(conn)?;
+        diesel::delete(pin_sheets::table.filter(pin_sheets::course_id.eq(course_id))).execute(conn)?;
+        let tee_set_ids = tee_sets::table
+            .filter(tee_sets::course_id.eq(course_id))
+            .select(tee_sets::id);
+        diesel::delete(tee_set_holes::table.filter(tee_set_holes::tee_set_id.eq_any(tee_set_ids))).execute(conn)?;
+        diesel::delete(
+            tee_set_hole_overrides::table.filter(tee_set_hole_overrides::tee_set_id.eq_any(tee_set_ids)),
+        )
+        .execute(conn)?;
+        diesel::delete(tee_sets::table.filter(tee_sets::course_id.eq(course_id))).execute(conn)?;
+        diesel::delete(
+            hole_course_associations::table.filter(
+                hole_course_associations::course_id
+                    .eq(course.id)
+                    .or(hole_course_associations::hole_id.eq_any(&hole_row_ids)),
+            ),
+        )
+        .execute(conn)?;
+        let deleted = diesel::delete(holes::table.filter(holes::id.eq_any(&hole_row_ids))).execute(conn)?;
+        diesel::delete(courses::table.filter(courses::id.eq(course.id))).execute(conn)?;
+
+        Ok(deleted)
+    })
+}
+
+/// Derives and stores the centerline of every hole on a course, replacing
+/// any from before. Returns the number of holes routed.
+pub fn route_course(conn: &mut PgConnection, course_id: i32) -> Result<usize, DieselError> {
+    let course_holes = fetch_course_hole_data(conn, course_id)?;
+
+    conn.transaction(|conn| {
+        let mut routed = 0;
+        for hole_data in &course_holes {
+            let hole_id = hole_data.hole.hole_id;
+            diesel::delete(hole_routes::table.find(hole_id)).execute(conn)?;
+
+            let Some(route) = routing::route_for(hole_data) else {
+                continue;
+            };
+            let (row, points) = route.to_rows(hole_id);
+            diesel::insert_into(hole_routes::table).values(&row).execute(conn)?;
+            diesel::insert_into(hole_route_points::table)
+                .values(&points)
+                .execute(conn)?;
+            routed += 1;
+        }
+        Ok(routed)
+    })
+}
+
+/// Samples `terrain` at every tee, flag and polygon vertex of a course and
+/// over a grid covering each hole, replacing any samples from before.
+/// Returns the number of holes the terrain covered.
+pub fn sample_course_elevations(conn: &mut PgConnection, course_id: i32, terrain: &Terrain) -> Result<usize, DieselError> {
+    let course_holes = fetch_course_hole_data(conn, course_id)?;
+    let source = terrain.source();
+
+    conn.transaction(|conn| {
+        let mut sampled = 0;
+        for hole_data in &course_holes {
+            let hole_id = hole_data.hole.hole_id;
+            diesel::delete(hole_elevations::table.find(hole_id)).execute(conn)?;
+
+            let flag = hole_data.hole.flag();
+            diesel::update(holes::table.filter(holes::hole_id.eq(hole_id)))
+                .set(holes::flag_elevation_ft.eq(flag.and_then(|flag| terrain.elevation_ft(flag))))
+                .execute(conn)?;
+            let mut points: Vec<LatLong> = flag.into_iter().collect();
+            for polygon in &hole_data.polygons {
+                let point = polygon.lat.zip(polygon.long).map(|(lat, long)| LatLong::new(lat, long));
+                diesel::update(polygons::table.find(polygon.id))
+                    .set(polygons::elevation_ft.eq(point.and_then(|point| terrain.elevation_ft(point))))
+                    .execute(conn)?;
+                points.extend(point);
+            }
+            for vector in &hole_data.vectors {
+                let point = vector.lat.zip(vector.long).map(|(lat, long)| LatLong::new(lat, long));
+                diesel::update(vectors::table.find(vector.id))
+                    .set(vectors::elevation_ft.eq(point.and_then(|point| terrain.elevation_ft(point))))
+                    .execute(conn)?;
+                points.extend(point);
+            }
+
+            let Some(grid) = ElevationGrid::sample(terrain, &points, ELEVATION_SPACING_YARDS, ELEVATION_MARGIN_YARDS)
+                .filter(|grid| grid.elevations_ft.iter().any(Option::is_some))
+            else {
+                continue;
+            };
+            diesel::insert_into(hole_elevations::table)
+                .values(&grid.to_row(hole_id, &source))
+                .execute(conn)?;
+            sampled += 1;
+        }
+        Ok(sampled)
+    })
+}
+
+/// Writes `{hole_id}.svg` for each hole into `out_dir`.
+pub fn render_course_svgs(holes: &[HoleData], out_dir: &Path) -> io::Result<Vec<PathBuf>> {
+    fs::create_dir_all(out_dir)?;
+
+    holes
+        .iter()
+        .map(|hole_data| {
+            let path = out_dir.join(format!("{}.svg", hole_data.hole.hole_id));
+            fs::write(&path, generate_svg(hole_data))?;
+            Ok(path)
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GeometryIssue {
+    pub hole_id: i32,
+    pub hole_number: Option<i32>,
+    pub severity: Severity,
+    pub message: String,
+}
+
+/// Checks the stored geometry of a course for problems that would break
+/// distance and lie calculations.
+pub fn validate_course(holes: &[HoleData]) -> Vec<GeometryIssue> {
+    let mut issues = Vec::new();
+    let mut numbers: HashMap<i32, i32> = HashMap::new();
+
+    for hole_data in holes {
+        let hole = &hole_data.hole;
+        let mut report = |severity: Severity, message: String| {
+            issues.push(GeometryIssue {
+                hole_id: hole.hole_id,
+                hole_number: hole.number,
+                severity,
+                message,
+            })
+        };
+
+        match hole.number {
+            Some(number) => {
+                if let Some(other) = numbers.insert(number, hole.hole_id) {
+                    report(Severity::Error, format!("hole number {} is also used by hole {}", number, other));
+                }
+            }
+            None => report(Severity::Warning, "hole has no number".to_string()),
+        }
+
+        let shapes = group_shapes(&hole_data.polygons);
+        if shapes.is_empty() {
+            report(Severity::Error, "hole has no polygons".to_string());
+        }
+        for (index, shape) in shapes.iter().enumerate() {
+            if shape.points.len() < 3 {
+                report(
+                    Severity::Error,
+                    format!("{} shape {} has only {} vertices", shape.surface_type, index, shape.points.len()),
+                );
+            } else if shape.area_sq_yards() < MIN_SHAPE_AREA_SQ_YARDS {
+                report(Severity::Warning, format!("{} shape {} has no area", shape.surface_type, index));
+            }
+            if shape.points.iter().any(|point| !point.is_valid()) {
+                report(
+                    Severity::Error,
+                    format!("{} shape {} has coordinates out of range", shape.surface_type, index),
+                );
+            }
+        }
+
+        match hole.flag() {
+            None => report(Severity::Error, "hole has no flag position".to_string()),
+            Some(flag) if !flag.is_valid() => report(Severity::Error, "flag position is out of range".to_string()),
+            Some(flag) => {
+                let greens: Vec<_> = shapes.iter().filter(|shape| shape.surface_type == "Green").collect();
+                if greens.is_empty() {
+                    report(Severity::Error, "hole has no green".to_string());
+                } else if !greens.iter().any(|green| green.contains(flag)) {
+                    report(Severity::Warning, "flag is not on the green".to_string());
+                }
+            }
+        }
+
+        let tees = hole_data
+            .vectors
+            .iter()
+            .filter(|vector| vector.vector_type.as_deref() != Some("Flag"))
+            .count();
+        if tees == 0 {
+            report(Severity::Error, "hole has no tee markers".to_string());
+        }
+    }
+
+    issues
+}
+
+/// Converts a course to a GeoJSON `FeatureCollection` with one feature per
+/// polygon, tee marker and flag.
+pub fn course_to_geojson(course: &Course, holes: &[HoleData]) -> Value {
+    let mut features = Vec::new();
+
+    for hole_data in holes {
+        let hole = &hole_data.hole;
+
+        for shape in group_shapes(&hole_data.polygons) {
+            let mut ring: Vec<[f64; 2]> = shape.points.iter().map(|p| [p.long, p.lat]).collect();
+            if let (Some(first), Some(last)) = (ring.first().copied(), ring.last().copied()) {
+                if first != last {
+                    ring.push(first);
+                }
+            }
+            features.push(json!({
+                "type": "Feature",
+                "geometry": { "type": "Polygon", "coordinates": [ring] },
+                "properties": {
+                    "hole_id": hole.hole_id,
+                    "hole_number": hole.number,
+                    "surface_type": shape.surface_type,
+                },
+            }));
+        }
+
+        for vector in &hole_data.vectors {
+            if let (Some(lat), Some(long)) = (vector.lat, vector.long) {
+                features.push(json!({
+                    "type": "Feature",
+                    "geometry": { "type": "Point", "coordinates": [long, lat] },
+                    "properties": {
+                        "hole_id": hole.hole_id,
+                        "hole_number": hole.number,
+                        "vector_type": vector.vector_type,
+                    },
+                }));
+            }
+        }
+
+        let has_flag_vector = hole_data
+            .vectors
+            .iter()
+            .any(|vector| vector.vector_type.as_deref() == Some("Flag"));
+        if let (false, Some(lat), Some(long)) = (has_flag_vector, hole.flag_lat, hole.flag_long) {
+            features.push(json!({
+                "type": "Feature",
+                "geometry": { "type": "Point", "coordinates": [long, lat] },
+                "properties": {
+                    "hole_id": hole.hole_id,
+                    "hole_number": hole.number,
+                    "vector_type": "Flag",
+                },
+            }));
+        }
+    }
+
+    json!({
+        "type": "FeatureCollection",
+        "properties": {
+            "course_id": course.course_id,
+            "course_name": course.course_name,
+        },
+        "features": features,
+    })
+}
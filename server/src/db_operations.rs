@@ -5,8 +5,12 @@ use diesel::result::Error as DieselError;
 use svg::Document;
 use svg::node::element::{Group, Path};
 
-use crate::models::{Course, CourseWithHoles, Hole, HoleData, Polygon, PolyVectorData, Vector};
-use crate::schema::{courses, holes, polygons, vectors};
+use crate::elevation::HoleElevation;
+use crate::models::{
+    Course, CourseWithHoles, Hole, HoleData, HoleElevationRow, HoleRoutePoint, HoleRouteRow, Polygon, Vector,
+};
+use crate::routing::HoleRoute;
+use crate::schema::{courses, hole_elevations, hole_route_points, hole_routes, holes, polygons, vectors};
 
 pub fn fetch_courses(conn: &mut PgConnection) -> Result<Vec<CourseWithHoles>, DieselError> {
     let courses = courses::table.load::<Course>(conn)?;
@@ -38,20 +42,6 @@ fn fetch_holes_from_course_id(
     Ok(holes)
 }
 
-fn fetch_poly_vector_data(
-    conn: &mut PgConnection,
-    hole_id: i32,
-) -> Result<PolyVectorData, DieselError> {
-    let polygons = polygons::table
-        .filter(polygons::hole_id.eq(hole_id))
-        .load::<Polygon>(conn)?;
-
-    let vectors = vectors::table
-        .filter(vectors::hole_id.eq(hole_id))
-        .load::<Vector>(conn)?;
-    Ok(PolyVectorData { polygons, vectors })
-}
-
 fn fetch_hole_from_hole_id(conn: &mut PgConnection, hole_id: i32) -> Result<HoleData, DieselError> {
     let hole = holes::table
         .filter(holes::hole_id.eq(hole_id))
@@ -65,15 +55,42 @@ fn fetch_hole_from_hole_id(conn: &mut PgConnection, hole_id: i32) -> Result<Hole
         .filter(vectors::hole_id.eq(hole_id))
         .load::<Vector>(conn)?;
 
-    Ok(HoleData {
+    let route = fetch_hole_route(conn, hole_id)?;
+    let elevation_row = hole_elevations::table
+        .find(hole_id)
+        .select(HoleElevationRow::as_select())
+        .first(conn)
+        .optional()?;
+
+    let mut hole_data = HoleData {
         hole,
         polygons,
         vectors,
-    })
+        route,
+        elevation: None,
+    };
+    hole_data.elevation = elevation_row.map(|row| HoleElevation::new(row, &hole_data));
+    Ok(hole_data)
+}
+
+fn fetch_hole_route(conn: &mut PgConnection, hole_id: i32) -> Result<Option<HoleRoute>, DieselError> {
+    let Some(row) = hole_routes::table
+        .find(hole_id)
+        .select(HoleRouteRow::as_select())
+        .first(conn)
+        .optional()?
+    else {
+        return Ok(None);
+    };
+    let points = hole_route_points::table
+        .filter(hole_route_points::hole_id.eq(hole_id))
+        .order(hole_route_points::point_index.asc())
+        .select(HoleRoutePoint::as_select())
+        .load(conn)?;
+    Ok(Some(HoleRoute::from_rows(row, points)))
 }
 
-fn generate_svg(hole_data: &HoleData) -> String {
-    let hole = &hole_data.hole;
+pub fn generate_svg(hole_data: &HoleData) -> String {
     let width = 1000.0;
     let height = 1000.0;
 
@@ -105,7 +122,7 @@ fn generate_svg(hole_data: &HoleData) -> String {
             .unwrap_or_else(|| "Unknown".to_string());
         surface_groups
             .entry(surface_type)
-            .or_insert_with(Vec::new)
+            .or_default()
             .push(vec![polygon]);
     }
 
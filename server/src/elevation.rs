@@ -0,0 +1,597 @@
+use std::fs::{self, File};
+use std::io::{BufReader, Read, Seek};
+use std::path::{Path, PathBuf};
+
+use chrono::{NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tiff::decoder::{Decoder, DecodingResult};
+use tiff::tags::Tag;
+
+use crate::geometry::{distance_yards, from_local, green_shape, group_shapes, to_local, LatLong, Shape};
+use crate::models::{HoleData, HoleElevationRow};
+use crate::routing::LANDING_DISTANCES;
+
+const FEET_PER_METRE: f64 = 3.28084;
+/// SRTM marks missing samples with this value.
+const HGT_VOID: i16 = -32768;
+/// GeoTIFF keys saying whether a file is in latitude and longitude, and
+/// whether its tie point is a pixel corner or centre.
+const GT_MODEL_TYPE_KEY: u16 = 1024;
+const GT_RASTER_TYPE_KEY: u16 = 1025;
+const MODEL_TYPE_GEOGRAPHIC: u16 = 2;
+const RASTER_PIXEL_IS_POINT: u16 = 2;
+
+/// One elevation raster in latitude and longitude, heights in metres.
+pub struct ElevationModel {
+    pub name: String,
+    /// Centre of the top-left pixel.
+    west: f64,
+    north: f64,
+    /// Degrees per pixel.
+    x_step: f64,
+    y_step: f64,
+    width: usize,
+    height: usize,
+    /// Row-major from the north-west corner; NaN where there is no data.
+    metres: Vec<f32>,
+}
+
+impl ElevationModel {
+    /// Loads an SRTM `.hgt` tile or a single-band GeoTIFF in WGS 84.
+    pub fn load(path: &Path) -> Result<ElevationModel, String> {
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(str::to_ascii_lowercase)
+            .unwrap_or_default();
+        let model = match extension.as_str() {
+            "hgt" => ElevationModel::from_hgt(path),
+            "tif" | "tiff" => ElevationModel::from_geotiff(path),
+            _ => Err("expected a .hgt or .tif file".to_string()),
+        };
+        model.map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    fn from_hgt(path: &Path) -> Result<ElevationModel, String> {
+        let name = path.file_stem().and_then(|s| s.to_str()).unwrap_or_default();
+        let bytes = fs::read(path).map_err(|e| e.to_string())?;
+        ElevationModel::read_hgt(name, &bytes)
+    }
+
+    /// SRTM tiles are named after their south-west corner, e.g. `N41W093`,
+    /// and hold big-endian 16-bit heights with the edges shared between tiles.
+    fn read_hgt(name: &str, bytes: &[u8]) -> Result<ElevationModel, String> {
+        let name = name.to_ascii_uppercase();
+        let parse = |s: &str| s.parse::<f64>().map_err(|_| format!("`{}` is not an SRTM tile name", name));
+        if name.len() != 7 {
+            return Err(format!("`{}` is not an SRTM tile name", name));
+        }
+        let south = match &name[0..1] {
+            "N" => parse(&name[1..3])?,
+            "S" => -parse(&name[1..3])?,
+            _ => return Err(format!("`{}` is not an SRTM tile name", name)),
+        };
+        let west = match &name[3..4] {
+            "E" => parse(&name[4..7])?,
+            "W" => -parse(&name[4..7])?,
+            _ => return Err(format!("`{}` is not an SRTM tile name", name)),
+        };
+
+        let size = ((bytes.len() / 2) as f64).sqrt() as usize;
+        if size < 2 || size * size * 2 != bytes.len() {
+            return Err(format!("{} bytes is not a square tile", bytes.len()));
+        }
+        let metres = bytes
+            .chunks_exact(2)
+            .map(|pair| match i16::from_be_bytes([pair[0], pair[1]]) {
+                HGT_VOID => f32::NAN,
+                height => f32::from(height),
+            })
+            .collect();
+        let step = 1.0 / (size - 1) as f64;
+        Ok(ElevationModel {
+            name,
+            west,
+            north: south + 1.0,
+            x_step: step,
+            y_step: step,
+            width: size,
+            height: size,
+            metres,
+        })
+    }
+
+    fn from_geotiff(path: &Path) -> Result<ElevationModel, String> {
+        let name = path.file_name().and_then(|s| s.to_str()).unwrap_or_default();
+        let file = File::open(path).map_err(|e| e.to_string())?;
+        ElevationModel::read_geotiff(name, BufReader::new(file))
+    }
+
+    fn read_geotiff(name: &str, reader: impl Read + Seek) -> Result<ElevationModel, String> {
+        let mut decoder = Decoder::new(reader).map_err(|e| e.to_string())?;
+        let (width, height) = decoder.dimensions().map_err(|e| e.to_string())?;
+        if width < 2 || height < 2 {
+            return Err(format!("{}x{} is too small to interpolate over", width, height));
+        }
+
+        let scale = decoder
+            .get_tag_f64_vec(Tag::ModelPixelScaleTag)
+            .map_err(|_| "no pixel scale; only north-up GeoTIFFs are supported".to_string())?;
+        let tie = decoder
+            .get_tag_f64_vec(Tag::ModelTiepointTag)
+            .map_err(|_| "no tie point; only north-up GeoTIFFs are supported".to_string())?;
+        if scale.len() < 2 || tie.len() < 6 {
+            return Err("malformed georeferencing tags".to_string());
+        }
+
+        // The key directory is a header of four values then four per key.
+        let keys = decoder.get_tag_u16_vec(Tag::GeoKeyDirectoryTag).unwrap_or_default();
+        let key = |id: u16| {
+            keys.chunks_exact(4)
+                .skip(1)
+                .find(|entry| entry[0] == id && entry[1] == 0)
+                .map(|entry| entry[3])
+        };
+        if key(GT_MODEL_TYPE_KEY).is_some_and(|model| model != MODEL_TYPE_GEOGRAPHIC) {
+            return Err("only GeoTIFFs in latitude and longitude are supported".to_string());
+        }
+        let half_pixel = if key(GT_RASTER_TYPE_KEY) == Some(RASTER_PIXEL_IS_POINT) { 0.0 } else { 0.5 };
+        let nodata: Option<f32> = decoder
+            .get_tag_ascii_string(Tag::GdalNodata)
+            .ok()
+            .and_then(|value| value.trim_end_matches('\0').trim().parse().ok());
+
+        let image = decoder.read_image().map_err(|e| e.to_string())?;
+        let mut metres: Vec<f32> = match image {
+            DecodingResult::U8(data) => data.into_iter().map(f32::from).collect(),
+            DecodingResult::U16(data) => data.into_iter().map(f32::from).collect(),
+            DecodingResult::I8(data) => data.into_iter().map(f32::from).collect(),
+            DecodingResult::I16(data) => data.into_iter().map(f32::from).collect(),
+            DecodingResult::U32(data) => data.into_iter().map(|v| v as f32).collect(),
+            DecodingResult::I32(data) => data.into_iter().map(|v| v as f32).collect(),
+            DecodingResult::F32(data) => data,
+            DecodingResult::F64(data) => data.into_iter().map(|v| v as f32).collect(),
+            _ => return Err("unsupported sample format".to_string()),
+        };
+        let (width, height) = (width as usize, height as usize);
+        if metres.len() != width * height {
+            return Err("only single-band GeoTIFFs are supported".to_string());
+        }
+        if let Some(nodata) = nodata {
+            for value in metres.iter_mut().filter(|value| **value == nodata) {
+                *value = f32::NAN;
+            }
+        }
+
+        Ok(ElevationModel {
+            name: name.to_string(),
+            west: tie[3] + (half_pixel - tie[0]) * scale[0],
+            north: tie[4] - (half_pixel - tie[1]) * scale[1],
+            x_step: scale[0],
+            y_step: scale[1],
+            width,
+            height,
+            metres,
+        })
+    }
+
+    fn at(&self, column: usize, row: usize) -> Option<f64> {
+        let value = *self.metres.get(row * self.width + column)?;
+        (!value.is_nan()).then_some(f64::from(value))
+    }
+
+    /// Height in metres at `point`, interpolated between the four nearest
+    /// samples.
+    pub fn sample(&self, point: LatLong) -> Option<f64> {
+        let x = (point.long - self.west) / self.x_step;
+        let y = (self.north - point.lat) / self.y_step;
+        if x < 0.0 || y < 0.0 || x > (self.width - 1) as f64 || y > (self.height - 1) as f64 {
+            return None;
+        }
+        let (column, row) = ((x as usize).min(self.width - 2), (y as usize).min(self.height - 2));
+        let (fx, fy) = (x - column as f64, y - row as f64);
+        let top = self.at(column, row)? * (1.0 - fx) + self.at(column + 1, row)? * fx;
+        let bottom = self.at(column, row + 1)? * (1.0 - fx) + self.at(column + 1, row + 1)? * fx;
+        Some(top * (1.0 - fy) + bottom * fy)
+    }
+}
+
+/// Every elevation model loaded for an import, searched in order.
+pub struct Terrain {
+    pub models: Vec<ElevationModel>,
+}
+
+impl Terrain {
+    /// Loads the given files, and every `.hgt` and `.tif` file in the given
+    /// directories.
+    pub fn load(paths: &[PathBuf]) -> Result<Terrain, String> {
+        let mut models = Vec::new();
+        for path in paths {
+            if path.is_dir() {
+                let mut files: Vec<PathBuf> = fs::read_dir(path)
+                    .map_err(|e| format!("{}: {}", path.display(), e))?
+                    .filter_map(|entry| Some(entry.ok()?.path()))
+                    .filter(|file| {
+                        file.extension()
+                            .and_then(|e| e.to_str())
+                            .is_some_and(|e| ["hgt", "tif", "tiff"].contains(&e.to_ascii_lowercase().as_str()))
+                    })
+                    .collect();
+                files.sort();
+                for file in files {
+                    models.push(ElevationModel::load(&file)?);
+                }
+            } else {
+                models.push(ElevationModel::load(path)?);
+            }
+        }
+        if models.is_empty() {
+            return Err("no elevation files found".to_string());
+        }
+        Ok(Terrain { models })
+    }
+
+    pub fn elevation_ft(&self, point: LatLong) -> Option<f64> {
+        self.models
+            .iter()
+            .find_map(|model| model.sample(point))
+            .map(|metres| metres * FEET_PER_METRE)
+    }
+
+    pub fn source(&self) -> String {
+        self.models
+            .iter()
+            .map(|model| model.name.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// Heights sampled on a regular grid over a hole, in feet.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ElevationGrid {
+    /// South-west corner.
+    pub origin: LatLong,
+    pub spacing_yards: f64,
+    pub columns: usize,
+    pub rows: usize,
+    /// Row-major from the south-west corner, east then north.
+    pub elevations_ft: Vec<Option<f64>>,
+}
+
+/// How steep the ground is and which way it falls.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct Slope {
+    pub percent: f64,
+    /// Compass bearing the ground falls toward.
+    pub falls_toward_deg: f64,
+}
+
+impl Slope {
+    /// From the rise per yard east and north.
+    fn from_gradient(east: f64, north: f64) -> Slope {
+        Slope {
+            percent: east.hypot(north) * 100.0,
+            falls_toward_deg: (-east).atan2(-north).to_degrees().rem_euclid(360.0),
+        }
+    }
+}
+
+impl ElevationGrid {
+    /// Samples `terrain` over the box around `points`, `margin_yards` wider
+    /// on every side.
+    pub fn sample(terrain: &Terrain, points: &[LatLong], spacing_yards: f64, margin_yards: f64) -> Option<ElevationGrid> {
+        let anchor = *points.first()?;
+        let local: Vec<(f64, f64)> = points.iter().map(|p| to_local(anchor, *p)).collect();
+        let min_x = local.iter().map(|p| p.0).fold(f64::MAX, f64::min) - margin_yards;
+        let min_y = local.iter().map(|p| p.1).fold(f64::MAX, f64::min) - margin_yards;
+        let max_x = local.iter().map(|p| p.0).fold(f64::MIN, f64::max) + margin_yards;
+        let max_y = local.iter().map(|p| p.1).fold(f64::MIN, f64::max) + margin_yards;
+
+        let origin = from_local(anchor, (min_x, min_y));
+        let columns = ((max_x - min_x) / spacing_yards).ceil() as usize + 1;
+        let rows = ((max_y - min_y) / spacing_yards).ceil() as usize + 1;
+        let elevations_ft = (0..rows)
+            .flat_map(|row| (0..columns).map(move |column| (column, row)))
+            .map(|(column, row)| {
+                let point = from_local(origin, (column as f64 * spacing_yards, row as f64 * spacing_yards));
+                terrain.elevation_ft(point)
+            })
+            .collect();
+        Some(ElevationGrid {
+            origin,
+            spacing_yards,
+            columns,
+            rows,
+            elevations_ft,
+        })
+    }
+
+    pub fn from_row(row: &HoleElevationRow) -> ElevationGrid {
+        ElevationGrid {
+            origin: LatLong::new(row.origin_lat, row.origin_long),
+            spacing_yards: row.spacing_yards,
+            columns: row.grid_columns as usize,
+            rows: row.grid_rows as usize,
+            elevations_ft: row.elevations_ft.clone(),
+        }
+    }
+
+    pub fn to_row(&self, hole_id: i32, source: &str) -> HoleElevationRow {
+        HoleElevationRow {
+            hole_id,
+            source: source.to_string(),
+            origin_lat: self.origin.lat,
+            origin_long: self.origin.long,
+            spacing_yards: self.spacing_yards,
+            grid_columns: self.columns as i32,
+            grid_rows: self.rows as i32,
+            elevations_ft: self.elevations_ft.clone(),
+            sampled_at: Utc::now().naive_utc(),
+        }
+    }
+
+    fn at(&self, column: usize, row: usize) -> Option<f64> {
+        *self.elevations_ft.get(row * self.columns + column)?
+    }
+
+    fn node(&self, column: usize, row: usize) -> LatLong {
+        from_local(
+            self.origin,
+            (column as f64 * self.spacing_yards, row as f64 * self.spacing_yards),
+        )
+    }
+
+    pub fn elevation_ft(&self, point: LatLong) -> Option<f64> {
+        if self.columns < 2 || self.rows < 2 {
+            return None;
+        }
+        let (x, y) = to_local(self.origin, point);
+        let (x, y) = (x / self.spacing_yards, y / self.spacing_yards);
+        if x < 0.0 || y < 0.0 || x > (self.columns - 1) as f64 || y > (self.rows - 1) as f64 {
+            return None;
+        }
+        let (column, row) = ((x as usize).min(self.columns - 2), (y as usize).min(self.rows - 2));
+        let (fx, fy) = (x - column as f64, y - row as f64);
+        let south = self.at(column, row)? * (1.0 - fx) + self.at(column + 1, row)? * fx;
+        let north = self.at(column, row + 1)? * (1.0 - fx) + self.at(column + 1, row + 1)? * fx;
+        Some(south * (1.0 - fy) + north * fy)
+    }
+
+    /// How far `to` sits above `from`; negative when it's below.
+    pub fn change_ft(&self, from: LatLong, to: LatLong) -> Option<f64> {
+        Some(self.elevation_ft(to)? - self.elevation_ft(from)?)
+    }
+
+    /// The slope across one grid spacing either side of `point`.
+    pub fn slope_at(&self, point: LatLong) -> Option<Slope> {
+        let step = self.spacing_yards;
+        let height = |dx: f64, dy: f64| {
+            let (x, y) = to_local(self.origin, point);
+            self.elevation_ft(from_local(self.origin, (x + dx, y + dy)))
+        };
+        let east = (height(step, 0.0)? - height(-step, 0.0)?) / (2.0 * step) / 3.0;
+        let north = (height(0.0, step)? - height(0.0, -step)?) / (2.0 * step) / 3.0;
+        Some(Slope::from_gradient(east, north))
+    }
+
+    /// The plane best fitting the grid inside `shape`, or the slope at its
+    /// centre when the shape is too small to hold three samples.
+    pub fn slope_over(&self, shape: &Shape) -> Option<Slope> {
+        let centre = shape.centroid()?;
+        let samples: Vec<(f64, f64, f64)> = (0..self.rows)
+            .flat_map(|row| (0..self.columns).map(move |column| (column, row)))
+            .filter_map(|(column, row)| {
+                let node = self.node(column, row);
+                let feet = self.at(column, row)?;
+                shape.contains(node).then(|| {
+                    let (x, y) = to_local(centre, node);
+                    (x, y, feet / 3.0)
+                })
+            })
+            .collect();
+        if samples.len() < 3 {
+            return self.slope_at(centre);
+        }
+
+        // Least squares for z = a x + b y + c, centred so c drops out.
+        let n = samples.len() as f64;
+        let mean = samples
+            .iter()
+            .fold((0.0, 0.0, 0.0), |(sx, sy, sz), (x, y, z)| (sx + x, sy + y, sz + z));
+        let (mx, my, mz) = (mean.0 / n, mean.1 / n, mean.2 / n);
+        let (mut sxx, mut syy, mut sxy, mut sxz, mut syz) = (0.0, 0.0, 0.0, 0.0, 0.0);
+        for (x, y, z) in &samples {
+            let (dx, dy, dz) = (x - mx, y - my, z - mz);
+            sxx += dx * dx;
+            syy += dy * dy;
+            sxy += dx * dy;
+            sxz += dx * dz;
+            syz += dy * dz;
+        }
+        let determinant = sxx * syy - sxy * sxy;
+        if determinant.abs() < f64::EPSILON {
+            return self.slope_at(centre);
+        }
+        let east = (sxz * syy - syz * sxy) / determinant;
+        let north = (syz * sxx - sxz * sxy) / determinant;
+        Some(Slope::from_gradient(east, north))
+    }
+}
+
+/// The ground where a drive of `distance_yards` down the playing line lands.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LandingSlope {
+    pub distance_yards: f64,
+    pub elevation_ft: Option<f64>,
+    pub slope: Option<Slope>,
+}
+
+/// A hole's terrain, and what it means for playing it.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct HoleElevation {
+    /// The elevation files the samples came from.
+    pub source: String,
+    pub sampled_at: NaiveDateTime,
+    /// At the back tee.
+    pub tee_elevation_ft: Option<f64>,
+    pub green_elevation_ft: Option<f64>,
+    /// Positive when the green sits above the back tee.
+    pub tee_to_green_ft: Option<f64>,
+    pub green_slope: Option<Slope>,
+    pub landing_slopes: Vec<LandingSlope>,
+    pub grid: ElevationGrid,
+}
+
+impl HoleElevation {
+    /// Works out the tee-to-green change and slopes from a hole's stored
+    /// samples.
+    pub fn new(row: HoleElevationRow, hole_data: &HoleData) -> HoleElevation {
+        let grid = ElevationGrid::from_row(&row);
+        let flag = hole_data.hole.flag();
+        let shapes = group_shapes(&hole_data.polygons);
+        let green = green_shape(&shapes, flag);
+
+        let back_tee = flag.and_then(|flag| {
+            hole_data
+                .vectors
+                .iter()
+                .filter(|v| v.vector_type.as_deref() != Some("Flag"))
+                .filter_map(|v| Some((LatLong::new(v.lat?, v.long?), v.elevation_ft)))
+                .max_by(|a, b| distance_yards(a.0, flag).total_cmp(&distance_yards(b.0, flag)))
+        });
+        let tee_elevation_ft = back_tee.and_then(|(tee, feet)| feet.or_else(|| grid.elevation_ft(tee)));
+        let green_elevation_ft = green
+            .and_then(Shape::centroid)
+            .and_then(|centre| grid.elevation_ft(centre))
+            .or(hole_data.hole.flag_elevation_ft);
+
+        let landing_slopes = hole_data
+            .route
+            .as_ref()
+            .map(|route| {
+                LANDING_DISTANCES
+                    .iter()
+                    .filter_map(|distance| Some((*distance, route.point_at(*distance)?)))
+                    .map(|(distance, point)| LandingSlope {
+                        distance_yards: distance,
+                        elevation_ft: grid.elevation_ft(point),
+                        slope: grid.slope_at(point),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        HoleElevation {
+            source: row.source,
+            sampled_at: row.sampled_at,
+            tee_elevation_ft,
+            green_elevation_ft,
+            tee_to_green_ft: tee_elevation_ft.zip(green_elevation_ft).map(|(tee, green)| green - tee),
+            green_slope: green.and_then(|green| grid.slope_over(green)),
+            landing_slopes,
+            grid,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use tiff::encoder::colortype::{self, ColorType};
+    use tiff::encoder::{TiffEncoder, TiffValue};
+
+    use super::*;
+
+    /// A 3x3 SRTM tile over N41W093, half a degree between samples.
+    fn hgt(heights: [i16; 9]) -> ElevationModel {
+        let bytes: Vec<u8> = heights.iter().flat_map(|height| height.to_be_bytes()).collect();
+        ElevationModel::read_hgt("n41w093", &bytes).unwrap()
+    }
+
+    const HEIGHTS: [i16; 9] = [100, 110, 120, 200, 210, 220, 300, 310, 320];
+
+    /// A GeoTIFF with its top-left pixel centred on 41.75N 92.75W, half a
+    /// degree between pixels.
+    fn geotiff<C: ColorType>(width: u32, height: u32, data: &[C::Inner], model_type: u16) -> Vec<u8>
+    where
+        [C::Inner]: TiffValue,
+    {
+        let mut cursor = Cursor::new(Vec::new());
+        let mut encoder = TiffEncoder::new(&mut cursor).unwrap();
+        let mut image = encoder.new_image::<C>(width, height).unwrap();
+        let tags = image.encoder();
+        tags.write_tag(Tag::ModelPixelScaleTag, &[0.5, 0.5, 0.0][..]).unwrap();
+        tags.write_tag(Tag::ModelTiepointTag, &[0.0, 0.0, 0.0, -93.0, 42.0, 0.0][..]).unwrap();
+        tags.write_tag(Tag::GeoKeyDirectoryTag, &[1u16, 1, 0, 1, GT_MODEL_TYPE_KEY, 0, 1, model_type][..])
+            .unwrap();
+        tags.write_tag(Tag::GdalNodata, "-9999").unwrap();
+        image.write_data(data).unwrap();
+        cursor.into_inner()
+    }
+
+    #[test]
+    fn samples_hgt_up_to_the_tile_edges() {
+        let model = hgt(HEIGHTS);
+        assert_eq!(model.name, "N41W093");
+        assert_eq!(model.sample(LatLong::new(42.0, -93.0)), Some(100.0));
+        assert_eq!(model.sample(LatLong::new(41.0, -92.0)), Some(320.0));
+        assert_eq!(model.sample(LatLong::new(41.5, -92.0)), Some(220.0));
+        assert_eq!(model.sample(LatLong::new(41.75, -92.75)), Some(155.0));
+        assert_eq!(model.sample(LatLong::new(40.999, -92.5)), None);
+        assert_eq!(model.sample(LatLong::new(41.5, -91.999)), None);
+    }
+
+    #[test]
+    fn hgt_voids_have_no_height() {
+        let mut heights = HEIGHTS;
+        heights[0] = HGT_VOID;
+        let model = hgt(heights);
+        assert_eq!(model.sample(LatLong::new(41.75, -92.75)), None);
+        assert_eq!(model.sample(LatLong::new(41.25, -92.25)), Some(265.0));
+
+        // The next model fills in where the first has a void.
+        let terrain = Terrain {
+            models: vec![model, hgt(HEIGHTS)],
+        };
+        assert_eq!(terrain.elevation_ft(LatLong::new(41.75, -92.75)), Some(155.0 * FEET_PER_METRE));
+    }
+
+    #[test]
+    fn rejects_malformed_hgt() {
+        assert!(ElevationModel::read_hgt("N41W093", &[0; 10]).is_err());
+        assert!(ElevationModel::read_hgt("X41W093", &[0; 18]).is_err());
+        assert!(ElevationModel::read_hgt("N41W93", &[0; 18]).is_err());
+    }
+
+    #[test]
+    fn samples_geotiff_around_nodata() {
+        let data = [1.0, 2.0, 3.0, 4.0, 5.0, -9999.0];
+        let bytes = geotiff::<colortype::Gray32Float>(3, 2, &data, 2);
+        let model = ElevationModel::read_geotiff("test.tif", Cursor::new(bytes)).unwrap();
+        assert_eq!(model.sample(LatLong::new(41.75, -92.75)), Some(1.0));
+        assert_eq!(model.sample(LatLong::new(41.5, -92.5)), Some(3.0));
+        assert_eq!(model.sample(LatLong::new(41.25, -91.75)), None);
+        assert_eq!(model.sample(LatLong::new(41.8, -92.75)), None);
+    }
+
+    #[test]
+    fn rejects_unsupported_geotiffs() {
+        let projected = geotiff::<colortype::Gray32Float>(2, 2, &[1.0; 4], 1);
+        assert!(ElevationModel::read_geotiff("projected.tif", Cursor::new(projected)).is_err());
+        let rgb = geotiff::<colortype::RGB8>(2, 2, &[1; 12], 2);
+        assert!(ElevationModel::read_geotiff("rgb.tif", Cursor::new(rgb)).is_err());
+        let single = geotiff::<colortype::Gray16>(1, 1, &[1], 2);
+        assert!(ElevationModel::read_geotiff("single.tif", Cursor::new(single)).is_err());
+        assert!(ElevationModel::read_geotiff("empty.tif", Cursor::new(Vec::new())).is_err());
+
+        let mut cursor = Cursor::new(Vec::new());
+        TiffEncoder::new(&mut cursor)
+            .unwrap()
+            .write_image::<colortype::Gray16>(2, 2, &[1; 4])
+            .unwrap();
+        let plain = ElevationModel::read_geotiff("plain.tif", Cursor::new(cursor.into_inner()));
+        assert!(plain.is_err_and(|e| e.contains("north-up")));
+    }
+}
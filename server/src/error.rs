@@ -0,0 +1,81 @@
+use std::fmt;
+
+use actix_web::http::StatusCode;
+use actix_web::{HttpResponse, ResponseError};
+use diesel::r2d2;
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
+use serde_json::json;
+
+/// Error returned by API handlers. Renders as `{"error": "..."}` with the
+/// matching status code.
+#[derive(Debug)]
+pub enum ApiError {
+    BadRequest(String),
+    Unauthorized(String),
+    Forbidden,
+    NotFound,
+    Conflict(String),
+    Internal(String),
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiError::BadRequest(message) => write!(f, "{}", message),
+            ApiError::Unauthorized(message) => write!(f, "{}", message),
+            ApiError::Forbidden => write!(f, "forbidden"),
+            ApiError::NotFound => write!(f, "not found"),
+            ApiError::Conflict(message) => write!(f, "{}", message),
+            ApiError::Internal(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+impl ResponseError for ApiError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            ApiError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            ApiError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            ApiError::Forbidden => StatusCode::FORBIDDEN,
+            ApiError::NotFound => StatusCode::NOT_FOUND,
+            ApiError::Conflict(_) => StatusCode::CONFLICT,
+            ApiError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        if let ApiError::Internal(message) = self {
+            log::error!("{}", message);
+        }
+        HttpResponse::build(self.status_code()).json(json!({ "error": self.to_string() }))
+    }
+}
+
+impl From<DieselError> for ApiError {
+    fn from(error: DieselError) -> Self {
+        match error {
+            DieselError::NotFound => ApiError::NotFound,
+            DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, info) => {
+                ApiError::Conflict(info.message().to_string())
+            }
+            DieselError::DatabaseError(DatabaseErrorKind::ForeignKeyViolation, info) => {
+                ApiError::BadRequest(info.message().to_string())
+            }
+            e => ApiError::Internal(format!("Database error: {}", e)),
+        }
+    }
+}
+
+impl From<r2d2::PoolError> for ApiError {
+    fn from(error: r2d2::PoolError) -> Self {
+        ApiError::Internal(format!("couldn't get db connection from pool: {}", error))
+    }
+}
+
+impl From<actix_web::error::BlockingError> for ApiError {
+    fn from(error: actix_web::error::BlockingError) -> Self {
+        ApiError::Internal(format!("Server error: {}", error))
+    }
+}
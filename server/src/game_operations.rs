@@ -0,0 +1,251 @@
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::error::ApiError;
+use crate::games::{self, GameFormat, GameHole, GameLedger, GamePlayer, GameSetup};
+use crate::handicap_operations::{self, RoundHandicapQuery};
+use crate::hole_operations;
+use crate::models::{HoleScore, Round, RoundGame, RoundPlayer};
+use crate::round_operations::authorize_round;
+use crate::schema::{round_games, round_players};
+use crate::tee_set_operations;
+
+const MAX_STAKE: i32 = 10_000;
+
+/// Playing handicaps by player id, for one allowance.
+type PlayingHandicaps = HashMap<i32, Option<i32>>;
+
+#[derive(Deserialize, Debug)]
+pub struct NewGameInput {
+    pub format: GameFormat,
+    /// Units per skin, bet or point. Defaults to 1.
+    pub stake: Option<i32>,
+    /// Share of each course handicap used, in percent. Defaults to the
+    /// format's recommended allowance.
+    pub allowance: Option<f64>,
+    /// Whether tied skins carry over. Defaults to true.
+    pub carry_overs: Option<bool>,
+    /// Presses automatically when a side is this many holes down.
+    pub press_after: Option<i32>,
+    /// Player ids on each side for Nassau, match play and best ball. Two
+    /// players can leave them out to play each other.
+    #[serde(default)]
+    pub side_a: Vec<i32>,
+    #[serde(default)]
+    pub side_b: Vec<i32>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = round_games)]
+struct NewRoundGame {
+    round_id: i32,
+    format: String,
+    stake: i32,
+    allowance_percent: f64,
+    carry_overs: bool,
+    press_after: Option<i32>,
+    side_a: Vec<i32>,
+    side_b: Vec<i32>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct GameResult {
+    pub game: RoundGame,
+    pub ledger: GameLedger,
+}
+
+fn fetch_round_players(conn: &mut PgConnection, round: &Round) -> Result<Vec<RoundPlayer>, ApiError> {
+    Ok(RoundPlayer::belonging_to(round)
+        .order(round_players::id.asc())
+        .select(RoundPlayer::as_select())
+        .load(conn)?)
+}
+
+/// Checks the sides of a team game, pairing off two players when none
+/// were given.
+fn resolve_sides(
+    players: &[RoundPlayer],
+    side_a: Vec<i32>,
+    side_b: Vec<i32>,
+) -> Result<(Vec<i32>, Vec<i32>), ApiError> {
+    if side_a.is_empty() && side_b.is_empty() {
+        return match players {
+            [a, b] => Ok((vec![a.id], vec![b.id])),
+            _ => Err(ApiError::BadRequest(
+                "choose the players on each side with side_a and side_b".to_string(),
+            )),
+        };
+    }
+    if side_a.is_empty() || side_a.len() != side_b.len() {
+        return Err(ApiError::BadRequest("both sides must have the same number of players".to_string()));
+    }
+    for (i, player_id) in side_a.iter().chain(&side_b).enumerate() {
+        if !players.iter().any(|player| player.id == *player_id) {
+            return Err(ApiError::BadRequest(format!("player {} is not in this round", player_id)));
+        }
+        if side_a.iter().chain(&side_b).take(i).any(|earlier| earlier == player_id) {
+            return Err(ApiError::BadRequest(format!("player {} is on a side twice", player_id)));
+        }
+    }
+    Ok((side_a, side_b))
+}
+
+fn game_result(
+    conn: &mut PgConnection,
+    round: &Round,
+    user_id: i32,
+    game: RoundGame,
+    handicaps: &mut HashMap<u64, PlayingHandicaps>,
+) -> Result<GameResult, ApiError> {
+    let format = GameFormat::parse(&game.format)
+        .ok_or_else(|| ApiError::Internal(format!("unknown game format `{}`", game.format)))?;
+
+    // Playing handicaps depend only on the allowance, so games sharing one
+    // share the lookup.
+    let playing = match handicaps.entry(game.allowance_percent.to_bits()) {
+        Entry::Occupied(entry) => entry.into_mut(),
+        Entry::Vacant(entry) => {
+            let query = RoundHandicapQuery {
+                allowance: Some(game.allowance_percent),
+            };
+            let players = handicap_operations::round_handicaps(conn, round.id, user_id, query)?.players;
+            entry.insert(players.into_iter().map(|player| (player.player_id, player.playing_handicap)).collect())
+        }
+    };
+
+    let round_players = fetch_round_players(conn, round)?;
+    let in_game = |player: &&RoundPlayer| {
+        !format.has_sides() || game.side_a.contains(&player.id) || game.side_b.contains(&player.id)
+    };
+    let players: Vec<GamePlayer> = round_players
+        .iter()
+        .filter(in_game)
+        .map(|player| GamePlayer {
+            player_id: player.id,
+            name: player.name.clone(),
+            playing_handicap: playing.get(&player.id).copied().flatten(),
+        })
+        .collect();
+
+    let tee_set = tee_set_operations::round_tee_set(conn, round)?;
+    let holes: Vec<GameHole> = hole_operations::played_holes(conn, round.course_id, tee_set.as_ref())?
+        .iter()
+        .enumerate()
+        .map(|(i, hole)| GameHole {
+            hole_id: hole.hole_id,
+            number: hole.number.unwrap_or(i as i32 + 1),
+            par: hole.par,
+            rank: hole.rank().unwrap_or(i as i32 + 1),
+        })
+        .collect();
+
+    let scores: HashMap<(i32, i32), i32> = HoleScore::belonging_to(round)
+        .select(HoleScore::as_select())
+        .load(conn)?
+        .into_iter()
+        .map(|score| ((score.player_id, score.hole_id), score.strokes))
+        .collect();
+
+    let setup = GameSetup {
+        format,
+        stake: game.stake,
+        carry_overs: game.carry_overs,
+        press_after: game.press_after,
+        side_a: game.side_a.clone(),
+        side_b: game.side_b.clone(),
+    };
+    let ledger = games::play(&setup, &holes, &players, &scores);
+    Ok(GameResult { game, ledger })
+}
+
+pub fn create_game(
+    conn: &mut PgConnection,
+    round_id: i32,
+    user_id: i32,
+    input: NewGameInput,
+) -> Result<GameResult, ApiError> {
+    let round = authorize_round(conn, round_id, user_id)?;
+    let players = fetch_round_players(conn, &round)?;
+    let format = input.format;
+
+    let stake = input.stake.unwrap_or(1);
+    if !(1..=MAX_STAKE).contains(&stake) {
+        return Err(ApiError::BadRequest(format!("stake must be between 1 and {}", MAX_STAKE)));
+    }
+    let allowance = input.allowance.unwrap_or_else(|| format.default_allowance());
+    if !(0.0..=100.0).contains(&allowance) {
+        return Err(ApiError::BadRequest("allowance must be between 0 and 100".to_string()));
+    }
+    if let Some(press_after) = input.press_after {
+        if !format.has_presses() {
+            return Err(ApiError::BadRequest("presses only apply to Nassau and match play".to_string()));
+        }
+        if press_after < 1 {
+            return Err(ApiError::BadRequest("press_after must be at least 1".to_string()));
+        }
+    }
+
+    let (side_a, side_b) = if format.has_sides() {
+        resolve_sides(&players, input.side_a, input.side_b)?
+    } else if !input.side_a.is_empty() || !input.side_b.is_empty() {
+        return Err(ApiError::BadRequest(format!("{} is played without sides", format.as_str())));
+    } else {
+        (Vec::new(), Vec::new())
+    };
+    if format == GameFormat::Skins && players.len() < 2 {
+        return Err(ApiError::BadRequest("skins needs at least two players".to_string()));
+    }
+
+    let game = diesel::insert_into(round_games::table)
+        .values(&NewRoundGame {
+            round_id,
+            format: format.as_str().to_string(),
+            stake,
+            allowance_percent: allowance,
+            carry_overs: input.carry_overs.unwrap_or(true),
+            press_after: input.press_after,
+            side_a,
+            side_b,
+        })
+        .returning(RoundGame::as_returning())
+        .get_result(conn)?;
+    game_result(conn, &round, user_id, game, &mut HashMap::new())
+}
+
+/// Every game on a round, each played over the scores entered so far.
+pub fn list_games(conn: &mut PgConnection, round_id: i32, user_id: i32) -> Result<Vec<GameResult>, ApiError> {
+    let round = authorize_round(conn, round_id, user_id)?;
+    let round_games = RoundGame::belonging_to(&round)
+        .order(round_games::id.asc())
+        .select(RoundGame::as_select())
+        .load(conn)?;
+
+    let mut handicaps = HashMap::new();
+    round_games
+        .into_iter()
+        .map(|game| game_result(conn, &round, user_id, game, &mut handicaps))
+        .collect()
+}
+
+fn fetch_game(conn: &mut PgConnection, round: &Round, game_id: i32) -> Result<RoundGame, ApiError> {
+    Ok(RoundGame::belonging_to(round)
+        .filter(round_games::id.eq(game_id))
+        .select(RoundGame::as_select())
+        .first(conn)?)
+}
+
+pub fn game(conn: &mut PgConnection, round_id: i32, game_id: i32, user_id: i32) -> Result<GameResult, ApiError> {
+    let round = authorize_round(conn, round_id, user_id)?;
+    let game = fetch_game(conn, &round, game_id)?;
+    game_result(conn, &round, user_id, game, &mut HashMap::new())
+}
+
+pub fn delete_game(conn: &mut PgConnection, round_id: i32, game_id: i32, user_id: i32) -> Result<(), ApiError> {
+    let round = authorize_round(conn, round_id, user_id)?;
+    let game = fetch_game(conn, &round, game_id)?;
+    diesel::delete(round_games::table.find(game.id)).execute(conn)?;
+    Ok(())
+}
@@ -0,0 +1,842 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::handicap;
+
+/// Formats a group can play alongside a round.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GameFormat {
+    /// Each hole is worth a skin to the outright low net score.
+    Skins,
+    /// Separate matches on the front nine, back nine and whole round.
+    Nassau,
+    /// Points for each hole against par on net score.
+    Stableford,
+    /// One match over the round, side against side.
+    MatchPlay,
+    /// Stroke play on the better net score of each side.
+    BestBall,
+}
+
+impl GameFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GameFormat::Skins => "skins",
+            GameFormat::Nassau => "nassau",
+            GameFormat::Stableford => "stableford",
+            GameFormat::MatchPlay => "match_play",
+            GameFormat::BestBall => "best_ball",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<GameFormat> {
+        match value {
+            "skins" => Some(GameFormat::Skins),
+            "nassau" => Some(GameFormat::Nassau),
+            "stableford" => Some(GameFormat::Stableford),
+            "match_play" => Some(GameFormat::MatchPlay),
+            "best_ball" => Some(GameFormat::BestBall),
+            _ => None,
+        }
+    }
+
+    /// Handicap allowance in percent, as recommended under the World
+    /// Handicap System for the nearest equivalent format.
+    pub fn default_allowance(&self) -> f64 {
+        match self {
+            GameFormat::Skins | GameFormat::Nassau | GameFormat::MatchPlay => 100.0,
+            GameFormat::Stableford => 95.0,
+            GameFormat::BestBall => 85.0,
+        }
+    }
+
+    /// Whether the game is two sides against each other rather than every
+    /// player for themselves.
+    pub fn has_sides(&self) -> bool {
+        matches!(self, GameFormat::Nassau | GameFormat::MatchPlay | GameFormat::BestBall)
+    }
+
+    /// Whether a side that falls behind can press.
+    pub fn has_presses(&self) -> bool {
+        matches!(self, GameFormat::Nassau | GameFormat::MatchPlay)
+    }
+
+    /// Head-to-head formats give strokes off the lowest handicap in the
+    /// game; stroke formats give everyone their full playing handicap.
+    fn off_the_low(&self) -> bool {
+        matches!(self, GameFormat::Skins | GameFormat::Nassau | GameFormat::MatchPlay)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Side {
+    A,
+    B,
+}
+
+#[derive(Debug, Clone)]
+pub struct GameHole {
+    pub hole_id: i32,
+    pub number: i32,
+    pub par: Option<i32>,
+    /// Stroke allocation order, 1 being the hardest hole.
+    pub rank: i32,
+}
+
+#[derive(Debug, Clone)]
+pub struct GamePlayer {
+    pub player_id: i32,
+    pub name: String,
+    /// `None` for a player without a Handicap Index, who plays off scratch.
+    pub playing_handicap: Option<i32>,
+}
+
+#[derive(Debug, Clone)]
+pub struct GameSetup {
+    pub format: GameFormat,
+    /// Units won or lost per skin, bet or Stableford point.
+    pub stake: i32,
+    /// Whether a tied skin rolls onto the next hole rather than being lost.
+    pub carry_overs: bool,
+    /// A side this many holes down in a bet starts a new one over the rest
+    /// of that bet's holes.
+    pub press_after: Option<i32>,
+    pub side_a: Vec<i32>,
+    pub side_b: Vec<i32>,
+}
+
+impl GameSetup {
+    fn side_of(&self, player_id: i32) -> Option<Side> {
+        if self.side_a.contains(&player_id) {
+            Some(Side::A)
+        } else if self.side_b.contains(&player_id) {
+            Some(Side::B)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct NetScore {
+    pub player_id: i32,
+    pub gross: i32,
+    /// Handicap strokes received on the hole.
+    pub received: i32,
+    pub net: i32,
+    /// Stableford points, when the hole has a par.
+    pub points: Option<i32>,
+}
+
+/// The better net score of each side on a hole.
+#[derive(Serialize, Debug, Clone, Copy)]
+pub struct SideScores {
+    pub a: i32,
+    pub b: i32,
+}
+
+#[derive(Serialize, Debug)]
+pub struct MatchState {
+    pub bet: String,
+    /// Holes side A is up; negative when side B leads.
+    pub up: i32,
+}
+
+#[derive(Serialize, Debug, Clone, Copy)]
+pub struct Payout {
+    pub player_id: i32,
+    pub amount: i32,
+}
+
+#[derive(Serialize, Debug)]
+pub struct LedgerHole {
+    pub hole_id: i32,
+    pub number: i32,
+    pub par: Option<i32>,
+    pub rank: i32,
+    pub scores: Vec<NetScore>,
+    pub sides: Option<SideScores>,
+    /// Players who won the hole, empty when it was halved.
+    pub winners: Vec<i32>,
+    /// Skins won on the hole, including any carried onto it.
+    pub skins: i32,
+    /// Skins carried onto the next hole.
+    pub carried: i32,
+    /// Where each running bet stands after the hole.
+    pub matches: Vec<MatchState>,
+    /// Money that changed hands on the hole, in stake units.
+    pub payouts: Vec<Payout>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Bet {
+    pub name: String,
+    /// The bet's first and last holes in playing order.
+    pub first_hole: i32,
+    pub last_hole: i32,
+    /// Side A's lead, in holes for matches and strokes for best ball.
+    pub up: i32,
+    pub holes_played: i32,
+    pub winner: Option<Side>,
+    pub settled: bool,
+    pub result: String,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Standing {
+    pub player_id: i32,
+    pub name: String,
+    pub side: Option<Side>,
+    /// Handicap strokes received over the round in this game.
+    pub strokes: i32,
+    pub gross: i32,
+    pub net: i32,
+    pub points: i32,
+    pub skins: i32,
+    pub balance: i32,
+}
+
+#[derive(Serialize, Debug)]
+pub struct GameLedger {
+    /// Holes every player in the game has a score for.
+    pub holes_played: usize,
+    /// Holes in the order they were played, from the first scored hole
+    /// after an unscored one, e.g. the 10th for a back-nine start.
+    pub holes: Vec<LedgerHole>,
+    pub bets: Vec<Bet>,
+    pub standings: Vec<Standing>,
+    /// Skins still riding on the next hole.
+    pub carried_over: i32,
+}
+
+pub fn stableford_points(net: i32, par: i32) -> i32 {
+    (2 + par - net).max(0)
+}
+
+/// A bet over a run of holes, by index into the round's holes in the order
+/// they're played.
+struct MatchBet {
+    name: String,
+    /// The bet this one was pressed from, or its own name.
+    segment: String,
+    holes: Vec<usize>,
+    up: i32,
+    played: i32,
+    pressed: bool,
+    winner: Option<Side>,
+    settled: bool,
+    result: String,
+}
+
+impl MatchBet {
+    fn new(name: &str, holes: Vec<usize>) -> MatchBet {
+        MatchBet {
+            name: name.to_string(),
+            segment: name.to_string(),
+            holes,
+            up: 0,
+            played: 0,
+            pressed: false,
+            winner: None,
+            settled: false,
+            result: "Not started".to_string(),
+        }
+    }
+
+    fn covers(&self, index: usize) -> bool {
+        self.holes.contains(&index)
+    }
+
+    /// Holes still to be played, counting any skipped for want of scores.
+    fn remaining(&self) -> i32 {
+        self.holes.len() as i32 - self.played
+    }
+}
+
+/// The two sides of a team game, named after their players.
+struct Teams<'a> {
+    setup: &'a GameSetup,
+    labels: [String; 2],
+}
+
+impl Teams<'_> {
+    fn label(&self, side: Side) -> &str {
+        match side {
+            Side::A => &self.labels[0],
+            Side::B => &self.labels[1],
+        }
+    }
+
+    fn leader(up: i32) -> Option<Side> {
+        match up.signum() {
+            1 => Some(Side::A),
+            -1 => Some(Side::B),
+            _ => None,
+        }
+    }
+
+    /// Each player on the winning side collects the stake from one on the
+    /// losing side.
+    fn settle(&self, winner: Side, payouts: &mut Vec<Payout>) {
+        let (winners, losers) = match winner {
+            Side::A => (&self.setup.side_a, &self.setup.side_b),
+            Side::B => (&self.setup.side_b, &self.setup.side_a),
+        };
+        for player_id in winners {
+            payouts.push(Payout {
+                player_id: *player_id,
+                amount: self.setup.stake,
+            });
+        }
+        for player_id in losers {
+            payouts.push(Payout {
+                player_id: *player_id,
+                amount: -self.setup.stake,
+            });
+        }
+    }
+
+    /// Plays a hole in a match bet and settles it once it's won or out of
+    /// holes.
+    fn play_match(&self, bet: &mut MatchBet, result: i32, payouts: &mut Vec<Payout>) {
+        bet.up += result;
+        bet.played += 1;
+        let remaining = bet.remaining();
+        let leader = Teams::leader(bet.up);
+        if bet.up.abs() > remaining || remaining == 0 {
+            bet.settled = true;
+            bet.winner = leader;
+            bet.result = match leader {
+                Some(side) if remaining > 0 => format!("{} won {}&{}", self.label(side), bet.up.abs(), remaining),
+                Some(side) => format!("{} won {} up", self.label(side), bet.up.abs()),
+                None => "Halved".to_string(),
+            };
+            if let Some(side) = leader {
+                self.settle(side, payouts);
+            }
+        } else {
+            bet.result = match leader {
+                Some(side) => format!("{} {} up thru {}", self.label(side), bet.up.abs(), bet.played),
+                None => format!("All square thru {}", bet.played),
+            };
+        }
+    }
+}
+
+/// Names a side after its players, e.g. "Ann & Bo".
+fn side_label(players: &[GamePlayer], side: &[i32], fallback: &str) -> String {
+    let names: Vec<&str> = side
+        .iter()
+        .filter_map(|id| players.iter().find(|player| player.player_id == *id))
+        .map(|player| player.name.as_str())
+        .collect();
+    if names.is_empty() {
+        return fallback.to_string();
+    }
+    names.join(" & ")
+}
+
+/// Where play started: the first scored hole after an unscored one, or the
+/// first hole when that has scores or nothing does.
+fn starting_index(complete: &[bool]) -> usize {
+    let count = complete.len();
+    if complete.first().copied().unwrap_or(true) {
+        return 0;
+    }
+    (0..count)
+        .find(|i| complete[*i] && !complete[(i + count - 1) % count])
+        .unwrap_or(0)
+}
+
+/// Plays a game over the holes every player has finished. `scores` holds
+/// gross strokes by player and hole id; `players` are the players in the
+/// game, and `holes` are in number order. Holes without every score are
+/// skipped, so rounds started on the 10th or in a shotgun play out in the
+/// order they were played.
+pub fn play(setup: &GameSetup, holes: &[GameHole], players: &[GamePlayer], scores: &HashMap<(i32, i32), i32>) -> GameLedger {
+    let handicaps: Vec<i32> = players.iter().map(|player| player.playing_handicap.unwrap_or(0)).collect();
+    let low = if setup.format.off_the_low() { handicaps.iter().copied().min().unwrap_or(0) } else { 0 };
+    let strokes: Vec<i32> = handicaps.iter().map(|handicap| handicap - low).collect();
+
+    let teams = Teams {
+        setup,
+        labels: [
+            side_label(players, &setup.side_a, "Side A"),
+            side_label(players, &setup.side_b, "Side B"),
+        ],
+    };
+    let count = holes.len();
+    let complete: Vec<bool> = holes
+        .iter()
+        .map(|hole| {
+            players
+                .iter()
+                .all(|player| scores.contains_key(&(player.player_id, hole.hole_id)))
+        })
+        .collect();
+    let start = starting_index(&complete);
+    let order: Vec<usize> = (0..count).map(|i| (start + i) % count).collect();
+    let segment = |keep: &dyn Fn(usize) -> bool| order.iter().copied().filter(|i| keep(*i)).collect();
+
+    let mut bets: Vec<MatchBet> = match setup.format {
+        GameFormat::Nassau if count > 1 => vec![
+            MatchBet::new("Front", segment(&|i| i < count / 2)),
+            MatchBet::new("Back", segment(&|i| i >= count / 2)),
+            MatchBet::new("Overall", order.clone()),
+        ],
+        GameFormat::Nassau | GameFormat::MatchPlay | GameFormat::BestBall if count > 0 => {
+            vec![MatchBet::new(
+                if setup.format == GameFormat::BestBall { "Best ball" } else { "Match" },
+                order.clone(),
+            )]
+        }
+        _ => Vec::new(),
+    };
+
+    let mut ledger = Vec::new();
+    let mut carried = 0;
+    for &index in order.iter().filter(|index| complete[**index]) {
+        let hole = &holes[index];
+        let gross: Vec<i32> = players
+            .iter()
+            .map(|player| scores[&(player.player_id, hole.hole_id)])
+            .collect();
+
+        let net_scores: Vec<NetScore> = players
+            .iter()
+            .zip(&gross)
+            .zip(&strokes)
+            .map(|((player, gross), strokes)| {
+                let received = handicap::strokes_received(*strokes, hole.rank, count as i32);
+                let net = gross - received;
+                NetScore {
+                    player_id: player.player_id,
+                    gross: *gross,
+                    received,
+                    net,
+                    points: hole.par.map(|par| stableford_points(net, par)),
+                }
+            })
+            .collect();
+
+        let mut entry = LedgerHole {
+            hole_id: hole.hole_id,
+            number: hole.number,
+            par: hole.par,
+            rank: hole.rank,
+            scores: Vec::new(),
+            sides: None,
+            winners: Vec::new(),
+            skins: 0,
+            carried: 0,
+            matches: Vec::new(),
+            payouts: Vec::new(),
+        };
+
+        match setup.format {
+            GameFormat::Skins => {
+                let low = net_scores.iter().map(|score| score.net).min().unwrap_or(0);
+                let low_scores: Vec<&NetScore> = net_scores.iter().filter(|score| score.net == low).collect();
+                let at_stake = carried + 1;
+                if let [winner] = low_scores.as_slice() {
+                    entry.winners.push(winner.player_id);
+                    entry.skins = at_stake;
+                    for score in &net_scores {
+                        let amount = if score.player_id == winner.player_id {
+                            at_stake * setup.stake * (players.len() as i32 - 1)
+                        } else {
+                            -at_stake * setup.stake
+                        };
+                        entry.payouts.push(Payout {
+                            player_id: score.player_id,
+                            amount,
+                        });
+                    }
+                    carried = 0;
+                } else {
+                    carried = if setup.carry_overs { at_stake } else { 0 };
+                }
+                entry.carried = carried;
+            }
+            GameFormat::Stableford => {
+                if net_scores.iter().all(|score| score.points.is_some()) {
+                    let points: Vec<i32> = net_scores.iter().map(|score| score.points.unwrap_or(0)).collect();
+                    let total: i32 = points.iter().sum();
+                    let best = points.iter().copied().max().unwrap_or(0);
+                    if points.iter().any(|p| *p != best) {
+                        entry.winners = net_scores
+                            .iter()
+                            .filter(|score| score.points == Some(best))
+                            .map(|score| score.player_id)
+                            .collect();
+                    }
+                    // Each player settles the points difference with every
+                    // other player.
+                    for (score, points) in net_scores.iter().zip(&points) {
+                        entry.payouts.push(Payout {
+                            player_id: score.player_id,
+                            amount: setup.stake * (points * players.len() as i32 - total),
+                        });
+                    }
+                }
+            }
+            GameFormat::Nassau | GameFormat::MatchPlay | GameFormat::BestBall => {
+                let best = |side: Side| {
+                    net_scores
+                        .iter()
+                        .filter(|score| setup.side_of(score.player_id) == Some(side))
+                        .map(|score| score.net)
+                        .min()
+                        .unwrap_or(0)
+                };
+                let sides = SideScores {
+                    a: best(Side::A),
+                    b: best(Side::B),
+                };
+                entry.sides = Some(sides);
+                if let Some(side) = Teams::leader(sides.b - sides.a) {
+                    entry.winners = net_scores
+                        .iter()
+                        .filter(|score| setup.side_of(score.player_id) == Some(side))
+                        .map(|score| score.player_id)
+                        .collect();
+                }
+
+                if setup.format == GameFormat::BestBall {
+                    if let Some(bet) = bets.first_mut() {
+                        bet.up += sides.b - sides.a;
+                        bet.played += 1;
+                        let leader = Teams::leader(bet.up);
+                        if bet.remaining() == 0 {
+                            bet.settled = true;
+                            bet.winner = leader;
+                            bet.result = match leader {
+                                Some(side) => format!("{} won by {}", teams.label(side), bet.up.abs()),
+                                None => "Tied".to_string(),
+                            };
+                            if let Some(side) = leader {
+                                teams.settle(side, &mut entry.payouts);
+                            }
+                        } else {
+                            bet.result = match leader {
+                                Some(side) => format!("{} {} ahead thru {}", teams.label(side), bet.up.abs(), bet.played),
+                                None => format!("Level thru {}", bet.played),
+                            };
+                        }
+                        entry.matches.push(MatchState {
+                            bet: bet.name.clone(),
+                            up: bet.up,
+                        });
+                    }
+                } else {
+                    let result = (sides.b - sides.a).signum();
+                    let mut presses: Vec<MatchBet> = Vec::new();
+                    // Bets per segment, counting the original, numbers the presses.
+                    let mut bet_counts: HashMap<String, usize> = HashMap::new();
+                    for bet in &bets {
+                        *bet_counts.entry(bet.segment.clone()).or_default() += 1;
+                    }
+                    for bet in bets.iter_mut().filter(|bet| bet.covers(index) && !bet.settled) {
+                        teams.play_match(bet, result, &mut entry.payouts);
+                        entry.matches.push(MatchState {
+                            bet: bet.name.clone(),
+                            up: bet.up,
+                        });
+                        if let Some(press_after) = setup.press_after {
+                            let rest = bet.holes.iter().position(|i| *i == index).map_or(&[][..], |at| &bet.holes[at + 1..]);
+                            if !bet.settled && !bet.pressed && bet.up.abs() >= press_after && !rest.is_empty() {
+                                bet.pressed = true;
+                                let count = bet_counts.entry(bet.segment.clone()).or_default();
+                                let mut press = MatchBet::new(&format!("{} press {}", bet.segment, count), rest.to_vec());
+                                press.segment = bet.segment.clone();
+                                *count += 1;
+                                presses.push(press);
+                            }
+                        }
+                    }
+                    bets.extend(presses);
+                }
+            }
+        }
+
+        // One payout per player for the hole, however many bets settled.
+        let mut payouts: Vec<Payout> = Vec::new();
+        for payout in entry.payouts.drain(..) {
+            match payouts.iter_mut().find(|p| p.player_id == payout.player_id) {
+                Some(existing) => existing.amount += payout.amount,
+                None => payouts.push(payout),
+            }
+        }
+        payouts.retain(|payout| payout.amount != 0);
+        entry.payouts = payouts;
+        entry.scores = net_scores;
+        ledger.push(entry);
+    }
+
+    let mut standings: Vec<Standing> = players
+        .iter()
+        .zip(&strokes)
+        .map(|(player, strokes)| Standing {
+            player_id: player.player_id,
+            name: player.name.clone(),
+            side: setup.side_of(player.player_id),
+            strokes: *strokes,
+            gross: 0,
+            net: 0,
+            points: 0,
+            skins: 0,
+            balance: 0,
+        })
+        .collect();
+    let positions: HashMap<i32, usize> = players.iter().enumerate().map(|(i, player)| (player.player_id, i)).collect();
+    for hole in &ledger {
+        for score in &hole.scores {
+            let standing = &mut standings[positions[&score.player_id]];
+            standing.gross += score.gross;
+            standing.net += score.net;
+            standing.points += score.points.unwrap_or(0);
+        }
+        if let [winner] = hole.winners.as_slice() {
+            standings[positions[winner]].skins += hole.skins;
+        }
+        for payout in &hole.payouts {
+            standings[positions[&payout.player_id]].balance += payout.amount;
+        }
+    }
+
+    GameLedger {
+        holes_played: ledger.len(),
+        holes: ledger,
+        bets: bets
+            .into_iter()
+            .map(|bet| Bet {
+                name: bet.name,
+                first_hole: holes[bet.holes[0]].number,
+                last_hole: holes[bet.holes[bet.holes.len() - 1]].number,
+                up: bet.up,
+                holes_played: bet.played,
+                winner: bet.winner,
+                settled: bet.settled,
+                result: bet.result,
+            })
+            .collect(),
+        standings,
+        carried_over: carried,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn holes(count: i32) -> Vec<GameHole> {
+        (1..=count)
+            .map(|number| GameHole {
+                hole_id: number,
+                number,
+                par: Some(4),
+                rank: number,
+            })
+            .collect()
+    }
+
+    fn players(handicaps: &[Option<i32>]) -> Vec<GamePlayer> {
+        let names = ["Al", "Bo", "Cy", "Di"];
+        handicaps
+            .iter()
+            .enumerate()
+            .map(|(i, playing_handicap)| GamePlayer {
+                player_id: i as i32 + 1,
+                name: names[i].to_string(),
+                playing_handicap: *playing_handicap,
+            })
+            .collect()
+    }
+
+    fn setup(format: GameFormat, side_a: Vec<i32>, side_b: Vec<i32>) -> GameSetup {
+        GameSetup {
+            format,
+            stake: 1,
+            carry_overs: true,
+            press_after: None,
+            side_a,
+            side_b,
+        }
+    }
+
+    /// Gross scores for each player, hole by hole from hole `first`.
+    fn scores(first: i32, cards: &[&[i32]]) -> HashMap<(i32, i32), i32> {
+        let mut scores = HashMap::new();
+        for (i, card) in cards.iter().enumerate() {
+            for (hole, strokes) in card.iter().enumerate() {
+                scores.insert((i as i32 + 1, first + hole as i32), *strokes);
+            }
+        }
+        scores
+    }
+
+    fn balances(ledger: &GameLedger) -> Vec<i32> {
+        ledger.standings.iter().map(|standing| standing.balance).collect()
+    }
+
+    #[test]
+    fn tied_skins_carry_over() {
+        let players = players(&[None, None, None]);
+        let scores = scores(1, &[&[4, 4, 4, 4], &[4, 4, 5, 4], &[5, 4, 3, 4]]);
+        let mut game = setup(GameFormat::Skins, Vec::new(), Vec::new());
+
+        let ledger = play(&game, &holes(4), &players, &scores);
+        let carried: Vec<i32> = ledger.holes.iter().map(|hole| hole.carried).collect();
+        assert_eq!(carried, [1, 2, 0, 1]);
+        assert_eq!(ledger.holes[2].winners, [3]);
+        assert_eq!(ledger.holes[2].skins, 3);
+        assert_eq!(ledger.standings[2].skins, 3);
+        assert_eq!(balances(&ledger), [-3, -3, 6]);
+        assert_eq!(ledger.carried_over, 1);
+
+        game.carry_overs = false;
+        let ledger = play(&game, &holes(4), &players, &scores);
+        assert_eq!(ledger.holes[2].skins, 1);
+        assert_eq!(balances(&ledger), [-1, -1, 2]);
+        assert_eq!(ledger.carried_over, 0);
+    }
+
+    #[test]
+    fn skins_are_won_on_net_scores() {
+        // Bo gets a stroke on the hardest hole, so their 5 beats Al's 5 there.
+        let players = players(&[Some(0), Some(1)]);
+        let ledger = play(
+            &setup(GameFormat::Skins, Vec::new(), Vec::new()),
+            &holes(2),
+            &players,
+            &scores(1, &[&[5, 4], &[5, 4]]),
+        );
+        assert_eq!(ledger.holes[0].winners, [2]);
+        assert_eq!(ledger.holes[0].scores[1].net, 4);
+        assert_eq!(ledger.carried_over, 1);
+    }
+
+    #[test]
+    fn nassau_presses_when_two_down() {
+        let mut game = setup(GameFormat::Nassau, vec![1], vec![2]);
+        game.press_after = Some(2);
+        let mut bo = [4; 18];
+        bo[0] = 3;
+        bo[1] = 3;
+        let ledger = play(&game, &holes(18), &players(&[None, None]), &scores(1, &[&[4; 18], &bo]));
+
+        let bets: Vec<(&str, i32, i32, i32, Option<Side>)> = ledger
+            .bets
+            .iter()
+            .map(|bet| (bet.name.as_str(), bet.first_hole, bet.last_hole, bet.up, bet.winner))
+            .collect();
+        assert_eq!(
+            bets,
+            [
+                ("Front", 1, 9, -2, Some(Side::B)),
+                ("Back", 10, 18, 0, None),
+                ("Overall", 1, 18, -2, Some(Side::B)),
+                ("Front press 1", 3, 9, 0, None),
+                ("Overall press 1", 3, 18, 0, None),
+            ]
+        );
+        assert!(ledger.bets.iter().all(|bet| bet.settled));
+        assert_eq!(ledger.bets[0].result, "Bo won 2&1");
+        assert_eq!(ledger.bets[3].result, "Halved");
+        assert_eq!(balances(&ledger), [-2, 2]);
+    }
+
+    #[test]
+    fn dormie_match_goes_on_until_it_is_won() {
+        let game = setup(GameFormat::MatchPlay, vec![1], vec![2]);
+        let players = players(&[None, None]);
+        let mut al = [4; 18];
+        al[0] = 3;
+        al[1] = 3;
+
+        // Two up with two to play: dormie, but not over.
+        let ledger = play(&game, &holes(18), &players, &scores(1, &[&al[..16], &[4; 16]]));
+        assert_eq!(ledger.holes_played, 16);
+        assert!(!ledger.bets[0].settled);
+        assert_eq!(ledger.bets[0].result, "Al 2 up thru 16");
+        assert_eq!(balances(&ledger), [0, 0]);
+
+        // Halving the 17th wins it.
+        let ledger = play(&game, &holes(18), &players, &scores(1, &[&al[..17], &[4; 17]]));
+        assert!(ledger.bets[0].settled);
+        assert_eq!(ledger.bets[0].result, "Al won 2&1");
+        assert_eq!(balances(&ledger), [1, -1]);
+
+        // Losing the last two halves it.
+        let mut bo = [4; 18];
+        bo[16] = 3;
+        bo[17] = 3;
+        let ledger = play(&game, &holes(18), &players, &scores(1, &[&al, &bo]));
+        assert_eq!(ledger.bets[0].winner, None);
+        assert_eq!(ledger.bets[0].result, "Halved");
+    }
+
+    #[test]
+    fn stableford_points_on_net_score() {
+        assert_eq!(stableford_points(6, 4), 0);
+        assert_eq!(stableford_points(5, 4), 1);
+        assert_eq!(stableford_points(4, 4), 2);
+        assert_eq!(stableford_points(3, 4), 3);
+        assert_eq!(stableford_points(2, 5), 5);
+
+        // Al's stroke on the first hole makes a gross par a net birdie.
+        let ledger = play(
+            &setup(GameFormat::Stableford, Vec::new(), Vec::new()),
+            &holes(2),
+            &players(&[Some(1), None]),
+            &scores(1, &[&[4, 5], &[4, 4]]),
+        );
+        let points: Vec<Vec<Option<i32>>> = ledger
+            .holes
+            .iter()
+            .map(|hole| hole.scores.iter().map(|score| score.points).collect())
+            .collect();
+        assert_eq!(points, [[Some(3), Some(2)], [Some(1), Some(2)]]);
+        assert_eq!(ledger.holes[0].winners, [1]);
+        assert_eq!(ledger.standings[0].points, 4);
+        assert_eq!(balances(&ledger), [0, 0]);
+    }
+
+    #[test]
+    fn best_ball_counts_the_better_score_of_each_side() {
+        let ledger = play(
+            &setup(GameFormat::BestBall, vec![1, 2], vec![3, 4]),
+            &holes(3),
+            &players(&[None, None, None, None]),
+            &scores(1, &[&[3, 5, 4], &[6, 4, 5], &[4, 4, 4], &[4, 6, 4]]),
+        );
+        let sides: Vec<(i32, i32)> = ledger
+            .holes
+            .iter()
+            .filter_map(|hole| hole.sides.map(|sides| (sides.a, sides.b)))
+            .collect();
+        assert_eq!(sides, [(3, 4), (4, 4), (4, 4)]);
+        assert_eq!(ledger.bets[0].up, 1);
+        assert_eq!(ledger.bets[0].result, "Al & Bo won by 1");
+        assert_eq!(balances(&ledger), [1, 1, -1, -1]);
+    }
+
+    #[test]
+    fn rounds_started_on_the_tenth_play_from_there() {
+        let game = setup(GameFormat::Nassau, vec![1], vec![2]);
+        let ledger = play(&game, &holes(18), &players(&[None, None]), &scores(10, &[&[3, 4, 4], &[4; 3]]));
+
+        let numbers: Vec<i32> = ledger.holes.iter().map(|hole| hole.number).collect();
+        assert_eq!(numbers, [10, 11, 12]);
+        let bets: Vec<(&str, i32, i32, i32)> = ledger
+            .bets
+            .iter()
+            .map(|bet| (bet.name.as_str(), bet.first_hole, bet.last_hole, bet.holes_played))
+            .collect();
+        assert_eq!(bets, [("Front", 1, 9, 0), ("Back", 10, 18, 3), ("Overall", 10, 9, 3)]);
+        assert_eq!(ledger.bets[1].result, "Al 1 up thru 3");
+    }
+}
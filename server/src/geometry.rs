@@ -0,0 +1,298 @@
+use chrono::{Duration, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+
+use crate::models::Polygon;
+
+const EARTH_RADIUS_YARDS: f64 = 6_371_008.8 * 1.093_613_3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LatLong {
+    pub lat: f64,
+    pub long: f64,
+}
+
+impl LatLong {
+    pub fn new(lat: f64, long: f64) -> Self {
+        LatLong { lat, long }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.lat.is_finite()
+            && self.long.is_finite()
+            && (-90.0..=90.0).contains(&self.lat)
+            && (-180.0..=180.0).contains(&self.long)
+    }
+}
+
+/// Where a ball lies, derived from the surface polygons under it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Lie {
+    Tee,
+    Fairway,
+    Rough,
+    Sand,
+    Green,
+    Water,
+    Recovery,
+}
+
+impl Lie {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Lie::Tee => "tee",
+            Lie::Fairway => "fairway",
+            Lie::Rough => "rough",
+            Lie::Sand => "sand",
+            Lie::Green => "green",
+            Lie::Water => "water",
+            Lie::Recovery => "recovery",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Lie> {
+        match value.to_lowercase().as_str() {
+            "tee" => Some(Lie::Tee),
+            "fairway" => Some(Lie::Fairway),
+            "rough" => Some(Lie::Rough),
+            "sand" => Some(Lie::Sand),
+            "green" => Some(Lie::Green),
+            "water" => Some(Lie::Water),
+            "recovery" => Some(Lie::Recovery),
+            _ => None,
+        }
+    }
+
+    /// Maps a Golfbert surface type onto a lie.
+    pub fn from_surface_type(surface_type: &str) -> Lie {
+        match surface_type.to_lowercase().as_str() {
+            "green" => Lie::Green,
+            "fairway" => Lie::Fairway,
+            "sand" | "bunker" => Lie::Sand,
+            "water" => Lie::Water,
+            "woods" | "trees" => Lie::Recovery,
+            "tee box" | "teebox" | "tee" => Lie::Tee,
+            _ => Lie::Rough,
+        }
+    }
+
+    /// Which surface wins where polygons overlap, e.g. a bunker cut into a
+    /// fairway or a fairway inside a rough outline.
+    fn precedence(&self) -> u8 {
+        match self {
+            Lie::Green => 6,
+            Lie::Sand => 5,
+            Lie::Water => 4,
+            Lie::Tee => 3,
+            Lie::Fairway => 2,
+            Lie::Recovery => 1,
+            Lie::Rough => 0,
+        }
+    }
+}
+
+/// The lie at `point`. Anything outside the mapped surfaces is rough.
+pub fn lie_at(shapes: &[Shape], point: LatLong) -> Lie {
+    shapes
+        .iter()
+        .filter(|shape| shape.contains(point))
+        .map(|shape| Lie::from_surface_type(&shape.surface_type))
+        .max_by_key(Lie::precedence)
+        .unwrap_or(Lie::Rough)
+}
+
+/// One polygon on a hole, rebuilt from its per-vertex rows in `polygons`.
+#[derive(Debug, Clone, Serialize)]
+pub struct Shape {
+    pub surface_type: String,
+    pub points: Vec<LatLong>,
+}
+
+impl Shape {
+    /// Area in square yards, using a flat projection around the first vertex.
+    pub fn area_sq_yards(&self) -> f64 {
+        let Some(origin) = self.points.first() else {
+            return 0.0;
+        };
+        let local: Vec<(f64, f64)> = self.points.iter().map(|p| to_local(*origin, *p)).collect();
+        ring_area(&local).abs()
+    }
+
+    /// Area-weighted centre, falling back to the vertex average for
+    /// degenerate rings.
+    pub fn centroid(&self) -> Option<LatLong> {
+        let origin = *self.points.first()?;
+        let local: Vec<(f64, f64)> = self.points.iter().map(|p| to_local(origin, *p)).collect();
+        let area = ring_area(&local);
+        if area.abs() < f64::EPSILON {
+            let n = local.len() as f64;
+            let (x, y) = local.iter().fold((0.0, 0.0), |(sx, sy), (x, y)| (sx + x, sy + y));
+            return Some(from_local(origin, (x / n, y / n)));
+        }
+
+        let (mut cx, mut cy) = (0.0, 0.0);
+        for i in 0..local.len() {
+            let (x1, y1) = local[i];
+            let (x2, y2) = local[(i + 1) % local.len()];
+            let cross = x1 * y2 - x2 * y1;
+            cx += (x1 + x2) * cross;
+            cy += (y1 + y2) * cross;
+        }
+        Some(from_local(origin, (cx / (6.0 * area), cy / (6.0 * area))))
+    }
+
+    pub fn contains(&self, point: LatLong) -> bool {
+        let mut inside = false;
+        let mut j = self.points.len().wrapping_sub(1);
+        for i in 0..self.points.len() {
+            let (a, b) = (self.points[i], self.points[j]);
+            if (a.lat > point.lat) != (b.lat > point.lat)
+                && point.long < (b.long - a.long) * (point.lat - a.lat) / (b.lat - a.lat) + a.long
+            {
+                inside = !inside;
+            }
+            j = i;
+        }
+        inside
+    }
+}
+
+/// Groups polygon vertex rows into shapes. Rows imported before
+/// `shape_index` existed fall back to splitting on a change of surface type.
+pub fn group_shapes(polygons: &[Polygon]) -> Vec<Shape> {
+    let mut rows: Vec<&Polygon> = polygons.iter().collect();
+    rows.sort_by_key(|p| p.id);
+
+    let mut shapes: Vec<Shape> = Vec::new();
+    let mut current_key: Option<(Option<i32>, String)> = None;
+
+    for row in rows {
+        let (Some(lat), Some(long)) = (row.lat, row.long) else {
+            continue;
+        };
+        let surface_type = row.surface_type.clone().unwrap_or_else(|| "Unknown".to_string());
+        let key = (row.shape_index, surface_type.clone());
+
+        if current_key.as_ref() != Some(&key) {
+            shapes.push(Shape {
+                surface_type,
+                points: Vec::new(),
+            });
+            current_key = Some(key);
+        }
+        if let Some(shape) = shapes.last_mut() {
+            shape.points.push(LatLong::new(lat, long));
+        }
+    }
+
+    shapes
+}
+
+/// The green the flag is on, or the largest green when the flag isn't
+/// inside one.
+pub fn green_shape(shapes: &[Shape], flag: Option<LatLong>) -> Option<&Shape> {
+    let greens = shapes
+        .iter()
+        .filter(|shape| Lie::from_surface_type(&shape.surface_type) == Lie::Green);
+    flag.and_then(|flag| greens.clone().find(|green| green.contains(flag)))
+        .or_else(|| greens.max_by(|a, b| a.area_sq_yards().total_cmp(&b.area_sq_yards())))
+}
+
+pub fn green_centre(shapes: &[Shape], flag: Option<LatLong>) -> Option<LatLong> {
+    green_shape(shapes, flag).and_then(Shape::centroid)
+}
+
+/// Where the line `origin + t * direction` is inside a ring of local points,
+/// as `(t0, t1)` spans in order of `t`.
+pub fn chords(ring: &[(f64, f64)], origin: (f64, f64), direction: (f64, f64)) -> Vec<(f64, f64)> {
+    let cross = |a: (f64, f64), b: (f64, f64)| a.0 * b.1 - a.1 * b.0;
+    let mut crossings = Vec::new();
+    for i in 0..ring.len() {
+        let (a, b) = (ring[i], ring[(i + 1) % ring.len()]);
+        let edge = (b.0 - a.0, b.1 - a.1);
+        let denominator = cross(direction, edge);
+        if denominator.abs() < f64::EPSILON {
+            continue;
+        }
+        let w = (a.0 - origin.0, a.1 - origin.1);
+        let s = cross(w, direction) / denominator;
+        if (0.0..1.0).contains(&s) {
+            crossings.push(cross(w, edge) / denominator);
+        }
+    }
+    crossings.sort_by(f64::total_cmp);
+    crossings.chunks_exact(2).map(|pair| (pair[0], pair[1])).collect()
+}
+
+/// Length of a route of great-circle segments, in yards.
+pub fn route_yards(points: &[LatLong]) -> f64 {
+    points
+        .windows(2)
+        .map(|pair| distance_yards(pair[0], pair[1]))
+        .fold(0.0, |sum, yards| sum + yards)
+}
+
+/// Great-circle distance in yards.
+pub fn distance_yards(a: LatLong, b: LatLong) -> f64 {
+    let (lat1, lat2) = (a.lat.to_radians(), b.lat.to_radians());
+    let d_lat = lat2 - lat1;
+    let d_long = (b.long - a.long).to_radians();
+    let h = (d_lat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (d_long / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_YARDS * h.sqrt().asin()
+}
+
+/// Projects `point` onto a flat plane centred on `origin`, returning
+/// (east, north) in yards. Accurate to well under a yard across a golf hole.
+pub fn to_local(origin: LatLong, point: LatLong) -> (f64, f64) {
+    let x = (point.long - origin.long).to_radians() * origin.lat.to_radians().cos() * EARTH_RADIUS_YARDS;
+    let y = (point.lat - origin.lat).to_radians() * EARTH_RADIUS_YARDS;
+    (x, y)
+}
+
+/// Inverse of [`to_local`].
+pub fn from_local(origin: LatLong, (x, y): (f64, f64)) -> LatLong {
+    let lat = origin.lat + (y / EARTH_RADIUS_YARDS).to_degrees();
+    let long = origin.long + (x / (EARTH_RADIUS_YARDS * origin.lat.to_radians().cos())).to_degrees();
+    LatLong::new(lat, long)
+}
+
+/// Mean solar time at `location` for a UTC time, an hour for every 15° of
+/// longitude. It's within an hour or two of the clocks almost everywhere, so
+/// the date it gives holds for the whole of a day's golf.
+pub fn local_time(location: LatLong, utc: NaiveDateTime) -> NaiveDateTime {
+    utc + Duration::seconds((location.long * 240.0).round() as i64)
+}
+
+/// Signed shoelace area of a closed ring given as local (x, y) points.
+pub fn ring_area(points: &[(f64, f64)]) -> f64 {
+    let n = points.len();
+    if n < 3 {
+        return 0.0;
+    }
+    let twice_area: f64 = (0..n)
+        .map(|i| {
+            let (x1, y1) = points[i];
+            let (x2, y2) = points[(i + 1) % n];
+            x1 * y2 - x2 * y1
+        })
+        .sum();
+    twice_area / 2.0
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    #[test]
+    fn local_time_follows_longitude() {
+        let utc = NaiveDate::from_ymd_opt(2026, 7, 1).unwrap().and_hms_opt(2, 0, 0).unwrap();
+        // The evening before in Iowa, midday in Sydney.
+        let iowa = local_time(LatLong::new(41.6, -93.0), utc);
+        assert_eq!(iowa.date(), NaiveDate::from_ymd_opt(2026, 6, 30).unwrap());
+        assert_eq!(iowa.format("%H:%M").to_string(), "19:48");
+        let sydney = local_time(LatLong::new(-33.9, 151.2), utc);
+        assert_eq!(sydney.format("%d %H:%M").to_string(), "01 12:04");
+    }
+}
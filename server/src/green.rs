@@ -0,0 +1,276 @@
+use serde::{Deserialize, Serialize};
+
+use crate::geometry::{chords, distance_yards, from_local, green_shape, group_shapes, to_local, LatLong, Shape};
+use crate::models::HoleData;
+
+/// Spacing of the cross-sections taken to find the widest part of a green.
+const WIDTH_STEP_YARDS: f64 = 1.0;
+/// Pin sheets are paced out at a yard a pace.
+pub const YARDS_PER_PACE: f64 = 1.0;
+
+type Point = (f64, f64);
+
+fn scale(a: Point, k: f64) -> Point {
+    (a.0 * k, a.1 * k)
+}
+
+fn add(a: Point, b: Point) -> Point {
+    (a.0 + b.0, a.1 + b.1)
+}
+
+/// The edge of the green a pin is paced from.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PinSide {
+    Left,
+    Right,
+}
+
+impl PinSide {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PinSide::Left => "left",
+            PinSide::Right => "right",
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Copy)]
+pub struct GreenPoint {
+    pub position: LatLong,
+    /// From the approach position.
+    pub distance_yards: f64,
+}
+
+/// Where the pin sits on the green. Front and back are measured along the
+/// line of play through the pin, left and right square to it as the player
+/// looks at the green.
+#[derive(Serialize, Debug, Clone, Copy)]
+pub struct PinPlacement {
+    pub position: LatLong,
+    pub distance_yards: f64,
+    pub from_front_yards: f64,
+    pub from_back_yards: f64,
+    pub from_left_yards: f64,
+    pub from_right_yards: f64,
+}
+
+#[derive(Serialize, Debug)]
+pub struct GreenDetail {
+    pub hole_id: i32,
+    pub approach_from: LatLong,
+    pub area_sq_yards: f64,
+    /// Front edge to back edge along the line of play through the centre.
+    pub depth_yards: f64,
+    /// Widest cross-section square to the line of play.
+    pub max_width_yards: f64,
+    pub front: GreenPoint,
+    pub centre: GreenPoint,
+    pub back: GreenPoint,
+    /// `None` when there is no pin or it isn't on the green.
+    pub pin: Option<PinPlacement>,
+}
+
+/// The chord of `ring` along `direction` that contains `origin`, or the one
+/// nearest to it.
+fn chord_through(ring: &[Point], origin: Point, direction: Point) -> Option<(f64, f64)> {
+    let gap = |(t0, t1): &(f64, f64)| t0.max(-t1).max(0.0);
+    chords(ring, origin, direction)
+        .into_iter()
+        .min_by(|a, b| gap(a).total_cmp(&gap(b)))
+}
+
+/// A green in local yards around its centre, with the line of play running
+/// from the approach position through the centre.
+struct LineOfPlay {
+    centre: LatLong,
+    ring: Vec<Point>,
+    forward: Point,
+    left: Point,
+}
+
+impl LineOfPlay {
+    fn new(green: &Shape, from: LatLong) -> Option<LineOfPlay> {
+        let centre = green.centroid()?;
+        let (dx, dy) = to_local(from, centre);
+        let length = dx.hypot(dy);
+        if length < f64::EPSILON {
+            return None;
+        }
+        let forward = (dx / length, dy / length);
+        Some(LineOfPlay {
+            centre,
+            ring: green.points.iter().map(|p| to_local(centre, *p)).collect(),
+            forward,
+            left: (-forward.1, forward.0),
+        })
+    }
+}
+
+/// Where a pin paced out from the front edge and one side is, walking on
+/// along the line of play through the centre and then across. `None` when
+/// the paces leave the green.
+pub fn pin_from_paces(green: &Shape, from: LatLong, front_paces: i32, side: PinSide, side_paces: i32) -> Option<LatLong> {
+    let line = LineOfPlay::new(green, from)?;
+    let (front, _) = chord_through(&line.ring, (0.0, 0.0), line.forward)?;
+    let station = scale(line.forward, front + f64::from(front_paces) * YARDS_PER_PACE);
+    let (right, left) = chord_through(&line.ring, station, line.left)?;
+    let across = f64::from(side_paces) * YARDS_PER_PACE;
+    let t = match side {
+        PinSide::Left => left - across,
+        PinSide::Right => right + across,
+    };
+    let pin = from_local(line.centre, add(station, scale(line.left, t)));
+    (right..=left).contains(&t).then_some(pin).filter(|pin| green.contains(*pin))
+}
+
+/// Green metrics seen from `from`, with the line of play running from there
+/// to the green's centre.
+pub fn analyse(hole_id: i32, green: &Shape, from: LatLong, pin: Option<LatLong>) -> Option<GreenDetail> {
+    let LineOfPlay {
+        centre,
+        ring,
+        forward,
+        left,
+    } = LineOfPlay::new(green, from)?;
+
+    let (front, back) = chord_through(&ring, (0.0, 0.0), forward)?;
+    let max_width = (0..=((back - front) / WIDTH_STEP_YARDS) as usize)
+        .map(|i| scale(forward, front + i as f64 * WIDTH_STEP_YARDS))
+        .filter_map(|station| {
+            let spans = chords(&ring, station, left);
+            Some(spans.last()?.1 - spans.first()?.0)
+        })
+        .fold(0.0, f64::max);
+
+    let point = |t: f64| {
+        let position = from_local(centre, scale(forward, t));
+        GreenPoint {
+            position,
+            distance_yards: distance_yards(from, position),
+        }
+    };
+
+    let pin = pin.filter(|pin| green.contains(*pin)).and_then(|pin| {
+        let local = to_local(centre, pin);
+        let (behind, beyond) = chord_through(&ring, local, forward)?;
+        let (right, left) = chord_through(&ring, local, left)?;
+        Some(PinPlacement {
+            position: pin,
+            distance_yards: distance_yards(from, pin),
+            from_front_yards: -behind,
+            from_back_yards: beyond,
+            from_left_yards: left,
+            from_right_yards: -right,
+        })
+    });
+
+    Some(GreenDetail {
+        hole_id,
+        approach_from: from,
+        area_sq_yards: green.area_sq_yards(),
+        depth_yards: back - front,
+        max_width_yards: max_width,
+        front: point(front),
+        centre: point(0.0),
+        back: point(back),
+        pin,
+    })
+}
+
+/// Where an approach is usually played from: the last turn of the hole's
+/// route, or the back tee when it hasn't been routed.
+pub fn approach_position(hole_data: &HoleData) -> Option<LatLong> {
+    if let Some(route) = &hole_data.route {
+        let line = route.playing_line();
+        if line.len() >= 2 {
+            return Some(line[line.len() - 2]);
+        }
+    }
+    let flag = hole_data.hole.flag()?;
+    hole_data
+        .tees()
+        .into_iter()
+        .max_by(|a, b| distance_yards(*a, flag).total_cmp(&distance_yards(*b, flag)))
+}
+
+/// Analyses the green of a hole, the one the flag is on when there are two.
+pub fn green_detail(hole_data: &HoleData, from: LatLong, pin: Option<LatLong>) -> Option<GreenDetail> {
+    let shapes = group_shapes(&hole_data.polygons);
+    let green = green_shape(&shapes, pin.or(hole_data.hole.flag()))?;
+    analyse(hole_data.hole.hole_id, green, from, pin)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CENTRE: LatLong = LatLong {
+        lat: 41.6,
+        long: -93.6,
+    };
+
+    /// A green 30 yards deep and 20 wide, played from 150 yards short of
+    /// its centre, straight north.
+    fn green() -> (Shape, LatLong) {
+        let corners = [(-10.0, -15.0), (10.0, -15.0), (10.0, 15.0), (-10.0, 15.0)];
+        let green = Shape {
+            surface_type: "Green".to_string(),
+            points: corners.iter().map(|corner| from_local(CENTRE, *corner)).collect(),
+        };
+        (green, from_local(CENTRE, (0.0, -150.0)))
+    }
+
+    fn assert_near(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 0.05, "{} is not {}", actual, expected);
+    }
+
+    #[test]
+    fn paces_from_the_front_and_a_side() {
+        let (green, from) = green();
+        let left = pin_from_paces(&green, from, 5, PinSide::Left, 4).unwrap();
+        let (x, y) = to_local(CENTRE, left);
+        assert_near(x, -6.0);
+        assert_near(y, -10.0);
+
+        let right = pin_from_paces(&green, from, 22, PinSide::Right, 3).unwrap();
+        let (x, y) = to_local(CENTRE, right);
+        assert_near(x, 7.0);
+        assert_near(y, 7.0);
+    }
+
+    #[test]
+    fn paces_off_the_green_place_no_pin() {
+        let (green, from) = green();
+        assert!(pin_from_paces(&green, from, 31, PinSide::Left, 4).is_none());
+        assert!(pin_from_paces(&green, from, 5, PinSide::Right, 21).is_none());
+        assert!(pin_from_paces(&green, CENTRE, 5, PinSide::Left, 4).is_none());
+    }
+
+    #[test]
+    fn measures_the_green_and_pin() {
+        let (green, from) = green();
+        let pin = pin_from_paces(&green, from, 5, PinSide::Left, 4);
+        let detail = analyse(1, &green, from, pin).unwrap();
+        assert_near(detail.depth_yards, 30.0);
+        assert_near(detail.max_width_yards, 20.0);
+        assert_near(detail.area_sq_yards, 600.0);
+        assert_near(detail.front.distance_yards, 135.0);
+        assert_near(detail.centre.distance_yards, 150.0);
+        assert_near(detail.back.distance_yards, 165.0);
+
+        let pin = detail.pin.unwrap();
+        assert_near(pin.from_front_yards, 5.0);
+        assert_near(pin.from_back_yards, 25.0);
+        assert_near(pin.from_left_yards, 4.0);
+        assert_near(pin.from_right_yards, 16.0);
+    }
+
+    #[test]
+    fn a_pin_off_the_green_is_left_out() {
+        let (green, from) = green();
+        let off = from_local(CENTRE, (0.0, 20.0));
+        let detail = analyse(1, &green, from, Some(off)).unwrap();
+        assert!(detail.pin.is_none());
+    }
+}
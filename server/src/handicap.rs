@@ -0,0 +1,446 @@
+use chrono::{Duration, NaiveDate};
+use serde::Serialize;
+
+use crate::models::TeeSet;
+
+/// Slope rating of a course of standard difficulty.
+const STANDARD_SLOPE: f64 = 113.0;
+pub const MAX_INDEX: f64 = 54.0;
+/// Only 18-hole scores are posted.
+const HOLES: usize = 18;
+/// Holes that must be played for a score to count; the rest are scored as
+/// net par.
+const MIN_HOLES_PLAYED: usize = 14;
+/// Until a player has an index, hole scores are capped at par plus this.
+const UNHANDICAPPED_MAX_OVER_PAR: i32 = 5;
+/// Most recent scores that make up the scoring record.
+const RECORD_SIZE: usize = 20;
+/// How far the index may rise above the low index before increases are halved.
+const SOFT_CAP: f64 = 3.0;
+/// How far the index may ever rise above the low index.
+const HARD_CAP: f64 = 5.0;
+/// The low index is the lowest index in this many days before the latest score.
+const LOW_INDEX_DAYS: i64 = 365;
+
+fn round_tenth(value: f64) -> f64 {
+    (value * 10.0).round() / 10.0
+}
+
+/// Strokes a player receives on a tee set for a given Handicap Index.
+pub fn course_handicap(index: f64, tee: &TeeSet) -> i32 {
+    (index * f64::from(tee.slope_rating) / STANDARD_SLOPE + tee.course_rating - f64::from(tee.par)).round() as i32
+}
+
+/// Course handicap scaled by the allowance for the format being played.
+pub fn playing_handicap(course_handicap: i32, allowance_percent: f64) -> i32 {
+    (f64::from(course_handicap) * allowance_percent / 100.0).round() as i32
+}
+
+/// Handicap strokes received on the hole ranked `rank` (1 is the hardest)
+/// out of `holes`. Plus handicaps give strokes back from the easiest hole up.
+pub fn strokes_received(handicap: i32, rank: i32, holes: i32) -> i32 {
+    if holes <= 0 {
+        return 0;
+    }
+    if handicap >= 0 {
+        handicap / holes + i32::from(rank <= handicap % holes)
+    } else {
+        let plus = -handicap;
+        -(plus / holes + i32::from(rank > holes - plus % holes))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct HoleResult {
+    pub par: i32,
+    /// Stroke allocation order, 1 being the hardest hole.
+    pub rank: i32,
+    pub strokes: Option<i32>,
+}
+
+/// Gross score with every hole capped at net double bogey and unplayed holes
+/// counted as net par, or `None` when too few holes were played to post it.
+pub fn adjusted_gross_score(holes: &[HoleResult], course_handicap: Option<i32>) -> Option<i32> {
+    let played = holes.iter().filter(|hole| hole.strokes.is_some()).count();
+    if holes.len() != HOLES || played < MIN_HOLES_PLAYED {
+        return None;
+    }
+
+    let count = holes.len() as i32;
+    Some(
+        holes
+            .iter()
+            .map(|hole| {
+                let received = course_handicap.map_or(0, |handicap| strokes_received(handicap, hole.rank, count));
+                let max = match course_handicap {
+                    Some(_) => hole.par + 2 + received,
+                    None => hole.par + UNHANDICAPPED_MAX_OVER_PAR,
+                };
+                hole.strokes.map_or(hole.par + received, |strokes| strokes.min(max))
+            })
+            .sum(),
+    )
+}
+
+pub fn score_differential(adjusted_gross_score: i32, tee: &TeeSet) -> f64 {
+    round_tenth(STANDARD_SLOPE / f64::from(tee.slope_rating) * (f64::from(adjusted_gross_score) - tee.course_rating))
+}
+
+/// How many of the lowest differentials count, and the adjustment made, for a
+/// record of `scores` scores.
+fn differentials_used(scores: usize) -> Option<(usize, f64)> {
+    Some(match scores {
+        0..=2 => return None,
+        3 => (1, -2.0),
+        4 => (1, -1.0),
+        5 => (1, 0.0),
+        6 => (2, -1.0),
+        7 | 8 => (2, 0.0),
+        9..=11 => (3, 0.0),
+        12..=14 => (4, 0.0),
+        15 | 16 => (5, 0.0),
+        17 | 18 => (6, 0.0),
+        19 => (7, 0.0),
+        _ => (8, 0.0),
+    })
+}
+
+/// A finished round ready to be posted.
+#[derive(Debug, Clone)]
+pub struct PostedScore {
+    pub round_id: i32,
+    pub played_on: NaiveDate,
+    pub tee: TeeSet,
+    pub holes: Vec<HoleResult>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ScoreEntry {
+    pub round_id: i32,
+    pub played_on: NaiveDate,
+    pub course_id: i32,
+    pub tee_set: String,
+    /// Course handicap from the index in effect before the round, used for
+    /// net double bogey.
+    pub course_handicap: Option<i32>,
+    pub adjusted_gross_score: i32,
+    pub differential: f64,
+    /// Exceptional score reductions applied to this differential.
+    pub exceptional_adjustment: f64,
+    /// Whether the differential counts towards the current index.
+    pub used: bool,
+    pub index_after: Option<f64>,
+}
+
+impl ScoreEntry {
+    fn adjusted_differential(&self) -> f64 {
+        self.differential + self.exceptional_adjustment
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct SkippedRound {
+    pub round_id: i32,
+    pub reason: String,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CapApplied {
+    Soft,
+    Hard,
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct HandicapRecord {
+    pub handicap_index: Option<f64>,
+    /// Lowest index in the year before the latest score, once the player has
+    /// a full record.
+    pub low_index: Option<f64>,
+    pub cap: Option<CapApplied>,
+    /// The scoring record, newest first.
+    pub scores: Vec<ScoreEntry>,
+    pub skipped: Vec<SkippedRound>,
+}
+
+/// Positions of the differentials that count from the most recent scores,
+/// and the index they give before caps.
+fn lowest_differentials(entries: &[ScoreEntry]) -> Option<(Vec<usize>, f64)> {
+    let start = entries.len().saturating_sub(RECORD_SIZE);
+    let (count, adjustment) = differentials_used(entries.len() - start)?;
+
+    let mut record: Vec<usize> = (start..entries.len()).collect();
+    record.sort_by(|a, b| {
+        entries[*a]
+            .adjusted_differential()
+            .total_cmp(&entries[*b].adjusted_differential())
+    });
+    record.truncate(count);
+
+    let average = record
+        .iter()
+        .map(|i| entries[*i].adjusted_differential())
+        .fold(0.0, |sum, differential| sum + differential)
+        / count as f64;
+    Some((record, round_tenth(average + adjustment).min(MAX_INDEX)))
+}
+
+/// Limits how far an index can rise above the low index: increases past the
+/// soft cap are halved, and none goes past the hard cap.
+fn apply_caps(mut index: f64, low: f64) -> (f64, Option<CapApplied>) {
+    let mut cap = None;
+    if index - low > SOFT_CAP {
+        index = low + SOFT_CAP + (index - low - SOFT_CAP) / 2.0;
+        cap = Some(CapApplied::Soft);
+    }
+    if index - low > HARD_CAP {
+        index = low + HARD_CAP;
+        cap = Some(CapApplied::Hard);
+    }
+    (round_tenth(index), cap)
+}
+
+/// Works through a player's scores in the order they were played, revising
+/// the index after each one as the World Handicap System does.
+pub fn calculate(scores: Vec<PostedScore>) -> HandicapRecord {
+    let mut record = HandicapRecord::default();
+    let mut entries: Vec<ScoreEntry> = Vec::new();
+    let mut revisions: Vec<(NaiveDate, f64)> = Vec::new();
+
+    for score in scores {
+        let index_before = record.handicap_index;
+        let course_handicap = index_before.map(|index| course_handicap(index, &score.tee));
+        let Some(adjusted) = adjusted_gross_score(&score.holes, course_handicap) else {
+            record.skipped.push(SkippedRound {
+                round_id: score.round_id,
+                reason: format!(
+                    "only {}-hole rounds with at least {} holes played are posted",
+                    HOLES, MIN_HOLES_PLAYED
+                ),
+            });
+            continue;
+        };
+        let differential = score_differential(adjusted, &score.tee);
+        entries.push(ScoreEntry {
+            round_id: score.round_id,
+            played_on: score.played_on,
+            course_id: score.tee.course_id,
+            tee_set: score.tee.name.clone(),
+            course_handicap,
+            adjusted_gross_score: adjusted,
+            differential,
+            exceptional_adjustment: 0.0,
+            used: false,
+            index_after: None,
+        });
+
+        if let Some(before) = index_before {
+            let reduction = match before - differential {
+                drop if drop >= 10.0 => -2.0,
+                drop if drop >= 7.0 => -1.0,
+                _ => 0.0,
+            };
+            for entry in entries.iter_mut().rev().take(RECORD_SIZE) {
+                entry.exceptional_adjustment += reduction;
+            }
+        }
+
+        let Some((_, mut index)) = lowest_differentials(&entries) else {
+            continue;
+        };
+
+        record.cap = None;
+        record.low_index = None;
+        if entries.len() >= RECORD_SIZE {
+            let window_start = score.played_on - Duration::days(LOW_INDEX_DAYS);
+            record.low_index = revisions
+                .iter()
+                .filter(|(played_on, _)| *played_on >= window_start)
+                .map(|(_, index)| *index)
+                .min_by(f64::total_cmp);
+        }
+        if let Some(low) = record.low_index {
+            (index, record.cap) = apply_caps(index, low);
+        }
+
+        record.handicap_index = Some(index);
+        revisions.push((score.played_on, index));
+        if let Some(entry) = entries.last_mut() {
+            entry.index_after = Some(index);
+        }
+    }
+
+    if let Some((used, _)) = lowest_differentials(&entries) {
+        for i in used {
+            entries[i].used = true;
+        }
+    }
+    let start = entries.len().saturating_sub(RECORD_SIZE);
+    record.scores = entries.split_off(start);
+    record.scores.reverse();
+    record
+}
+
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDateTime;
+
+    use super::*;
+
+    fn tee(course_rating: f64, slope_rating: i32) -> TeeSet {
+        TeeSet {
+            id: 1,
+            course_id: 1,
+            name: "White".to_string(),
+            course_rating,
+            slope_rating,
+            par: 72,
+            created_at: NaiveDateTime::default(),
+            updated_at: NaiveDateTime::default(),
+            colour: None,
+            gender: "men".to_string(),
+        }
+    }
+
+    /// Eighteen par 4s ranked by hole number, every hole scored `strokes`.
+    fn holes(strokes: i32) -> Vec<HoleResult> {
+        (1..=18)
+            .map(|rank| HoleResult {
+                par: 4,
+                rank,
+                strokes: Some(strokes),
+            })
+            .collect()
+    }
+
+    fn date(year: i32, day: u32) -> NaiveDate {
+        NaiveDate::from_yo_opt(year, day).unwrap()
+    }
+
+    /// Scores on a tee where the differential is the gross score less 72.
+    fn posted(differentials: impl IntoIterator<Item = (NaiveDate, i32)>) -> Vec<PostedScore> {
+        differentials
+            .into_iter()
+            .enumerate()
+            .map(|(i, (played_on, differential))| PostedScore {
+                round_id: i as i32 + 1,
+                played_on,
+                tee: tee(72.0, 113),
+                holes: spread(differential),
+            })
+            .collect()
+    }
+
+    /// Spreads strokes over par across the card, hardest holes first, so net
+    /// double bogey doesn't trim them.
+    fn spread(over_par: i32) -> Vec<HoleResult> {
+        let mut holes = holes(4);
+        for (i, hole) in holes.iter_mut().enumerate() {
+            let extra = over_par / 18 + i32::from((i as i32) < over_par % 18);
+            hole.strokes = Some(4 + extra);
+        }
+        holes
+    }
+
+    #[test]
+    fn net_double_bogey_caps_each_hole() {
+        // A course handicap of 20 gives two strokes on the two hardest holes
+        // and one on the rest.
+        let mut card = holes(5);
+        card[0].strokes = Some(10);
+        card[17].strokes = Some(8);
+        assert_eq!(adjusted_gross_score(&card, Some(20)), Some(16 * 5 + 8 + 7));
+        // Without an index every hole is capped at par plus five.
+        assert_eq!(adjusted_gross_score(&card, None), Some(16 * 5 + 9 + 8));
+    }
+
+    #[test]
+    fn unplayed_holes_count_as_net_par() {
+        let mut card = holes(5);
+        for hole in &mut card[14..] {
+            hole.strokes = None;
+        }
+        assert_eq!(adjusted_gross_score(&card, Some(20)), Some(14 * 5 + 4 * 5));
+        card[13].strokes = None;
+        assert_eq!(adjusted_gross_score(&card, Some(20)), None);
+        assert_eq!(adjusted_gross_score(&card[..9], Some(20)), None);
+    }
+
+    #[test]
+    fn score_differential_matches_the_worked_example() {
+        assert_eq!(score_differential(85, &tee(73.1, 131)), 10.3);
+        assert_eq!(course_handicap(10.3, &tee(73.1, 131)), 13);
+        assert_eq!(playing_handicap(13, 95.0), 12);
+    }
+
+    #[test]
+    fn fewer_than_twenty_scores_use_fewer_differentials() {
+        assert_eq!(differentials_used(2), None);
+        assert_eq!(differentials_used(3), Some((1, -2.0)));
+        assert_eq!(differentials_used(6), Some((2, -1.0)));
+        assert_eq!(differentials_used(12), Some((4, 0.0)));
+        assert_eq!(differentials_used(20), Some((8, 0.0)));
+
+        let record = calculate(posted([(date(2025, 1), 18), (date(2025, 2), 13), (date(2025, 3), 23)]));
+        assert_eq!(record.handicap_index, Some(11.0));
+        assert_eq!(record.scores.len(), 3);
+        assert!(record.scores[1].used);
+    }
+
+    #[test]
+    fn best_eight_of_twenty() {
+        // Twenty scores, each a shot better than the last, so none is
+        // exceptional and the index only falls.
+        let record = calculate(posted((1..=20).map(|i| (date(2025, i), 21 - i as i32))));
+        assert_eq!(record.handicap_index, Some(4.5));
+        assert_eq!(record.cap, None);
+        let used: Vec<f64> = record
+            .scores
+            .iter()
+            .filter(|score| score.used)
+            .map(|score| score.differential)
+            .collect();
+        assert_eq!(used, [1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0]);
+        assert_eq!(record.scores[0].round_id, 20);
+    }
+
+    #[test]
+    fn caps_limit_increases_over_the_low_index() {
+        assert_eq!(apply_caps(12.5, 10.0), (12.5, None));
+        assert_eq!(apply_caps(14.0, 10.0), (13.5, Some(CapApplied::Soft)));
+        assert_eq!(apply_caps(17.0, 10.0), (15.0, Some(CapApplied::Soft)));
+        assert_eq!(apply_caps(18.0, 10.0), (15.0, Some(CapApplied::Hard)));
+
+        // Twenty 10s set the index and low index at 10; the early revisions
+        // made with fewer scores fall outside the year. Then poor scores push
+        // out the 10s one by one.
+        let steady = (1..=20).map(|i| (if i <= 6 { date(2024, i) } else { date(2025, i) }, 10));
+        let poor = |count: u32| (21..21 + count).map(|i| (date(2025, i), 40));
+
+        let soft = calculate(posted(steady.clone().chain(poor(13))));
+        assert_eq!(soft.low_index, Some(10.0));
+        assert_eq!(soft.cap, Some(CapApplied::Soft));
+        assert_eq!(soft.handicap_index, Some(13.4));
+
+        let hard = calculate(posted(steady.chain(poor(14))));
+        assert_eq!(hard.cap, Some(CapApplied::Hard));
+        assert_eq!(hard.handicap_index, Some(15.0));
+    }
+
+    #[test]
+    fn exceptional_scores_reduce_the_record() {
+        let steady = || (1..=20).map(|i| (date(2025, i), 20));
+
+        // Seven to ten below the index takes a stroke off every differential.
+        let record = calculate(posted(steady().chain([(date(2025, 21), 12)])));
+        assert_eq!(record.scores[0].exceptional_adjustment, -1.0);
+        assert_eq!(record.scores[19].exceptional_adjustment, -1.0);
+        assert_eq!(record.handicap_index, Some(18.0));
+
+        // Ten or more below takes two.
+        let record = calculate(posted(steady().chain([(date(2025, 21), 9)])));
+        assert_eq!(record.scores[0].exceptional_adjustment, -2.0);
+        assert_eq!(record.handicap_index, Some(16.6));
+    }
+}
@@ -0,0 +1,172 @@
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::error::ApiError;
+use crate::handicap::{self, HandicapRecord, HoleResult, PostedScore, SkippedRound};
+use crate::models::{Hole, HoleScore, Round, RoundPlayer, TeeSet};
+use crate::hole_operations;
+use crate::round_operations::{authorize_round, ROUND_FINISHED};
+use crate::schema::{hole_scores, round_players, rounds};
+use crate::tee_set_operations;
+
+/// Allowance for individual stroke play.
+pub const DEFAULT_ALLOWANCE_PERCENT: f64 = 95.0;
+
+#[derive(Deserialize, Debug)]
+pub struct RoundHandicapQuery {
+    /// Share of the course handicap used, in percent.
+    pub allowance: Option<f64>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct PlayerHandicap {
+    pub player_id: i32,
+    pub name: String,
+    /// Index in effect when the round was played.
+    pub handicap_index: Option<f64>,
+    pub course_handicap: Option<i32>,
+    pub playing_handicap: Option<i32>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct RoundHandicaps {
+    pub round_id: i32,
+    pub tee_set: Option<TeeSet>,
+    pub allowance_percent: f64,
+    pub players: Vec<PlayerHandicap>,
+}
+
+/// Builds a score to post from a finished round, or says why it can't be
+/// posted.
+fn posted_score(
+    conn: &mut PgConnection,
+    holes_by_tee: &mut HashMap<i32, Vec<Hole>>,
+    round: &Round,
+    player: &RoundPlayer,
+) -> Result<Result<PostedScore, String>, ApiError> {
+    let Some(tee) = tee_set_operations::round_tee_set(conn, round)? else {
+        return Ok(Err(match round.tee_set.as_deref() {
+            Some(name) => format!("tee set `{}` has no course rating", name),
+            None => "the round has no tee set".to_string(),
+        }));
+    };
+
+    if let Entry::Vacant(entry) = holes_by_tee.entry(tee.id) {
+        entry.insert(hole_operations::played_holes(conn, round.course_id, Some(&tee))?);
+    }
+    let holes = &holes_by_tee[&tee.id];
+
+    let scores: HashMap<i32, i32> = hole_scores::table
+        .filter(hole_scores::player_id.eq(player.id))
+        .select(HoleScore::as_select())
+        .load(conn)?
+        .into_iter()
+        .map(|score| (score.hole_id, score.strokes))
+        .collect();
+
+    let mut results = Vec::with_capacity(holes.len());
+    for (i, hole) in holes.iter().enumerate() {
+        let Some(par) = hole.par else {
+            return Ok(Err(format!("hole {} has no par", hole.hole_id)));
+        };
+        results.push(HoleResult {
+            par,
+            rank: hole.rank().unwrap_or(i as i32 + 1),
+            strokes: scores.get(&hole.hole_id).copied(),
+        });
+    }
+
+    Ok(Ok(PostedScore {
+        round_id: round.id,
+        played_on: round.played_on,
+        tee,
+        holes: results,
+    }))
+}
+
+/// The user's handicap record from their finished rounds, optionally only
+/// those played before `before`.
+fn handicap_record(conn: &mut PgConnection, user_id: i32, before: Option<&Round>) -> Result<HandicapRecord, ApiError> {
+    let played: Vec<(RoundPlayer, Round)> = round_players::table
+        .inner_join(rounds::table)
+        .filter(round_players::user_id.eq(user_id))
+        .filter(rounds::status.eq(ROUND_FINISHED))
+        .order((rounds::played_on.asc(), rounds::finished_at.asc(), rounds::id.asc()))
+        .select((RoundPlayer::as_select(), Round::as_select()))
+        .load(conn)?;
+
+    let mut holes_by_tee = HashMap::new();
+    let mut scores = Vec::new();
+    let mut skipped = Vec::new();
+    for (player, round) in &played {
+        if before.is_some_and(|before| (round.played_on, round.id) >= (before.played_on, before.id)) {
+            continue;
+        }
+        match posted_score(conn, &mut holes_by_tee, round, player)? {
+            Ok(score) => scores.push(score),
+            Err(reason) => skipped.push(SkippedRound {
+                round_id: round.id,
+                reason,
+            }),
+        }
+    }
+
+    let mut record = handicap::calculate(scores);
+    record.skipped.extend(skipped);
+    record.skipped.sort_by_key(|skipped| skipped.round_id);
+    Ok(record)
+}
+
+pub fn handicap_for_user(conn: &mut PgConnection, user_id: i32) -> Result<HandicapRecord, ApiError> {
+    handicap_record(conn, user_id, None)
+}
+
+/// Course and playing handicaps for everyone in a round, from the index each
+/// player had going into it.
+pub fn round_handicaps(
+    conn: &mut PgConnection,
+    round_id: i32,
+    user_id: i32,
+    query: RoundHandicapQuery,
+) -> Result<RoundHandicaps, ApiError> {
+    let allowance = query.allowance.unwrap_or(DEFAULT_ALLOWANCE_PERCENT);
+    if !(0.0..=100.0).contains(&allowance) {
+        return Err(ApiError::BadRequest("allowance must be between 0 and 100".to_string()));
+    }
+
+    let round = authorize_round(conn, round_id, user_id)?;
+    let tee_set = tee_set_operations::round_tee_set(conn, &round)?;
+    let round_players = RoundPlayer::belonging_to(&round)
+        .order(round_players::id.asc())
+        .select(RoundPlayer::as_select())
+        .load(conn)?;
+
+    let mut players = Vec::with_capacity(round_players.len());
+    for player in round_players {
+        let handicap_index = match player.user_id {
+            Some(player_user_id) => handicap_record(conn, player_user_id, Some(&round))?.handicap_index,
+            None => None,
+        };
+        let course_handicap = handicap_index
+            .zip(tee_set.as_ref())
+            .map(|(index, tee)| handicap::course_handicap(index, tee));
+
+        players.push(PlayerHandicap {
+            player_id: player.id,
+            name: player.name,
+            handicap_index,
+            course_handicap,
+            playing_handicap: course_handicap.map(|handicap| handicap::playing_handicap(handicap, allowance)),
+        });
+    }
+
+    Ok(RoundHandicaps {
+        round_id,
+        tee_set,
+        allowance_percent: allowance,
+        players,
+    })
+}
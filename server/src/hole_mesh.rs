@@ -0,0 +1,456 @@
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::cache::BuiltCache;
+use crate::geometry::{distance_yards, from_local, group_shapes, lie_at, to_local, LatLong, Lie};
+use crate::models::HoleData;
+
+const METRES_PER_YARD: f64 = 0.9144;
+const METRES_PER_FOOT: f64 = 0.3048;
+/// How far the mesh reaches past the hole's outermost shapes.
+const MESH_MARGIN_YARDS: f64 = 20.0;
+/// glTF constants.
+const GLB_MAGIC: u32 = 0x4654_6C67;
+const GLB_JSON_CHUNK: u32 = 0x4E4F_534A;
+const GLB_BIN_CHUNK: u32 = 0x004E_4942;
+const ARRAY_BUFFER: u32 = 34962;
+const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+const UNSIGNED_BYTE: u32 = 5121;
+const UNSIGNED_SHORT: u32 = 5123;
+const UNSIGNED_INT: u32 = 5125;
+const FLOAT: u32 = 5126;
+
+/// How finely the terrain is triangulated. Lower levels keep mobile
+/// downloads small; every level is capped so large holes coarsen instead of
+/// growing without bound.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum LevelOfDetail {
+    Low,
+    #[default]
+    Medium,
+    High,
+}
+
+impl LevelOfDetail {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LevelOfDetail::Low => "low",
+            LevelOfDetail::Medium => "medium",
+            LevelOfDetail::High => "high",
+        }
+    }
+
+    fn spacing_yards(&self) -> f64 {
+        match self {
+            LevelOfDetail::Low => 8.0,
+            LevelOfDetail::Medium => 4.0,
+            LevelOfDetail::High => 2.0,
+        }
+    }
+
+    fn max_vertices(&self) -> usize {
+        match self {
+            LevelOfDetail::Low => 4_000,
+            LevelOfDetail::Medium => 16_000,
+            LevelOfDetail::High => 65_000,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct MeshQuery {
+    #[serde(default)]
+    pub lod: LevelOfDetail,
+}
+
+/// Recently built GLB meshes by hole and level of detail. Building one
+/// samples the terrain at every vertex, so they are reused until they
+/// expire.
+pub type MeshCache = BuiltCache<(i32, LevelOfDetail)>;
+
+/// The sRGB colour a surface is drawn in, matching the SVG maps.
+fn surface_colour(lie: Lie) -> [u8; 3] {
+    match lie {
+        Lie::Green => [0x22, 0x8B, 0x22],
+        Lie::Fairway | Lie::Tee => [0x32, 0xCD, 0x32],
+        Lie::Sand => [0xF4, 0xA4, 0x60],
+        Lie::Water => [0x41, 0x69, 0xE1],
+        Lie::Rough | Lie::Recovery => [0x00, 0x64, 0x00],
+    }
+}
+
+/// glTF vertex colours are linear.
+fn linear(channel: u8) -> u8 {
+    let c = f64::from(channel) / 255.0;
+    let linear = if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) };
+    (linear * 255.0).round() as u8
+}
+
+/// A hole's terrain as a regular grid of triangles, in metres with x east,
+/// y up and z south, around the back tee.
+pub struct HoleMesh {
+    pub origin: LatLong,
+    pub spacing_yards: f64,
+    pub columns: usize,
+    pub rows: usize,
+    pub positions: Vec<[f32; 3]>,
+    pub normals: Vec<[f32; 3]>,
+    /// Linear RGBA.
+    pub colours: Vec<[u8; 4]>,
+    pub indices: Vec<u32>,
+    /// The flag in mesh coordinates.
+    pub flag: Option<[f32; 3]>,
+}
+
+impl HoleMesh {
+    /// Triangulates the ground under a hole's shapes, raised to the imported
+    /// terrain when there is some and flat otherwise, and coloured by
+    /// surface. `None` when the hole has no geometry.
+    pub fn build(hole_data: &HoleData, lod: LevelOfDetail) -> Option<HoleMesh> {
+        let shapes = group_shapes(&hole_data.polygons);
+        let flag = hole_data.hole.flag();
+        let mut points: Vec<LatLong> = shapes.iter().flat_map(|shape| shape.points.iter().copied()).collect();
+        points.extend(hole_data.tees());
+        points.extend(flag);
+        let origin = hole_data
+            .route
+            .as_ref()
+            .and_then(|route| route.playing_line().first().copied())
+            .or_else(|| {
+                let flag = flag?;
+                hole_data
+                    .tees()
+                    .into_iter()
+                    .max_by(|a, b| distance_yards(*a, flag).total_cmp(&distance_yards(*b, flag)))
+            })
+            .or_else(|| points.first().copied())?;
+
+        let local: Vec<(f64, f64)> = points.iter().map(|p| to_local(origin, *p)).collect();
+        let min_x = local.iter().map(|p| p.0).fold(f64::MAX, f64::min) - MESH_MARGIN_YARDS;
+        let min_y = local.iter().map(|p| p.1).fold(f64::MAX, f64::min) - MESH_MARGIN_YARDS;
+        let max_x = local.iter().map(|p| p.0).fold(f64::MIN, f64::max) + MESH_MARGIN_YARDS;
+        let max_y = local.iter().map(|p| p.1).fold(f64::MIN, f64::max) + MESH_MARGIN_YARDS;
+
+        let mut spacing = lod.spacing_yards();
+        let size = |spacing: f64| {
+            (
+                ((max_x - min_x) / spacing).ceil() as usize + 1,
+                ((max_y - min_y) / spacing).ceil() as usize + 1,
+            )
+        };
+        while size(spacing).0 * size(spacing).1 > lod.max_vertices() {
+            spacing *= 1.25;
+        }
+        let (columns, rows) = size(spacing);
+        let node = |column: usize, row: usize| (min_x + column as f64 * spacing, min_y + row as f64 * spacing);
+
+        let grid = hole_data.elevation.as_ref().map(|elevation| &elevation.grid);
+        let feet: Vec<Option<f64>> = (0..rows)
+            .flat_map(|row| (0..columns).map(move |column| (column, row)))
+            .map(|(column, row)| grid?.elevation_ft(from_local(origin, node(column, row))))
+            .collect();
+        // The mesh sits on y = 0 at its lowest point; gaps in the terrain are
+        // filled at that height.
+        let base_ft = feet.iter().flatten().copied().fold(f64::MAX, f64::min);
+        let heights: Vec<f64> = feet
+            .iter()
+            .map(|feet| feet.map_or(0.0, |feet| (feet - base_ft) * METRES_PER_FOOT))
+            .collect();
+        let height = |column: usize, row: usize| heights[row * columns + column];
+
+        let mut positions = Vec::with_capacity(columns * rows);
+        let mut normals = Vec::with_capacity(columns * rows);
+        let mut colours = Vec::with_capacity(columns * rows);
+        let step = spacing * METRES_PER_YARD;
+        for row in 0..rows {
+            for column in 0..columns {
+                let (x, y) = node(column, row);
+                positions.push([
+                    (x * METRES_PER_YARD) as f32,
+                    height(column, row) as f32,
+                    (-y * METRES_PER_YARD) as f32,
+                ]);
+
+                let (west, east) = (column.saturating_sub(1), (column + 1).min(columns - 1));
+                let (south, north) = (row.saturating_sub(1), (row + 1).min(rows - 1));
+                let rise_east = (height(east, row) - height(west, row)) / (step * (east - west).max(1) as f64);
+                let rise_north = (height(column, north) - height(column, south)) / (step * (north - south).max(1) as f64);
+                let length = (rise_east * rise_east + 1.0 + rise_north * rise_north).sqrt();
+                normals.push([
+                    (-rise_east / length) as f32,
+                    (1.0 / length) as f32,
+                    (rise_north / length) as f32,
+                ]);
+
+                let [r, g, b] = surface_colour(lie_at(&shapes, from_local(origin, (x, y))));
+                colours.push([linear(r), linear(g), linear(b), 255]);
+            }
+        }
+
+        let mut indices = Vec::with_capacity((columns - 1) * (rows - 1) * 6);
+        let index = |column: usize, row: usize| (row * columns + column) as u32;
+        for row in 0..rows - 1 {
+            for column in 0..columns - 1 {
+                let (sw, se) = (index(column, row), index(column + 1, row));
+                let (nw, ne) = (index(column, row + 1), index(column + 1, row + 1));
+                indices.extend([sw, se, nw, se, ne, nw]);
+            }
+        }
+
+        let flag = flag.map(|flag| {
+            let (x, y) = to_local(origin, flag);
+            let feet = grid.and_then(|grid| grid.elevation_ft(flag));
+            [
+                (x * METRES_PER_YARD) as f32,
+                feet.map_or(0.0, |feet| (feet - base_ft) * METRES_PER_FOOT) as f32,
+                (-y * METRES_PER_YARD) as f32,
+            ]
+        });
+
+        Some(HoleMesh {
+            origin,
+            spacing_yards: spacing,
+            columns,
+            rows,
+            positions,
+            normals,
+            colours,
+            indices,
+            flag,
+        })
+    }
+
+    /// The mesh as a binary glTF file with one node, its vertex colours
+    /// carrying the surfaces. Where the mesh sits on the ground is in the
+    /// scene's `extras`.
+    pub fn to_glb(&self, hole_id: i32, lod: LevelOfDetail) -> Vec<u8> {
+        let mut bin: Vec<u8> = Vec::new();
+        let mut views = Vec::new();
+        let mut view = |bin: &mut Vec<u8>, bytes: Vec<u8>, target: u32| {
+            let offset = bin.len();
+            bin.extend(&bytes);
+            bin.resize(bin.len().next_multiple_of(4), 0);
+            views.push(json!({ "buffer": 0, "byteOffset": offset, "byteLength": bytes.len(), "target": target }));
+        };
+
+        let floats = |vectors: &[[f32; 3]]| vectors.iter().flatten().flat_map(|v| v.to_le_bytes()).collect();
+        view(&mut bin, floats(&self.positions), ARRAY_BUFFER);
+        view(&mut bin, floats(&self.normals), ARRAY_BUFFER);
+        view(&mut bin, self.colours.iter().flatten().copied().collect(), ARRAY_BUFFER);
+        let vertex_count = self.positions.len();
+        let (index_type, index_bytes) = if vertex_count <= usize::from(u16::MAX) {
+            (UNSIGNED_SHORT, self.indices.iter().flat_map(|i| (*i as u16).to_le_bytes()).collect())
+        } else {
+            (UNSIGNED_INT, self.indices.iter().flat_map(|i| i.to_le_bytes()).collect())
+        };
+        view(&mut bin, index_bytes, ELEMENT_ARRAY_BUFFER);
+
+        let bound = |pick: fn(f32, f32) -> f32, start: f32| {
+            (0..3)
+                .map(|axis| self.positions.iter().map(|p| p[axis]).fold(start, pick))
+                .collect::<Vec<f32>>()
+        };
+        let document = json!({
+            "asset": { "version": "2.0", "generator": "ai-caddie-backend" },
+            "scene": 0,
+            "scenes": [{
+                "nodes": [0],
+                "extras": {
+                    "hole_id": hole_id,
+                    "origin": self.origin,
+                    "lod": lod.as_str(),
+                    "spacing_yards": self.spacing_yards,
+                    "columns": self.columns,
+                    "rows": self.rows,
+                    "flag": self.flag,
+                },
+            }],
+            "nodes": [{ "mesh": 0, "name": format!("hole-{}", hole_id) }],
+            "meshes": [{
+                "name": "terrain",
+                "primitives": [{
+                    "attributes": { "POSITION": 0, "NORMAL": 1, "COLOR_0": 2 },
+                    "indices": 3,
+                    "material": 0,
+                    "mode": 4,
+                }],
+            }],
+            "materials": [{
+                "name": "turf",
+                "pbrMetallicRoughness": { "baseColorFactor": [1.0, 1.0, 1.0, 1.0], "metallicFactor": 0.0, "roughnessFactor": 1.0 },
+            }],
+            "accessors": [
+                {
+                    "bufferView": 0, "componentType": FLOAT, "count": vertex_count, "type": "VEC3",
+                    "min": bound(f32::min, f32::MAX), "max": bound(f32::max, f32::MIN),
+                },
+                { "bufferView": 1, "componentType": FLOAT, "count": vertex_count, "type": "VEC3" },
+                { "bufferView": 2, "componentType": UNSIGNED_BYTE, "normalized": true, "count": vertex_count, "type": "VEC4" },
+                { "bufferView": 3, "componentType": index_type, "count": self.indices.len(), "type": "SCALAR" },
+            ],
+            "bufferViews": views,
+            "buffers": [{ "byteLength": bin.len() }],
+        });
+
+        let mut json = document.to_string().into_bytes();
+        json.resize(json.len().next_multiple_of(4), b' ');
+        let length = 12 + 8 + json.len() + 8 + bin.len();
+        let mut glb = Vec::with_capacity(length);
+        for word in [GLB_MAGIC, 2, length as u32, json.len() as u32, GLB_JSON_CHUNK] {
+            glb.extend(word.to_le_bytes());
+        }
+        glb.extend(json);
+        for word in [bin.len() as u32, GLB_BIN_CHUNK] {
+            glb.extend(word.to_le_bytes());
+        }
+        glb.extend(bin);
+        glb
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::*;
+    use crate::models::{Hole, Polygon, Vector};
+
+    const TEE: LatLong = LatLong { lat: 41.0, long: -93.0 };
+
+    fn word(glb: &[u8], offset: usize) -> usize {
+        u32::from_le_bytes(glb[offset..offset + 4].try_into().unwrap()) as usize
+    }
+
+    /// Checks the GLB container and returns its JSON and binary chunks.
+    fn chunks(glb: &[u8]) -> (Value, &[u8]) {
+        assert_eq!(word(glb, 0), GLB_MAGIC as usize);
+        assert_eq!(word(glb, 4), 2);
+        assert_eq!(word(glb, 8), glb.len());
+
+        let json_length = word(glb, 12);
+        assert_eq!(json_length % 4, 0);
+        assert_eq!(word(glb, 16), GLB_JSON_CHUNK as usize);
+        let document = serde_json::from_slice(&glb[20..20 + json_length]).unwrap();
+
+        let bin_start = 20 + json_length;
+        let bin_length = word(glb, bin_start);
+        assert_eq!(bin_length % 4, 0);
+        assert_eq!(word(glb, bin_start + 4), GLB_BIN_CHUNK as usize);
+        assert_eq!(bin_start + 8 + bin_length, glb.len());
+        (document, &glb[bin_start + 8..])
+    }
+
+    /// A 200-yard hole north from the tee to a 20-yard square green.
+    fn hole_data() -> HoleData {
+        let flag = from_local(TEE, (0.0, 200.0));
+        let corners = [(-10.0, 190.0), (10.0, 190.0), (10.0, 210.0), (-10.0, 210.0)];
+        HoleData {
+            hole: Hole {
+                id: 1,
+                hole_id: 1,
+                number: Some(1),
+                course_id: None,
+                rotation: None,
+                range_x_min: None,
+                range_x_max: None,
+                range_y_min: None,
+                range_y_max: None,
+                dimensions_width: None,
+                dimensions_height: None,
+                flag_lat: Some(flag.lat),
+                flag_long: Some(flag.long),
+                par: Some(3),
+                flag_elevation_ft: None,
+                name: None,
+                stroke_index: None,
+                stroke_index_women: None,
+            },
+            polygons: corners
+                .iter()
+                .enumerate()
+                .map(|(i, corner)| {
+                    let point = from_local(TEE, *corner);
+                    Polygon {
+                        id: i as i32,
+                        hole_id: Some(1),
+                        surface_type: Some("Green".to_string()),
+                        lat: Some(point.lat),
+                        long: Some(point.long),
+                        shape_index: Some(0),
+                        elevation_ft: None,
+                    }
+                })
+                .collect(),
+            vectors: vec![Vector {
+                id: 1,
+                hole_id: Some(1),
+                vector_type: Some("Blue".to_string()),
+                lat: Some(TEE.lat),
+                long: Some(TEE.long),
+                elevation_ft: None,
+            }],
+            route: None,
+            elevation: None,
+        }
+    }
+
+    #[test]
+    fn builds_whole_triangles_over_the_grid() {
+        for lod in [LevelOfDetail::Low, LevelOfDetail::Medium, LevelOfDetail::High] {
+            let mesh = HoleMesh::build(&hole_data(), lod).unwrap();
+            assert_eq!(mesh.positions.len(), mesh.columns * mesh.rows);
+            assert!(mesh.positions.len() <= lod.max_vertices());
+            assert_eq!(mesh.indices.len() % 3, 0);
+            assert_eq!(mesh.indices.len(), (mesh.columns - 1) * (mesh.rows - 1) * 6);
+            assert!(mesh.indices.iter().all(|&i| (i as usize) < mesh.positions.len()));
+            let flag = mesh.flag.unwrap();
+            assert!((flag[2] + 200.0 * METRES_PER_YARD as f32).abs() < 0.1);
+        }
+    }
+
+    #[test]
+    fn glb_chunks_and_views_are_aligned() {
+        // Three vertices leave the colours and indices short of a multiple
+        // of four bytes.
+        let mesh = HoleMesh {
+            origin: TEE,
+            spacing_yards: 1.0,
+            columns: 3,
+            rows: 1,
+            positions: vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, -1.0]],
+            normals: vec![[0.0, 1.0, 0.0]; 3],
+            colours: vec![[10, 20, 30, 255]; 3],
+            indices: vec![0, 1, 2],
+            flag: None,
+        };
+        let glb = mesh.to_glb(7, LevelOfDetail::Low);
+        let (document, bin) = chunks(&glb);
+
+        assert_eq!(document["buffers"][0]["byteLength"].as_u64(), Some(bin.len() as u64));
+        let views = document["bufferViews"].as_array().unwrap();
+        let lengths: Vec<u64> = views.iter().map(|view| view["byteLength"].as_u64().unwrap()).collect();
+        assert_eq!(lengths, [36, 36, 12, 6]);
+        for view in views {
+            let offset = view["byteOffset"].as_u64().unwrap();
+            assert_eq!(offset % 4, 0);
+            assert!(offset + view["byteLength"].as_u64().unwrap() <= bin.len() as u64);
+        }
+
+        let indices = &document["accessors"][3];
+        assert_eq!(indices["componentType"].as_u64(), Some(u64::from(UNSIGNED_SHORT)));
+        assert_eq!(indices["count"].as_u64().unwrap() % 3, 0);
+        assert_eq!(document["accessors"][0]["max"], serde_json::json!([1.0, 0.0, 0.0]));
+        assert_eq!(document["scenes"][0]["extras"]["hole_id"], 7);
+    }
+
+    #[test]
+    fn built_mesh_exports_as_glb() {
+        let mesh = HoleMesh::build(&hole_data(), LevelOfDetail::Medium).unwrap();
+        let (document, _) = chunks(&mesh.to_glb(1, LevelOfDetail::Medium));
+        let accessors = document["accessors"].as_array().unwrap();
+        assert_eq!(accessors[0]["count"].as_u64(), Some(mesh.positions.len() as u64));
+        assert_eq!(accessors[3]["count"].as_u64(), Some(mesh.indices.len() as u64));
+        assert_eq!(accessors[3]["count"].as_u64().unwrap() % 3, 0);
+    }
+}
@@ -0,0 +1,207 @@
+use std::collections::HashMap;
+
+use diesel::prelude::*;
+use serde::Deserialize;
+
+use crate::error::ApiError;
+use crate::models::{Hole, TeeSet, TeeSetHoleOverride};
+use crate::round_operations::fetch_course_holes;
+use crate::schema::{holes, tee_set_hole_overrides};
+use crate::tee_set_operations;
+
+const PAR_RANGE: std::ops::RangeInclusive<i32> = 3..=6;
+const MAX_NAME_LENGTH: usize = 40;
+
+#[derive(Deserialize, AsChangeset, Debug)]
+#[diesel(table_name = holes, treat_none_as_null = true)]
+pub struct HoleInput {
+    #[diesel(skip_update)]
+    pub hole_id: i32,
+    pub par: Option<i32>,
+    pub stroke_index: Option<i32>,
+    pub stroke_index_women: Option<i32>,
+    pub name: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct TeeSetHoleInput {
+    pub hole_id: i32,
+    pub par: Option<i32>,
+    pub stroke_index: Option<i32>,
+    pub name: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct HolesQuery {
+    pub tee_set_id: Option<i32>,
+}
+
+fn clean_name(name: Option<String>) -> Result<Option<String>, ApiError> {
+    let name = name.map(|name| name.trim().to_string()).filter(|name| !name.is_empty());
+    if name.as_ref().is_some_and(|name| name.chars().count() > MAX_NAME_LENGTH) {
+        return Err(ApiError::BadRequest(format!("hole names are at most {} characters", MAX_NAME_LENGTH)));
+    }
+    Ok(name)
+}
+
+fn check_par(par: Option<i32>) -> Result<(), ApiError> {
+    if par.is_some_and(|par| !PAR_RANGE.contains(&par)) {
+        return Err(ApiError::BadRequest(format!(
+            "par must be between {} and {}",
+            PAR_RANGE.start(),
+            PAR_RANGE.end()
+        )));
+    }
+    Ok(())
+}
+
+/// Stroke indexes run from 1 to the number of holes, each used once.
+fn check_stroke_indexes(label: &str, indexes: impl Iterator<Item = Option<i32>>, holes: usize) -> Result<(), ApiError> {
+    let mut seen = vec![false; holes];
+    for index in indexes.flatten() {
+        let Some(slot) = usize::try_from(index - 1).ok().and_then(|slot| seen.get_mut(slot)) else {
+            return Err(ApiError::BadRequest(format!("{} must be between 1 and {}", label, holes)));
+        };
+        if *slot {
+            return Err(ApiError::BadRequest(format!("{} {} is used on more than one hole", label, index)));
+        }
+        *slot = true;
+    }
+    Ok(())
+}
+
+fn check_course_hole(holes: &[Hole], hole_id: i32) -> Result<(), ApiError> {
+    if holes.iter().any(|hole| hole.hole_id == hole_id) {
+        Ok(())
+    } else {
+        Err(ApiError::BadRequest(format!("hole {} is not on this course", hole_id)))
+    }
+}
+
+/// Applies a tee set's overrides to the course's holes and resolves each
+/// stroke index for the set's gender, so `par`, `stroke_index` and `name`
+/// are what the set plays.
+fn apply_tee_set(mut holes: Vec<Hole>, tee_set: &TeeSet, overrides: &[TeeSetHoleOverride]) -> Vec<Hole> {
+    let overrides: HashMap<i32, &TeeSetHoleOverride> =
+        overrides.iter().map(|entry| (entry.hole_id, entry)).collect();
+    for hole in &mut holes {
+        let entry = overrides.get(&hole.hole_id);
+        hole.stroke_index = entry
+            .and_then(|entry| entry.stroke_index)
+            .or(hole.stroke_index_for(&tee_set.gender));
+        hole.stroke_index_women = None;
+        hole.par = entry.and_then(|entry| entry.par).or(hole.par);
+        hole.name = entry.and_then(|entry| entry.name.clone()).or(hole.name.take());
+    }
+    holes
+}
+
+fn fetch_overrides(conn: &mut PgConnection, tee_set_id: i32) -> Result<Vec<TeeSetHoleOverride>, ApiError> {
+    Ok(tee_set_hole_overrides::table
+        .filter(tee_set_hole_overrides::tee_set_id.eq(tee_set_id))
+        .select(TeeSetHoleOverride::as_select())
+        .load(conn)?)
+}
+
+/// A course's holes in order as played from a tee set, or as stored when
+/// there is none.
+pub fn played_holes(conn: &mut PgConnection, course_id: i32, tee_set: Option<&TeeSet>) -> Result<Vec<Hole>, ApiError> {
+    let holes = fetch_course_holes(conn, course_id)?;
+    match tee_set {
+        Some(tee_set) => Ok(apply_tee_set(holes, tee_set, &fetch_overrides(conn, tee_set.id)?)),
+        None => Ok(holes),
+    }
+}
+
+pub fn course_holes(conn: &mut PgConnection, course_id: i32, query: HolesQuery) -> Result<Vec<Hole>, ApiError> {
+    let tee_set = query
+        .tee_set_id
+        .map(|tee_set_id| tee_set_operations::fetch_tee_set(conn, course_id, tee_set_id))
+        .transpose()?;
+    let holes = played_holes(conn, course_id, tee_set.as_ref())?;
+    if holes.is_empty() {
+        return Err(ApiError::NotFound);
+    }
+    Ok(holes)
+}
+
+/// Sets the par, stroke indexes and name of some of a course's holes.
+/// Fields left out are cleared.
+pub fn update_holes(conn: &mut PgConnection, course_id: i32, inputs: Vec<HoleInput>) -> Result<Vec<Hole>, ApiError> {
+    let mut holes = fetch_course_holes(conn, course_id)?;
+    if holes.is_empty() {
+        return Err(ApiError::NotFound);
+    }
+
+    let mut updates = Vec::with_capacity(inputs.len());
+    for mut input in inputs {
+        check_course_hole(&holes, input.hole_id)?;
+        check_par(input.par)?;
+        input.name = clean_name(input.name)?;
+        if let Some(hole) = holes.iter_mut().find(|hole| hole.hole_id == input.hole_id) {
+            hole.par = input.par;
+            hole.stroke_index = input.stroke_index;
+            hole.stroke_index_women = input.stroke_index_women;
+        }
+        updates.push(input);
+    }
+    check_stroke_indexes("stroke_index", holes.iter().map(|hole| hole.stroke_index), holes.len())?;
+    check_stroke_indexes(
+        "stroke_index_women",
+        holes.iter().map(|hole| hole.stroke_index_for("women")),
+        holes.len(),
+    )?;
+
+    conn.transaction(|conn| -> Result<(), ApiError> {
+        for input in &updates {
+            diesel::update(holes::table.filter(holes::hole_id.eq(input.hole_id)))
+                .set(input)
+                .execute(conn)?;
+        }
+        Ok(())
+    })?;
+    fetch_course_holes(conn, course_id)
+}
+
+/// Replaces the holes a tee set plays differently from the course, e.g. a
+/// par 5 played as a par 4 from the forward tees.
+pub fn update_tee_set_holes(
+    conn: &mut PgConnection,
+    course_id: i32,
+    tee_set_id: i32,
+    inputs: Vec<TeeSetHoleInput>,
+) -> Result<Vec<Hole>, ApiError> {
+    let tee_set = tee_set_operations::fetch_tee_set(conn, course_id, tee_set_id)?;
+    let holes = fetch_course_holes(conn, course_id)?;
+
+    let mut overrides: Vec<TeeSetHoleOverride> = Vec::with_capacity(inputs.len());
+    for input in inputs {
+        check_course_hole(&holes, input.hole_id)?;
+        if overrides.iter().any(|entry| entry.hole_id == input.hole_id) {
+            return Err(ApiError::BadRequest(format!("hole {} is listed twice", input.hole_id)));
+        }
+        check_par(input.par)?;
+        let entry = TeeSetHoleOverride {
+            tee_set_id,
+            hole_id: input.hole_id,
+            par: input.par,
+            stroke_index: input.stroke_index,
+            name: clean_name(input.name)?,
+        };
+        if entry.par.is_some() || entry.stroke_index.is_some() || entry.name.is_some() {
+            overrides.push(entry);
+        }
+    }
+    let played = apply_tee_set(holes, &tee_set, &overrides);
+    check_stroke_indexes("stroke_index", played.iter().map(|hole| hole.stroke_index), played.len())?;
+
+    conn.transaction(|conn| -> Result<(), ApiError> {
+        diesel::delete(tee_set_hole_overrides::table.filter(tee_set_hole_overrides::tee_set_id.eq(tee_set_id)))
+            .execute(conn)?;
+        diesel::insert_into(tee_set_hole_overrides::table)
+            .values(&overrides)
+            .execute(conn)?;
+        Ok(())
+    })?;
+    Ok(played)
+}
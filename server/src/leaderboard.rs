@@ -0,0 +1,352 @@
+use serde::{Deserialize, Serialize};
+
+use crate::handicap;
+
+/// Holes counted back, in turn, from the end of the last round to split
+/// players who finish level.
+const COUNTBACK_HOLES: [usize; 4] = [9, 6, 3, 1];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Division {
+    #[default]
+    Gross,
+    Net,
+}
+
+#[derive(Debug, Clone)]
+pub struct CardHole {
+    pub number: i32,
+    pub par: Option<i32>,
+    /// Stroke allocation order, 1 being the hardest hole.
+    pub rank: i32,
+    pub strokes: Option<i32>,
+}
+
+/// An entrant's card for one tournament round, holes in number order.
+#[derive(Debug, Clone)]
+pub struct RoundCard {
+    pub round_number: i32,
+    pub playing_handicap: Option<i32>,
+    pub holes: Vec<CardHole>,
+}
+
+impl RoundCard {
+    /// Scores for the division, `None` for holes not yet played.
+    fn scores(&self, division: Division) -> Vec<Option<i32>> {
+        let count = self.holes.len() as i32;
+        let handicap = match division {
+            Division::Gross => 0,
+            Division::Net => self.playing_handicap.unwrap_or(0),
+        };
+        self.holes
+            .iter()
+            .map(|hole| {
+                hole.strokes
+                    .map(|strokes| strokes - handicap::strokes_received(handicap, hole.rank, count))
+            })
+            .collect()
+    }
+
+    fn is_complete(&self) -> bool {
+        self.holes.iter().all(|hole| hole.strokes.is_some())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct EntrantCards {
+    pub entrant_id: i32,
+    pub name: String,
+    pub flight_id: Option<i32>,
+    /// Cards for the rounds the entrant has started, in round order.
+    pub cards: Vec<RoundCard>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct RoundLine {
+    pub round_number: i32,
+    pub thru: i32,
+    pub strokes: i32,
+    pub to_par: Option<i32>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct LeaderboardEntry {
+    /// `None` until the entrant has played a hole.
+    pub position: Option<i32>,
+    pub tied: bool,
+    /// e.g. "1", "T3", or "-" before the entrant has started.
+    pub place: String,
+    pub entrant_id: i32,
+    pub name: String,
+    pub flight_id: Option<i32>,
+    pub holes_played: i32,
+    /// Holes played in the entrant's latest round.
+    pub thru: i32,
+    /// To par in the latest round.
+    pub today: Option<i32>,
+    pub to_par: Option<i32>,
+    pub strokes: i32,
+    pub finished: bool,
+    pub rounds: Vec<RoundLine>,
+    /// Last 9, 6, 3 and 1 holes of the final round, for finished entrants
+    /// level with someone else.
+    pub countback: Option<[i32; 4]>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Leaderboard {
+    pub division: Division,
+    pub rounds: usize,
+    pub entries: Vec<LeaderboardEntry>,
+}
+
+fn countback(card: &RoundCard, division: Division) -> [i32; 4] {
+    let scores = card.scores(division);
+    COUNTBACK_HOLES.map(|holes| scores.iter().rev().take(holes).flatten().sum())
+}
+
+/// Ranks entrants by score to par over the holes they have played, so
+/// players part way through a round sit where they stand. Ties between
+/// finished entrants are split on countback over the final round.
+pub fn build(entrants: Vec<EntrantCards>, rounds: usize, division: Division) -> Leaderboard {
+    let mut entries: Vec<(LeaderboardEntry, Option<&RoundCard>)> = Vec::with_capacity(entrants.len());
+    for entrant in &entrants {
+        let mut lines = Vec::with_capacity(entrant.cards.len());
+        let mut to_par = Some(0);
+        for card in &entrant.cards {
+            let scores = card.scores(division);
+            let played: Vec<(i32, Option<i32>)> = scores
+                .iter()
+                .zip(&card.holes)
+                .filter_map(|(score, hole)| score.map(|score| (score, hole.par)))
+                .collect();
+            let round_to_par = played
+                .iter()
+                .map(|(score, par)| par.map(|par| score - par))
+                .sum::<Option<i32>>();
+            if !played.is_empty() {
+                to_par = to_par.zip(round_to_par).map(|(total, round)| total + round);
+            }
+            lines.push(RoundLine {
+                round_number: card.round_number,
+                thru: played.len() as i32,
+                strokes: played.iter().map(|(score, _)| score).sum(),
+                to_par: round_to_par,
+            });
+        }
+
+        let holes_played: i32 = lines.iter().map(|line| line.thru).sum();
+        let latest = lines.iter().rev().find(|line| line.thru > 0);
+        let finished = entrant.cards.len() == rounds && entrant.cards.iter().all(RoundCard::is_complete);
+        entries.push((
+            LeaderboardEntry {
+                position: None,
+                tied: false,
+                place: "-".to_string(),
+                entrant_id: entrant.entrant_id,
+                name: entrant.name.clone(),
+                flight_id: entrant.flight_id,
+                holes_played,
+                thru: latest.map_or(0, |line| line.thru),
+                today: latest.and_then(|line| line.to_par),
+                to_par: if holes_played > 0 { to_par } else { None },
+                strokes: lines.iter().map(|line| line.strokes).sum(),
+                finished,
+                rounds: lines,
+                countback: None,
+            },
+            entrant.cards.last().filter(|_| finished),
+        ));
+    }
+
+    // Strokes only decide places on courses without pars.
+    let score = |entry: &LeaderboardEntry| {
+        let score = entry.to_par.map_or((1, entry.strokes), |to_par| (0, to_par));
+        (entry.holes_played == 0, score)
+    };
+    let level = |a: &LeaderboardEntry, b: &LeaderboardEntry| a.holes_played > 0 && score(a) == score(b);
+    for i in 0..entries.len() {
+        let tied_finisher = entries[i].1.is_some()
+            && entries
+                .iter()
+                .enumerate()
+                .any(|(j, (other, card))| j != i && card.is_some() && level(&entries[i].0, other));
+        if tied_finisher {
+            entries[i].0.countback = entries[i].1.map(|card| countback(card, division));
+        }
+    }
+
+    // Finished entrants come before those still playing on the same score,
+    // ordered among themselves by countback.
+    entries.sort_by(|(a, _), (b, _)| {
+        score(a)
+            .cmp(&score(b))
+            .then(b.finished.cmp(&a.finished))
+            .then(a.countback.is_none().cmp(&b.countback.is_none()))
+            .then(a.countback.cmp(&b.countback))
+            .then(a.name.cmp(&b.name))
+    });
+    let mut entries: Vec<LeaderboardEntry> = entries.into_iter().map(|(entry, _)| entry).collect();
+
+    for i in 0..entries.len() {
+        if entries[i].holes_played == 0 {
+            break;
+        }
+        let shares_with_previous = i > 0 && level(&entries[i - 1], &entries[i]) && {
+            let (a, b) = (&entries[i - 1].countback, &entries[i].countback);
+            a.is_none() || b.is_none() || a == b
+        };
+        if shares_with_previous {
+            entries[i].position = entries[i - 1].position;
+            entries[i].tied = true;
+            entries[i - 1].tied = true;
+        } else {
+            entries[i].position = Some(i as i32 + 1);
+        }
+    }
+    for entry in &mut entries {
+        if let Some(position) = entry.position {
+            entry.place = format!("{}{}", if entry.tied { "T" } else { "" }, position);
+        }
+    }
+
+    Leaderboard {
+        division,
+        rounds,
+        entries,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An 18-hole card of par 4s with the hole's stroke index as `ranks`
+    /// gives it, `None` for holes not yet played.
+    fn card(playing_handicap: Option<i32>, ranks: &[i32], strokes: &[Option<i32>]) -> RoundCard {
+        RoundCard {
+            round_number: 1,
+            playing_handicap,
+            holes: (0..18)
+                .map(|i| CardHole {
+                    number: i as i32 + 1,
+                    par: Some(4),
+                    rank: ranks[i],
+                    strokes: strokes.get(i).copied().flatten(),
+                })
+                .collect(),
+        }
+    }
+
+    fn pars() -> Vec<Option<i32>> {
+        vec![Some(4); 18]
+    }
+
+    fn entrant(entrant_id: i32, name: &str, card: RoundCard) -> EntrantCards {
+        EntrantCards {
+            entrant_id,
+            name: name.to_string(),
+            flight_id: None,
+            cards: vec![card],
+        }
+    }
+
+    fn by_number() -> Vec<i32> {
+        (1..=18).collect()
+    }
+
+    fn places(board: &Leaderboard) -> Vec<(&str, &str)> {
+        board
+            .entries
+            .iter()
+            .map(|entry| (entry.name.as_str(), entry.place.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn countback_splits_tied_finishers() {
+        // Both level par; Bo bogeys the 1st and birdies the 18th, so plays
+        // the last nine a shot better.
+        let mut bo = pars();
+        bo[0] = Some(5);
+        bo[17] = Some(3);
+        let mut al = pars();
+        al[17] = Some(5);
+        al[0] = Some(3);
+        let board = build(
+            vec![
+                entrant(1, "Al", card(None, &by_number(), &al)),
+                entrant(2, "Bo", card(None, &by_number(), &bo)),
+            ],
+            1,
+            Division::Gross,
+        );
+        assert_eq!(places(&board), [("Bo", "1"), ("Al", "2")]);
+        assert_eq!(board.entries[0].countback, Some([35, 23, 11, 3]));
+        assert_eq!(board.entries[1].countback, Some([37, 25, 13, 5]));
+    }
+
+    #[test]
+    fn identical_cards_stay_tied() {
+        let mut al = pars();
+        al[4] = Some(3);
+        let board = build(
+            vec![
+                entrant(1, "Al", card(None, &by_number(), &al)),
+                entrant(2, "Bo", card(None, &by_number(), &pars())),
+                entrant(3, "Cy", card(None, &by_number(), &pars())),
+            ],
+            1,
+            Division::Gross,
+        );
+        assert_eq!(places(&board), [("Al", "1"), ("Bo", "T2"), ("Cy", "T2")]);
+        assert_eq!(board.entries[1].position, Some(2));
+        assert!(board.entries[1].tied && board.entries[2].tied);
+    }
+
+    #[test]
+    fn finisher_shares_a_place_with_someone_still_playing() {
+        let mut playing = pars();
+        playing[17] = None;
+        let board = build(
+            vec![
+                entrant(1, "Al", card(None, &by_number(), &playing)),
+                entrant(2, "Bo", card(None, &by_number(), &pars())),
+                entrant(3, "Cy", card(None, &by_number(), &[])),
+            ],
+            1,
+            Division::Gross,
+        );
+        assert_eq!(places(&board), [("Bo", "T1"), ("Al", "T1"), ("Cy", "-")]);
+        assert!(board.entries[0].finished);
+        assert_eq!(board.entries[1].thru, 17);
+        assert_eq!(board.entries[0].countback, None);
+        assert_eq!(board.entries[2].position, None);
+    }
+
+    #[test]
+    fn net_countback_allocates_strokes_by_stroke_index() {
+        // Bo gets one stroke, on the hardest hole: the 18th here. Their bogey
+        // on the 1st leaves them level with Al net, but a shot better over
+        // the last nine.
+        let mut ranks = by_number();
+        ranks.reverse();
+        let mut bo = pars();
+        bo[0] = Some(5);
+        let entrants = || {
+            vec![
+                entrant(1, "Al", card(Some(0), &ranks, &pars())),
+                entrant(2, "Bo", card(Some(1), &ranks, &bo)),
+            ]
+        };
+
+        let net = build(entrants(), 1, Division::Net);
+        assert_eq!(places(&net), [("Bo", "1"), ("Al", "2")]);
+        assert_eq!(net.entries[0].to_par, Some(0));
+        assert_eq!(net.entries[0].countback, Some([35, 23, 11, 3]));
+
+        let gross = build(entrants(), 1, Division::Gross);
+        assert_eq!(places(&gross), [("Al", "1"), ("Bo", "2")]);
+    }
+}
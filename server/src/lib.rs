@@ -0,0 +1,63 @@
+use diesel::PgConnection;
+use diesel::migration::MigrationVersion;
+use diesel::r2d2::{self, ConnectionManager};
+use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
+
+use config::DatabaseConfig;
+
+pub mod auth;
+pub mod aws_operations;
+pub mod bag_operations;
+pub mod cache;
+pub mod config;
+pub mod course_operations;
+pub mod db_operations;
+pub mod elevation;
+pub mod error;
+pub mod game_operations;
+pub mod games;
+pub mod geometry;
+pub mod green;
+pub mod handicap;
+pub mod handicap_operations;
+pub mod hole_mesh;
+pub mod hole_operations;
+pub mod leaderboard;
+pub mod models;
+pub mod pin_operations;
+pub mod plays_like;
+pub mod recommend_operations;
+pub mod round_event_operations;
+pub mod round_events;
+pub mod round_operations;
+pub mod routes;
+pub mod routing;
+pub mod schema;
+pub mod shot_detection;
+pub mod shot_operations;
+pub mod simulation;
+pub mod stats_operations;
+pub mod strokes_gained;
+pub mod tee_set_operations;
+pub mod tile_operations;
+pub mod tiles;
+pub mod tournament_operations;
+pub mod weather;
+pub mod weather_operations;
+
+pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations");
+
+pub type DbPool = r2d2::Pool<ConnectionManager<PgConnection>>;
+
+pub fn establish_connection(database: &DatabaseConfig) -> Result<DbPool, r2d2::PoolError> {
+    let manager = ConnectionManager::<PgConnection>::new(&database.url);
+    r2d2::Pool::builder()
+        .max_size(database.pool_size)
+        .build(manager)
+}
+
+/// Applies any migrations that have not run yet and returns their versions.
+pub fn run_migrations(conn: &mut PgConnection) -> Result<Vec<String>, Box<dyn std::error::Error + Send + Sync>> {
+    let applied = conn.run_pending_migrations(MIGRATIONS)?;
+    Ok(applied.iter().map(MigrationVersion::to_string).collect())
+}
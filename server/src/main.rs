@@ -1,80 +1,96 @@
-use std::env;
+use std::process;
+use std::time::Duration;
 
 use actix_cors::Cors;
-use actix_web::{App, HttpResponse, HttpServer, Responder, web};
-use diesel::PgConnection;
-use diesel::r2d2::{self, ConnectionManager};
-use dotenv::dotenv;
+use actix_web::{App, HttpServer, web};
+use openssl::ssl::{SslAcceptor, SslAcceptorBuilder, SslFiletype, SslMethod};
 
-mod aws_operations;
-mod db_operations;
-mod models;
-mod schema;
+use ai_caddie_backend::config::{Config, CorsConfig, TlsConfig};
+use ai_caddie_backend::hole_mesh::MeshCache;
+use ai_caddie_backend::round_events::RoundHub;
+use ai_caddie_backend::strokes_gained::Baseline;
+use ai_caddie_backend::tiles::TileCache;
+use ai_caddie_backend::weather::{self, WeatherProvider};
+use ai_caddie_backend::{establish_connection, routes};
 
-type DbPool = r2d2::Pool<ConnectionManager<PgConnection>>;
+fn build_cors(cors: &CorsConfig) -> Cors {
+    let mut builder = Cors::default()
+        .allow_any_method()
+        .allow_any_header()
+        .max_age(3600);
 
-fn establish_connection() -> DbPool {
-    dotenv().ok();
-    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
-    let manager = ConnectionManager::<PgConnection>::new(database_url);
-    r2d2::Pool::builder()
-        .build(manager)
-        .expect("Failed to create pool")
-}
-
-async fn get_courses(pool: web::Data<DbPool>) -> impl Responder {
-    print!("HIT");
-    let mut conn = pool.get().expect("couldn't get db connection from pool");
-    let result = web::block(move || db_operations::fetch_courses(&mut conn)).await;
-
-    match result {
-        Ok(Ok(courses)) => {
-            // Automatically serialize and return JSON
-            HttpResponse::Ok().json(courses)
-        }
-        Ok(Err(_)) | Err(_) => {
-            // Handle internal server errors
-            HttpResponse::InternalServerError().finish()
-        }
-    }
-}
-
-async fn get_hole_data(pool: web::Data<DbPool>, hole_id: web::Path<i32>) -> impl Responder {
-    let hole_id = hole_id.into_inner();
-    let mut conn = pool.get().expect("couldn't get db connection from pool");
-
-    match web::block(move || db_operations::fetch_hole_data(&mut conn, hole_id)).await {
-        Ok(result) => match result {
-            Ok(hole_data) => HttpResponse::Ok()
-                .content_type("application/json")
-                .json(hole_data),
-            Err(e) => HttpResponse::InternalServerError().body(format!("Database error: {:?}", e)),
-        },
-        Err(e) => HttpResponse::InternalServerError().body(format!("Server error: {:?}", e)),
+    for origin in &cors.allowed_origins {
+        builder = if origin == "*" {
+            builder.allow_any_origin()
+        } else {
+            builder.allowed_origin(origin)
+        };
     }
+    builder
 }
 
-async fn trigger_aws_curls(course_id: web::Path<String>) -> impl Responder {
-    let course_id = course_id.into_inner();
-    match aws_operations::get_holes_from_golfbert(course_id).await {
-        Ok(_) => HttpResponse::Ok().body("AWS curls executed successfully"),
-        Err(e) => HttpResponse::InternalServerError().body(format!("Error: {}", e)),
-    }
+fn build_tls_acceptor(tls: &TlsConfig) -> std::io::Result<SslAcceptorBuilder> {
+    let mut builder = SslAcceptor::mozilla_intermediate(SslMethod::tls())?;
+    builder.set_private_key_file(&tls.key_path, SslFiletype::PEM)?;
+    builder.set_certificate_chain_file(&tls.cert_path)?;
+    Ok(builder)
 }
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
-    let pool = establish_connection();
+    let config = Config::load().unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(1);
+    });
 
-    HttpServer::new(move || {
+    let pool = establish_connection(&config.database).unwrap_or_else(|e| {
+        eprintln!("Failed to create database pool: {}", e);
+        process::exit(1);
+    });
+
+    let baseline = Baseline::load(config.analytics.strokes_baseline_path.as_deref()).unwrap_or_else(|e| {
+        eprintln!("Failed to load strokes-gained baseline: {}", e);
+        process::exit(1);
+    });
+    let baseline = web::Data::new(baseline);
+
+    let weather = weather::load_provider(&config.providers.weather).unwrap_or_else(|e| {
+        eprintln!("Failed to load weather provider: {}", e);
+        process::exit(1);
+    });
+    let weather: web::Data<dyn WeatherProvider> = web::Data::from(weather);
+
+    let tiles = web::Data::new(TileCache::new(
+        Duration::from_secs(config.tiles.cache_ttl_secs),
+        config.tiles.cache_max_tiles,
+    ));
+    let meshes = web::Data::new(MeshCache::new(
+        Duration::from_secs(config.meshes.cache_ttl_secs),
+        config.meshes.cache_max_meshes,
+    ));
+    let hub = web::Data::new(RoundHub::new());
+
+    let bind_address = config.server.bind_address.clone();
+    let tls = config.server.tls.clone();
+    let config = web::Data::new(config);
+
+    let server = HttpServer::new(move || {
         App::new()
-            .wrap(Cors::permissive())
+            .wrap(build_cors(&config.cors))
             .app_data(web::Data::new(pool.clone()))
-            .route("/hole/{hole_id}", web::get().to(get_hole_data))
-            .route("/trigger_aws", web::post().to(trigger_aws_curls))
-            .route("/courses", web::get().to(get_courses))
-    })
-    .bind("127.0.0.1:8080")?
-    .run()
-    .await
+            .app_data(config.clone())
+            .app_data(baseline.clone())
+            .app_data(weather.clone())
+            .app_data(tiles.clone())
+            .app_data(meshes.clone())
+            .app_data(hub.clone())
+            .configure(|cfg| routes::configure(cfg, &config))
+    });
+
+    let server = match tls {
+        Some(tls) => server.bind_openssl(&bind_address, build_tls_acceptor(&tls)?)?,
+        None => server.bind(&bind_address)?,
+    };
+
+    server.run().await
 }
@@ -1,4 +1,8 @@
+use chrono::{NaiveDate, NaiveDateTime};
 use diesel::prelude::*;
+use crate::elevation::HoleElevation;
+use crate::geometry::LatLong;
+use crate::routing::HoleRoute;
 use crate::schema::*;
 use serde::{Serialize, Deserialize};
 
@@ -28,6 +32,37 @@ pub struct Hole {
     pub dimensions_height: Option<i32>,
     pub flag_lat: Option<f64>,
     pub flag_long: Option<f64>,
+    pub par: Option<i32>,
+    pub flag_elevation_ft: Option<f64>,
+    pub name: Option<String>,
+    /// Handicap stroke index, 1 being the hardest hole.
+    pub stroke_index: Option<i32>,
+    /// Women's stroke index, where it differs from `stroke_index`.
+    pub stroke_index_women: Option<i32>,
+}
+
+impl Hole {
+    /// The flag position stored with the hole.
+    pub fn flag(&self) -> Option<LatLong> {
+        match (self.flag_lat, self.flag_long) {
+            (Some(lat), Some(long)) => Some(LatLong::new(lat, long)),
+            _ => None,
+        }
+    }
+
+    /// Stroke allocation order, falling back to the hole number on courses
+    /// without stroke indexes.
+    pub fn rank(&self) -> Option<i32> {
+        self.stroke_index.or(self.number)
+    }
+
+    /// The stroke index played from tees set up for `gender`.
+    pub fn stroke_index_for(&self, gender: &str) -> Option<i32> {
+        match gender {
+            "women" => self.stroke_index_women.or(self.stroke_index),
+            _ => self.stroke_index,
+        }
+    }
 }
 
 #[derive(Queryable, Associations, Serialize, Deserialize, Debug)]
@@ -39,6 +74,8 @@ pub struct Polygon {
     pub surface_type: Option<String>,
     pub lat: Option<f64>,
     pub long: Option<f64>,
+    pub shape_index: Option<i32>,
+    pub elevation_ft: Option<f64>,
 }
 
 #[derive(Queryable, Associations, Serialize, Deserialize, Debug)]
@@ -50,6 +87,7 @@ pub struct Vector {
     pub vector_type: Option<String>,
     pub lat: Option<f64>,
     pub long: Option<f64>,
+    pub elevation_ft: Option<f64>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -57,6 +95,23 @@ pub struct HoleData {
     pub hole: Hole,
     pub polygons: Vec<Polygon>,
     pub vectors: Vec<Vector>,
+    /// Centerline and dogleg geometry, once it has been derived.
+    #[serde(default)]
+    pub route: Option<HoleRoute>,
+    /// Terrain sampled from an elevation model, once it has been imported.
+    #[serde(default)]
+    pub elevation: Option<HoleElevation>,
+}
+
+impl HoleData {
+    /// Tee marker positions; every vector other than the flag is a tee.
+    pub fn tees(&self) -> Vec<LatLong> {
+        self.vectors
+            .iter()
+            .filter(|v| v.vector_type.as_deref() != Some("Flag"))
+            .filter_map(|v| Some(LatLong::new(v.lat?, v.long?)))
+            .collect()
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -64,6 +119,42 @@ pub struct PolyVectorData {
     pub polygons: Vec<Polygon>,
     pub vectors: Vec<Vector>,
 }
+#[derive(Queryable, Selectable, Insertable, AsChangeset, Debug)]
+#[diesel(table_name = hole_routes)]
+pub struct HoleRouteRow {
+    pub hole_id: i32,
+    pub yards: f64,
+    pub dogleg: String,
+    pub dogleg_degrees: f64,
+    pub landing_width_200: Option<f64>,
+    pub landing_width_250: Option<f64>,
+    pub landing_width_300: Option<f64>,
+}
+
+#[derive(Queryable, Selectable, Insertable, Debug)]
+#[diesel(table_name = hole_route_points)]
+pub struct HoleRoutePoint {
+    pub hole_id: i32,
+    pub point_index: i32,
+    pub lat: f64,
+    pub long: f64,
+    pub turn: bool,
+}
+
+#[derive(Queryable, Selectable, Insertable, Debug)]
+#[diesel(table_name = hole_elevations)]
+pub struct HoleElevationRow {
+    pub hole_id: i32,
+    pub source: String,
+    pub origin_lat: f64,
+    pub origin_long: f64,
+    pub spacing_yards: f64,
+    pub grid_columns: i32,
+    pub grid_rows: i32,
+    pub elevations_ft: Vec<Option<f64>>,
+    pub sampled_at: NaiveDateTime,
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct HoleWithSVG {
     pub hole: Hole,
@@ -84,4 +175,336 @@ pub struct HoleCourseAssociation {
 pub struct CourseWithHoles {
     pub course: Course,
     pub holes: Vec<Hole>,
-}
\ No newline at end of file
+}
+
+#[derive(Queryable, Selectable, Identifiable, Serialize, Debug)]
+#[diesel(table_name = users)]
+pub struct User {
+    pub id: i32,
+    pub email: String,
+    #[serde(skip_serializing)]
+    pub password_hash: String,
+    pub display_name: Option<String>,
+    pub role: String,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Queryable, Selectable, Identifiable, Associations, Debug)]
+#[diesel(belongs_to(User))]
+#[diesel(table_name = refresh_tokens)]
+pub struct RefreshToken {
+    pub id: i32,
+    pub user_id: i32,
+    pub token_hash: String,
+    pub expires_at: NaiveDateTime,
+    pub revoked_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Queryable, Selectable, Identifiable, Associations, Serialize, Debug, Clone)]
+#[diesel(belongs_to(User))]
+#[diesel(table_name = clubs)]
+pub struct Club {
+    pub id: i32,
+    #[serde(skip_serializing)]
+    pub user_id: i32,
+    pub club_type: String,
+    pub name: String,
+    pub loft: Option<f64>,
+    pub brand: Option<String>,
+    pub model: Option<String>,
+    pub carry_yards: Option<f64>,
+    pub total_yards: Option<f64>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Serialize, Debug)]
+pub struct HoleWithBag {
+    pub hole_data: HoleData,
+    pub bag: Vec<Club>,
+}
+
+#[derive(Queryable, Selectable, Identifiable, Associations, Serialize, Debug, Clone)]
+#[diesel(belongs_to(User))]
+#[diesel(table_name = rounds)]
+pub struct Round {
+    pub id: i32,
+    pub user_id: i32,
+    pub course_id: i32,
+    pub tee_set: Option<String>,
+    pub played_on: NaiveDate,
+    pub status: String,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+    pub finished_at: Option<NaiveDateTime>,
+    pub tee_set_id: Option<i32>,
+}
+
+#[derive(Queryable, Selectable, Identifiable, Associations, Serialize, Debug, Clone)]
+#[diesel(belongs_to(Round))]
+#[diesel(table_name = round_players)]
+pub struct RoundPlayer {
+    pub id: i32,
+    pub round_id: i32,
+    pub user_id: Option<i32>,
+    pub name: String,
+}
+
+/// A change to a round, numbered in order within the round so live clients
+/// can tell what they have missed.
+#[derive(Queryable, Selectable, Identifiable, Associations, Serialize, Debug, Clone)]
+#[diesel(belongs_to(Round))]
+#[diesel(table_name = round_events)]
+pub struct RoundEvent {
+    pub id: i32,
+    pub round_id: i32,
+    pub sequence: i32,
+    pub kind: String,
+    pub payload: serde_json::Value,
+    pub created_at: NaiveDateTime,
+}
+
+/// A side game played over a round, such as skins or a Nassau.
+#[derive(Queryable, Selectable, Identifiable, Associations, Serialize, Debug, Clone)]
+#[diesel(belongs_to(Round))]
+#[diesel(table_name = round_games)]
+pub struct RoundGame {
+    pub id: i32,
+    pub round_id: i32,
+    pub format: String,
+    pub stake: i32,
+    pub allowance_percent: f64,
+    pub carry_overs: bool,
+    pub press_after: Option<i32>,
+    pub side_a: Vec<i32>,
+    pub side_b: Vec<i32>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Queryable, Selectable, Identifiable, Associations, Serialize, Debug, Clone)]
+#[diesel(belongs_to(Round))]
+#[diesel(belongs_to(RoundPlayer, foreign_key = player_id))]
+#[diesel(table_name = hole_scores)]
+pub struct HoleScore {
+    pub id: i32,
+    pub round_id: i32,
+    pub player_id: i32,
+    pub hole_id: i32,
+    pub strokes: i32,
+    pub putts: Option<i32>,
+    pub penalties: i32,
+    pub fairway_hit: Option<bool>,
+    pub green_in_regulation: Option<bool>,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Queryable, Selectable, Identifiable, Associations, Serialize, Debug, Clone)]
+#[diesel(belongs_to(Round))]
+#[diesel(belongs_to(RoundPlayer, foreign_key = player_id))]
+#[diesel(table_name = shots)]
+pub struct Shot {
+    pub id: i32,
+    pub round_id: i32,
+    pub player_id: i32,
+    pub hole_id: i32,
+    pub shot_number: i32,
+    pub start_lat: f64,
+    pub start_long: f64,
+    pub end_lat: Option<f64>,
+    pub end_long: Option<f64>,
+    pub club_id: Option<i32>,
+    pub club: Option<String>,
+    pub lie: Option<String>,
+    pub penalty_strokes: i32,
+    pub distance_yards: Option<f64>,
+    pub remaining_yards: Option<f64>,
+    pub start_lie: Option<String>,
+    pub end_lie: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub carry_yards: Option<f64>,
+    pub target_lat: Option<f64>,
+    pub target_long: Option<f64>,
+}
+
+impl Shot {
+    pub fn start(&self) -> LatLong {
+        LatLong::new(self.start_lat, self.start_long)
+    }
+
+    pub fn end(&self) -> Option<LatLong> {
+        match (self.end_lat, self.end_long) {
+            (Some(lat), Some(long)) => Some(LatLong::new(lat, long)),
+            _ => None,
+        }
+    }
+
+    /// Where the player was aiming, if they said.
+    pub fn target(&self) -> Option<LatLong> {
+        match (self.target_lat, self.target_long) {
+            (Some(lat), Some(long)) => Some(LatLong::new(lat, long)),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Queryable, Selectable, Identifiable, Serialize, Debug, Clone)]
+#[diesel(table_name = tee_sets)]
+pub struct TeeSet {
+    pub id: i32,
+    pub course_id: i32,
+    pub name: String,
+    pub course_rating: f64,
+    pub slope_rating: i32,
+    pub par: i32,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+    pub colour: Option<String>,
+    pub gender: String,
+}
+
+impl TeeSet {
+    /// Whether a hole's tee marker belongs to this set. Golfbert names
+    /// markers after their colour.
+    pub fn uses_marker(&self, marker: &str) -> bool {
+        let marker = marker.trim();
+        self.colour.as_deref().unwrap_or(&self.name).eq_ignore_ascii_case(marker)
+            || self.name.eq_ignore_ascii_case(marker)
+    }
+}
+
+#[derive(Queryable, Selectable, Identifiable, Insertable, Associations, Serialize, Debug, Clone)]
+#[diesel(belongs_to(TeeSet))]
+#[diesel(table_name = tee_set_holes)]
+#[diesel(primary_key(tee_set_id, hole_id))]
+pub struct TeeSetHole {
+    #[serde(skip_serializing)]
+    pub tee_set_id: i32,
+    pub hole_id: i32,
+    pub tee_lat: f64,
+    pub tee_long: f64,
+    pub yards: i32,
+}
+
+/// Par, stroke index or name a tee set plays a hole with, where it differs
+/// from the hole's own.
+#[derive(Queryable, Selectable, Identifiable, Insertable, Associations, Serialize, Debug, Clone)]
+#[diesel(belongs_to(TeeSet))]
+#[diesel(table_name = tee_set_hole_overrides)]
+#[diesel(primary_key(tee_set_id, hole_id))]
+pub struct TeeSetHoleOverride {
+    #[serde(skip_serializing)]
+    pub tee_set_id: i32,
+    pub hole_id: i32,
+    pub par: Option<i32>,
+    pub stroke_index: Option<i32>,
+    pub name: Option<String>,
+}
+
+#[derive(Queryable, Selectable, Identifiable, Serialize, Debug, Clone)]
+#[diesel(table_name = pin_sheets)]
+pub struct PinSheet {
+    pub id: i32,
+    pub course_id: i32,
+    pub played_on: NaiveDate,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+/// A hole's pin on one day. Pins entered as paces keep them so the sheet
+/// can be printed the way it was written.
+#[derive(Queryable, Selectable, Identifiable, Insertable, Associations, Serialize, Debug, Clone)]
+#[diesel(belongs_to(PinSheet))]
+#[diesel(table_name = pin_positions)]
+#[diesel(primary_key(pin_sheet_id, hole_id))]
+pub struct PinPosition {
+    #[serde(skip_serializing)]
+    pub pin_sheet_id: i32,
+    pub hole_id: i32,
+    pub lat: f64,
+    pub long: f64,
+    pub front_paces: Option<i32>,
+    pub side_paces: Option<i32>,
+    pub side: Option<String>,
+}
+
+impl PinPosition {
+    pub fn position(&self) -> LatLong {
+        LatLong::new(self.lat, self.long)
+    }
+}
+
+#[derive(Queryable, Selectable, Identifiable, Serialize, Debug, Clone)]
+#[diesel(table_name = tournaments)]
+pub struct Tournament {
+    pub id: i32,
+    pub name: String,
+    pub organiser_id: i32,
+    /// Share of each course handicap used for the net division.
+    pub allowance_percent: f64,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+/// One round of a tournament, on its own course and day.
+#[derive(Queryable, Selectable, Identifiable, Associations, Serialize, Debug, Clone)]
+#[diesel(belongs_to(Tournament))]
+#[diesel(table_name = tournament_rounds)]
+pub struct TournamentRound {
+    pub id: i32,
+    pub tournament_id: i32,
+    pub round_number: i32,
+    pub course_id: i32,
+    pub tee_set_id: Option<i32>,
+    pub played_on: NaiveDate,
+}
+
+/// Entrants are placed in the first flight whose `max_index` their
+/// Handicap Index is within; a flight without one takes everyone left.
+#[derive(Queryable, Selectable, Identifiable, Associations, Serialize, Debug, Clone)]
+#[diesel(belongs_to(Tournament))]
+#[diesel(table_name = tournament_flights)]
+pub struct TournamentFlight {
+    pub id: i32,
+    pub tournament_id: i32,
+    pub name: String,
+    pub max_index: Option<f64>,
+}
+
+#[derive(Queryable, Selectable, Identifiable, Associations, Serialize, Debug, Clone)]
+#[diesel(belongs_to(Tournament))]
+#[diesel(table_name = tournament_entrants)]
+pub struct TournamentEntrant {
+    pub id: i32,
+    pub tournament_id: i32,
+    pub user_id: Option<i32>,
+    pub name: String,
+    /// Index when the entrant was entered, used for every round.
+    pub handicap_index: Option<f64>,
+    pub flight_id: Option<i32>,
+}
+
+/// A tee time in one tournament round. Starting the group creates the
+/// stored round its scores are kept in.
+#[derive(Queryable, Selectable, Identifiable, Associations, Serialize, Debug, Clone)]
+#[diesel(belongs_to(TournamentRound))]
+#[diesel(table_name = tournament_groups)]
+pub struct TournamentGroup {
+    pub id: i32,
+    pub tournament_round_id: i32,
+    pub tee_time: NaiveDateTime,
+    pub starting_hole: i32,
+    pub round_id: Option<i32>,
+}
+
+#[derive(Queryable, Selectable, Identifiable, Insertable, Associations, Serialize, Debug, Clone)]
+#[diesel(belongs_to(TournamentGroup, foreign_key = group_id))]
+#[diesel(belongs_to(TournamentEntrant, foreign_key = entrant_id))]
+#[diesel(table_name = tournament_group_entrants)]
+#[diesel(primary_key(group_id, entrant_id))]
+pub struct TournamentGroupEntrant {
+    pub group_id: i32,
+    pub entrant_id: i32,
+    /// The entrant's player in the group's round, once it has started.
+    pub round_player_id: Option<i32>,
+}
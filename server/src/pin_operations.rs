@@ -0,0 +1,213 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::{NaiveDate, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::course_operations;
+use crate::error::ApiError;
+use crate::geometry::{green_shape, group_shapes, local_time, LatLong};
+use crate::green::{self, PinSide};
+use crate::models::{HoleData, PinPosition, PinSheet};
+use crate::schema::{pin_positions, pin_sheets};
+
+/// How many sheets the course list returns.
+const RECENT_SHEETS: i64 = 30;
+
+/// One hole's pin, either as a position or paced out from the front edge
+/// and one side of the green.
+#[derive(Deserialize, Debug)]
+pub struct PinInput {
+    pub hole_id: i32,
+    pub lat: Option<f64>,
+    pub long: Option<f64>,
+    /// Paces on from the front edge along the line of play, each a yard
+    /// ([`green::YARDS_PER_PACE`]).
+    pub front_paces: Option<i32>,
+    /// Paces in from `side`, also a yard each.
+    pub side_paces: Option<i32>,
+    pub side: Option<PinSide>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct PinSheetInput {
+    pub pins: Vec<PinInput>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct PinSheetDetails {
+    #[serde(flatten)]
+    pub sheet: PinSheet,
+    pub pins: Vec<PinPosition>,
+}
+
+fn resolve_pin(hole_data: &HoleData, input: &PinInput) -> Result<PinPosition, ApiError> {
+    let hole_id = input.hole_id;
+    let paces = (input.front_paces, input.side_paces, input.side);
+    let (position, front_paces, side_paces, side) = match (input.lat, input.long, paces) {
+        (Some(lat), Some(long), (None, None, None)) => (LatLong::new(lat, long), None, None, None),
+        (None, None, (Some(front), Some(across), Some(side))) => {
+            if front < 0 || across < 0 {
+                return Err(ApiError::BadRequest(format!("paces for hole {} can't be negative", hole_id)));
+            }
+            let shapes = group_shapes(&hole_data.polygons);
+            let pin = green::approach_position(hole_data)
+                .zip(green_shape(&shapes, hole_data.hole.flag()))
+                .and_then(|(from, green)| green::pin_from_paces(green, from, front, side, across))
+                .ok_or_else(|| {
+                    ApiError::BadRequest(format!(
+                        "{} on and {} from the {} is off the green on hole {}",
+                        front,
+                        across,
+                        side.as_str(),
+                        hole_id
+                    ))
+                })?;
+            (pin, Some(front), Some(across), Some(side.as_str().to_string()))
+        }
+        _ => {
+            return Err(ApiError::BadRequest(format!(
+                "give hole {} either lat and long, or front_paces, side_paces and side",
+                hole_id
+            )))
+        }
+    };
+    if !position.is_valid() {
+        return Err(ApiError::BadRequest(format!("pin for hole {} is not a valid position", hole_id)));
+    }
+
+    Ok(PinPosition {
+        pin_sheet_id: 0,
+        hole_id,
+        lat: position.lat,
+        long: position.long,
+        front_paces,
+        side_paces,
+        side,
+    })
+}
+
+/// Replaces a course's pin sheet for a day.
+pub fn upload_pin_sheet(
+    conn: &mut PgConnection,
+    course_id: i32,
+    played_on: NaiveDate,
+    input: PinSheetInput,
+) -> Result<PinSheetDetails, ApiError> {
+    course_operations::fetch_course(conn, course_id)?;
+    let holes: HashMap<i32, HoleData> = course_operations::fetch_course_hole_data(conn, course_id)?
+        .into_iter()
+        .map(|hole_data| (hole_data.hole.hole_id, hole_data))
+        .collect();
+
+    let mut seen = HashSet::new();
+    let mut pins = Vec::with_capacity(input.pins.len());
+    for pin in &input.pins {
+        let hole_data = holes
+            .get(&pin.hole_id)
+            .ok_or_else(|| ApiError::BadRequest(format!("hole {} is not on this course", pin.hole_id)))?;
+        if !seen.insert(pin.hole_id) {
+            return Err(ApiError::BadRequest(format!("hole {} has more than one pin", pin.hole_id)));
+        }
+        pins.push(resolve_pin(hole_data, pin)?);
+    }
+
+    conn.transaction(|conn| {
+        let sheet = diesel::insert_into(pin_sheets::table)
+            .values((pin_sheets::course_id.eq(course_id), pin_sheets::played_on.eq(played_on)))
+            .on_conflict((pin_sheets::course_id, pin_sheets::played_on))
+            .do_update()
+            .set(pin_sheets::updated_at.eq(diesel::dsl::now))
+            .returning(PinSheet::as_returning())
+            .get_result(conn)?;
+        for pin in &mut pins {
+            pin.pin_sheet_id = sheet.id;
+        }
+
+        diesel::delete(pin_positions::table.filter(pin_positions::pin_sheet_id.eq(sheet.id))).execute(conn)?;
+        diesel::insert_into(pin_positions::table).values(&pins).execute(conn)?;
+        Ok(PinSheetDetails { sheet, pins })
+    })
+}
+
+fn with_pins(conn: &mut PgConnection, sheets: Vec<PinSheet>) -> Result<Vec<PinSheetDetails>, ApiError> {
+    let pins = PinPosition::belonging_to(&sheets)
+        .order(pin_positions::hole_id.asc())
+        .select(PinPosition::as_select())
+        .load(conn)?
+        .grouped_by(&sheets);
+    Ok(sheets
+        .into_iter()
+        .zip(pins)
+        .map(|(sheet, pins)| PinSheetDetails { sheet, pins })
+        .collect())
+}
+
+/// A course's most recent pin sheets, newest first.
+pub fn fetch_pin_sheets(conn: &mut PgConnection, course_id: i32) -> Result<Vec<PinSheetDetails>, ApiError> {
+    let sheets = pin_sheets::table
+        .filter(pin_sheets::course_id.eq(course_id))
+        .order(pin_sheets::played_on.desc())
+        .limit(RECENT_SHEETS)
+        .select(PinSheet::as_select())
+        .load(conn)?;
+    with_pins(conn, sheets)
+}
+
+pub fn fetch_pin_sheet(conn: &mut PgConnection, course_id: i32, played_on: NaiveDate) -> Result<PinSheetDetails, ApiError> {
+    let sheet = pin_sheets::table
+        .filter(pin_sheets::course_id.eq(course_id))
+        .filter(pin_sheets::played_on.eq(played_on))
+        .select(PinSheet::as_select())
+        .first(conn)?;
+    Ok(with_pins(conn, vec![sheet])?.remove(0))
+}
+
+pub fn delete_pin_sheet(conn: &mut PgConnection, course_id: i32, played_on: NaiveDate) -> Result<(), ApiError> {
+    let deleted = diesel::delete(
+        pin_sheets::table
+            .filter(pin_sheets::course_id.eq(course_id))
+            .filter(pin_sheets::played_on.eq(played_on)),
+    )
+    .execute(conn)?;
+
+    if deleted == 0 {
+        return Err(ApiError::NotFound);
+    }
+    Ok(())
+}
+
+/// The pin on a hole on a given day, if that day's sheet has one.
+pub fn pin_on(conn: &mut PgConnection, hole_id: i32, played_on: NaiveDate) -> Result<Option<LatLong>, ApiError> {
+    Ok(pin_positions::table
+        .inner_join(pin_sheets::table)
+        .filter(pin_positions::hole_id.eq(hole_id))
+        .filter(pin_sheets::played_on.eq(played_on))
+        .select(PinPosition::as_select())
+        .first(conn)
+        .optional()?
+        .map(|pin| pin.position()))
+}
+
+/// Today's date where the course is, which decides the day's sheet. Without
+/// a position it's today's date in UTC.
+pub fn today_at(location: Option<LatLong>) -> NaiveDate {
+    let now = Utc::now().naive_utc();
+    location.map_or(now, |location| local_time(location, now)).date()
+}
+
+pub fn todays_pin(conn: &mut PgConnection, hole_id: i32, location: LatLong) -> Result<Option<LatLong>, ApiError> {
+    pin_on(conn, hole_id, today_at(Some(location)))
+}
+
+/// Every recorded pin on the given holes, keyed by hole and day.
+pub fn pins_for_holes(conn: &mut PgConnection, hole_ids: &[i32]) -> Result<HashMap<(i32, NaiveDate), LatLong>, ApiError> {
+    Ok(pin_positions::table
+        .inner_join(pin_sheets::table)
+        .filter(pin_positions::hole_id.eq_any(hole_ids))
+        .select((PinPosition::as_select(), pin_sheets::played_on))
+        .load::<(PinPosition, NaiveDate)>(conn)?
+        .into_iter()
+        .map(|(pin, played_on)| ((pin.hole_id, played_on), pin.position()))
+        .collect())
+}
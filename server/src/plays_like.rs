@@ -0,0 +1,288 @@
+use serde::{Deserialize, Serialize};
+
+use crate::geometry::Lie;
+use crate::simulation::lie_factor;
+use crate::weather::Weather;
+
+/// Headwinds cost more than tailwinds give back.
+const HEADWIND_PER_MPH: f64 = 0.01;
+const TAILWIND_PER_MPH: f64 = 0.005;
+/// Ball drift in yards per mph of crosswind for every 100 yards of flight.
+const CROSSWIND_DRIFT: f64 = 0.7;
+/// Yards added for every yard the target sits above the ball.
+const ELEVATION_YARDS_PER_YARD: f64 = 1.0;
+/// Share of a change in air density that shows up in distance.
+const DENSITY_EFFECT: f64 = 0.5;
+/// The air club distances are taken to be measured in.
+const STANDARD_TEMPERATURE_F: f64 = 70.0;
+const STANDARD_PRESSURE_HPA: f64 = 1013.25;
+const STANDARD_HUMIDITY_PERCENT: f64 = 50.0;
+/// Gas constants for dry air and water vapour, J/(kg·K).
+const DRY_AIR_CONSTANT: f64 = 287.058;
+const VAPOUR_CONSTANT: f64 = 461.495;
+const FEET_PER_METRE: f64 = 3.28084;
+
+/// Conditions for a shot, all optional. Anything missing is taken as the
+/// standard conditions club distances are measured in. Wind direction is
+/// where the wind blows from, in degrees clockwise from north.
+#[derive(Deserialize, Debug, Clone, Copy, Default)]
+pub struct Conditions {
+    pub wind_mph: Option<f64>,
+    pub wind_from_deg: Option<f64>,
+    pub temperature_f: Option<f64>,
+    pub humidity_percent: Option<f64>,
+    /// Sea-level pressure, as weather reports give it.
+    pub pressure_hpa: Option<f64>,
+    /// Height of the course above sea level.
+    pub altitude_ft: Option<f64>,
+    /// How far the target is above the ball; negative when it's below.
+    pub elevation_change_ft: Option<f64>,
+}
+
+impl Conditions {
+    pub fn from_weather(weather: &Weather) -> Conditions {
+        Conditions {
+            wind_mph: Some(weather.wind_mph),
+            wind_from_deg: Some(weather.wind_from_deg),
+            temperature_f: Some(weather.temperature_f),
+            humidity_percent: Some(weather.humidity_percent),
+            pressure_hpa: Some(weather.pressure_hpa),
+            ..Conditions::default()
+        }
+    }
+
+    /// Fills anything missing here from `other`.
+    pub fn or(self, other: Conditions) -> Conditions {
+        Conditions {
+            wind_mph: self.wind_mph.or(other.wind_mph),
+            wind_from_deg: self.wind_from_deg.or(other.wind_from_deg),
+            temperature_f: self.temperature_f.or(other.temperature_f),
+            humidity_percent: self.humidity_percent.or(other.humidity_percent),
+            pressure_hpa: self.pressure_hpa.or(other.pressure_hpa),
+            altitude_ft: self.altitude_ft.or(other.altitude_ft),
+            elevation_change_ft: self.elevation_change_ft.or(other.elevation_change_ft),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.wind_mph.is_none()
+            && self.temperature_f.is_none()
+            && self.humidity_percent.is_none()
+            && self.pressure_hpa.is_none()
+            && self.altitude_ft.is_none()
+            && self.elevation_change_ft.is_none()
+    }
+
+    fn has_air(&self) -> bool {
+        self.temperature_f.is_some()
+            || self.humidity_percent.is_some()
+            || self.pressure_hpa.is_some()
+            || self.altitude_ft.is_some()
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AdjustmentKind {
+    Wind,
+    Elevation,
+    AirDensity,
+    Lie,
+}
+
+/// Yards one effect adds to (or takes off) the shot.
+#[derive(Serialize, Debug, Clone)]
+pub struct Adjustment {
+    pub kind: AdjustmentKind,
+    pub yards: f64,
+    pub description: String,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct PlaysLike {
+    pub distance_yards: f64,
+    pub plays_like_yards: f64,
+    /// Wind along the shot; positive is into the wind.
+    pub headwind_mph: f64,
+    /// Wind across the shot; positive blows from the right.
+    pub crosswind_mph: f64,
+    /// Yards the wind moves the ball right (negative is left) per 100 yards
+    /// of flight.
+    pub drift_per_100: f64,
+    pub adjustments: Vec<Adjustment>,
+}
+
+impl PlaysLike {
+    /// How much longer than its yardage the shot plays, leaving out the lie,
+    /// which clubs allow for separately.
+    pub fn conditions_factor(&self) -> f64 {
+        if self.distance_yards <= 0.0 {
+            return 1.0;
+        }
+        let yards: f64 = self
+            .adjustments
+            .iter()
+            .filter(|adjustment| adjustment.kind != AdjustmentKind::Lie)
+            .map(|adjustment| adjustment.yards)
+            .fold(0.0, |sum, yards| sum + yards);
+        (self.distance_yards + yards) / self.distance_yards
+    }
+}
+
+/// Air pressure at `altitude_ft` for a given sea-level pressure.
+fn station_pressure(sea_level_hpa: f64, altitude_ft: f64) -> f64 {
+    sea_level_hpa * (1.0 - 2.25577e-5 * altitude_ft / FEET_PER_METRE).powf(5.25588)
+}
+
+/// Density of moist air in kg/m³.
+fn air_density(temperature_f: f64, pressure_hpa: f64, humidity_percent: f64) -> f64 {
+    let celsius = (temperature_f - 32.0) * 5.0 / 9.0;
+    let kelvin = celsius + 273.15;
+    let saturation_hpa = 6.1078 * 10f64.powf(7.5 * celsius / (celsius + 237.3));
+    let vapour_hpa = saturation_hpa * humidity_percent.clamp(0.0, 100.0) / 100.0;
+    let dry_hpa = pressure_hpa - vapour_hpa;
+    (dry_hpa * 100.0) / (DRY_AIR_CONSTANT * kelvin) + (vapour_hpa * 100.0) / (VAPOUR_CONSTANT * kelvin)
+}
+
+/// What a shot of `distance_yards` on `bearing_deg` from `lie` plays like,
+/// with each effect broken out. Adjustments are worked out independently
+/// from the raw yardage and added up.
+pub fn plays_like(distance_yards: f64, bearing_deg: f64, lie: Lie, conditions: &Conditions) -> PlaysLike {
+    let mut adjustments = Vec::new();
+    let (mut headwind, mut crosswind) = (0.0, 0.0);
+
+    if let (Some(mph), Some(from)) = (conditions.wind_mph, conditions.wind_from_deg) {
+        let relative = (from - bearing_deg).to_radians();
+        headwind = mph * relative.cos();
+        crosswind = mph * relative.sin();
+        let per_mph = if headwind >= 0.0 { HEADWIND_PER_MPH } else { TAILWIND_PER_MPH };
+        adjustments.push(Adjustment {
+            kind: AdjustmentKind::Wind,
+            yards: distance_yards * headwind * per_mph,
+            description: format!(
+                "{:.0} mph {}",
+                headwind.abs(),
+                if headwind >= 0.0 { "into the wind" } else { "downwind" }
+            ),
+        });
+    }
+
+    if let Some(feet) = conditions.elevation_change_ft {
+        adjustments.push(Adjustment {
+            kind: AdjustmentKind::Elevation,
+            yards: feet / 3.0 * ELEVATION_YARDS_PER_YARD,
+            description: format!("{:.0} ft {}", feet.abs(), if feet >= 0.0 { "uphill" } else { "downhill" }),
+        });
+    }
+
+    if conditions.has_air() {
+        let temperature = conditions.temperature_f.unwrap_or(STANDARD_TEMPERATURE_F);
+        let humidity = conditions.humidity_percent.unwrap_or(STANDARD_HUMIDITY_PERCENT);
+        let altitude = conditions.altitude_ft.unwrap_or(0.0);
+        let pressure = station_pressure(conditions.pressure_hpa.unwrap_or(STANDARD_PRESSURE_HPA), altitude);
+        let standard = air_density(STANDARD_TEMPERATURE_F, STANDARD_PRESSURE_HPA, STANDARD_HUMIDITY_PERCENT);
+        let ratio = air_density(temperature, pressure, humidity) / standard;
+        let mut description = format!(
+            "air {:.1}% {} than standard at {:.0}°F",
+            (ratio - 1.0).abs() * 100.0,
+            if ratio >= 1.0 { "heavier" } else { "thinner" },
+            temperature
+        );
+        if altitude.abs() >= 1.0 {
+            description = format!("{} and {:.0} ft up", description, altitude);
+        }
+        adjustments.push(Adjustment {
+            kind: AdjustmentKind::AirDensity,
+            yards: distance_yards * DENSITY_EFFECT * (ratio - 1.0),
+            description,
+        });
+    }
+
+    if lie_factor(lie) < 1.0 {
+        adjustments.push(Adjustment {
+            kind: AdjustmentKind::Lie,
+            yards: distance_yards / lie_factor(lie) - distance_yards,
+            description: format!("from the {}", lie.as_str()),
+        });
+    }
+
+    let plays_like_yards = distance_yards
+        + adjustments
+            .iter()
+            .map(|adjustment| adjustment.yards)
+            .fold(0.0, |sum, yards| sum + yards);
+    PlaysLike {
+        distance_yards,
+        plays_like_yards,
+        headwind_mph: headwind,
+        crosswind_mph: crosswind,
+        // Wind from the right pushes the ball left.
+        drift_per_100: -crosswind * CROSSWIND_DRIFT,
+        adjustments,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wind(mph: f64, from_deg: f64) -> Conditions {
+        Conditions {
+            wind_mph: Some(mph),
+            wind_from_deg: Some(from_deg),
+            ..Conditions::default()
+        }
+    }
+
+    #[test]
+    fn headwinds_cost_more_than_tailwinds_give() {
+        let into = plays_like(150.0, 0.0, Lie::Fairway, &wind(10.0, 0.0));
+        let down = plays_like(150.0, 0.0, Lie::Fairway, &wind(10.0, 180.0));
+        assert!((into.headwind_mph - 10.0).abs() < 1e-9);
+        assert!((down.headwind_mph + 10.0).abs() < 1e-9);
+        assert!((into.plays_like_yards - 165.0).abs() < 1e-9);
+        assert!((down.plays_like_yards - 142.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn crosswinds_drift_the_ball() {
+        let from_right = plays_like(150.0, 0.0, Lie::Fairway, &wind(10.0, 90.0));
+        assert!(from_right.headwind_mph.abs() < 1e-9);
+        assert!((from_right.crosswind_mph - 10.0).abs() < 1e-9);
+        assert!((from_right.drift_per_100 + 7.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn standard_air_needs_no_adjustment() {
+        let standard = Conditions {
+            temperature_f: Some(STANDARD_TEMPERATURE_F),
+            humidity_percent: Some(STANDARD_HUMIDITY_PERCENT),
+            pressure_hpa: Some(STANDARD_PRESSURE_HPA),
+            altitude_ft: Some(0.0),
+            ..Conditions::default()
+        };
+        let shot = plays_like(150.0, 0.0, Lie::Fairway, &standard);
+        let air = shot.adjustments.iter().find(|a| a.kind == AdjustmentKind::AirDensity).unwrap();
+        assert!(air.yards.abs() < 1e-9);
+        assert!((shot.plays_like_yards - 150.0).abs() < 1e-9);
+
+        let thin = Conditions {
+            altitude_ft: Some(5000.0),
+            ..standard
+        };
+        assert!(plays_like(150.0, 0.0, Lie::Fairway, &thin).plays_like_yards < 150.0);
+    }
+
+    #[test]
+    fn conditions_factor_leaves_out_the_lie() {
+        let conditions = Conditions {
+            elevation_change_ft: Some(15.0),
+            ..Conditions::default()
+        };
+        let fairway = plays_like(100.0, 0.0, Lie::Fairway, &conditions);
+        let sand = plays_like(100.0, 0.0, Lie::Sand, &conditions);
+        assert!(sand.plays_like_yards > fairway.plays_like_yards);
+        assert!((fairway.conditions_factor() - 1.05).abs() < 1e-9);
+        assert!((sand.conditions_factor() - 1.05).abs() < 1e-9);
+    }
+}
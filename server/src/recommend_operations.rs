@@ -0,0 +1,519 @@
+use chrono::Utc;
+use diesel::PgConnection;
+use serde::{Deserialize, Serialize};
+
+use crate::db_operations;
+use crate::error::ApiError;
+use crate::geometry::{distance_yards, from_local, green_shape, lie_at, to_local, LatLong, Lie};
+use crate::green::{self, GreenDetail};
+use crate::pin_operations;
+use crate::plays_like::{self, Adjustment, AdjustmentKind, Conditions, PlaysLike};
+use crate::shot_operations::HoleContext;
+use crate::simulation::{self, lie_factor, playable, ShotPattern, SimulationResult};
+use crate::stats_operations::{self, ClubStatistics};
+use crate::strokes_gained::Baseline;
+use crate::tee_set_operations;
+use crate::weather::{Weather, WeatherProvider};
+
+/// How many options to return.
+const MAX_OPTIONS: usize = 5;
+/// Shorter than the target by more than this is a lay-up.
+const LAYUP_YARDS: f64 = 20.0;
+/// Extra carry wanted over a hazard.
+const CARRY_MARGIN_YARDS: f64 = 5.0;
+/// Spacing of the samples taken along the line to find hazards.
+const LINE_STEP_YARDS: f64 = 5.0;
+/// Adjustments smaller than this aren't worth a mention in the summary.
+const NOTABLE_ADJUSTMENT_YARDS: f64 = 2.0;
+
+/// The shot's conditions come from the same query string, read separately
+/// as [`Conditions`]: query strings lose their number types when serde
+/// buffers a flattened struct. A missing `elevation_change_ft` is taken from
+/// the imported terrain between the ball and the target.
+#[derive(Deserialize, Debug)]
+pub struct RecommendQuery {
+    /// Where the ball is. Without it the shot is played from the tee of
+    /// `tee_set_id`.
+    pub lat: Option<f64>,
+    pub long: Option<f64>,
+    pub tee_set_id: Option<i32>,
+    /// Defaults to the flag.
+    pub target_lat: Option<f64>,
+    pub target_long: Option<f64>,
+}
+
+/// Read alongside [`Conditions`], like [`RecommendQuery`].
+#[derive(Deserialize, Debug)]
+pub struct DistancesQuery {
+    pub lat: Option<f64>,
+    pub long: Option<f64>,
+    pub tee_set_id: Option<i32>,
+    /// Fills in any conditions not given from the weather provider.
+    #[serde(default)]
+    pub live_weather: bool,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct SimulateQuery {
+    pub lat: Option<f64>,
+    pub long: Option<f64>,
+    pub tee_set_id: Option<i32>,
+    pub seed: Option<u64>,
+    pub samples: Option<usize>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct GreenQuery {
+    /// Where the approach is played from. Without it, the tee of
+    /// `tee_set_id`, or else the last turn of the hole.
+    pub lat: Option<f64>,
+    pub long: Option<f64>,
+    pub tee_set_id: Option<i32>,
+    /// Defaults to today's pin.
+    pub pin_lat: Option<f64>,
+    pub pin_long: Option<f64>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ClubOption {
+    pub club_id: i32,
+    pub club: String,
+    /// How far the club goes from this lie in these conditions.
+    pub expected_yards: f64,
+    pub distance_source: &'static str,
+    /// Where to aim, allowing for the wind and the player's usual miss.
+    pub aim: LatLong,
+    pub layup: bool,
+    /// Share of likely landing spots in water, sand or trees.
+    pub hazard_risk: f64,
+    /// Average yards left to the target, plus an allowance for bad lies.
+    /// Lower is better.
+    pub score: f64,
+    pub reasons: Vec<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Recommendation {
+    pub hole_id: i32,
+    pub position: LatLong,
+    pub lie: Lie,
+    pub target: LatLong,
+    pub distance_yards: f64,
+    pub plays_like_yards: f64,
+    pub summary: String,
+    pub options: Vec<ClubOption>,
+    pub adjustments: Vec<Adjustment>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct DistanceTarget {
+    pub name: &'static str,
+    pub position: LatLong,
+    pub distance_yards: f64,
+    /// Only worked out when conditions are given.
+    pub plays_like: Option<PlaysLike>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct HoleDistances {
+    pub hole_id: i32,
+    pub position: LatLong,
+    pub lie: Lie,
+    /// The reading used when `live_weather` was asked for.
+    pub weather: Option<Weather>,
+    pub targets: Vec<DistanceTarget>,
+}
+
+/// Yards added to a landing spot's score for the trouble it brings.
+fn lie_penalty(lie: Lie) -> f64 {
+    match lie {
+        Lie::Water => 60.0,
+        Lie::Recovery => 30.0,
+        Lie::Sand => 20.0,
+        Lie::Rough => 8.0,
+        Lie::Tee | Lie::Fairway | Lie::Green => 0.0,
+    }
+}
+
+/// The furthest water or sand on the line before `distance`, and its kind.
+fn last_hazard(context: &HoleContext, start: LatLong, unit: (f64, f64), distance: f64) -> Option<(f64, Lie)> {
+    let mut found = None;
+    let mut along = LINE_STEP_YARDS;
+    while along < distance {
+        let point = from_local(start, (unit.0 * along, unit.1 * along));
+        let lie = lie_at(&context.shapes, point);
+        if matches!(lie, Lie::Water | Lie::Sand) {
+            found = Some((along, lie));
+        }
+        along += LINE_STEP_YARDS;
+    }
+    found
+}
+
+fn evaluate(
+    context: &HoleContext,
+    stats: &ClubStatistics,
+    model: &ShotPattern,
+    start: LatLong,
+    target: LatLong,
+    lie: Lie,
+    plays_like: &PlaysLike,
+) -> ClubOption {
+    let (tx, ty) = to_local(start, target);
+    let target_yards = tx.hypot(ty).max(1.0);
+    let unit = (tx / target_yards, ty / target_yards);
+    let right = (unit.1, -unit.0);
+
+    let factor = plays_like.conditions_factor();
+    let flight = model.total * lie_factor(lie) / factor;
+    let drift = (plays_like.drift_per_100 + model.bias_per_100) * flight / 100.0;
+    let lateral_sd = model.lateral_sd_per_100 * flight / 100.0;
+    let layup = flight < target_yards - LAYUP_YARDS;
+
+    // Aim off so the expected drift and usual miss bring the ball back to the line.
+    let aim_along = flight.min(target_yards);
+    let aim = from_local(
+        start,
+        (unit.0 * aim_along - right.0 * drift, unit.1 * aim_along - right.1 * drift),
+    );
+
+    // Score a small grid of landing spots around where the ball should finish.
+    let mut score = 0.0;
+    let mut hazard_risk = 0.0;
+    for (depth_step, depth_weight) in [(-1.2, 0.25), (0.0, 0.5), (1.2, 0.25)] {
+        for (side_step, side_weight) in [(-1.2, 0.25), (0.0, 0.5), (1.2, 0.25)] {
+            let along = flight + depth_step * model.depth_sd;
+            let across = side_step * lateral_sd;
+            let landing = from_local(
+                start,
+                (unit.0 * along + right.0 * across, unit.1 * along + right.1 * across),
+            );
+            let landing_lie = lie_at(&context.shapes, landing);
+            let weight = depth_weight * side_weight;
+            score += weight * (distance_yards(landing, target) + lie_penalty(landing_lie));
+            if matches!(landing_lie, Lie::Water | Lie::Sand | Lie::Recovery) {
+                hazard_risk += weight;
+            }
+        }
+    }
+
+    let mut reasons = Vec::new();
+    reasons.push(match model.source {
+        "shots" => format!("Your {} goes {:.0} yards from a clean lie", stats.club, model.total),
+        _ => format!(
+            "Using the {:.0} yards in your bag for the {}; record shots to personalise this",
+            model.total, stats.club
+        ),
+    });
+    if lie_factor(lie) < 1.0 {
+        reasons.push(format!(
+            "From the {} expect about {:.0}% less distance",
+            lie.as_str(),
+            (1.0 - lie_factor(lie)) * 100.0
+        ));
+    }
+    if layup {
+        reasons.push(format!("Lays up {:.0} yards short of the target", target_yards - flight));
+    } else if flight > target_yards + 10.0 {
+        reasons.push(format!("Likely to finish {:.0} yards past the target", flight - target_yards));
+    }
+    if let Some((hazard_yards, hazard)) = last_hazard(context, start, unit, flight.min(target_yards)) {
+        let needed = hazard_yards + CARRY_MARGIN_YARDS;
+        let carry = model.carry * lie_factor(lie) / factor;
+        if carry < needed {
+            score += lie_penalty(hazard) / 2.0;
+            reasons.push(format!(
+                "Needs {:.0} to carry the {} but carries about {:.0}",
+                needed,
+                hazard.as_str(),
+                carry
+            ));
+        } else {
+            reasons.push(format!(
+                "Carries the {} at {:.0} yards with {:.0} to spare",
+                hazard.as_str(),
+                hazard_yards,
+                carry - hazard_yards
+            ));
+        }
+    }
+    if drift.abs() >= 3.0 {
+        reasons.push(format!(
+            "Aim {:.0} yards {}",
+            drift.abs(),
+            if drift > 0.0 { "left" } else { "right" }
+        ));
+    }
+    if hazard_risk >= 0.1 {
+        reasons.push(format!("{:.0}% chance of finding trouble", hazard_risk * 100.0));
+    }
+
+    ClubOption {
+        club_id: stats.club_id,
+        club: stats.club.clone(),
+        expected_yards: flight,
+        distance_source: model.source,
+        aim,
+        layup,
+        hazard_risk,
+        score,
+        reasons,
+    }
+}
+
+/// Ranks the player's clubs for the shot from `position` to the target,
+/// best first.
+pub fn recommend(
+    context: &HoleContext,
+    bag: &[ClubStatistics],
+    position: LatLong,
+    target: LatLong,
+    conditions: Conditions,
+) -> Recommendation {
+    let lie = context.lie_at(position);
+    let distance = distance_yards(position, target);
+    let (east, north) = to_local(position, target);
+    let bearing = east.atan2(north).to_degrees();
+    let conditions = Conditions {
+        elevation_change_ft: conditions
+            .elevation_change_ft
+            .or_else(|| context.elevation_change_ft(position, target)),
+        ..conditions
+    };
+    let plays_like = plays_like::plays_like(distance, bearing, lie, &conditions);
+
+    let mut options: Vec<ClubOption> = bag
+        .iter()
+        .filter(|stats| playable(&stats.club_type, lie))
+        .filter_map(|stats| {
+            let model = ShotPattern::from_statistics(stats)?;
+            Some(evaluate(context, stats, &model, position, target, lie, &plays_like))
+        })
+        .collect();
+    options.sort_by(|a, b| a.score.total_cmp(&b.score));
+    options.truncate(MAX_OPTIONS);
+
+    let mut summary = match options.first() {
+        Some(best) if best.layup => format!(
+            "{:.0} yards playing {:.0}: {}, leaving about {:.0}",
+            distance,
+            plays_like.plays_like_yards,
+            best.club,
+            distance - best.expected_yards
+        ),
+        Some(best) => format!(
+            "{:.0} yards playing {:.0}: {} at the target",
+            distance, plays_like.plays_like_yards, best.club
+        ),
+        None => "No club in your bag has a distance for this shot yet".to_string(),
+    };
+    let reasons: Vec<&str> = plays_like
+        .adjustments
+        .iter()
+        .filter(|adjustment| adjustment.kind != AdjustmentKind::Lie)
+        .filter(|adjustment| adjustment.yards.abs() >= NOTABLE_ADJUSTMENT_YARDS)
+        .map(|adjustment| adjustment.description.as_str())
+        .collect();
+    if !reasons.is_empty() {
+        summary = format!("{} ({})", summary, reasons.join(", "));
+    }
+
+    Recommendation {
+        hole_id: context.hole.hole_id,
+        position,
+        lie,
+        target,
+        distance_yards: distance,
+        plays_like_yards: plays_like.plays_like_yards,
+        summary,
+        options,
+        adjustments: plays_like.adjustments,
+    }
+}
+
+/// The ball position given, or the selected tee set's tee on this hole.
+fn resolve_position(
+    conn: &mut PgConnection,
+    hole_id: i32,
+    lat: Option<f64>,
+    long: Option<f64>,
+    tee_set_id: Option<i32>,
+) -> Result<LatLong, ApiError> {
+    let position = match (lat, long, tee_set_id) {
+        (Some(lat), Some(long), _) => LatLong::new(lat, long),
+        (None, None, Some(tee_set_id)) => tee_set_operations::tee_position(conn, tee_set_id, hole_id)?,
+        _ => {
+            return Err(ApiError::BadRequest(
+                "give lat and long together, or a tee_set_id to play from its tee".to_string(),
+            ))
+        }
+    };
+    if position.is_valid() {
+        Ok(position)
+    } else {
+        Err(ApiError::BadRequest("lat and long must be a valid position".to_string()))
+    }
+}
+
+pub fn recommend_for_user(
+    conn: &mut PgConnection,
+    user_id: i32,
+    hole_id: i32,
+    query: RecommendQuery,
+    conditions: Conditions,
+) -> Result<Recommendation, ApiError> {
+    let position = resolve_position(conn, hole_id, query.lat, query.long, query.tee_set_id)?;
+
+    let context = HoleContext::load(conn, hole_id)?;
+    let target = match (query.target_lat, query.target_long) {
+        (Some(lat), Some(long)) => LatLong::new(lat, long),
+        (None, None) => context
+            .flag()
+            .ok_or_else(|| ApiError::BadRequest("hole has no flag position; give a target".to_string()))?,
+        _ => {
+            return Err(ApiError::BadRequest(
+                "target_lat and target_long must be given together".to_string(),
+            ))
+        }
+    };
+    if !target.is_valid() {
+        return Err(ApiError::BadRequest("target is not a valid position".to_string()));
+    }
+
+    let bag = stats_operations::club_statistics(conn, user_id)?;
+    Ok(recommend(&context, &bag, position, target, conditions))
+}
+
+pub fn simulate_for_user(
+    conn: &mut PgConnection,
+    baseline: &Baseline,
+    user_id: i32,
+    hole_id: i32,
+    query: SimulateQuery,
+) -> Result<SimulationResult, ApiError> {
+    let position = resolve_position(conn, hole_id, query.lat, query.long, query.tee_set_id)?;
+    let samples = query.samples.unwrap_or(simulation::DEFAULT_SAMPLES);
+    if !(1..=simulation::MAX_SAMPLES).contains(&samples) {
+        return Err(ApiError::BadRequest(format!(
+            "samples must be between 1 and {}",
+            simulation::MAX_SAMPLES
+        )));
+    }
+
+    let context = HoleContext::load(conn, hole_id)?;
+    let bag = stats_operations::club_statistics(conn, user_id)?;
+    simulation::simulate(
+        &context,
+        baseline,
+        &bag,
+        position,
+        query.seed.unwrap_or(0),
+        samples,
+    )
+    .ok_or_else(|| ApiError::BadRequest("hole has no flag position to simulate towards".to_string()))
+}
+
+pub fn green_for_hole(conn: &mut PgConnection, hole_id: i32, query: GreenQuery) -> Result<GreenDetail, ApiError> {
+    let hole_data = db_operations::fetch_hole_data(conn, hole_id)?;
+    let from = match (query.lat, query.long, query.tee_set_id) {
+        (None, None, None) => green::approach_position(&hole_data)
+            .ok_or_else(|| ApiError::BadRequest("hole has no route or tee; give lat and long".to_string()))?,
+        (lat, long, tee_set_id) => resolve_position(conn, hole_id, lat, long, tee_set_id)?,
+    };
+    let pin = match (query.pin_lat, query.pin_long) {
+        (Some(lat), Some(long)) => {
+            let pin = LatLong::new(lat, long);
+            if !pin.is_valid() {
+                return Err(ApiError::BadRequest("pin is not a valid position".to_string()));
+            }
+            Some(pin)
+        }
+        (None, None) => pin_operations::todays_pin(conn, hole_id, from)?.or(hole_data.hole.flag()),
+        _ => {
+            return Err(ApiError::BadRequest(
+                "pin_lat and pin_long must be given together".to_string(),
+            ))
+        }
+    };
+
+    green::green_detail(&hole_data, from, pin)
+        .ok_or_else(|| ApiError::BadRequest("hole has no green to measure from here".to_string()))
+}
+
+/// Yardages to the front, centre and back of the green and to the pin, and
+/// what each plays like when conditions are known.
+pub fn distances_for_hole(
+    conn: &mut PgConnection,
+    provider: &dyn WeatherProvider,
+    hole_id: i32,
+    query: DistancesQuery,
+    conditions: Conditions,
+) -> Result<HoleDistances, ApiError> {
+    let position = resolve_position(conn, hole_id, query.lat, query.long, query.tee_set_id)?;
+    let context = HoleContext::load(conn, hole_id)?;
+    let lie = context.lie_at(position);
+
+    let weather = if query.live_weather {
+        Some(
+            provider
+                .conditions(position, Utc::now().naive_utc())
+                .map_err(|e| ApiError::Internal(format!("Weather error: {}", e)))?,
+        )
+    } else {
+        None
+    };
+    let conditions = match &weather {
+        Some(weather) => conditions.or(Conditions::from_weather(weather)),
+        None => conditions,
+    };
+
+    let flag = context.flag();
+    let mut points: Vec<(&'static str, LatLong)> = Vec::new();
+    if let Some(green) = green_shape(&context.shapes, flag)
+        .and_then(|green| green::analyse(hole_id, green, position, None))
+    {
+        points.extend([
+            ("front", green.front.position),
+            ("centre", green.centre.position),
+            ("back", green.back.position),
+        ]);
+    }
+    points.extend(flag.map(|flag| ("pin", flag)));
+    if points.is_empty() {
+        return Err(ApiError::BadRequest("hole has no green or flag to measure to".to_string()));
+    }
+
+    // Plays-like distances are for callers who gave or fetched conditions;
+    // the terrain only adds its elevation change to them.
+    let adjusted = !conditions.is_empty();
+    let targets = points
+        .into_iter()
+        .map(|(name, target)| {
+            let distance = distance_yards(position, target);
+            let (east, north) = to_local(position, target);
+            let plays_like = adjusted.then(|| {
+                let conditions = Conditions {
+                    elevation_change_ft: conditions
+                        .elevation_change_ft
+                        .or_else(|| context.elevation_change_ft(position, target)),
+                    ..conditions
+                };
+                plays_like::plays_like(distance, east.atan2(north).to_degrees(), lie, &conditions)
+            });
+            DistanceTarget {
+                name,
+                position: target,
+                distance_yards: distance,
+                plays_like,
+            }
+        })
+        .collect();
+
+    Ok(HoleDistances {
+        hole_id,
+        position,
+        lie,
+        weather,
+        targets,
+    })
+}
@@ -0,0 +1,158 @@
+use diesel::dsl::max;
+use diesel::prelude::*;
+use serde::Serialize;
+
+use crate::error::ApiError;
+use crate::models::{Round, RoundEvent, Shot};
+use crate::round_events::{EVENT_ROUND_FINISHED, EVENT_SCORES, EVENT_SHOTS};
+use crate::round_operations::{self, PlayerScorecard, Scorecard};
+use crate::schema::{round_events, rounds, shots};
+use crate::shot_operations::{self, ShotFilter};
+
+/// A reconnecting client further behind than this gets a fresh snapshot
+/// instead of a replay.
+const MAX_REPLAY_EVENTS: i64 = 500;
+
+#[derive(Insertable)]
+#[diesel(table_name = round_events)]
+struct NewRoundEvent<'a> {
+    round_id: i32,
+    sequence: i32,
+    kind: &'a str,
+    payload: serde_json::Value,
+}
+
+/// A player's shots on one hole after a change, so clients replace what
+/// they had rather than patching in renumbered shots.
+#[derive(Serialize)]
+struct HoleShots {
+    player_id: i32,
+    hole_id: i32,
+    shots: Vec<Shot>,
+}
+
+/// Runs a change to a round together with recording the event for it, in
+/// one transaction that holds the round row locked throughout. Events are
+/// then numbered in the order the changes were made, and a change is never
+/// saved without its event or the other way round.
+pub fn with_event<T>(
+    conn: &mut PgConnection,
+    round_id: i32,
+    change: impl FnOnce(&mut PgConnection) -> Result<(T, RoundEvent), ApiError>,
+) -> Result<(T, RoundEvent), ApiError> {
+    conn.transaction(|conn| {
+        rounds::table.find(round_id).select(rounds::id).for_update().first::<i32>(conn)?;
+        change(conn)
+    })
+}
+
+/// Saves an event with the round's next sequence number. Only call it from
+/// inside [`with_event`], which holds the lock that keeps numbers unique.
+fn record_event(
+    conn: &mut PgConnection,
+    round_id: i32,
+    kind: &str,
+    payload: &impl Serialize,
+) -> Result<RoundEvent, ApiError> {
+    let payload = serde_json::to_value(payload).map_err(|e| ApiError::Internal(e.to_string()))?;
+    let sequence = latest_sequence(conn, round_id)? + 1;
+    Ok(diesel::insert_into(round_events::table)
+        .values(&NewRoundEvent {
+            round_id,
+            sequence,
+            kind,
+            payload,
+        })
+        .returning(RoundEvent::as_returning())
+        .get_result(conn)?)
+}
+
+/// The sequence number of the round's last event, 0 before the first.
+pub fn latest_sequence(conn: &mut PgConnection, round_id: i32) -> Result<i32, ApiError> {
+    Ok(round_events::table
+        .filter(round_events::round_id.eq(round_id))
+        .select(max(round_events::sequence))
+        .first::<Option<i32>>(conn)?
+        .unwrap_or(0))
+}
+
+/// Events after `since`, oldest first, or `None` when there are too many to
+/// replay.
+pub fn events_since(conn: &mut PgConnection, round_id: i32, since: i32) -> Result<Option<Vec<RoundEvent>>, ApiError> {
+    let events = round_events::table
+        .filter(round_events::round_id.eq(round_id))
+        .filter(round_events::sequence.gt(since))
+        .order(round_events::sequence.asc())
+        .limit(MAX_REPLAY_EVENTS + 1)
+        .select(RoundEvent::as_select())
+        .load(conn)?;
+    Ok((events.len() as i64 <= MAX_REPLAY_EVENTS).then_some(events))
+}
+
+/// Records the scorecards of the players whose scores were just saved.
+pub fn scores_recorded(
+    conn: &mut PgConnection,
+    scorecard: &Scorecard,
+    player_ids: &[i32],
+) -> Result<RoundEvent, ApiError> {
+    let players: Vec<&PlayerScorecard> = scorecard
+        .players
+        .iter()
+        .filter(|card| player_ids.contains(&card.player.id))
+        .collect();
+    record_event(conn, scorecard.round.id, EVENT_SCORES, &players)
+}
+
+/// Records a player's shots on a hole as they now stand.
+pub fn shots_changed(conn: &mut PgConnection, round_id: i32, player_id: i32, hole_id: i32) -> Result<RoundEvent, ApiError> {
+    let shots = shots::table
+        .filter(shots::round_id.eq(round_id))
+        .filter(shots::player_id.eq(player_id))
+        .filter(shots::hole_id.eq(hole_id))
+        .order(shots::shot_number.asc())
+        .select(Shot::as_select())
+        .load(conn)?;
+    record_event(conn, round_id, EVENT_SHOTS, &HoleShots { player_id, hole_id, shots })
+}
+
+pub fn round_finished(conn: &mut PgConnection, round: &Round) -> Result<RoundEvent, ApiError> {
+    record_event(conn, round.id, EVENT_ROUND_FINISHED, round)
+}
+
+/// How a subscriber is brought up to date.
+pub enum CatchUp {
+    /// The round and its shots as they stood at `sequence`.
+    Snapshot {
+        sequence: i32,
+        scorecard: Box<Scorecard>,
+        shots: Vec<Shot>,
+    },
+    /// The events after the subscriber's last one.
+    Replay(Vec<RoundEvent>),
+}
+
+/// Replays the events after `since`, or falls back to a snapshot when
+/// there's no sequence number, it's ahead of the round's history, or too
+/// much has happened since.
+pub fn catch_up(conn: &mut PgConnection, round_id: i32, user_id: i32, since: Option<i32>) -> Result<CatchUp, ApiError> {
+    let sequence = latest_sequence(conn, round_id)?;
+    if let Some(since) = since.filter(|since| (0..=sequence).contains(since)) {
+        if let Some(events) = events_since(conn, round_id, since)? {
+            return Ok(CatchUp::Replay(events));
+        }
+    }
+
+    // A share lock waits out any change in progress and holds off the next,
+    // so the snapshot matches its sequence number exactly.
+    conn.transaction(|conn| {
+        rounds::table.find(round_id).select(rounds::id).for_share().first::<i32>(conn)?;
+        let sequence = latest_sequence(conn, round_id)?;
+        let scorecard = Box::new(round_operations::fetch_scorecard(conn, round_id, user_id)?);
+        let shots = shot_operations::list_shots(conn, round_id, user_id, ShotFilter::default())?;
+        Ok(CatchUp::Snapshot {
+            sequence,
+            scorecard,
+            shots,
+        })
+    })
+}
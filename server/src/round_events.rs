@@ -0,0 +1,64 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+use crate::models::{RoundEvent, Shot};
+use crate::round_operations::Scorecard;
+
+pub const EVENT_SCORES: &str = "scores";
+pub const EVENT_SHOTS: &str = "shots";
+pub const EVENT_ROUND_FINISHED: &str = "round_finished";
+
+/// Events buffered per round for slow subscribers. One that falls further
+/// behind reloads what it missed from the database.
+const CHANNEL_CAPACITY: usize = 64;
+
+/// What the server sends down a live round socket, as JSON text frames.
+#[derive(Serialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LiveMessage<'a> {
+    /// The round and its shots as stored, sent when a client connects
+    /// without a sequence number or is too far behind to replay. Events
+    /// follow from `sequence`.
+    Snapshot {
+        sequence: i32,
+        scorecard: &'a Scorecard,
+        shots: &'a [Shot],
+    },
+    Event(&'a RoundEvent),
+}
+
+type RoundChannels = HashMap<i32, broadcast::Sender<Arc<RoundEvent>>>;
+
+/// Fans recorded round events out to the sockets subscribed to each round.
+/// Only events already saved are published, so a subscriber can always
+/// recover anything it drops from the database.
+#[derive(Default)]
+pub struct RoundHub {
+    channels: Mutex<RoundChannels>,
+}
+
+impl RoundHub {
+    pub fn new() -> RoundHub {
+        RoundHub::default()
+    }
+
+    pub fn subscribe(&self, round_id: i32) -> broadcast::Receiver<Arc<RoundEvent>> {
+        let mut channels = self.channels.lock().unwrap_or_else(|e| e.into_inner());
+        channels.retain(|_, sender| sender.receiver_count() > 0);
+        channels
+            .entry(round_id)
+            .or_insert_with(|| broadcast::channel(CHANNEL_CAPACITY).0)
+            .subscribe()
+    }
+
+    pub fn publish(&self, event: RoundEvent) {
+        let channels = self.channels.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(sender) = channels.get(&event.round_id) {
+            // Only fails when every subscriber has gone, which is fine.
+            let _ = sender.send(Arc::new(event));
+        }
+    }
+}
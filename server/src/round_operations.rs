@@ -0,0 +1,613 @@
+use std::collections::HashMap;
+
+use chrono::{NaiveDate, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::auth;
+use crate::error::ApiError;
+use crate::hole_operations;
+use crate::pin_operations;
+use crate::models::{Hole, HoleScore, Round, RoundPlayer, TeeSet};
+use crate::schema::{hole_scores, holes, round_players, rounds};
+use crate::tee_set_operations;
+
+pub const ROUND_IN_PROGRESS: &str = "in_progress";
+pub const ROUND_FINISHED: &str = "finished";
+
+const MAX_PLAYERS: usize = 4;
+const MAX_STROKES_PER_HOLE: i32 = 20;
+
+/// A course with the tee set a round names, by id or by name.
+type TeesPlayed = (i32, Option<i32>, Option<String>);
+
+#[derive(Deserialize, Debug)]
+pub struct NewPlayerInput {
+    pub name: String,
+    pub user_id: Option<i32>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct StartRoundInput {
+    pub course_id: i32,
+    /// One of the course's tee sets. A bare name is still accepted for
+    /// courses without any.
+    pub tee_set_id: Option<i32>,
+    pub tee_set: Option<String>,
+    /// Picks between tee sets sharing `tee_set`'s name.
+    pub tee_set_gender: Option<String>,
+    pub played_on: Option<NaiveDate>,
+    #[serde(default)]
+    pub players: Vec<NewPlayerInput>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct HoleScoreInput {
+    pub player_id: i32,
+    pub hole_id: i32,
+    pub strokes: i32,
+    pub putts: Option<i32>,
+    #[serde(default)]
+    pub penalties: i32,
+    pub fairway_hit: Option<bool>,
+    pub green_in_regulation: Option<bool>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct JoinRoundInput {
+    pub invite_code: String,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = rounds)]
+struct NewRound {
+    user_id: i32,
+    course_id: i32,
+    tee_set_id: Option<i32>,
+    tee_set: Option<String>,
+    played_on: NaiveDate,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = round_players)]
+struct NewRoundPlayer {
+    round_id: i32,
+    user_id: Option<i32>,
+    name: String,
+}
+
+#[derive(Insertable, AsChangeset)]
+#[diesel(table_name = hole_scores, treat_none_as_null = true)]
+struct NewHoleScore {
+    round_id: i32,
+    player_id: i32,
+    hole_id: i32,
+    strokes: i32,
+    putts: Option<i32>,
+    penalties: i32,
+    fairway_hit: Option<bool>,
+    green_in_regulation: Option<bool>,
+}
+
+#[derive(Serialize, Debug, Default, Clone)]
+pub struct ScoreTotals {
+    pub holes_played: i32,
+    pub strokes: i32,
+    pub putts: i32,
+    pub penalties: i32,
+    /// Strokes relative to par over the holes played that have a par.
+    pub to_par: Option<i32>,
+    pub par_played: Option<i32>,
+    pub fairways_hit: i32,
+    pub fairways_possible: i32,
+    pub greens_in_regulation: i32,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ScorecardLine {
+    pub hole_id: i32,
+    pub number: Option<i32>,
+    pub name: Option<String>,
+    pub par: Option<i32>,
+    pub stroke_index: Option<i32>,
+    /// From the round's tee set.
+    pub yards: Option<i32>,
+    pub score: Option<HoleScore>,
+    pub to_par: Option<i32>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct PlayerScorecard {
+    pub player: RoundPlayer,
+    pub holes: Vec<ScorecardLine>,
+    pub front_nine: ScoreTotals,
+    pub back_nine: ScoreTotals,
+    pub totals: ScoreTotals,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Scorecard {
+    pub round: Round,
+    pub tee_set: Option<TeeSet>,
+    pub course_par: Option<i32>,
+    pub course_yards: Option<i32>,
+    pub players: Vec<PlayerScorecard>,
+}
+
+/// Shown once; only its hash is kept.
+#[derive(Serialize, Debug)]
+pub struct PlayerInvite {
+    pub player_id: i32,
+    pub invite_code: String,
+}
+
+#[derive(Serialize, Debug)]
+pub struct RoundSummary {
+    pub round: Round,
+    pub players: Vec<RoundPlayer>,
+    pub totals: Option<ScoreTotals>,
+}
+
+/// Loads a round the user owns or is playing in.
+pub fn authorize_round(conn: &mut PgConnection, round_id: i32, user_id: i32) -> Result<Round, ApiError> {
+    let round = rounds::table
+        .find(round_id)
+        .select(Round::as_select())
+        .first(conn)?;
+
+    if round.user_id == user_id {
+        return Ok(round);
+    }
+    let is_player: bool = diesel::select(diesel::dsl::exists(
+        round_players::table
+            .filter(round_players::round_id.eq(round_id))
+            .filter(round_players::user_id.eq(user_id)),
+    ))
+    .get_result(conn)?;
+
+    if is_player {
+        Ok(round)
+    } else {
+        Err(ApiError::NotFound)
+    }
+}
+
+/// Like [`authorize_round`] but also rejects rounds that are already finished.
+pub fn authorize_open_round(conn: &mut PgConnection, round_id: i32, user_id: i32) -> Result<Round, ApiError> {
+    let round = authorize_round(conn, round_id, user_id)?;
+    if round.status == ROUND_FINISHED {
+        return Err(ApiError::Conflict("round is already finished".to_string()));
+    }
+    Ok(round)
+}
+
+pub fn fetch_course_holes(conn: &mut PgConnection, course_id: i32) -> Result<Vec<Hole>, ApiError> {
+    Ok(holes::table
+        .filter(holes::course_id.eq(Some(course_id)))
+        .order(holes::number.asc())
+        .load::<Hole>(conn)?)
+}
+
+pub fn start_round(conn: &mut PgConnection, user_id: i32, input: StartRoundInput) -> Result<Scorecard, ApiError> {
+    if fetch_course_holes(conn, input.course_id)?.is_empty() {
+        return Err(ApiError::BadRequest(format!("course {} has no holes", input.course_id)));
+    }
+
+    // Linking someone else would put the round in their list and let them
+    // score it, so only the caller can be linked to a player here.
+    if input.players.iter().any(|player| player.user_id.is_some_and(|id| id != user_id)) {
+        return Err(ApiError::BadRequest("players can only be linked to your own account".to_string()));
+    }
+
+    let mut players = input.players;
+    if !players.iter().any(|player| player.user_id == Some(user_id)) {
+        let owner = auth::fetch_user(conn, user_id)?;
+        players.insert(
+            0,
+            NewPlayerInput {
+                name: owner.display_name.unwrap_or(owner.email),
+                user_id: Some(user_id),
+            },
+        );
+    }
+
+    let tee_set = match (input.tee_set_id, input.tee_set.as_deref()) {
+        (Some(tee_set_id), _) => Some(
+            tee_set_operations::fetch_tee_set(conn, input.course_id, tee_set_id).map_err(|e| match e {
+                ApiError::NotFound => ApiError::BadRequest(format!("tee set {} is not on this course", tee_set_id)),
+                e => e,
+            })?,
+        ),
+        (None, Some(name)) => {
+            tee_set_operations::find_tee_set(conn, input.course_id, name, input.tee_set_gender.as_deref())?
+        }
+        (None, None) => None,
+    };
+    let tee_set_name = tee_set.as_ref().map(|tee_set| tee_set.name.clone()).or(input.tee_set);
+    let played_on = match input.played_on {
+        Some(played_on) => played_on,
+        None => {
+            let flag = fetch_course_holes(conn, input.course_id)?.iter().find_map(Hole::flag);
+            pin_operations::today_at(flag)
+        }
+    };
+
+    let players = create_round(conn, user_id, input.course_id, tee_set.as_ref(), tee_set_name, played_on, players)?;
+    fetch_scorecard(conn, players[0].round_id, user_id)
+}
+
+/// Inserts a round owned by `user_id` with exactly the players given, who
+/// need not include the owner. Returns the players in the order given.
+pub fn create_round(
+    conn: &mut PgConnection,
+    user_id: i32,
+    course_id: i32,
+    tee_set: Option<&TeeSet>,
+    tee_set_name: Option<String>,
+    played_on: NaiveDate,
+    players: Vec<NewPlayerInput>,
+) -> Result<Vec<RoundPlayer>, ApiError> {
+    if players.is_empty() {
+        return Err(ApiError::BadRequest("a round needs at least one player".to_string()));
+    }
+    if players.len() > MAX_PLAYERS {
+        return Err(ApiError::BadRequest(format!("a round can have at most {} players", MAX_PLAYERS)));
+    }
+    if players.iter().any(|player| player.name.trim().is_empty()) {
+        return Err(ApiError::BadRequest("every player needs a name".to_string()));
+    }
+
+    conn.transaction(|conn| {
+        let round_id = diesel::insert_into(rounds::table)
+            .values(&NewRound {
+                user_id,
+                course_id,
+                tee_set_id: tee_set.map(|tee_set| tee_set.id),
+                tee_set: tee_set_name,
+                played_on,
+            })
+            .returning(rounds::id)
+            .get_result::<i32>(conn)?;
+
+        let new_players: Vec<NewRoundPlayer> = players
+            .into_iter()
+            .map(|player| NewRoundPlayer {
+                round_id,
+                user_id: player.user_id,
+                name: player.name.trim().to_string(),
+            })
+            .collect();
+        Ok(diesel::insert_into(round_players::table)
+            .values(&new_players)
+            .returning(RoundPlayer::as_returning())
+            .get_results(conn)?)
+    })
+}
+
+/// Inserts or replaces hole scores for any players in the round.
+pub fn record_scores(
+    conn: &mut PgConnection,
+    round_id: i32,
+    user_id: i32,
+    scores: Vec<HoleScoreInput>,
+) -> Result<Scorecard, ApiError> {
+    let round = authorize_open_round(conn, round_id, user_id)?;
+
+    let player_ids: Vec<i32> = round_players::table
+        .filter(round_players::round_id.eq(round_id))
+        .select(round_players::id)
+        .load(conn)?;
+    let course_holes = fetch_course_holes(conn, round.course_id)?;
+
+    let mut rows = Vec::with_capacity(scores.len());
+    for score in scores {
+        if !player_ids.contains(&score.player_id) {
+            return Err(ApiError::BadRequest(format!("player {} is not in this round", score.player_id)));
+        }
+        if !course_holes.iter().any(|hole| hole.hole_id == score.hole_id) {
+            return Err(ApiError::BadRequest(format!("hole {} is not on this course", score.hole_id)));
+        }
+        if !(1..=MAX_STROKES_PER_HOLE).contains(&score.strokes) {
+            return Err(ApiError::BadRequest(format!(
+                "strokes must be between 1 and {}",
+                MAX_STROKES_PER_HOLE
+            )));
+        }
+        if score.putts.is_some_and(|putts| putts < 0 || putts > score.strokes) {
+            return Err(ApiError::BadRequest("putts must be between 0 and strokes".to_string()));
+        }
+        if score.penalties < 0 || score.penalties >= score.strokes {
+            return Err(ApiError::BadRequest("penalties must be fewer than strokes".to_string()));
+        }
+
+        rows.push(NewHoleScore {
+            round_id,
+            player_id: score.player_id,
+            hole_id: score.hole_id,
+            strokes: score.strokes,
+            putts: score.putts,
+            penalties: score.penalties,
+            fairway_hit: score.fairway_hit,
+            green_in_regulation: score.green_in_regulation,
+        });
+    }
+
+    conn.transaction(|conn| -> Result<(), ApiError> {
+        for row in &rows {
+            diesel::insert_into(hole_scores::table)
+                .values(row)
+                .on_conflict((hole_scores::player_id, hole_scores::hole_id))
+                .do_update()
+                .set(row)
+                .execute(conn)?;
+        }
+        Ok(())
+    })?;
+
+    fetch_scorecard(conn, round_id, user_id)
+}
+
+pub fn finish_round(conn: &mut PgConnection, round_id: i32, user_id: i32) -> Result<Scorecard, ApiError> {
+    authorize_open_round(conn, round_id, user_id)?;
+
+    diesel::update(rounds::table.find(round_id))
+        .set((
+            rounds::status.eq(ROUND_FINISHED),
+            rounds::finished_at.eq(Utc::now().naive_utc()),
+        ))
+        .execute(conn)?;
+
+    fetch_scorecard(conn, round_id, user_id)
+}
+
+/// Issues a code the owner can hand to whoever is playing as `player_id`,
+/// replacing any earlier one, so they can link their own account to the slot.
+pub fn invite_player(
+    conn: &mut PgConnection,
+    round_id: i32,
+    player_id: i32,
+    user_id: i32,
+) -> Result<PlayerInvite, ApiError> {
+    let round = authorize_round(conn, round_id, user_id)?;
+    if round.user_id != user_id {
+        return Err(ApiError::Forbidden);
+    }
+    let player = RoundPlayer::belonging_to(&round)
+        .filter(round_players::id.eq(player_id))
+        .select(RoundPlayer::as_select())
+        .first(conn)?;
+    if player.user_id.is_some() {
+        return Err(ApiError::Conflict("player is already linked to an account".to_string()));
+    }
+
+    let invite_code = auth::random_token();
+    diesel::update(round_players::table.find(player.id))
+        .set(round_players::invite_hash.eq(auth::hash_token(&invite_code)))
+        .execute(conn)?;
+    Ok(PlayerInvite { player_id, invite_code })
+}
+
+/// Links the user to the player slot an invite was issued for. The code
+/// only works once.
+pub fn join_round(conn: &mut PgConnection, user_id: i32, input: JoinRoundInput) -> Result<Scorecard, ApiError> {
+    let round_id = conn.transaction(|conn| {
+        let player = round_players::table
+            .filter(round_players::invite_hash.eq(auth::hash_token(input.invite_code.trim())))
+            .select(RoundPlayer::as_select())
+            .for_update()
+            .first(conn)
+            .optional()?
+            .ok_or(ApiError::NotFound)?;
+
+        let already_playing: bool = diesel::select(diesel::dsl::exists(
+            round_players::table
+                .filter(round_players::round_id.eq(player.round_id))
+                .filter(round_players::user_id.eq(user_id)),
+        ))
+        .get_result(conn)?;
+        if already_playing {
+            return Err(ApiError::Conflict("you are already playing in this round".to_string()));
+        }
+
+        diesel::update(round_players::table.find(player.id))
+            .set((
+                round_players::user_id.eq(Some(user_id)),
+                round_players::invite_hash.eq(None::<String>),
+            ))
+            .execute(conn)?;
+        Ok(player.round_id)
+    })?;
+    fetch_scorecard(conn, round_id, user_id)
+}
+
+pub fn fetch_scorecard(conn: &mut PgConnection, round_id: i32, user_id: i32) -> Result<Scorecard, ApiError> {
+    let round = authorize_round(conn, round_id, user_id)?;
+    let tee_set = tee_set_operations::round_tee_set(conn, &round)?;
+    let course_holes = hole_operations::played_holes(conn, round.course_id, tee_set.as_ref())?;
+    let players = RoundPlayer::belonging_to(&round)
+        .order(round_players::id.asc())
+        .select(RoundPlayer::as_select())
+        .load(conn)?;
+    let scores = HoleScore::belonging_to(&round)
+        .select(HoleScore::as_select())
+        .load(conn)?;
+    let yards: HashMap<i32, i32> = match &tee_set {
+        Some(tee_set) => tee_set_operations::fetch_tee_set_holes(conn, tee_set.id)?
+            .into_iter()
+            .map(|hole| (hole.hole_id, hole.yards))
+            .collect(),
+        None => HashMap::new(),
+    };
+
+    Ok(build_scorecard(round, tee_set, &course_holes, &yards, players, scores))
+}
+
+/// Every round the user owns or plays in, newest first, with the user's own
+/// totals where they are a player.
+pub fn list_rounds(conn: &mut PgConnection, user_id: i32) -> Result<Vec<RoundSummary>, ApiError> {
+    let played_in = round_players::table
+        .filter(round_players::user_id.eq(user_id))
+        .select(round_players::round_id);
+
+    let user_rounds = rounds::table
+        .filter(rounds::user_id.eq(user_id).or(rounds::id.eq_any(played_in)))
+        .order((rounds::played_on.desc(), rounds::id.desc()))
+        .select(Round::as_select())
+        .load(conn)?;
+
+    let all_players = RoundPlayer::belonging_to(&user_rounds)
+        .order(round_players::id.asc())
+        .select(RoundPlayer::as_select())
+        .load(conn)?
+        .grouped_by(&user_rounds);
+    let all_scores = HoleScore::belonging_to(&user_rounds)
+        .select(HoleScore::as_select())
+        .load(conn)?
+        .grouped_by(&user_rounds);
+
+    // Pars by hole, for each course and tee set played.
+    let mut pars_by_tees: HashMap<TeesPlayed, HashMap<i32, Option<i32>>> = HashMap::new();
+    let mut summaries = Vec::with_capacity(user_rounds.len());
+
+    for ((round, players), scores) in user_rounds.into_iter().zip(all_players).zip(all_scores) {
+        let key = (round.course_id, round.tee_set_id, round.tee_set.clone());
+        let pars = match pars_by_tees.entry(key) {
+            std::collections::hash_map::Entry::Occupied(entry) => entry.into_mut(),
+            std::collections::hash_map::Entry::Vacant(entry) => {
+                let tee_set = tee_set_operations::round_tee_set(conn, &round)?;
+                let pars = hole_operations::played_holes(conn, round.course_id, tee_set.as_ref())?
+                    .into_iter()
+                    .map(|hole| (hole.hole_id, hole.par))
+                    .collect();
+                entry.insert(pars)
+            }
+        };
+
+        let totals = players
+            .iter()
+            .find(|player| player.user_id == Some(user_id))
+            .map(|player| {
+                let player_scores: Vec<&HoleScore> =
+                    scores.iter().filter(|score| score.player_id == player.id).collect();
+                totals_for(&player_scores, |hole_id| pars.get(&hole_id).copied().flatten())
+            });
+
+        summaries.push(RoundSummary { round, players, totals });
+    }
+
+    Ok(summaries)
+}
+
+fn build_scorecard(
+    round: Round,
+    tee_set: Option<TeeSet>,
+    course_holes: &[Hole],
+    yards: &HashMap<i32, i32>,
+    players: Vec<RoundPlayer>,
+    scores: Vec<HoleScore>,
+) -> Scorecard {
+    let par_of = |hole_id: i32| {
+        course_holes
+            .iter()
+            .find(|hole| hole.hole_id == hole_id)
+            .and_then(|hole| hole.par)
+    };
+    let course_par = course_holes.iter().map(|hole| hole.par).sum::<Option<i32>>();
+    let course_yards = course_holes
+        .iter()
+        .map(|hole| yards.get(&hole.hole_id).copied())
+        .sum::<Option<i32>>();
+
+    let players = players
+        .into_iter()
+        .map(|player| {
+            let mut player_scores: HashMap<i32, HoleScore> = scores
+                .iter()
+                .filter(|score| score.player_id == player.id)
+                .map(|score| (score.hole_id, score.clone()))
+                .collect();
+
+            let holes: Vec<ScorecardLine> = course_holes
+                .iter()
+                .map(|hole| {
+                    let score = player_scores.remove(&hole.hole_id);
+                    let to_par = match (&score, hole.par) {
+                        (Some(score), Some(par)) => Some(score.strokes - par),
+                        _ => None,
+                    };
+                    ScorecardLine {
+                        hole_id: hole.hole_id,
+                        number: hole.number,
+                        name: hole.name.clone(),
+                        par: hole.par,
+                        stroke_index: hole.stroke_index,
+                        yards: yards.get(&hole.hole_id).copied(),
+                        score,
+                        to_par,
+                    }
+                })
+                .collect();
+
+            let nine = |front: bool| {
+                let scores: Vec<&HoleScore> = holes
+                    .iter()
+                    .filter(|line| line.number.is_some_and(|number| (number <= 9) == front))
+                    .filter_map(|line| line.score.as_ref())
+                    .collect();
+                totals_for(&scores, par_of)
+            };
+            let front_nine = nine(true);
+            let back_nine = nine(false);
+            let all: Vec<&HoleScore> = holes.iter().filter_map(|line| line.score.as_ref()).collect();
+            let totals = totals_for(&all, par_of);
+
+            PlayerScorecard {
+                player,
+                holes,
+                front_nine,
+                back_nine,
+                totals,
+            }
+        })
+        .collect();
+
+    Scorecard {
+        round,
+        tee_set,
+        course_par,
+        course_yards,
+        players,
+    }
+}
+
+fn totals_for(scores: &[&HoleScore], par_of: impl Fn(i32) -> Option<i32>) -> ScoreTotals {
+    let mut totals = ScoreTotals::default();
+    let mut to_par = 0;
+    let mut par_played = 0;
+
+    for score in scores {
+        totals.holes_played += 1;
+        totals.strokes += score.strokes;
+        totals.putts += score.putts.unwrap_or(0);
+        totals.penalties += score.penalties;
+        totals.greens_in_regulation += i32::from(score.green_in_regulation == Some(true));
+
+        let par = par_of(score.hole_id);
+        if let Some(par) = par {
+            to_par += score.strokes - par;
+            par_played += par;
+        }
+        if par != Some(3) {
+            if let Some(hit) = score.fairway_hit {
+                totals.fairways_possible += 1;
+                totals.fairways_hit += i32::from(hit);
+            }
+        }
+    }
+
+    if par_played > 0 {
+        totals.to_par = Some(to_par);
+        totals.par_played = Some(par_played);
+    }
+    totals
+}
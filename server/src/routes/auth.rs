@@ -0,0 +1,84 @@
+use actix_web::{web, HttpResponse};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::auth::{self, AuthenticatedUser};
+use crate::config::Config;
+use crate::error::ApiError;
+use crate::DbPool;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/auth")
+            .route("/register", web::post().to(register))
+            .route("/login", web::post().to(login))
+            .route("/refresh", web::post().to(refresh))
+            .route("/logout", web::post().to(logout))
+            .route("/me", web::get().to(me)),
+    );
+}
+
+#[derive(Deserialize)]
+struct RegisterRequest {
+    email: String,
+    password: String,
+    display_name: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct LoginRequest {
+    email: String,
+    password: String,
+}
+
+#[derive(Deserialize)]
+struct RefreshRequest {
+    refresh_token: String,
+}
+
+async fn register(
+    pool: web::Data<DbPool>,
+    config: web::Data<Config>,
+    body: web::Json<RegisterRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let mut conn = pool.get()?;
+    let body = body.into_inner();
+    let (user, tokens) = web::block(move || {
+        auth::register(&mut conn, &config.auth, &body.email, &body.password, body.display_name)
+    })
+    .await??;
+
+    Ok(HttpResponse::Created().json(json!({ "user": user, "tokens": tokens })))
+}
+
+async fn login(
+    pool: web::Data<DbPool>,
+    config: web::Data<Config>,
+    body: web::Json<LoginRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let mut conn = pool.get()?;
+    let tokens = web::block(move || auth::login(&mut conn, &config.auth, &body.email, &body.password)).await??;
+    Ok(HttpResponse::Ok().json(tokens))
+}
+
+async fn refresh(
+    pool: web::Data<DbPool>,
+    config: web::Data<Config>,
+    body: web::Json<RefreshRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let mut conn = pool.get()?;
+    let tokens = web::block(move || auth::refresh(&mut conn, &config.auth, &body.refresh_token)).await??;
+    Ok(HttpResponse::Ok().json(tokens))
+}
+
+async fn logout(pool: web::Data<DbPool>, body: web::Json<RefreshRequest>) -> Result<HttpResponse, ApiError> {
+    let mut conn = pool.get()?;
+    web::block(move || auth::logout(&mut conn, &body.refresh_token)).await??;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+async fn me(user: AuthenticatedUser, pool: web::Data<DbPool>) -> Result<HttpResponse, ApiError> {
+    let mut conn = pool.get()?;
+    let user = web::block(move || auth::fetch_user(&mut conn, user.id)).await??;
+    Ok(HttpResponse::Ok().json(user))
+}
@@ -0,0 +1,107 @@
+use actix_web::{web, HttpResponse};
+
+use crate::auth::AuthenticatedUser;
+use crate::bag_operations::{self, ClubInput};
+use crate::db_operations;
+use crate::error::ApiError;
+use crate::models::HoleWithBag;
+use crate::stats_operations;
+use crate::DbPool;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/bag")
+            .route("", web::get().to(get_bag))
+            .route("/stats", web::get().to(get_bag_stats))
+            .route("/clubs", web::post().to(add_club))
+            .route("/clubs/{club_id}", web::get().to(get_club))
+            .route("/clubs/{club_id}", web::put().to(update_club))
+            .route("/clubs/{club_id}", web::delete().to(delete_club))
+            .route("/clubs/{club_id}/stats", web::get().to(get_club_stats)),
+    )
+    .route("/hole/{hole_id}/with_bag", web::get().to(get_hole_with_bag));
+}
+
+async fn get_bag(user: AuthenticatedUser, pool: web::Data<DbPool>) -> Result<HttpResponse, ApiError> {
+    let mut conn = pool.get()?;
+    let bag = web::block(move || bag_operations::fetch_bag(&mut conn, user.id)).await??;
+    Ok(HttpResponse::Ok().json(bag))
+}
+
+async fn get_bag_stats(user: AuthenticatedUser, pool: web::Data<DbPool>) -> Result<HttpResponse, ApiError> {
+    let mut conn = pool.get()?;
+    let stats = web::block(move || stats_operations::club_statistics(&mut conn, user.id)).await??;
+    Ok(HttpResponse::Ok().json(stats))
+}
+
+async fn get_club_stats(
+    user: AuthenticatedUser,
+    pool: web::Data<DbPool>,
+    club_id: web::Path<i32>,
+) -> Result<HttpResponse, ApiError> {
+    let mut conn = pool.get()?;
+    let club_id = club_id.into_inner();
+    let stats = web::block(move || stats_operations::club_statistics_for(&mut conn, user.id, club_id)).await??;
+    Ok(HttpResponse::Ok().json(stats))
+}
+
+async fn get_club(
+    user: AuthenticatedUser,
+    pool: web::Data<DbPool>,
+    club_id: web::Path<i32>,
+) -> Result<HttpResponse, ApiError> {
+    let mut conn = pool.get()?;
+    let club_id = club_id.into_inner();
+    let club = web::block(move || bag_operations::fetch_club(&mut conn, user.id, club_id)).await??;
+    Ok(HttpResponse::Ok().json(club))
+}
+
+async fn add_club(
+    user: AuthenticatedUser,
+    pool: web::Data<DbPool>,
+    body: web::Json<ClubInput>,
+) -> Result<HttpResponse, ApiError> {
+    let mut conn = pool.get()?;
+    let club = web::block(move || bag_operations::add_club(&mut conn, user.id, body.into_inner())).await??;
+    Ok(HttpResponse::Created().json(club))
+}
+
+async fn update_club(
+    user: AuthenticatedUser,
+    pool: web::Data<DbPool>,
+    club_id: web::Path<i32>,
+    body: web::Json<ClubInput>,
+) -> Result<HttpResponse, ApiError> {
+    let mut conn = pool.get()?;
+    let club_id = club_id.into_inner();
+    let club =
+        web::block(move || bag_operations::update_club(&mut conn, user.id, club_id, body.into_inner())).await??;
+    Ok(HttpResponse::Ok().json(club))
+}
+
+async fn delete_club(
+    user: AuthenticatedUser,
+    pool: web::Data<DbPool>,
+    club_id: web::Path<i32>,
+) -> Result<HttpResponse, ApiError> {
+    let mut conn = pool.get()?;
+    let club_id = club_id.into_inner();
+    web::block(move || bag_operations::delete_club(&mut conn, user.id, club_id)).await??;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+async fn get_hole_with_bag(
+    user: AuthenticatedUser,
+    pool: web::Data<DbPool>,
+    hole_id: web::Path<i32>,
+) -> Result<HttpResponse, ApiError> {
+    let mut conn = pool.get()?;
+    let hole_id = hole_id.into_inner();
+    let hole_with_bag = web::block(move || -> Result<HoleWithBag, ApiError> {
+        let hole_data = db_operations::fetch_hole_data(&mut conn, hole_id)?;
+        let bag = bag_operations::fetch_bag(&mut conn, user.id)?;
+        Ok(HoleWithBag { hole_data, bag })
+    })
+    .await??;
+    Ok(HttpResponse::Ok().json(hole_with_bag))
+}
@@ -0,0 +1,252 @@
+use actix_web::http::header;
+use actix_web::{web, HttpResponse};
+use chrono::NaiveDate;
+
+use crate::auth::{AdminUser, AuthenticatedUser};
+use crate::config::Config;
+use crate::error::ApiError;
+use crate::hole_mesh::{HoleMesh, MeshCache, MeshQuery};
+use crate::hole_operations::{self, HoleInput, HolesQuery, TeeSetHoleInput};
+use crate::pin_operations::{self, PinSheetInput};
+use crate::tee_set_operations::{self, TeeSetInput};
+use crate::tile_operations;
+use crate::tiles::{TileCache, TileId};
+use crate::weather::WeatherProvider;
+use crate::weather_operations::{self, ConditionsQuery};
+use crate::{aws_operations, db_operations, DbPool};
+
+pub fn configure(cfg: &mut web::ServiceConfig, config: &Config) {
+    // Registered first so `/hole/{hole_id}` doesn't claim `101.glb` as an id.
+    cfg.route("/hole/{hole_id}.glb", web::get().to(get_hole_mesh))
+        .route("/hole/{hole_id}", web::get().to(get_hole_data))
+        .route("/tiles/{z}/{x}/{y}.mvt", web::get().to(get_tile))
+        .route("/courses", web::get().to(get_courses))
+        .route("/courses/{course_id}/holes", web::get().to(get_course_holes))
+        .route("/courses/{course_id}/holes", web::put().to(update_course_holes))
+        .route("/courses/{course_id}/tee_sets", web::get().to(get_tee_sets))
+        .route("/courses/{course_id}/tee_sets", web::post().to(create_tee_set))
+        .route("/courses/{course_id}/tee_sets/{tee_set_id}", web::put().to(update_tee_set))
+        .route("/courses/{course_id}/tee_sets/{tee_set_id}", web::delete().to(delete_tee_set))
+        .route("/courses/{course_id}/tee_sets/{tee_set_id}/holes", web::put().to(update_tee_set_holes))
+        .route("/courses/{course_id}/conditions", web::get().to(get_conditions))
+        .route("/courses/{course_id}/pin_sheets", web::get().to(get_pin_sheets))
+        .route("/courses/{course_id}/pin_sheets/{played_on}", web::get().to(get_pin_sheet))
+        .route("/courses/{course_id}/pin_sheets/{played_on}", web::put().to(upload_pin_sheet))
+        .route("/courses/{course_id}/pin_sheets/{played_on}", web::delete().to(delete_pin_sheet));
+
+    if config.features.golfbert_import {
+        cfg.route("/trigger_aws/{course_id}", web::post().to(trigger_aws_curls));
+    }
+}
+
+async fn get_courses(pool: web::Data<DbPool>) -> Result<HttpResponse, ApiError> {
+    let mut conn = pool.get()?;
+    let courses = web::block(move || db_operations::fetch_courses(&mut conn)).await??;
+    Ok(HttpResponse::Ok().json(courses))
+}
+
+async fn get_hole_data(pool: web::Data<DbPool>, hole_id: web::Path<i32>) -> Result<HttpResponse, ApiError> {
+    let mut conn = pool.get()?;
+    let hole_id = hole_id.into_inner();
+    let hole_data = web::block(move || db_operations::fetch_hole_data(&mut conn, hole_id)).await??;
+    Ok(HttpResponse::Ok().json(hole_data))
+}
+
+async fn get_hole_mesh(
+    _user: AuthenticatedUser,
+    pool: web::Data<DbPool>,
+    cache: web::Data<MeshCache>,
+    hole_id: web::Path<i32>,
+    query: web::Query<MeshQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let mut conn = pool.get()?;
+    let hole_id = hole_id.into_inner();
+    let lod = query.into_inner().lod;
+    let glb = web::block(move || {
+        cache.get_or_build((hole_id, lod), || {
+            let hole_data = db_operations::fetch_hole_data(&mut conn, hole_id)?;
+            HoleMesh::build(&hole_data, lod)
+                .map(|mesh| mesh.to_glb(hole_id, lod))
+                .ok_or_else(|| ApiError::BadRequest("hole has no geometry to build a mesh from".to_string()))
+        })
+    })
+    .await??;
+    Ok(HttpResponse::Ok().content_type("model/gltf-binary").body(glb.to_vec()))
+}
+
+async fn get_tile(
+    pool: web::Data<DbPool>,
+    cache: web::Data<TileCache>,
+    path: web::Path<(u8, u32, u32)>,
+) -> Result<HttpResponse, ApiError> {
+    let (z, x, y) = path.into_inner();
+    let tile = TileId::new(z, x, y).ok_or_else(|| ApiError::BadRequest(format!("{}/{}/{} is not a tile", z, x, y)))?;
+    let mut conn = pool.get()?;
+    let max_age = cache.ttl().as_secs();
+    let bytes =
+        web::block(move || cache.get_or_build(tile, || tile_operations::build_tile(&mut conn, tile))).await??;
+    Ok(HttpResponse::Ok()
+        .content_type("application/vnd.mapbox-vector-tile")
+        .insert_header((header::CACHE_CONTROL, format!("public, max-age={}", max_age)))
+        .body(bytes.to_vec()))
+}
+
+async fn trigger_aws_curls(
+    _admin: AdminUser,
+    pool: web::Data<DbPool>,
+    config: web::Data<Config>,
+    course_id: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    let mut conn = pool.get()?;
+    let course_id = course_id.into_inner();
+    web::block(move || {
+        aws_operations::get_holes_from_golfbert(course_id, &config.providers.golfbert, &mut conn)
+            .map_err(|e| ApiError::Internal(format!("course import failed: {}", e)))
+    })
+    .await??;
+    Ok(HttpResponse::Ok().body("AWS curls executed successfully"))
+}
+
+/// Par, stroke index and name of each hole, as played from a tee set when
+/// one is given.
+async fn get_course_holes(
+    pool: web::Data<DbPool>,
+    course_id: web::Path<i32>,
+    query: web::Query<HolesQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let mut conn = pool.get()?;
+    let course_id = course_id.into_inner();
+    let holes =
+        web::block(move || hole_operations::course_holes(&mut conn, course_id, query.into_inner())).await??;
+    Ok(HttpResponse::Ok().json(holes))
+}
+
+async fn update_course_holes(
+    _admin: AdminUser,
+    pool: web::Data<DbPool>,
+    course_id: web::Path<i32>,
+    body: web::Json<Vec<HoleInput>>,
+) -> Result<HttpResponse, ApiError> {
+    let mut conn = pool.get()?;
+    let course_id = course_id.into_inner();
+    let holes =
+        web::block(move || hole_operations::update_holes(&mut conn, course_id, body.into_inner())).await??;
+    Ok(HttpResponse::Ok().json(holes))
+}
+
+async fn get_tee_sets(pool: web::Data<DbPool>, course_id: web::Path<i32>) -> Result<HttpResponse, ApiError> {
+    let mut conn = pool.get()?;
+    let course_id = course_id.into_inner();
+    let tee_sets = web::block(move || tee_set_operations::fetch_tee_sets(&mut conn, course_id)).await??;
+    Ok(HttpResponse::Ok().json(tee_sets))
+}
+
+async fn create_tee_set(
+    _admin: AdminUser,
+    pool: web::Data<DbPool>,
+    course_id: web::Path<i32>,
+    body: web::Json<TeeSetInput>,
+) -> Result<HttpResponse, ApiError> {
+    let mut conn = pool.get()?;
+    let course_id = course_id.into_inner();
+    let tee_set =
+        web::block(move || tee_set_operations::create_tee_set(&mut conn, course_id, body.into_inner())).await??;
+    Ok(HttpResponse::Created().json(tee_set))
+}
+
+async fn update_tee_set(
+    _admin: AdminUser,
+    pool: web::Data<DbPool>,
+    path: web::Path<(i32, i32)>,
+    body: web::Json<TeeSetInput>,
+) -> Result<HttpResponse, ApiError> {
+    let mut conn = pool.get()?;
+    let (course_id, tee_set_id) = path.into_inner();
+    let tee_set = web::block(move || {
+        tee_set_operations::update_tee_set(&mut conn, course_id, tee_set_id, body.into_inner())
+    })
+    .await??;
+    Ok(HttpResponse::Ok().json(tee_set))
+}
+
+async fn delete_tee_set(
+    _admin: AdminUser,
+    pool: web::Data<DbPool>,
+    path: web::Path<(i32, i32)>,
+) -> Result<HttpResponse, ApiError> {
+    let mut conn = pool.get()?;
+    let (course_id, tee_set_id) = path.into_inner();
+    web::block(move || tee_set_operations::delete_tee_set(&mut conn, course_id, tee_set_id)).await??;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+async fn update_tee_set_holes(
+    _admin: AdminUser,
+    pool: web::Data<DbPool>,
+    path: web::Path<(i32, i32)>,
+    body: web::Json<Vec<TeeSetHoleInput>>,
+) -> Result<HttpResponse, ApiError> {
+    let mut conn = pool.get()?;
+    let (course_id, tee_set_id) = path.into_inner();
+    let holes = web::block(move || {
+        hole_operations::update_tee_set_holes(&mut conn, course_id, tee_set_id, body.into_inner())
+    })
+    .await??;
+    Ok(HttpResponse::Ok().json(holes))
+}
+
+async fn get_conditions(
+    pool: web::Data<DbPool>,
+    weather: web::Data<dyn WeatherProvider>,
+    course_id: web::Path<i32>,
+    query: web::Query<ConditionsQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let mut conn = pool.get()?;
+    let course_id = course_id.into_inner();
+    let conditions = web::block(move || {
+        weather_operations::course_conditions(&mut conn, weather.get_ref(), course_id, query.into_inner())
+    })
+    .await??;
+    Ok(HttpResponse::Ok().json(conditions))
+}
+
+async fn get_pin_sheets(pool: web::Data<DbPool>, course_id: web::Path<i32>) -> Result<HttpResponse, ApiError> {
+    let mut conn = pool.get()?;
+    let course_id = course_id.into_inner();
+    let sheets = web::block(move || pin_operations::fetch_pin_sheets(&mut conn, course_id)).await??;
+    Ok(HttpResponse::Ok().json(sheets))
+}
+
+async fn get_pin_sheet(pool: web::Data<DbPool>, path: web::Path<(i32, NaiveDate)>) -> Result<HttpResponse, ApiError> {
+    let mut conn = pool.get()?;
+    let (course_id, played_on) = path.into_inner();
+    let sheet = web::block(move || pin_operations::fetch_pin_sheet(&mut conn, course_id, played_on)).await??;
+    Ok(HttpResponse::Ok().json(sheet))
+}
+
+/// Uploads the day's pin sheet, replacing any already entered for that date.
+async fn upload_pin_sheet(
+    _admin: AdminUser,
+    pool: web::Data<DbPool>,
+    path: web::Path<(i32, NaiveDate)>,
+    body: web::Json<PinSheetInput>,
+) -> Result<HttpResponse, ApiError> {
+    let mut conn = pool.get()?;
+    let (course_id, played_on) = path.into_inner();
+    let sheet = web::block(move || {
+        pin_operations::upload_pin_sheet(&mut conn, course_id, played_on, body.into_inner())
+    })
+    .await??;
+    Ok(HttpResponse::Ok().json(sheet))
+}
+
+async fn delete_pin_sheet(
+    _admin: AdminUser,
+    pool: web::Data<DbPool>,
+    path: web::Path<(i32, NaiveDate)>,
+) -> Result<HttpResponse, ApiError> {
+    let mut conn = pool.get()?;
+    let (course_id, played_on) = path.into_inner();
+    web::block(move || pin_operations::delete_pin_sheet(&mut conn, course_id, played_on)).await??;
+    Ok(HttpResponse::NoContent().finish())
+}
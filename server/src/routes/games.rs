@@ -0,0 +1,63 @@
+use actix_web::{web, HttpResponse};
+
+use crate::auth::AuthenticatedUser;
+use crate::error::ApiError;
+use crate::game_operations::{self, NewGameInput};
+use crate::DbPool;
+
+/// Mounted inside the `/rounds` scope, like the shot routes.
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/{round_id}/games")
+            .route("", web::get().to(list_games))
+            .route("", web::post().to(create_game))
+            .route("/{game_id}", web::get().to(get_game))
+            .route("/{game_id}", web::delete().to(delete_game)),
+    );
+}
+
+async fn list_games(
+    user: AuthenticatedUser,
+    pool: web::Data<DbPool>,
+    round_id: web::Path<i32>,
+) -> Result<HttpResponse, ApiError> {
+    let mut conn = pool.get()?;
+    let round_id = round_id.into_inner();
+    let games = web::block(move || game_operations::list_games(&mut conn, round_id, user.id)).await??;
+    Ok(HttpResponse::Ok().json(games))
+}
+
+async fn create_game(
+    user: AuthenticatedUser,
+    pool: web::Data<DbPool>,
+    round_id: web::Path<i32>,
+    body: web::Json<NewGameInput>,
+) -> Result<HttpResponse, ApiError> {
+    let mut conn = pool.get()?;
+    let round_id = round_id.into_inner();
+    let game =
+        web::block(move || game_operations::create_game(&mut conn, round_id, user.id, body.into_inner())).await??;
+    Ok(HttpResponse::Created().json(game))
+}
+
+async fn get_game(
+    user: AuthenticatedUser,
+    pool: web::Data<DbPool>,
+    path: web::Path<(i32, i32)>,
+) -> Result<HttpResponse, ApiError> {
+    let mut conn = pool.get()?;
+    let (round_id, game_id) = path.into_inner();
+    let game = web::block(move || game_operations::game(&mut conn, round_id, game_id, user.id)).await??;
+    Ok(HttpResponse::Ok().json(game))
+}
+
+async fn delete_game(
+    user: AuthenticatedUser,
+    pool: web::Data<DbPool>,
+    path: web::Path<(i32, i32)>,
+) -> Result<HttpResponse, ApiError> {
+    let mut conn = pool.get()?;
+    let (round_id, game_id) = path.into_inner();
+    web::block(move || game_operations::delete_game(&mut conn, round_id, game_id, user.id)).await??;
+    Ok(HttpResponse::NoContent().finish())
+}
@@ -0,0 +1,265 @@
+use std::time::{Duration, Instant};
+
+use actix_web::{web, HttpRequest, HttpResponse};
+use actix_ws::{CloseCode, CloseReason, Message, Session};
+use serde::Deserialize;
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::auth::{self, AuthenticatedUser};
+use crate::config::Config;
+use crate::error::ApiError;
+use crate::round_event_operations::{self, CatchUp};
+use crate::round_events::{LiveMessage, RoundHub};
+use crate::round_operations;
+use crate::DbPool;
+
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(15);
+/// A client that hasn't answered a ping in this long is dropped.
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(45);
+
+#[derive(Deserialize)]
+pub struct LiveQuery {
+    /// The last event the client saw, to replay what it missed.
+    since: Option<i32>,
+    /// Browsers can't set headers on a WebSocket, so the access token may
+    /// come in the query instead.
+    access_token: Option<String>,
+}
+
+/// Sends a message, returning false once the client has gone.
+async fn send(session: &mut Session, message: &LiveMessage<'_>) -> bool {
+    match serde_json::to_string(message) {
+        Ok(text) => session.text(text).await.is_ok(),
+        Err(e) => {
+            log::error!("failed to encode live round message: {}", e);
+            true
+        }
+    }
+}
+
+/// Sends a catch-up and returns the sequence number the client is now at.
+async fn send_catch_up(session: &mut Session, catch_up: &CatchUp, mut sequence: i32) -> Option<i32> {
+    match catch_up {
+        CatchUp::Snapshot {
+            sequence: at,
+            scorecard,
+            shots,
+        } => {
+            let snapshot = LiveMessage::Snapshot {
+                sequence: *at,
+                scorecard,
+                shots,
+            };
+            if !send(session, &snapshot).await {
+                return None;
+            }
+            sequence = *at;
+        }
+        CatchUp::Replay(events) => {
+            for event in events {
+                if !send(session, &LiveMessage::Event(event)).await {
+                    return None;
+                }
+                sequence = event.sequence;
+            }
+        }
+    }
+    Some(sequence)
+}
+
+/// A WebSocket of the round's changes as they are saved. The client gets a
+/// snapshot, or the events after `?since=`, then each event in order. After
+/// a dropped connection it reconnects with the last sequence number it saw.
+pub async fn live_round(
+    req: HttpRequest,
+    body: web::Payload,
+    user: Result<AuthenticatedUser, ApiError>,
+    pool: web::Data<DbPool>,
+    hub: web::Data<RoundHub>,
+    round_id: web::Path<i32>,
+    query: web::Query<LiveQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let LiveQuery { since, access_token } = query.into_inner();
+    let user = match access_token {
+        Some(token) => {
+            let config = req
+                .app_data::<web::Data<Config>>()
+                .ok_or_else(|| ApiError::Internal("configuration is not registered".to_string()))?;
+            auth::authenticate_token(&config.auth, &token)?
+        }
+        None => user?,
+    };
+    let (round_id, user_id) = (round_id.into_inner(), user.id);
+
+    // Subscribe before reading, so nothing saved in between is missed.
+    let mut receiver = hub.subscribe(round_id);
+    let mut conn = pool.get()?;
+    let catch_up = web::block(move || {
+        round_operations::authorize_round(&mut conn, round_id, user_id)?;
+        round_event_operations::catch_up(&mut conn, round_id, user_id, since)
+    })
+    .await??;
+
+    let (response, mut session, mut messages) =
+        actix_ws::handle(&req, body).map_err(|e| ApiError::BadRequest(e.to_string()))?;
+
+    actix_web::rt::spawn(async move {
+        let Some(mut sequence) = send_catch_up(&mut session, &catch_up, since.unwrap_or(0)).await else {
+            return;
+        };
+        let mut heartbeat = tokio::time::interval(HEARTBEAT_INTERVAL);
+        let mut last_heard = Instant::now();
+
+        let reason = loop {
+            tokio::select! {
+                message = messages.recv() => match message {
+                    Some(Ok(Message::Ping(bytes))) => {
+                        last_heard = Instant::now();
+                        if session.pong(&bytes).await.is_err() {
+                            return;
+                        }
+                    }
+                    Some(Ok(Message::Close(reason))) => break reason,
+                    Some(Ok(_)) => last_heard = Instant::now(),
+                    Some(Err(_)) | None => return,
+                },
+                event = receiver.recv() => {
+                    let missed = match event {
+                        Ok(event) if event.sequence <= sequence => continue,
+                        Ok(event) if event.sequence == sequence + 1 => {
+                            if !send(&mut session, &LiveMessage::Event(&event)).await {
+                                return;
+                            }
+                            sequence = event.sequence;
+                            continue;
+                        }
+                        // Published out of order, or dropped while this
+                        // client was slow; the database has the rest.
+                        Ok(_) | Err(RecvError::Lagged(_)) => sequence,
+                        Err(RecvError::Closed) => break None,
+                    };
+                    let pool = pool.clone();
+                    let catch_up = web::block(move || {
+                        let mut conn = pool.get()?;
+                        round_event_operations::catch_up(&mut conn, round_id, user_id, Some(missed))
+                    })
+                    .await;
+                    match catch_up {
+                        Ok(Ok(catch_up)) => match send_catch_up(&mut session, &catch_up, sequence).await {
+                            Some(at) => sequence = at,
+                            None => return,
+                        },
+                        _ => {
+                            break Some(CloseReason {
+                                code: CloseCode::Error,
+                                description: Some("failed to load round events".to_string()),
+                            })
+                        }
+                    }
+                }
+                _ = heartbeat.tick() => {
+                    if last_heard.elapsed() > CLIENT_TIMEOUT {
+                        break None;
+                    }
+                    if session.ping(b"").await.is_err() {
+                        return;
+                    }
+                }
+            }
+        };
+        let _ = session.close(reason).await;
+    });
+
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http::StatusCode;
+    use actix_web::{test, App};
+    use chrono::Utc;
+    use diesel::prelude::*;
+
+    use super::*;
+    use crate::round_operations::{JoinRoundInput, NewPlayerInput};
+    use crate::schema::users;
+
+    fn upgrade(round_id: i32, access_token: &str) -> test::TestRequest {
+        test::TestRequest::get()
+            .uri(&format!("/rounds/{}/live?access_token={}", round_id, access_token))
+            .insert_header(("upgrade", "websocket"))
+            .insert_header(("connection", "upgrade"))
+            .insert_header(("sec-websocket-version", "13"))
+            .insert_header(("sec-websocket-key", "dGhlIHNhbXBsZSBub25jZQ=="))
+    }
+
+    /// Needs a database in `DATABASE_URL`; skipped without one.
+    #[actix_web::test]
+    async fn invited_player_can_subscribe() {
+        let Ok(url) = std::env::var("DATABASE_URL") else {
+            return;
+        };
+        let mut config = Config::default();
+        config.database.url = url;
+        config.database.pool_size = 2;
+        config.auth.jwt_secret = "a test secret of at least 32 bytes".to_string();
+        let pool = crate::establish_connection(&config.database).unwrap();
+        let mut conn = pool.get().unwrap();
+        crate::run_migrations(&mut conn).unwrap();
+
+        let suffix = auth::random_token();
+        let mut register = |name: &str| {
+            let email = format!("{}-{}@live.test", name, suffix).to_lowercase();
+            auth::register(&mut conn, &config.auth, &email, "password1", None).unwrap()
+        };
+        let (owner, _) = register("owner");
+        let (guest, guest_tokens) = register("guest");
+        let (stranger, stranger_tokens) = register("stranger");
+
+        let players = round_operations::create_round(
+            &mut conn,
+            owner.id,
+            9999,
+            None,
+            None,
+            Utc::now().date_naive(),
+            vec![
+                NewPlayerInput {
+                    name: "Owner".to_string(),
+                    user_id: Some(owner.id),
+                },
+                NewPlayerInput {
+                    name: "Guest".to_string(),
+                    user_id: None,
+                },
+            ],
+        )
+        .unwrap();
+        let round_id = players[0].round_id;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(pool.clone()))
+                .app_data(web::Data::new(config.clone()))
+                .app_data(web::Data::new(RoundHub::new()))
+                .route("/rounds/{round_id}/live", web::get().to(live_round)),
+        )
+        .await;
+
+        let response = test::call_service(&app, upgrade(round_id, &guest_tokens.access_token).to_request()).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let invite = round_operations::invite_player(&mut conn, round_id, players[1].id, owner.id).unwrap();
+        let join = JoinRoundInput {
+            invite_code: invite.invite_code,
+        };
+        round_operations::join_round(&mut conn, guest.id, join).unwrap();
+        let response = test::call_service(&app, upgrade(round_id, &guest_tokens.access_token).to_request()).await;
+        assert_eq!(response.status(), StatusCode::SWITCHING_PROTOCOLS);
+        let response = test::call_service(&app, upgrade(round_id, &stranger_tokens.access_token).to_request()).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        diesel::delete(users::table.filter(users::id.eq_any([owner.id, guest.id, stranger.id])))
+            .execute(&mut conn)
+            .unwrap();
+    }
+}
@@ -0,0 +1,24 @@
+use actix_web::web;
+
+use crate::config::Config;
+
+pub mod auth;
+pub mod bag;
+pub mod courses;
+pub mod games;
+pub mod live;
+pub mod recommend;
+pub mod rounds;
+pub mod shots;
+pub mod stats;
+pub mod tournaments;
+
+pub fn configure(cfg: &mut web::ServiceConfig, config: &Config) {
+    auth::configure(cfg);
+    bag::configure(cfg);
+    courses::configure(cfg, config);
+    recommend::configure(cfg);
+    rounds::configure(cfg);
+    stats::configure(cfg);
+    tournaments::configure(cfg);
+}
@@ -0,0 +1,85 @@
+use actix_web::{web, HttpResponse};
+
+use crate::auth::AuthenticatedUser;
+use crate::error::ApiError;
+use crate::plays_like::Conditions;
+use crate::recommend_operations::{self, DistancesQuery, GreenQuery, RecommendQuery, SimulateQuery};
+use crate::strokes_gained::Baseline;
+use crate::weather::WeatherProvider;
+use crate::DbPool;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.route("/hole/{hole_id}/recommend", web::get().to(get_recommendation))
+        .route("/hole/{hole_id}/simulate", web::get().to(get_simulation))
+        .route("/hole/{hole_id}/green", web::get().to(get_green))
+        .route("/hole/{hole_id}/distances", web::get().to(get_distances));
+}
+
+async fn get_recommendation(
+    user: AuthenticatedUser,
+    pool: web::Data<DbPool>,
+    hole_id: web::Path<i32>,
+    query: web::Query<RecommendQuery>,
+    conditions: web::Query<Conditions>,
+) -> Result<HttpResponse, ApiError> {
+    let mut conn = pool.get()?;
+    let hole_id = hole_id.into_inner();
+    let conditions = conditions.into_inner();
+    let recommendation = web::block(move || {
+        recommend_operations::recommend_for_user(&mut conn, user.id, hole_id, query.into_inner(), conditions)
+    })
+    .await??;
+    Ok(HttpResponse::Ok().json(recommendation))
+}
+
+async fn get_simulation(
+    user: AuthenticatedUser,
+    pool: web::Data<DbPool>,
+    baseline: web::Data<Baseline>,
+    hole_id: web::Path<i32>,
+    query: web::Query<SimulateQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let mut conn = pool.get()?;
+    let hole_id = hole_id.into_inner();
+    let result = web::block(move || {
+        recommend_operations::simulate_for_user(&mut conn, &baseline, user.id, hole_id, query.into_inner())
+    })
+    .await??;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+async fn get_green(
+    _user: AuthenticatedUser,
+    pool: web::Data<DbPool>,
+    hole_id: web::Path<i32>,
+    query: web::Query<GreenQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let mut conn = pool.get()?;
+    let hole_id = hole_id.into_inner();
+    let green = web::block(move || recommend_operations::green_for_hole(&mut conn, hole_id, query.into_inner())).await??;
+    Ok(HttpResponse::Ok().json(green))
+}
+
+async fn get_distances(
+    _user: AuthenticatedUser,
+    pool: web::Data<DbPool>,
+    weather: web::Data<dyn WeatherProvider>,
+    hole_id: web::Path<i32>,
+    query: web::Query<DistancesQuery>,
+    conditions: web::Query<Conditions>,
+) -> Result<HttpResponse, ApiError> {
+    let mut conn = pool.get()?;
+    let hole_id = hole_id.into_inner();
+    let conditions = conditions.into_inner();
+    let distances = web::block(move || {
+        recommend_operations::distances_for_hole(
+            &mut conn,
+            weather.get_ref(),
+            hole_id,
+            query.into_inner(),
+            conditions,
+        )
+    })
+    .await??;
+    Ok(HttpResponse::Ok().json(distances))
+}
@@ -0,0 +1,150 @@
+use actix_web::{web, HttpResponse};
+
+use super::{games, live, shots};
+use crate::auth::AuthenticatedUser;
+use crate::error::ApiError;
+use crate::handicap_operations::{self, RoundHandicapQuery};
+use crate::round_event_operations;
+use crate::round_events::RoundHub;
+use crate::round_operations::{self, HoleScoreInput, JoinRoundInput, StartRoundInput};
+use crate::stats_operations;
+use crate::strokes_gained::Baseline;
+use crate::DbPool;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/rounds")
+            .route("", web::get().to(list_rounds))
+            .route("", web::post().to(start_round))
+            .route("/join", web::post().to(join_round))
+            .route("/{round_id}", web::get().to(get_round))
+            .route("/{round_id}/scores", web::put().to(record_scores))
+            .route("/{round_id}/players/{player_id}/invite", web::post().to(invite_player))
+            .route("/{round_id}/finish", web::post().to(finish_round))
+            .route("/{round_id}/strokes_gained", web::get().to(get_strokes_gained))
+            .route("/{round_id}/handicap", web::get().to(get_handicaps))
+            .route("/{round_id}/live", web::get().to(live::live_round))
+            .configure(shots::configure)
+            .configure(games::configure),
+    );
+}
+
+async fn list_rounds(user: AuthenticatedUser, pool: web::Data<DbPool>) -> Result<HttpResponse, ApiError> {
+    let mut conn = pool.get()?;
+    let rounds = web::block(move || round_operations::list_rounds(&mut conn, user.id)).await??;
+    Ok(HttpResponse::Ok().json(rounds))
+}
+
+async fn start_round(
+    user: AuthenticatedUser,
+    pool: web::Data<DbPool>,
+    body: web::Json<StartRoundInput>,
+) -> Result<HttpResponse, ApiError> {
+    let mut conn = pool.get()?;
+    let scorecard = web::block(move || round_operations::start_round(&mut conn, user.id, body.into_inner())).await??;
+    Ok(HttpResponse::Created().json(scorecard))
+}
+
+async fn join_round(
+    user: AuthenticatedUser,
+    pool: web::Data<DbPool>,
+    body: web::Json<JoinRoundInput>,
+) -> Result<HttpResponse, ApiError> {
+    let mut conn = pool.get()?;
+    let scorecard = web::block(move || round_operations::join_round(&mut conn, user.id, body.into_inner())).await??;
+    Ok(HttpResponse::Ok().json(scorecard))
+}
+
+async fn invite_player(
+    user: AuthenticatedUser,
+    pool: web::Data<DbPool>,
+    path: web::Path<(i32, i32)>,
+) -> Result<HttpResponse, ApiError> {
+    let mut conn = pool.get()?;
+    let (round_id, player_id) = path.into_inner();
+    let invite =
+        web::block(move || round_operations::invite_player(&mut conn, round_id, player_id, user.id)).await??;
+    Ok(HttpResponse::Created().json(invite))
+}
+
+async fn get_round(
+    user: AuthenticatedUser,
+    pool: web::Data<DbPool>,
+    round_id: web::Path<i32>,
+) -> Result<HttpResponse, ApiError> {
+    let mut conn = pool.get()?;
+    let round_id = round_id.into_inner();
+    let scorecard = web::block(move || round_operations::fetch_scorecard(&mut conn, round_id, user.id)).await??;
+    Ok(HttpResponse::Ok().json(scorecard))
+}
+
+async fn record_scores(
+    user: AuthenticatedUser,
+    pool: web::Data<DbPool>,
+    hub: web::Data<RoundHub>,
+    round_id: web::Path<i32>,
+    body: web::Json<Vec<HoleScoreInput>>,
+) -> Result<HttpResponse, ApiError> {
+    let mut conn = pool.get()?;
+    let round_id = round_id.into_inner();
+    let (scorecard, event) = web::block(move || {
+        let scores = body.into_inner();
+        let player_ids: Vec<i32> = scores.iter().map(|score| score.player_id).collect();
+        round_event_operations::with_event(&mut conn, round_id, |conn| {
+            let scorecard = round_operations::record_scores(conn, round_id, user.id, scores)?;
+            let event = round_event_operations::scores_recorded(conn, &scorecard, &player_ids)?;
+            Ok((scorecard, event))
+        })
+    })
+    .await??;
+    hub.publish(event);
+    Ok(HttpResponse::Ok().json(scorecard))
+}
+
+async fn finish_round(
+    user: AuthenticatedUser,
+    pool: web::Data<DbPool>,
+    hub: web::Data<RoundHub>,
+    round_id: web::Path<i32>,
+) -> Result<HttpResponse, ApiError> {
+    let mut conn = pool.get()?;
+    let round_id = round_id.into_inner();
+    let (scorecard, event) = web::block(move || {
+        round_event_operations::with_event(&mut conn, round_id, |conn| {
+            let scorecard = round_operations::finish_round(conn, round_id, user.id)?;
+            let event = round_event_operations::round_finished(conn, &scorecard.round)?;
+            Ok((scorecard, event))
+        })
+    })
+    .await??;
+    hub.publish(event);
+    Ok(HttpResponse::Ok().json(scorecard))
+}
+
+async fn get_strokes_gained(
+    user: AuthenticatedUser,
+    pool: web::Data<DbPool>,
+    baseline: web::Data<Baseline>,
+    round_id: web::Path<i32>,
+) -> Result<HttpResponse, ApiError> {
+    let mut conn = pool.get()?;
+    let round_id = round_id.into_inner();
+    let strokes_gained =
+        web::block(move || stats_operations::round_strokes_gained(&mut conn, &baseline, round_id, user.id)).await??;
+    Ok(HttpResponse::Ok().json(strokes_gained))
+}
+
+async fn get_handicaps(
+    user: AuthenticatedUser,
+    pool: web::Data<DbPool>,
+    round_id: web::Path<i32>,
+    query: web::Query<RoundHandicapQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let mut conn = pool.get()?;
+    let round_id = round_id.into_inner();
+    let handicaps = web::block(move || {
+        handicap_operations::round_handicaps(&mut conn, round_id, user.id, query.into_inner())
+    })
+    .await??;
+    Ok(HttpResponse::Ok().json(handicaps))
+}
@@ -0,0 +1,135 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use serde::Deserialize;
+
+use crate::auth::AuthenticatedUser;
+use crate::error::ApiError;
+use crate::round_event_operations;
+use crate::round_events::RoundHub;
+use crate::shot_detection::{self, Trace};
+use crate::shot_operations::{self, NewShotInput, ShotDetails, ShotFilter};
+use crate::DbPool;
+
+/// Mounted inside the `/rounds` scope, which would otherwise swallow these paths.
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/{round_id}/shots")
+            .route("", web::get().to(list_shots))
+            .route("", web::post().to(record_shot))
+            .route("/detect", web::post().to(detect_shots))
+            .route("/{shot_id}", web::put().to(update_shot))
+            .route("/{shot_id}", web::delete().to(delete_shot)),
+    );
+}
+
+async fn list_shots(
+    user: AuthenticatedUser,
+    pool: web::Data<DbPool>,
+    round_id: web::Path<i32>,
+    filter: web::Query<ShotFilter>,
+) -> Result<HttpResponse, ApiError> {
+    let mut conn = pool.get()?;
+    let round_id = round_id.into_inner();
+    let shots =
+        web::block(move || shot_operations::list_shots(&mut conn, round_id, user.id, filter.into_inner())).await??;
+    Ok(HttpResponse::Ok().json(shots))
+}
+
+async fn record_shot(
+    user: AuthenticatedUser,
+    pool: web::Data<DbPool>,
+    hub: web::Data<RoundHub>,
+    round_id: web::Path<i32>,
+    body: web::Json<NewShotInput>,
+) -> Result<HttpResponse, ApiError> {
+    let mut conn = pool.get()?;
+    let round_id = round_id.into_inner();
+    let (shot, event) = web::block(move || {
+        round_event_operations::with_event(&mut conn, round_id, |conn| {
+            let shot = shot_operations::record_shot(conn, round_id, user.id, body.into_inner())?;
+            let event = round_event_operations::shots_changed(conn, round_id, shot.player_id, shot.hole_id)?;
+            Ok((shot, event))
+        })
+    })
+    .await??;
+    hub.publish(event);
+    Ok(HttpResponse::Created().json(shot))
+}
+
+#[derive(Deserialize)]
+struct DetectQuery {
+    hole_id: i32,
+}
+
+/// Accepts the trace either as GPX or as JSON `{"points": [...]}`, chosen by
+/// the request's content type.
+async fn detect_shots(
+    user: AuthenticatedUser,
+    pool: web::Data<DbPool>,
+    req: HttpRequest,
+    round_id: web::Path<i32>,
+    query: web::Query<DetectQuery>,
+    body: web::Bytes,
+) -> Result<HttpResponse, ApiError> {
+    let content_type = req
+        .headers()
+        .get("content-type")
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("");
+    let points = if content_type.contains("xml") {
+        let document = std::str::from_utf8(&body).map_err(|_| ApiError::BadRequest("GPX must be UTF-8".to_string()))?;
+        shot_detection::parse_gpx(document).map_err(ApiError::BadRequest)?
+    } else {
+        serde_json::from_slice::<Trace>(&body)
+            .map_err(|e| ApiError::BadRequest(format!("invalid trace: {}", e)))?
+            .points
+    };
+
+    let mut conn = pool.get()?;
+    let round_id = round_id.into_inner();
+    let detected = web::block(move || {
+        shot_operations::detect_shots(&mut conn, round_id, user.id, query.hole_id, points)
+    })
+    .await??;
+    Ok(HttpResponse::Ok().json(detected))
+}
+
+async fn update_shot(
+    user: AuthenticatedUser,
+    pool: web::Data<DbPool>,
+    hub: web::Data<RoundHub>,
+    path: web::Path<(i32, i32)>,
+    body: web::Json<ShotDetails>,
+) -> Result<HttpResponse, ApiError> {
+    let mut conn = pool.get()?;
+    let (round_id, shot_id) = path.into_inner();
+    let (shot, event) = web::block(move || {
+        round_event_operations::with_event(&mut conn, round_id, |conn| {
+            let shot = shot_operations::update_shot(conn, round_id, shot_id, user.id, body.into_inner())?;
+            let event = round_event_operations::shots_changed(conn, round_id, shot.player_id, shot.hole_id)?;
+            Ok((shot, event))
+        })
+    })
+    .await??;
+    hub.publish(event);
+    Ok(HttpResponse::Ok().json(shot))
+}
+
+async fn delete_shot(
+    user: AuthenticatedUser,
+    pool: web::Data<DbPool>,
+    hub: web::Data<RoundHub>,
+    path: web::Path<(i32, i32)>,
+) -> Result<HttpResponse, ApiError> {
+    let mut conn = pool.get()?;
+    let (round_id, shot_id) = path.into_inner();
+    let ((), event) = web::block(move || {
+        round_event_operations::with_event(&mut conn, round_id, |conn| {
+            let shot = shot_operations::delete_shot(conn, round_id, shot_id, user.id)?;
+            let event = round_event_operations::shots_changed(conn, round_id, shot.player_id, shot.hole_id)?;
+            Ok(((), event))
+        })
+    })
+    .await??;
+    hub.publish(event);
+    Ok(HttpResponse::NoContent().finish())
+}
@@ -0,0 +1,36 @@
+use actix_web::{web, HttpResponse};
+
+use crate::auth::AuthenticatedUser;
+use crate::error::ApiError;
+use crate::handicap_operations;
+use crate::stats_operations::{self, StrokesGainedQuery};
+use crate::strokes_gained::Baseline;
+use crate::DbPool;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/stats")
+            .route("/strokes_gained", web::get().to(get_strokes_gained))
+            .route("/handicap", web::get().to(get_handicap)),
+    );
+}
+
+async fn get_strokes_gained(
+    user: AuthenticatedUser,
+    pool: web::Data<DbPool>,
+    baseline: web::Data<Baseline>,
+    query: web::Query<StrokesGainedQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let mut conn = pool.get()?;
+    let report = web::block(move || {
+        stats_operations::strokes_gained_report(&mut conn, &baseline, user.id, query.into_inner())
+    })
+    .await??;
+    Ok(HttpResponse::Ok().json(report))
+}
+
+async fn get_handicap(user: AuthenticatedUser, pool: web::Data<DbPool>) -> Result<HttpResponse, ApiError> {
+    let mut conn = pool.get()?;
+    let record = web::block(move || handicap_operations::handicap_for_user(&mut conn, user.id)).await??;
+    Ok(HttpResponse::Ok().json(record))
+}
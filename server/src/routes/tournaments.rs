@@ -0,0 +1,136 @@
+use actix_web::{web, HttpResponse};
+
+use crate::auth::AuthenticatedUser;
+use crate::error::ApiError;
+use crate::tournament_operations::{
+    self, JoinTournamentInput, LeaderboardQuery, NewEntrantInput, NewGroupInput, NewTournamentInput,
+};
+use crate::DbPool;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/tournaments")
+            .route("", web::get().to(list_tournaments))
+            .route("", web::post().to(create_tournament))
+            .route("/join", web::post().to(join_tournament))
+            .route("/{tournament_id}", web::get().to(get_tournament))
+            .route("/{tournament_id}/entrants", web::post().to(add_entrant))
+            .route(
+                "/{tournament_id}/entrants/{entrant_id}/invite",
+                web::post().to(invite_entrant),
+            )
+            .route(
+                "/{tournament_id}/rounds/{round_number}/groups",
+                web::post().to(add_group),
+            )
+            .route("/{tournament_id}/groups/{group_id}/start", web::post().to(start_group))
+            .route("/{tournament_id}/leaderboard", web::get().to(get_leaderboard)),
+    );
+}
+
+async fn list_tournaments(user: AuthenticatedUser, pool: web::Data<DbPool>) -> Result<HttpResponse, ApiError> {
+    let mut conn = pool.get()?;
+    let tournaments = web::block(move || tournament_operations::list_tournaments(&mut conn, user.id)).await??;
+    Ok(HttpResponse::Ok().json(tournaments))
+}
+
+async fn create_tournament(
+    user: AuthenticatedUser,
+    pool: web::Data<DbPool>,
+    body: web::Json<NewTournamentInput>,
+) -> Result<HttpResponse, ApiError> {
+    let mut conn = pool.get()?;
+    let tournament =
+        web::block(move || tournament_operations::create_tournament(&mut conn, user.id, body.into_inner())).await??;
+    Ok(HttpResponse::Created().json(tournament))
+}
+
+async fn get_tournament(
+    user: AuthenticatedUser,
+    pool: web::Data<DbPool>,
+    tournament_id: web::Path<i32>,
+) -> Result<HttpResponse, ApiError> {
+    let mut conn = pool.get()?;
+    let tournament_id = tournament_id.into_inner();
+    let tournament = web::block(move || tournament_operations::tournament(&mut conn, tournament_id, user.id)).await??;
+    Ok(HttpResponse::Ok().json(tournament))
+}
+
+async fn add_entrant(
+    user: AuthenticatedUser,
+    pool: web::Data<DbPool>,
+    tournament_id: web::Path<i32>,
+    body: web::Json<NewEntrantInput>,
+) -> Result<HttpResponse, ApiError> {
+    let mut conn = pool.get()?;
+    let tournament_id = tournament_id.into_inner();
+    let entrant =
+        web::block(move || tournament_operations::add_entrant(&mut conn, tournament_id, user.id, body.into_inner()))
+            .await??;
+    Ok(HttpResponse::Created().json(entrant))
+}
+
+async fn invite_entrant(
+    user: AuthenticatedUser,
+    pool: web::Data<DbPool>,
+    path: web::Path<(i32, i32)>,
+) -> Result<HttpResponse, ApiError> {
+    let mut conn = pool.get()?;
+    let (tournament_id, entrant_id) = path.into_inner();
+    let invite =
+        web::block(move || tournament_operations::invite_entrant(&mut conn, tournament_id, entrant_id, user.id))
+            .await??;
+    Ok(HttpResponse::Created().json(invite))
+}
+
+async fn join_tournament(
+    user: AuthenticatedUser,
+    pool: web::Data<DbPool>,
+    body: web::Json<JoinTournamentInput>,
+) -> Result<HttpResponse, ApiError> {
+    let mut conn = pool.get()?;
+    let tournament =
+        web::block(move || tournament_operations::join_tournament(&mut conn, user.id, body.into_inner())).await??;
+    Ok(HttpResponse::Ok().json(tournament))
+}
+
+async fn add_group(
+    user: AuthenticatedUser,
+    pool: web::Data<DbPool>,
+    path: web::Path<(i32, i32)>,
+    body: web::Json<NewGroupInput>,
+) -> Result<HttpResponse, ApiError> {
+    let mut conn = pool.get()?;
+    let (tournament_id, round_number) = path.into_inner();
+    let group = web::block(move || {
+        tournament_operations::add_group(&mut conn, tournament_id, round_number, user.id, body.into_inner())
+    })
+    .await??;
+    Ok(HttpResponse::Created().json(group))
+}
+
+async fn start_group(
+    user: AuthenticatedUser,
+    pool: web::Data<DbPool>,
+    path: web::Path<(i32, i32)>,
+) -> Result<HttpResponse, ApiError> {
+    let mut conn = pool.get()?;
+    let (tournament_id, group_id) = path.into_inner();
+    let scorecard =
+        web::block(move || tournament_operations::start_group(&mut conn, tournament_id, group_id, user.id)).await??;
+    Ok(HttpResponse::Created().json(scorecard))
+}
+
+async fn get_leaderboard(
+    user: AuthenticatedUser,
+    pool: web::Data<DbPool>,
+    tournament_id: web::Path<i32>,
+    query: web::Query<LeaderboardQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let mut conn = pool.get()?;
+    let tournament_id = tournament_id.into_inner();
+    let leaderboard =
+        web::block(move || tournament_operations::leaderboard(&mut conn, tournament_id, user.id, query.into_inner()))
+            .await??;
+    Ok(HttpResponse::Ok().json(leaderboard))
+}
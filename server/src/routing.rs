@@ -0,0 +1,501 @@
+use serde::{Deserialize, Serialize};
+
+use crate::geometry::{
+    chords, distance_yards, from_local, green_centre, group_shapes, route_yards, to_local, LatLong, Lie, Shape,
+};
+use crate::models::{HoleData, HoleRoutePoint, HoleRouteRow};
+
+/// Spacing of the cross-sections taken through the fairway.
+const STATION_STEP_YARDS: f64 = 10.0;
+/// Fairway further than this either side of the tee-to-green line belongs to
+/// another hole.
+const MAX_OFFSET_YARDS: f64 = 120.0;
+/// Narrower crossings are the tips of fairway polygons, not the fairway.
+const MIN_FAIRWAY_WIDTH_YARDS: f64 = 10.0;
+/// Centerline points closer than this to the simplified line aren't turns.
+const SIMPLIFY_TOLERANCE_YARDS: f64 = 10.0;
+/// Turns gentler than this leave the hole straight.
+const DOGLEG_MIN_DEGREES: f64 = 10.0;
+/// Turns this close to the green are the approach, not a dogleg.
+const DOGLEG_GREEN_MARGIN_YARDS: f64 = 50.0;
+/// How far off the centerline a landing zone may start.
+const LANDING_SEARCH_YARDS: f64 = 20.0;
+/// Distances from the tee where the fairway width is measured.
+pub const LANDING_DISTANCES: [f64; 3] = [200.0, 250.0, 300.0];
+
+type Point = (f64, f64);
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Dogleg {
+    Straight,
+    Left,
+    Right,
+}
+
+impl Dogleg {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Dogleg::Straight => "straight",
+            Dogleg::Left => "left",
+            Dogleg::Right => "right",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Dogleg> {
+        match value {
+            "straight" => Some(Dogleg::Straight),
+            "left" => Some(Dogleg::Left),
+            "right" => Some(Dogleg::Right),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct RoutePoint {
+    pub lat: f64,
+    pub long: f64,
+    /// Whether the playing line bends here.
+    pub turn: bool,
+}
+
+impl RoutePoint {
+    pub fn position(&self) -> LatLong {
+        LatLong::new(self.lat, self.long)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct LandingZone {
+    /// Along the playing line from the back tee.
+    pub distance_yards: f64,
+    /// Fairway width across the line, if there is fairway there.
+    pub width_yards: Option<f64>,
+}
+
+/// The centerline of a hole from the back tee to the centre of the green.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct HoleRoute {
+    pub points: Vec<RoutePoint>,
+    /// Length of the playing line through the turn points.
+    pub yards: f64,
+    pub dogleg: Dogleg,
+    /// The sharpest turn on the playing line.
+    pub dogleg_degrees: f64,
+    pub landing_zones: Vec<LandingZone>,
+}
+
+impl HoleRoute {
+    pub fn from_rows(row: HoleRouteRow, points: Vec<HoleRoutePoint>) -> HoleRoute {
+        let widths = [row.landing_width_200, row.landing_width_250, row.landing_width_300];
+        HoleRoute {
+            points: points
+                .into_iter()
+                .map(|point| RoutePoint {
+                    lat: point.lat,
+                    long: point.long,
+                    turn: point.turn,
+                })
+                .collect(),
+            yards: row.yards,
+            dogleg: Dogleg::parse(&row.dogleg).unwrap_or(Dogleg::Straight),
+            dogleg_degrees: row.dogleg_degrees,
+            landing_zones: LANDING_DISTANCES
+                .iter()
+                .zip(widths)
+                .map(|(&distance_yards, width_yards)| LandingZone {
+                    distance_yards,
+                    width_yards,
+                })
+                .collect(),
+        }
+    }
+
+    pub fn to_rows(&self, hole_id: i32) -> (HoleRouteRow, Vec<HoleRoutePoint>) {
+        let width = |i: usize| self.landing_zones.get(i).and_then(|zone| zone.width_yards);
+        let row = HoleRouteRow {
+            hole_id,
+            yards: self.yards,
+            dogleg: self.dogleg.as_str().to_string(),
+            dogleg_degrees: self.dogleg_degrees,
+            landing_width_200: width(0),
+            landing_width_250: width(1),
+            landing_width_300: width(2),
+        };
+        let points = self
+            .points
+            .iter()
+            .enumerate()
+            .map(|(i, point)| HoleRoutePoint {
+                hole_id,
+                point_index: i as i32,
+                lat: point.lat,
+                long: point.long,
+                turn: point.turn,
+            })
+            .collect();
+        (row, points)
+    }
+
+    /// The tee, each turn point and the green.
+    pub fn playing_line(&self) -> Vec<LatLong> {
+        let last = self.points.len().saturating_sub(1);
+        self.points
+            .iter()
+            .enumerate()
+            .filter(|(i, point)| *i == 0 || *i == last || point.turn)
+            .map(|(_, point)| point.position())
+            .collect()
+    }
+
+    /// The point `yards` along the playing line from the back tee.
+    pub fn point_at(&self, yards: f64) -> Option<LatLong> {
+        let line = self.playing_line();
+        let origin = *line.first()?;
+        let local: Vec<Point> = line.iter().map(|p| to_local(origin, *p)).collect();
+        point_along(&local, yards).map(|(point, _)| from_local(origin, point))
+    }
+
+    /// The playing line from another tee, joining it where the tee is level
+    /// with it so turns behind a forward tee are skipped.
+    pub fn playing_line_from(&self, tee: LatLong) -> Vec<LatLong> {
+        let line = self.playing_line();
+        let Some(origin) = line.first().copied() else {
+            return vec![tee];
+        };
+        let local: Vec<Point> = line.iter().map(|p| to_local(origin, *p)).collect();
+        let tee_local = to_local(origin, tee);
+
+        let mut along = 0.0;
+        let mut best = (f64::MAX, 0.0);
+        for pair in local.windows(2) {
+            let (start, end) = (pair[0], pair[1]);
+            let length = distance(start, end);
+            let t = if length > 0.0 {
+                (dot(sub(tee_local, start), sub(end, start)) / (length * length)).clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+            let off = distance(tee_local, add(start, scale(sub(end, start), t)));
+            if off < best.0 {
+                best = (off, along + t * length);
+            }
+            along += length;
+        }
+
+        let mut result = vec![tee];
+        let mut along = 0.0;
+        for (i, point) in line.iter().enumerate().skip(1) {
+            along += distance(local[i - 1], local[i]);
+            if along > best.1 {
+                result.push(*point);
+            }
+        }
+        result
+    }
+}
+
+fn add(a: Point, b: Point) -> Point {
+    (a.0 + b.0, a.1 + b.1)
+}
+
+fn sub(a: Point, b: Point) -> Point {
+    (a.0 - b.0, a.1 - b.1)
+}
+
+fn scale(a: Point, k: f64) -> Point {
+    (a.0 * k, a.1 * k)
+}
+
+fn dot(a: Point, b: Point) -> f64 {
+    a.0 * b.0 + a.1 * b.1
+}
+
+fn cross(a: Point, b: Point) -> f64 {
+    a.0 * b.1 - a.1 * b.0
+}
+
+fn distance(a: Point, b: Point) -> f64 {
+    (a.0 - b.0).hypot(a.1 - b.1)
+}
+
+/// The stretch of fairway crossed by the line `origin + t * direction` that
+/// is nearest `t = near`, as `(t0, t1)`.
+fn fairway_crossing(fairways: &[Vec<Point>], origin: Point, direction: Point, near: f64) -> Option<(f64, f64)> {
+    let gap = |(t0, t1): &(f64, f64)| (t0 - near).max(near - t1).max(0.0);
+    fairways
+        .iter()
+        .flat_map(|ring| chords(ring, origin, direction))
+        .filter(|(t0, t1)| t1 - t0 >= MIN_FAIRWAY_WIDTH_YARDS)
+        .filter(|(t0, t1)| t1.min(MAX_OFFSET_YARDS) > t0.max(-MAX_OFFSET_YARDS))
+        .min_by(|a, b| gap(a).total_cmp(&gap(b)))
+}
+
+/// Marks the points Douglas-Peucker keeps within `tolerance`.
+fn simplify(points: &[Point], tolerance: f64) -> Vec<bool> {
+    let mut keep = vec![false; points.len()];
+    if points.is_empty() {
+        return keep;
+    }
+    keep[0] = true;
+    keep[points.len() - 1] = true;
+
+    let mut stack = vec![(0, points.len() - 1)];
+    while let Some((first, last)) = stack.pop() {
+        let (a, b) = (points[first], points[last]);
+        let length = distance(a, b);
+        let farthest = (first + 1..last)
+            .map(|i| {
+                let off = if length > 0.0 {
+                    cross(sub(b, a), sub(points[i], a)).abs() / length
+                } else {
+                    distance(a, points[i])
+                };
+                (i, off)
+            })
+            .max_by(|x, y| x.1.total_cmp(&y.1));
+
+        if let Some((i, _)) = farthest.filter(|(_, off)| *off > tolerance) {
+            keep[i] = true;
+            stack.push((first, i));
+            stack.push((i, last));
+        }
+    }
+    keep
+}
+
+/// Signed turn in degrees at `at`, positive to the left.
+fn turn_degrees(before: Point, at: Point, after: Point) -> f64 {
+    let (incoming, outgoing) = (sub(at, before), sub(after, at));
+    cross(incoming, outgoing).atan2(dot(incoming, outgoing)).to_degrees()
+}
+
+/// The point `yards` along a polyline and the direction of travel there.
+fn point_along(line: &[Point], yards: f64) -> Option<(Point, Point)> {
+    let mut remaining = yards;
+    for pair in line.windows(2) {
+        let length = distance(pair[0], pair[1]);
+        if length <= 0.0 {
+            continue;
+        }
+        let unit = scale(sub(pair[1], pair[0]), 1.0 / length);
+        if remaining <= length {
+            return Some((add(pair[0], scale(unit, remaining)), unit));
+        }
+        remaining -= length;
+    }
+    None
+}
+
+/// Routes a stored hole from its polygons, tee markers and flag.
+pub fn route_for(hole_data: &HoleData) -> Option<HoleRoute> {
+    route_hole(&group_shapes(&hole_data.polygons), &hole_data.tees(), hole_data.hole.flag())
+}
+
+/// Derives a hole's centerline from its fairways, running from the back tee
+/// to the centre of the green. Holes without fairway, like most par 3s, get
+/// a straight line.
+pub fn route_hole(shapes: &[Shape], tees: &[LatLong], flag: Option<LatLong>) -> Option<HoleRoute> {
+    let end = green_centre(shapes, flag).or(flag)?;
+    let tee = tees
+        .iter()
+        .copied()
+        .max_by(|a, b| distance_yards(*a, end).total_cmp(&distance_yards(*b, end)))?;
+
+    let axis = to_local(tee, end);
+    let length = distance((0.0, 0.0), axis);
+    if length < STATION_STEP_YARDS {
+        return None;
+    }
+    let unit = scale(axis, 1.0 / length);
+    let normal = (-unit.1, unit.0);
+
+    let fairways: Vec<Vec<Point>> = shapes
+        .iter()
+        .filter(|shape| Lie::from_surface_type(&shape.surface_type) == Lie::Fairway)
+        .map(|shape| shape.points.iter().map(|p| to_local(tee, *p)).collect())
+        .collect();
+
+    let mut centerline = vec![(0.0, 0.0)];
+    let mut offset = 0.0;
+    let mut station = STATION_STEP_YARDS;
+    while station < length - STATION_STEP_YARDS {
+        let origin = scale(unit, station);
+        if let Some((t0, t1)) = fairway_crossing(&fairways, origin, normal, offset) {
+            offset = ((t0 + t1) / 2.0).clamp(-MAX_OFFSET_YARDS, MAX_OFFSET_YARDS);
+            centerline.push(add(origin, scale(normal, offset)));
+        }
+        station += STATION_STEP_YARDS;
+    }
+    centerline.push(axis);
+
+    let keep = simplify(&centerline, SIMPLIFY_TOLERANCE_YARDS);
+    let last = centerline.len() - 1;
+    let points: Vec<RoutePoint> = centerline
+        .iter()
+        .enumerate()
+        .map(|(i, p)| {
+            let position = from_local(tee, *p);
+            RoutePoint {
+                lat: position.lat,
+                long: position.long,
+                turn: keep[i] && i != 0 && i != last,
+            }
+        })
+        .collect();
+
+    let line: Vec<Point> = centerline
+        .iter()
+        .zip(&keep)
+        .filter(|(_, keep)| **keep)
+        .map(|(p, _)| *p)
+        .collect();
+    let sharpest = line
+        .windows(3)
+        .filter(|w| distance(w[1], axis) > DOGLEG_GREEN_MARGIN_YARDS)
+        .map(|w| turn_degrees(w[0], w[1], w[2]))
+        .max_by(|a, b| a.abs().total_cmp(&b.abs()))
+        .unwrap_or(0.0);
+    let dogleg = match sharpest {
+        turn if turn.abs() < DOGLEG_MIN_DEGREES => Dogleg::Straight,
+        turn if turn > 0.0 => Dogleg::Left,
+        _ => Dogleg::Right,
+    };
+
+    let landing_zones = LANDING_DISTANCES
+        .iter()
+        .map(|&yards| LandingZone {
+            distance_yards: yards,
+            width_yards: point_along(&line, yards).and_then(|(point, direction)| {
+                fairway_crossing(&fairways, point, (-direction.1, direction.0), 0.0)
+                    .filter(|(t0, t1)| *t0 <= LANDING_SEARCH_YARDS && *t1 >= -LANDING_SEARCH_YARDS)
+                    .map(|(t0, t1)| t1 - t0)
+            }),
+        })
+        .collect();
+
+    let route = HoleRoute {
+        points,
+        yards: 0.0,
+        dogleg,
+        dogleg_degrees: sharpest.abs(),
+        landing_zones,
+    };
+    Some(HoleRoute {
+        yards: route_yards(&route.playing_line()),
+        ..route
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEE: LatLong = LatLong { lat: 41.0, long: -93.0 };
+
+    /// A rectangle in local yards around the tee.
+    fn shape(surface_type: &str, (west, south): (f64, f64), (east, north): (f64, f64)) -> Shape {
+        Shape {
+            surface_type: surface_type.to_string(),
+            points: [(west, south), (east, south), (east, north), (west, north)]
+                .into_iter()
+                .map(|corner| from_local(TEE, corner))
+                .collect(),
+        }
+    }
+
+    /// A 400-yard hole straight north with a 40-yard fairway.
+    fn straight_hole() -> Vec<Shape> {
+        vec![
+            shape("fairway", (-20.0, 20.0), (20.0, 370.0)),
+            shape("green", (-15.0, 385.0), (15.0, 415.0)),
+        ]
+    }
+
+    /// North for 250 yards, then a sharp turn west to the green.
+    fn dogleg_left_hole() -> Vec<Shape> {
+        vec![
+            shape("fairway", (-20.0, 20.0), (20.0, 270.0)),
+            shape("fairway", (-220.0, 230.0), (20.0, 270.0)),
+            shape("green", (-260.0, 235.0), (-230.0, 265.0)),
+        ]
+    }
+
+    #[test]
+    fn straight_hole_runs_tee_to_green_centre() {
+        let route = route_hole(&straight_hole(), &[TEE], None).unwrap();
+        assert_eq!(route.dogleg, Dogleg::Straight);
+        assert!((route.yards - 400.0).abs() < 1.0, "{}", route.yards);
+        assert!(route.points.iter().all(|point| !point.turn));
+        for zone in &route.landing_zones {
+            let width = zone.width_yards.unwrap();
+            assert!((width - 40.0).abs() < 1.0, "{} wide at {}", width, zone.distance_yards);
+        }
+    }
+
+    #[test]
+    fn fairway_turning_west_is_a_dogleg_left() {
+        let route = route_hole(&dogleg_left_hole(), &[TEE], None).unwrap();
+        assert_eq!(route.dogleg, Dogleg::Left);
+        assert!(route.dogleg_degrees >= DOGLEG_MIN_DEGREES);
+        assert!(route.points.iter().any(|point| point.turn));
+        // Round the corner, not straight across it.
+        let straight = distance_yards(TEE, route.points.last().unwrap().position());
+        assert!(route.yards > straight + 20.0, "{} vs {}", route.yards, straight);
+    }
+
+    #[test]
+    fn routes_from_the_back_tee_and_needs_a_tee_and_a_green() {
+        let back = from_local(TEE, (0.0, -20.0));
+        let route = route_hole(&straight_hole(), &[TEE, back], None).unwrap();
+        assert!(distance_yards(route.points[0].position(), back) < 0.5);
+
+        assert!(route_hole(&straight_hole(), &[], None).is_none());
+        assert!(route_hole(&[], &[TEE], None).is_none());
+        // Too short to have a line.
+        assert!(route_hole(&[], &[TEE], Some(from_local(TEE, (0.0, 5.0)))).is_none());
+    }
+
+    #[test]
+    fn holes_without_fairway_get_a_straight_line_to_the_flag() {
+        let flag = from_local(TEE, (0.0, 160.0));
+        let route = route_hole(&[], &[TEE], Some(flag)).unwrap();
+        assert_eq!(route.dogleg, Dogleg::Straight);
+        assert_eq!(route.playing_line().len(), 2);
+        assert!((route.yards - 160.0).abs() < 1.0);
+        assert!(route.landing_zones.iter().all(|zone| zone.width_yards.is_none()));
+    }
+
+    #[test]
+    fn forward_tee_joins_the_line_past_turns_behind_it() {
+        let route = route_hole(&dogleg_left_hole(), &[TEE], None).unwrap();
+        let turns = route.playing_line().len() - 2;
+
+        // Level with the back tee, so every turn is still ahead.
+        let line = route.playing_line_from(from_local(TEE, (5.0, 0.0)));
+        assert_eq!(line.len(), turns + 2);
+
+        // Round the corner, so the turns on the first leg are dropped.
+        let forward = from_local(TEE, (-150.0, 250.0));
+        let line = route.playing_line_from(forward);
+        assert!(line.len() < turns + 2, "{} points", line.len());
+        assert_eq!(line[0], forward);
+        assert_eq!(line.last(), route.playing_line().last());
+    }
+
+    #[test]
+    fn rows_round_trip() {
+        let route = route_hole(&dogleg_left_hole(), &[TEE], None).unwrap();
+        let (row, points) = route.to_rows(7);
+        assert_eq!(row.hole_id, 7);
+        assert!(points.iter().enumerate().all(|(i, point)| point.point_index == i as i32));
+
+        let loaded = HoleRoute::from_rows(row, points);
+        assert_eq!(loaded.dogleg, route.dogleg);
+        assert_eq!(loaded.yards, route.yards);
+        assert_eq!(loaded.playing_line(), route.playing_line());
+        let widths = |route: &HoleRoute| route.landing_zones.iter().map(|zone| zone.width_yards).collect::<Vec<_>>();
+        assert_eq!(widths(&loaded), widths(&route));
+    }
+}
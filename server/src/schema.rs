@@ -1,5 +1,23 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    clubs (id) {
+        id -> Int4,
+        user_id -> Int4,
+        #[max_length = 20]
+        club_type -> Varchar,
+        #[max_length = 40]
+        name -> Varchar,
+        loft -> Nullable<Float8>,
+        brand -> Nullable<Varchar>,
+        model -> Nullable<Varchar>,
+        carry_yards -> Nullable<Float8>,
+        total_yards -> Nullable<Float8>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     courses (id) {
         id -> Int4,
@@ -15,6 +33,61 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    hole_elevations (hole_id) {
+        hole_id -> Int4,
+        #[max_length = 255]
+        source -> Varchar,
+        origin_lat -> Float8,
+        origin_long -> Float8,
+        spacing_yards -> Float8,
+        grid_columns -> Int4,
+        grid_rows -> Int4,
+        elevations_ft -> Array<Nullable<Float8>>,
+        sampled_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    hole_route_points (id) {
+        id -> Int4,
+        hole_id -> Int4,
+        point_index -> Int4,
+        lat -> Float8,
+        long -> Float8,
+        turn -> Bool,
+    }
+}
+
+diesel::table! {
+    hole_routes (hole_id) {
+        hole_id -> Int4,
+        yards -> Float8,
+        #[max_length = 10]
+        dogleg -> Varchar,
+        dogleg_degrees -> Float8,
+        landing_width_200 -> Nullable<Float8>,
+        landing_width_250 -> Nullable<Float8>,
+        landing_width_300 -> Nullable<Float8>,
+        computed_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    hole_scores (id) {
+        id -> Int4,
+        round_id -> Int4,
+        player_id -> Int4,
+        hole_id -> Int4,
+        strokes -> Int4,
+        putts -> Nullable<Int4>,
+        penalties -> Int4,
+        fairway_hit -> Nullable<Bool>,
+        green_in_regulation -> Nullable<Bool>,
+        updated_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     holes (id) {
         id -> Int4,
@@ -30,6 +103,35 @@ diesel::table! {
         dimensions_height -> Nullable<Int4>,
         flag_lat -> Nullable<Float8>,
         flag_long -> Nullable<Float8>,
+        par -> Nullable<Int4>,
+        flag_elevation_ft -> Nullable<Float8>,
+        #[max_length = 40]
+        name -> Nullable<Varchar>,
+        stroke_index -> Nullable<Int4>,
+        stroke_index_women -> Nullable<Int4>,
+    }
+}
+
+diesel::table! {
+    pin_positions (pin_sheet_id, hole_id) {
+        pin_sheet_id -> Int4,
+        hole_id -> Int4,
+        lat -> Float8,
+        long -> Float8,
+        front_paces -> Nullable<Int4>,
+        side_paces -> Nullable<Int4>,
+        #[max_length = 5]
+        side -> Nullable<Varchar>,
+    }
+}
+
+diesel::table! {
+    pin_sheets (id) {
+        id -> Int4,
+        course_id -> Int4,
+        played_on -> Date,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
     }
 }
 
@@ -41,6 +143,222 @@ diesel::table! {
         surface_type -> Nullable<Varchar>,
         lat -> Nullable<Float8>,
         long -> Nullable<Float8>,
+        shape_index -> Nullable<Int4>,
+        elevation_ft -> Nullable<Float8>,
+    }
+}
+
+diesel::table! {
+    refresh_tokens (id) {
+        id -> Int4,
+        user_id -> Int4,
+        #[max_length = 64]
+        token_hash -> Varchar,
+        expires_at -> Timestamp,
+        revoked_at -> Nullable<Timestamp>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    round_events (id) {
+        id -> Int4,
+        round_id -> Int4,
+        sequence -> Int4,
+        #[max_length = 20]
+        kind -> Varchar,
+        payload -> Jsonb,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    round_games (id) {
+        id -> Int4,
+        round_id -> Int4,
+        #[max_length = 20]
+        format -> Varchar,
+        stake -> Int4,
+        allowance_percent -> Float8,
+        carry_overs -> Bool,
+        press_after -> Nullable<Int4>,
+        side_a -> Array<Int4>,
+        side_b -> Array<Int4>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    round_players (id) {
+        id -> Int4,
+        round_id -> Int4,
+        user_id -> Nullable<Int4>,
+        name -> Varchar,
+        #[max_length = 64]
+        invite_hash -> Nullable<Varchar>,
+    }
+}
+
+diesel::table! {
+    rounds (id) {
+        id -> Int4,
+        user_id -> Int4,
+        course_id -> Int4,
+        #[max_length = 20]
+        tee_set -> Nullable<Varchar>,
+        played_on -> Date,
+        #[max_length = 20]
+        status -> Varchar,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+        finished_at -> Nullable<Timestamp>,
+        tee_set_id -> Nullable<Int4>,
+    }
+}
+
+diesel::table! {
+    shots (id) {
+        id -> Int4,
+        round_id -> Int4,
+        player_id -> Int4,
+        hole_id -> Int4,
+        shot_number -> Int4,
+        start_lat -> Float8,
+        start_long -> Float8,
+        end_lat -> Nullable<Float8>,
+        end_long -> Nullable<Float8>,
+        club_id -> Nullable<Int4>,
+        #[max_length = 40]
+        club -> Nullable<Varchar>,
+        #[max_length = 20]
+        lie -> Nullable<Varchar>,
+        penalty_strokes -> Int4,
+        distance_yards -> Nullable<Float8>,
+        remaining_yards -> Nullable<Float8>,
+        #[max_length = 20]
+        start_lie -> Nullable<Varchar>,
+        #[max_length = 20]
+        end_lie -> Nullable<Varchar>,
+        created_at -> Timestamp,
+        carry_yards -> Nullable<Float8>,
+        target_lat -> Nullable<Float8>,
+        target_long -> Nullable<Float8>,
+    }
+}
+
+diesel::table! {
+    tee_set_hole_overrides (tee_set_id, hole_id) {
+        tee_set_id -> Int4,
+        hole_id -> Int4,
+        par -> Nullable<Int4>,
+        stroke_index -> Nullable<Int4>,
+        #[max_length = 40]
+        name -> Nullable<Varchar>,
+    }
+}
+
+diesel::table! {
+    tee_set_holes (tee_set_id, hole_id) {
+        tee_set_id -> Int4,
+        hole_id -> Int4,
+        tee_lat -> Float8,
+        tee_long -> Float8,
+        yards -> Int4,
+    }
+}
+
+diesel::table! {
+    tee_sets (id) {
+        id -> Int4,
+        course_id -> Int4,
+        #[max_length = 20]
+        name -> Varchar,
+        course_rating -> Float8,
+        slope_rating -> Int4,
+        par -> Int4,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+        #[max_length = 20]
+        colour -> Nullable<Varchar>,
+        #[max_length = 10]
+        gender -> Varchar,
+    }
+}
+
+diesel::table! {
+    tournament_entrants (id) {
+        id -> Int4,
+        tournament_id -> Int4,
+        user_id -> Nullable<Int4>,
+        #[max_length = 100]
+        name -> Varchar,
+        handicap_index -> Nullable<Float8>,
+        flight_id -> Nullable<Int4>,
+        #[max_length = 64]
+        invite_hash -> Nullable<Varchar>,
+    }
+}
+
+diesel::table! {
+    tournament_flights (id) {
+        id -> Int4,
+        tournament_id -> Int4,
+        #[max_length = 40]
+        name -> Varchar,
+        max_index -> Nullable<Float8>,
+    }
+}
+
+diesel::table! {
+    tournament_group_entrants (group_id, entrant_id) {
+        group_id -> Int4,
+        entrant_id -> Int4,
+        round_player_id -> Nullable<Int4>,
+    }
+}
+
+diesel::table! {
+    tournament_groups (id) {
+        id -> Int4,
+        tournament_round_id -> Int4,
+        tee_time -> Timestamp,
+        starting_hole -> Int4,
+        round_id -> Nullable<Int4>,
+    }
+}
+
+diesel::table! {
+    tournament_rounds (id) {
+        id -> Int4,
+        tournament_id -> Int4,
+        round_number -> Int4,
+        course_id -> Int4,
+        tee_set_id -> Nullable<Int4>,
+        played_on -> Date,
+    }
+}
+
+diesel::table! {
+    tournaments (id) {
+        id -> Int4,
+        #[max_length = 100]
+        name -> Varchar,
+        organiser_id -> Int4,
+        allowance_percent -> Float8,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    users (id) {
+        id -> Int4,
+        email -> Varchar,
+        password_hash -> Varchar,
+        display_name -> Nullable<Varchar>,
+        #[max_length = 20]
+        role -> Varchar,
+        created_at -> Timestamp,
     }
 }
 
@@ -52,16 +370,69 @@ diesel::table! {
         vector_type -> Nullable<Varchar>,
         lat -> Nullable<Float8>,
         long -> Nullable<Float8>,
+        elevation_ft -> Nullable<Float8>,
     }
 }
 
+diesel::joinable!(clubs -> users (user_id));
 diesel::joinable!(hole_course_associations -> courses (course_id));
 diesel::joinable!(hole_course_associations -> holes (hole_id));
+diesel::joinable!(hole_route_points -> hole_routes (hole_id));
+diesel::joinable!(hole_scores -> round_players (player_id));
+diesel::joinable!(hole_scores -> rounds (round_id));
+diesel::joinable!(pin_positions -> pin_sheets (pin_sheet_id));
+diesel::joinable!(refresh_tokens -> users (user_id));
+diesel::joinable!(round_events -> rounds (round_id));
+diesel::joinable!(round_games -> rounds (round_id));
+diesel::joinable!(round_players -> rounds (round_id));
+diesel::joinable!(round_players -> users (user_id));
+diesel::joinable!(rounds -> tee_sets (tee_set_id));
+diesel::joinable!(rounds -> users (user_id));
+diesel::joinable!(shots -> clubs (club_id));
+diesel::joinable!(shots -> round_players (player_id));
+diesel::joinable!(shots -> rounds (round_id));
+diesel::joinable!(tee_set_hole_overrides -> tee_sets (tee_set_id));
+diesel::joinable!(tee_set_holes -> tee_sets (tee_set_id));
+diesel::joinable!(tournament_entrants -> tournament_flights (flight_id));
+diesel::joinable!(tournament_entrants -> tournaments (tournament_id));
+diesel::joinable!(tournament_entrants -> users (user_id));
+diesel::joinable!(tournament_flights -> tournaments (tournament_id));
+diesel::joinable!(tournament_group_entrants -> round_players (round_player_id));
+diesel::joinable!(tournament_group_entrants -> tournament_entrants (entrant_id));
+diesel::joinable!(tournament_group_entrants -> tournament_groups (group_id));
+diesel::joinable!(tournament_groups -> rounds (round_id));
+diesel::joinable!(tournament_groups -> tournament_rounds (tournament_round_id));
+diesel::joinable!(tournament_rounds -> tee_sets (tee_set_id));
+diesel::joinable!(tournament_rounds -> tournaments (tournament_id));
+diesel::joinable!(tournaments -> users (organiser_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
+    clubs,
     courses,
     hole_course_associations,
+    hole_elevations,
+    hole_route_points,
+    hole_routes,
+    hole_scores,
     holes,
+    pin_positions,
+    pin_sheets,
     polygons,
+    refresh_tokens,
+    round_events,
+    round_games,
+    round_players,
+    rounds,
+    shots,
+    tee_set_hole_overrides,
+    tee_set_holes,
+    tee_sets,
+    tournament_entrants,
+    tournament_flights,
+    tournament_group_entrants,
+    tournament_groups,
+    tournament_rounds,
+    tournaments,
+    users,
     vectors,
 );
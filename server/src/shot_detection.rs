@@ -0,0 +1,387 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::geometry::{distance_yards, from_local, lie_at, to_local, LatLong, Lie};
+use crate::shot_operations::HoleContext;
+
+/// A point further than this from where a stop began ends the stop.
+const STOP_RADIUS_YARDS: f64 = 8.0;
+/// Standing still for less time than this is walking, not a shot.
+const MIN_STOP_SECS: i64 = 15;
+/// Consecutive stops closer than this are the same spot, e.g. stepping
+/// around the ball or walking back to the cart.
+const MERGE_RADIUS_YARDS: f64 = 12.0;
+/// Fixes less accurate than this are dropped before clustering.
+const MAX_ACCURACY_YARDS: f64 = 30.0;
+/// How far from a tee marker the first stop may be and still count as the tee.
+const TEE_SNAP_YARDS: f64 = 40.0;
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub struct TracePoint {
+    pub lat: f64,
+    pub long: f64,
+    pub time: DateTime<Utc>,
+    /// Horizontal accuracy in yards, when the phone reports it.
+    pub accuracy: Option<f64>,
+}
+
+impl TracePoint {
+    fn position(&self) -> LatLong {
+        LatLong::new(self.lat, self.long)
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Trace {
+    pub points: Vec<TracePoint>,
+}
+
+/// Somewhere the player stood still long enough to play a shot.
+#[derive(Serialize, Debug, Clone)]
+pub struct Stop {
+    pub position: LatLong,
+    pub arrived_at: DateTime<Utc>,
+    pub left_at: DateTime<Utc>,
+    pub points: usize,
+    pub lie: Lie,
+}
+
+/// A shot inferred from the trace, in the same shape the shots endpoint
+/// accepts so the player can confirm it as is.
+#[derive(Serialize, Debug)]
+pub struct ProposedShot {
+    pub shot_number: i32,
+    pub start_lat: f64,
+    pub start_long: f64,
+    pub end_lat: Option<f64>,
+    pub end_long: Option<f64>,
+    pub start_lie: Lie,
+    pub end_lie: Option<Lie>,
+    pub distance_yards: Option<f64>,
+    pub remaining_yards: Option<f64>,
+    /// How far the player walked along the trace to the next stop.
+    pub walk_yards: Option<f64>,
+    pub walk_secs: Option<i64>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct DetectedShots {
+    pub hole_id: i32,
+    pub points_used: usize,
+    pub stops: Vec<Stop>,
+    pub shots: Vec<ProposedShot>,
+    /// Putts can't be told apart from walking around the green, so detection
+    /// stops at the first stop on the green and the player adds them.
+    pub reached_green: bool,
+}
+
+/// Reads track points out of a GPX document. Points without a timestamp are
+/// skipped since they can't be placed in the trace.
+pub fn parse_gpx(document: &str) -> Result<Vec<TracePoint>, String> {
+    let doc = roxmltree::Document::parse(document).map_err(|e| format!("invalid GPX: {}", e))?;
+
+    let mut points = Vec::new();
+    for node in doc.descendants().filter(|n| n.has_tag_name("trkpt") || n.has_tag_name("wpt")) {
+        let coordinate = |name: &str| -> Result<f64, String> {
+            node.attribute(name)
+                .ok_or_else(|| format!("track point is missing `{}`", name))?
+                .parse()
+                .map_err(|_| format!("track point has an invalid `{}`", name))
+        };
+        let Some(time) = node
+            .children()
+            .find(|c| c.has_tag_name("time"))
+            .and_then(|c| c.text())
+        else {
+            continue;
+        };
+        let time = DateTime::parse_from_rfc3339(time.trim())
+            .map_err(|_| format!("invalid track point time `{}`", time))?
+            .with_timezone(&Utc);
+
+        points.push(TracePoint {
+            lat: coordinate("lat")?,
+            long: coordinate("lon")?,
+            time,
+            accuracy: None,
+        });
+    }
+    Ok(points)
+}
+
+/// Splits a trace into stops: runs of points that stay within
+/// `STOP_RADIUS_YARDS` of where they started for at least `MIN_STOP_SECS`.
+pub fn find_stops(context: &HoleContext, points: &[TracePoint]) -> Vec<Stop> {
+    let mut stops: Vec<Stop> = Vec::new();
+    let mut start = 0;
+
+    while start < points.len() {
+        let anchor = points[start].position();
+        let mut end = start + 1;
+        while end < points.len() && distance_yards(anchor, points[end].position()) <= STOP_RADIUS_YARDS {
+            end += 1;
+        }
+
+        let run = &points[start..end];
+        let (first, last) = (run[0], run[run.len() - 1]);
+        if (last.time - first.time).num_seconds() >= MIN_STOP_SECS {
+            let position = centroid(run);
+            match stops.last_mut() {
+                Some(previous) if distance_yards(previous.position, position) <= MERGE_RADIUS_YARDS => {
+                    previous.left_at = last.time;
+                    previous.points += run.len();
+                }
+                _ => stops.push(Stop {
+                    position,
+                    arrived_at: first.time,
+                    left_at: last.time,
+                    points: run.len(),
+                    lie: lie_at(&context.shapes, position),
+                }),
+            }
+            start = end;
+        } else {
+            start += 1;
+        }
+    }
+
+    stops
+}
+
+fn centroid(points: &[TracePoint]) -> LatLong {
+    let origin = points[0].position();
+    let (sum_x, sum_y) = points
+        .iter()
+        .map(|p| to_local(origin, p.position()))
+        .fold((0.0, 0.0), |(sx, sy), (x, y)| (sx + x, sy + y));
+    let n = points.len() as f64;
+    from_local(origin, (sum_x / n, sum_y / n))
+}
+
+/// Length of the trace walked between two times.
+fn walked_yards(points: &[TracePoint], from: DateTime<Utc>, to: DateTime<Utc>) -> f64 {
+    points
+        .windows(2)
+        .filter(|pair| pair[0].time >= from && pair[1].time <= to)
+        .map(|pair| distance_yards(pair[0].position(), pair[1].position()))
+        .sum()
+}
+
+/// Turns a hole's GPS trace into a proposed shot list. Detection starts at
+/// the stop nearest a tee marker and ends at the first stop on the green.
+pub fn detect_shots(context: &HoleContext, mut points: Vec<TracePoint>) -> DetectedShots {
+    points.retain(|p| p.position().is_valid() && p.accuracy.is_none_or(|a| a <= MAX_ACCURACY_YARDS));
+    points.sort_by_key(|p| p.time);
+
+    let stops = find_stops(context, &points);
+
+    let from_tee = stops
+        .iter()
+        .enumerate()
+        .filter_map(|(i, stop)| {
+            context
+                .tees
+                .iter()
+                .map(|tee| distance_yards(*tee, stop.position))
+                .min_by(f64::total_cmp)
+                .filter(|d| *d <= TEE_SNAP_YARDS)
+                .map(|d| (i, d))
+        })
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map_or(0, |(i, _)| i);
+
+    let mut played: Vec<&Stop> = Vec::new();
+    let mut reached_green = false;
+    for stop in &stops[from_tee.min(stops.len())..] {
+        played.push(stop);
+        if stop.lie == Lie::Green && played.len() > 1 {
+            reached_green = true;
+            break;
+        }
+    }
+
+    let flag = context.flag();
+    let mut shots = Vec::new();
+    for (i, stop) in played.iter().enumerate() {
+        if reached_green && i == played.len() - 1 {
+            break;
+        }
+        let next = played.get(i + 1);
+        let end = next.map(|n| n.position);
+        shots.push(ProposedShot {
+            shot_number: i as i32 + 1,
+            start_lat: stop.position.lat,
+            start_long: stop.position.long,
+            end_lat: end.map(|e| e.lat),
+            end_long: end.map(|e| e.long),
+            start_lie: if i == 0 { Lie::Tee } else { stop.lie },
+            end_lie: next.map(|n| n.lie),
+            distance_yards: end.map(|e| distance_yards(stop.position, e)),
+            remaining_yards: match (end, flag) {
+                (Some(end), Some(flag)) => Some(distance_yards(end, flag)),
+                _ => None,
+            },
+            walk_yards: next.map(|n| walked_yards(&points, stop.left_at, n.arrived_at)),
+            walk_secs: next.map(|n| (n.arrived_at - stop.left_at).num_seconds()),
+        });
+    }
+
+    DetectedShots {
+        hole_id: context.hole.hole_id,
+        points_used: points.len(),
+        stops,
+        shots,
+        reached_green,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+    use crate::geometry::Shape;
+    use crate::models::Hole;
+
+    const TEE: LatLong = LatLong { lat: 41.0, long: -93.0 };
+
+    fn shape(surface_type: &str, (west, south): (f64, f64), (east, north): (f64, f64)) -> Shape {
+        Shape {
+            surface_type: surface_type.to_string(),
+            points: [(west, south), (east, south), (east, north), (west, north)]
+                .into_iter()
+                .map(|corner| from_local(TEE, corner))
+                .collect(),
+        }
+    }
+
+    /// A 400-yard hole straight north, flag in the middle of the green.
+    fn context() -> HoleContext {
+        let flag = from_local(TEE, (0.0, 400.0));
+        HoleContext {
+            hole: Hole {
+                id: 1,
+                hole_id: 1,
+                number: Some(1),
+                course_id: None,
+                rotation: None,
+                range_x_min: None,
+                range_x_max: None,
+                range_y_min: None,
+                range_y_max: None,
+                dimensions_width: None,
+                dimensions_height: None,
+                flag_lat: Some(flag.lat),
+                flag_long: Some(flag.long),
+                par: Some(4),
+                flag_elevation_ft: None,
+                name: None,
+                stroke_index: None,
+                stroke_index_women: None,
+            },
+            shapes: vec![
+                shape("tee box", (-5.0, -5.0), (5.0, 5.0)),
+                shape("fairway", (-20.0, 20.0), (20.0, 370.0)),
+                shape("green", (-15.0, 385.0), (15.0, 415.0)),
+            ],
+            tees: vec![TEE],
+            pin: None,
+            elevation: None,
+        }
+    }
+
+    /// Stands at each spot, in local yards, for the given seconds, walking at
+    /// least 10 yards between fixes from one to the next. There's a fix every
+    /// 5 seconds.
+    fn trace(stops: &[((f64, f64), i64)]) -> Vec<TracePoint> {
+        let mut spots: Vec<(f64, f64)> = Vec::new();
+        for &((x, y), secs) in stops {
+            if let Some(&(from_x, from_y)) = spots.last() {
+                let steps = ((x - from_x).hypot(y - from_y) / 10.0).floor() as usize;
+                for step in 1..steps {
+                    let k = step as f64 / steps as f64;
+                    spots.push((from_x + (x - from_x) * k, from_y + (y - from_y) * k));
+                }
+            }
+            spots.extend(std::iter::repeat_n((x, y), secs as usize / 5 + 1));
+        }
+
+        let start = DateTime::parse_from_rfc3339("2026-10-19T08:00:00Z").unwrap().with_timezone(&Utc);
+        spots
+            .into_iter()
+            .enumerate()
+            .map(|(i, spot)| {
+                let position = from_local(TEE, spot);
+                TracePoint {
+                    lat: position.lat,
+                    long: position.long,
+                    time: start + Duration::seconds(i as i64 * 5),
+                    accuracy: None,
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn nearby_stops_merge_and_short_ones_are_walking() {
+        let points = trace(&[((0.0, 100.0), 30), ((0.0, 110.0), 30), ((0.0, 200.0), 10), ((0.0, 300.0), 20)]);
+        let stops = find_stops(&context(), &points);
+        assert_eq!(stops.len(), 2);
+        assert_eq!(stops[0].points, 14);
+        assert_eq!((stops[0].left_at - stops[0].arrived_at).num_seconds(), 65);
+        assert_eq!(stops[0].lie, Lie::Fairway);
+        assert!(distance_yards(stops[1].position, from_local(TEE, (0.0, 300.0))) < 0.5);
+    }
+
+    #[test]
+    fn starts_from_the_stop_at_the_tee() {
+        let points = trace(&[((60.0, -60.0), 60), ((8.0, 2.0), 30), ((0.0, 250.0), 30)]);
+        let detected = detect_shots(&context(), points);
+        assert_eq!(detected.stops.len(), 3);
+        assert_eq!(detected.shots.len(), 2);
+        let drive = &detected.shots[0];
+        assert_eq!(drive.start_lie, Lie::Tee);
+        assert!((drive.distance_yards.unwrap() - 248.1).abs() < 0.5);
+        assert_eq!(drive.end_lie, Some(Lie::Fairway));
+        assert!((drive.remaining_yards.unwrap() - 150.0).abs() < 0.5);
+        assert!(!detected.reached_green);
+    }
+
+    #[test]
+    fn stops_at_the_first_stop_on_the_green() {
+        let points = trace(&[
+            ((0.0, 0.0), 30),
+            ((0.0, 250.0), 30),
+            ((0.0, 390.0), 30),
+            ((10.0, 410.0), 30),
+        ]);
+        let detected = detect_shots(&context(), points);
+        assert!(detected.reached_green);
+        assert_eq!(detected.stops.len(), 4);
+        assert_eq!(detected.shots.len(), 2);
+        let approach = &detected.shots[1];
+        assert_eq!(approach.start_lie, Lie::Fairway);
+        assert_eq!(approach.end_lie, Some(Lie::Green));
+        assert!((approach.remaining_yards.unwrap() - 10.0).abs() < 0.5);
+        assert!((approach.walk_yards.unwrap() - 140.0).abs() < 1.0);
+    }
+
+    #[test]
+    fn gpx_points_without_a_time_are_skipped() {
+        let gpx = r#"<?xml version="1.0"?>
+            <gpx version="1.1" xmlns="http://www.topografix.com/GPX/1/1">
+              <trk><trkseg>
+                <trkpt lat="41.0" lon="-93.0"><time>2026-10-19T08:00:00Z</time></trkpt>
+                <trkpt lat="41.0001" lon="-93.0"></trkpt>
+                <trkpt lat="41.0002" lon="-93.0"><time>2026-10-19T08:00:05Z</time></trkpt>
+              </trkseg></trk>
+            </gpx>"#;
+        let points = parse_gpx(gpx).unwrap();
+        assert_eq!(points.len(), 2);
+        assert_eq!(points[1].lat, 41.0002);
+        assert_eq!((points[1].time - points[0].time).num_seconds(), 5);
+
+        assert!(parse_gpx(&gpx.replace("08:00:05Z", "eight")).is_err());
+        assert!(parse_gpx(&gpx.replace(r#"lon="-93.0">"#, ">")).is_err());
+    }
+}
@@ -0,0 +1,471 @@
+use chrono::NaiveDate;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::db_operations;
+use crate::elevation::ElevationGrid;
+use crate::error::ApiError;
+use crate::geometry::{distance_yards, group_shapes, lie_at, LatLong, Lie, Shape};
+use crate::models::{Club, Hole, HoleData, Round, RoundPlayer, Shot};
+use crate::pin_operations;
+use crate::round_operations::{authorize_open_round, authorize_round, fetch_course_holes};
+use crate::schema::{clubs, round_players, shots};
+use crate::shot_detection::{self, DetectedShots, TracePoint};
+
+const MAX_PENALTY_STROKES: i32 = 2;
+/// About four hours of one-second fixes, far more than one hole needs.
+const MAX_TRACE_POINTS: usize = 15_000;
+/// Balls this close to a tee marker are on the tee.
+const TEE_MARKER_YARDS: f64 = 10.0;
+
+/// What the player records for a shot. Distances and lies are filled in by
+/// the server.
+#[derive(Deserialize, Debug, Clone)]
+pub struct ShotDetails {
+    pub start_lat: f64,
+    pub start_long: f64,
+    pub end_lat: Option<f64>,
+    pub end_long: Option<f64>,
+    pub club_id: Option<i32>,
+    /// The lie as the player described it, kept alongside the computed one.
+    pub lie: Option<String>,
+    #[serde(default)]
+    pub penalty_strokes: i32,
+    /// Carry as the player saw it or a launch monitor measured it.
+    pub carry_yards: Option<f64>,
+    pub target_lat: Option<f64>,
+    pub target_long: Option<f64>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct NewShotInput {
+    /// Defaults to the caller's own player in the round.
+    pub player_id: Option<i32>,
+    pub hole_id: i32,
+    #[serde(flatten)]
+    pub details: ShotDetails,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct ShotFilter {
+    pub hole_id: Option<i32>,
+    pub player_id: Option<i32>,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+pub struct ShotMeasurement {
+    pub distance_yards: Option<f64>,
+    pub remaining_yards: Option<f64>,
+    pub start_lie: Lie,
+    pub end_lie: Option<Lie>,
+}
+
+#[derive(Insertable, AsChangeset)]
+#[diesel(table_name = shots, treat_none_as_null = true)]
+struct ShotRow {
+    start_lat: f64,
+    start_long: f64,
+    end_lat: Option<f64>,
+    end_long: Option<f64>,
+    club_id: Option<i32>,
+    club: Option<String>,
+    lie: Option<String>,
+    penalty_strokes: i32,
+    distance_yards: Option<f64>,
+    remaining_yards: Option<f64>,
+    start_lie: Option<String>,
+    end_lie: Option<String>,
+    carry_yards: Option<f64>,
+    target_lat: Option<f64>,
+    target_long: Option<f64>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = shots)]
+struct NewShot {
+    round_id: i32,
+    player_id: i32,
+    hole_id: i32,
+    shot_number: i32,
+    #[diesel(embed)]
+    row: ShotRow,
+}
+
+/// The geometry needed to measure shots on one hole.
+pub struct HoleContext {
+    pub hole: Hole,
+    pub shapes: Vec<Shape>,
+    pub tees: Vec<LatLong>,
+    /// The day's pin from the pin sheet, when there is one.
+    pub pin: Option<LatLong>,
+    /// Terrain heights, when an elevation model has been imported.
+    pub elevation: Option<ElevationGrid>,
+}
+
+impl HoleContext {
+    /// The hole as it is set up today, by the date where the course is.
+    pub fn load(conn: &mut PgConnection, hole_id: i32) -> Result<HoleContext, ApiError> {
+        let hole_data = db_operations::fetch_hole_data(conn, hole_id)?;
+        let today = pin_operations::today_at(hole_data.hole.flag().or(hole_data.tees().first().copied()));
+        Self::with_pin_on(conn, hole_data, today)
+    }
+
+    /// The hole as it was set up on the day a round was played.
+    pub fn load_on(conn: &mut PgConnection, hole_id: i32, played_on: NaiveDate) -> Result<HoleContext, ApiError> {
+        let hole_data = db_operations::fetch_hole_data(conn, hole_id)?;
+        Self::with_pin_on(conn, hole_data, played_on)
+    }
+
+    fn with_pin_on(conn: &mut PgConnection, hole_data: HoleData, played_on: NaiveDate) -> Result<HoleContext, ApiError> {
+        let hole_id = hole_data.hole.hole_id;
+        Ok(HoleContext {
+            shapes: group_shapes(&hole_data.polygons),
+            tees: hole_data.tees(),
+            pin: pin_operations::pin_on(conn, hole_id, played_on)?,
+            elevation: hole_data.elevation.map(|elevation| elevation.grid),
+            hole: hole_data.hole,
+        })
+    }
+
+    /// The day's pin, or the imported flag when no sheet covers the hole.
+    pub fn flag(&self) -> Option<LatLong> {
+        self.pin.or(self.hole.flag())
+    }
+
+    /// How far `to` sits above `from`, from the imported terrain.
+    pub fn elevation_change_ft(&self, from: LatLong, to: LatLong) -> Option<f64> {
+        self.elevation.as_ref()?.change_ft(from, to)
+    }
+
+    /// The lie at `point`, counting anywhere by a tee marker as the tee since
+    /// markers often sit just outside the mapped tee box.
+    pub fn lie_at(&self, point: LatLong) -> Lie {
+        if self.tees.iter().any(|tee| distance_yards(*tee, point) <= TEE_MARKER_YARDS) {
+            Lie::Tee
+        } else {
+            lie_at(&self.shapes, point)
+        }
+    }
+}
+
+/// Measures a shot against the hole: how far it went, how far is left to
+/// the flag, and the lie at each end. The first shot on a hole is always
+/// played from the tee.
+pub fn measure_shot(context: &HoleContext, start: LatLong, end: Option<LatLong>, first_on_hole: bool) -> ShotMeasurement {
+    let start_lie = if first_on_hole {
+        Lie::Tee
+    } else {
+        context.lie_at(start)
+    };
+
+    ShotMeasurement {
+        distance_yards: end.map(|end| distance_yards(start, end)),
+        remaining_yards: match (end, context.flag()) {
+            (Some(end), Some(flag)) => Some(distance_yards(end, flag)),
+            _ => None,
+        },
+        start_lie,
+        end_lie: end.map(|end| context.lie_at(end)),
+    }
+}
+
+fn validate_round_hole(conn: &mut PgConnection, round: &Round, hole_id: i32) -> Result<(), ApiError> {
+    if fetch_course_holes(conn, round.course_id)?
+        .iter()
+        .any(|hole| hole.hole_id == hole_id)
+    {
+        Ok(())
+    } else {
+        Err(ApiError::BadRequest(format!("hole {} is not on this course", hole_id)))
+    }
+}
+
+fn validate_point(lat: f64, long: f64, field: &str) -> Result<LatLong, ApiError> {
+    let point = LatLong::new(lat, long);
+    if point.is_valid() {
+        Ok(point)
+    } else {
+        Err(ApiError::BadRequest(format!("{} is not a valid coordinate", field)))
+    }
+}
+
+fn build_row(
+    context: &HoleContext,
+    details: ShotDetails,
+    club: Option<Club>,
+    first_on_hole: bool,
+) -> Result<ShotRow, ApiError> {
+    let start = validate_point(details.start_lat, details.start_long, "start")?;
+    let end = match (details.end_lat, details.end_long) {
+        (Some(lat), Some(long)) => Some(validate_point(lat, long, "end")?),
+        (None, None) => None,
+        _ => return Err(ApiError::BadRequest("end_lat and end_long must be given together".to_string())),
+    };
+    let target = match (details.target_lat, details.target_long) {
+        (Some(lat), Some(long)) => Some(validate_point(lat, long, "target")?),
+        (None, None) => None,
+        _ => return Err(ApiError::BadRequest("target_lat and target_long must be given together".to_string())),
+    };
+    if let Some(carry) = details.carry_yards {
+        if !(0.0..=450.0).contains(&carry) {
+            return Err(ApiError::BadRequest("carry_yards must be between 0 and 450".to_string()));
+        }
+    }
+    if !(0..=MAX_PENALTY_STROKES).contains(&details.penalty_strokes) {
+        return Err(ApiError::BadRequest(format!(
+            "penalty_strokes must be between 0 and {}",
+            MAX_PENALTY_STROKES
+        )));
+    }
+    let lie = match details.lie {
+        Some(lie) => Some(
+            Lie::parse(&lie)
+                .ok_or_else(|| ApiError::BadRequest(format!("unknown lie `{}`", lie)))?
+                .as_str()
+                .to_string(),
+        ),
+        None => None,
+    };
+
+    let measurement = measure_shot(context, start, end, first_on_hole);
+    Ok(ShotRow {
+        start_lat: start.lat,
+        start_long: start.long,
+        end_lat: end.map(|end| end.lat),
+        end_long: end.map(|end| end.long),
+        club_id: club.as_ref().map(|club| club.id),
+        club: club.map(|club| club.name),
+        lie,
+        penalty_strokes: details.penalty_strokes,
+        distance_yards: measurement.distance_yards,
+        remaining_yards: measurement.remaining_yards,
+        start_lie: Some(measurement.start_lie.as_str().to_string()),
+        end_lie: measurement.end_lie.map(|lie| lie.as_str().to_string()),
+        carry_yards: details.carry_yards,
+        target_lat: target.map(|target| target.lat),
+        target_long: target.map(|target| target.long),
+    })
+}
+
+fn resolve_player(
+    conn: &mut PgConnection,
+    round: &Round,
+    user_id: i32,
+    player_id: Option<i32>,
+) -> Result<RoundPlayer, ApiError> {
+    let query = round_players::table
+        .filter(round_players::round_id.eq(round.id))
+        .select(RoundPlayer::as_select());
+
+    let player = match player_id {
+        Some(player_id) => query.filter(round_players::id.eq(player_id)).first(conn).optional()?,
+        None => query.filter(round_players::user_id.eq(user_id)).first(conn).optional()?,
+    };
+    player.ok_or_else(|| ApiError::BadRequest("player is not in this round".to_string()))
+}
+
+/// A club from the bag of whoever is playing the shot.
+fn resolve_club(
+    conn: &mut PgConnection,
+    player: &RoundPlayer,
+    user_id: i32,
+    club_id: Option<i32>,
+) -> Result<Option<Club>, ApiError> {
+    let Some(club_id) = club_id else {
+        return Ok(None);
+    };
+    let owner = player.user_id.unwrap_or(user_id);
+    clubs::table
+        .filter(clubs::id.eq(club_id))
+        .filter(clubs::user_id.eq(owner))
+        .select(Club::as_select())
+        .first(conn)
+        .optional()?
+        .map(Some)
+        .ok_or_else(|| ApiError::BadRequest(format!("club {} is not in the player's bag", club_id)))
+}
+
+fn fetch_shot(conn: &mut PgConnection, round_id: i32, shot_id: i32) -> Result<Shot, ApiError> {
+    Ok(shots::table
+        .filter(shots::id.eq(shot_id))
+        .filter(shots::round_id.eq(round_id))
+        .select(Shot::as_select())
+        .first(conn)?)
+}
+
+/// A player's shot on a hole by its number.
+fn shot_at(conn: &mut PgConnection, shot: &Shot, shot_number: i32) -> Result<Option<Shot>, ApiError> {
+    Ok(shots::table
+        .filter(shots::player_id.eq(shot.player_id))
+        .filter(shots::hole_id.eq(shot.hole_id))
+        .filter(shots::shot_number.eq(shot_number))
+        .select(Shot::as_select())
+        .for_update()
+        .first(conn)
+        .optional()?)
+}
+
+/// Ends a stored shot at `end` and measures it again, for when a shot next
+/// to it has moved, gone, or changed places.
+fn remeasure_shot(
+    conn: &mut PgConnection,
+    context: &HoleContext,
+    shot: &Shot,
+    end: Option<LatLong>,
+) -> Result<(), ApiError> {
+    let measurement = measure_shot(context, shot.start(), end, shot.shot_number == 1);
+    diesel::update(shots::table.find(shot.id))
+        .set((
+            shots::end_lat.eq(end.map(|end| end.lat)),
+            shots::end_long.eq(end.map(|end| end.long)),
+            shots::distance_yards.eq(measurement.distance_yards),
+            shots::remaining_yards.eq(measurement.remaining_yards),
+            shots::start_lie.eq(measurement.start_lie.as_str()),
+            shots::end_lie.eq(measurement.end_lie.map(|lie| lie.as_str())),
+        ))
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Records the next shot for a player on a hole. If the previous shot has no
+/// end position yet, it ends where this one starts.
+pub fn record_shot(conn: &mut PgConnection, round_id: i32, user_id: i32, input: NewShotInput) -> Result<Shot, ApiError> {
+    let round = authorize_open_round(conn, round_id, user_id)?;
+    let player = resolve_player(conn, &round, user_id, input.player_id)?;
+    validate_round_hole(conn, &round, input.hole_id)?;
+    let club = resolve_club(conn, &player, user_id, input.details.club_id)?;
+    let context = HoleContext::load_on(conn, input.hole_id, round.played_on)?;
+
+    conn.transaction(|conn| {
+        let previous = shots::table
+            .filter(shots::player_id.eq(player.id))
+            .filter(shots::hole_id.eq(input.hole_id))
+            .order(shots::shot_number.desc())
+            .select(Shot::as_select())
+            .for_update()
+            .first(conn)
+            .optional()?;
+
+        let row = build_row(&context, input.details, club, previous.is_none())?;
+
+        if let Some(previous) = &previous {
+            if previous.end().is_none() {
+                let end = LatLong::new(row.start_lat, row.start_long);
+                remeasure_shot(conn, &context, previous, Some(end))?;
+            }
+        }
+
+        Ok(diesel::insert_into(shots::table)
+            .values(&NewShot {
+                round_id,
+                player_id: player.id,
+                hole_id: input.hole_id,
+                shot_number: previous.map_or(1, |shot| shot.shot_number + 1),
+                row,
+            })
+            .returning(Shot::as_returning())
+            .get_result(conn)?)
+    })
+}
+
+/// Replaces what was recorded for a shot. The previous shot ends where this
+/// one now starts.
+pub fn update_shot(
+    conn: &mut PgConnection,
+    round_id: i32,
+    shot_id: i32,
+    user_id: i32,
+    details: ShotDetails,
+) -> Result<Shot, ApiError> {
+    let round = authorize_open_round(conn, round_id, user_id)?;
+    let shot = fetch_shot(conn, round.id, shot_id)?;
+    let player = resolve_player(conn, &round, user_id, Some(shot.player_id))?;
+    let club = resolve_club(conn, &player, user_id, details.club_id)?;
+    let context = HoleContext::load_on(conn, shot.hole_id, round.played_on)?;
+    let row = build_row(&context, details, club, shot.shot_number == 1)?;
+
+    conn.transaction(|conn| {
+        let updated = diesel::update(shots::table.find(shot.id))
+            .set(&row)
+            .returning(Shot::as_returning())
+            .get_result(conn)?;
+        if let Some(previous) = shot_at(conn, &shot, shot.shot_number - 1)? {
+            remeasure_shot(conn, &context, &previous, Some(updated.start()))?;
+        }
+        Ok(updated)
+    })
+}
+
+/// Deletes a shot and renumbers the player's later shots on the hole. The
+/// previous shot then ends where the next one starts, and both are measured
+/// again. Returns the deleted shot.
+pub fn delete_shot(conn: &mut PgConnection, round_id: i32, shot_id: i32, user_id: i32) -> Result<Shot, ApiError> {
+    let round = authorize_open_round(conn, round_id, user_id)?;
+    let shot = fetch_shot(conn, round.id, shot_id)?;
+    let context = HoleContext::load_on(conn, shot.hole_id, round.played_on)?;
+
+    conn.transaction(|conn| -> Result<(), ApiError> {
+        diesel::delete(shots::table.find(shot.id)).execute(conn)?;
+
+        let later: Vec<i32> = shots::table
+            .filter(shots::player_id.eq(shot.player_id))
+            .filter(shots::hole_id.eq(shot.hole_id))
+            .filter(shots::shot_number.gt(shot.shot_number))
+            .order(shots::shot_number.asc())
+            .select(shots::id)
+            .load(conn)?;
+        for id in later {
+            diesel::update(shots::table.find(id))
+                .set(shots::shot_number.eq(shots::shot_number - 1))
+                .execute(conn)?;
+        }
+
+        let next = shot_at(conn, &shot, shot.shot_number)?;
+        if let Some(previous) = shot_at(conn, &shot, shot.shot_number - 1)? {
+            let end = next.as_ref().map(Shot::start).or(previous.end());
+            remeasure_shot(conn, &context, &previous, end)?;
+        }
+        if let Some(next) = next {
+            remeasure_shot(conn, &context, &next, next.end())?;
+        }
+        Ok(())
+    })?;
+    Ok(shot)
+}
+
+/// Proposes shots for a hole from a GPS trace. Nothing is saved; the player
+/// confirms each proposal through `record_shot`.
+pub fn detect_shots(
+    conn: &mut PgConnection,
+    round_id: i32,
+    user_id: i32,
+    hole_id: i32,
+    points: Vec<TracePoint>,
+) -> Result<DetectedShots, ApiError> {
+    let round = authorize_open_round(conn, round_id, user_id)?;
+    validate_round_hole(conn, &round, hole_id)?;
+    if points.len() > MAX_TRACE_POINTS {
+        return Err(ApiError::BadRequest(format!(
+            "a trace can have at most {} points",
+            MAX_TRACE_POINTS
+        )));
+    }
+    let context = HoleContext::load_on(conn, hole_id, round.played_on)?;
+    Ok(shot_detection::detect_shots(&context, points))
+}
+
+pub fn list_shots(conn: &mut PgConnection, round_id: i32, user_id: i32, filter: ShotFilter) -> Result<Vec<Shot>, ApiError> {
+    let round = authorize_round(conn, round_id, user_id)?;
+
+    let mut query = Shot::belonging_to(&round)
+        .select(Shot::as_select())
+        .order((shots::player_id.asc(), shots::hole_id.asc(), shots::shot_number.asc()))
+        .into_boxed();
+    if let Some(hole_id) = filter.hole_id {
+        query = query.filter(shots::hole_id.eq(hole_id));
+    }
+    if let Some(player_id) = filter.player_id {
+        query = query.filter(shots::player_id.eq(player_id));
+    }
+    Ok(query.load(conn)?)
+}
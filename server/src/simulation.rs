@@ -0,0 +1,507 @@
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use rand_distr::{Distribution, StandardNormal};
+use serde::Serialize;
+
+use crate::geometry::{distance_yards, from_local, lie_at, to_local, LatLong, Lie};
+use crate::shot_operations::HoleContext;
+use crate::stats_operations::ClubStatistics;
+
+/// Clubs need this many clean-lie shots before their own numbers replace the
+/// distances entered in the bag.
+const MIN_STATS_SHOTS: usize = 3;
+/// Golfbert doesn't map out-of-bounds stakes, so anything this far outside
+/// the mapped hole is treated as out of bounds.
+const OB_MARGIN_YARDS: f64 = 30.0;
+/// Step used to walk back from water to a drop point.
+const DROP_STEP_YARDS: f64 = 5.0;
+/// Aim directions tried either side of the line to the flag.
+const AIM_OFFSETS_DEG: [f64; 11] = [-20.0, -16.0, -12.0, -8.0, -4.0, 0.0, 4.0, 8.0, 12.0, 16.0, 20.0];
+/// Samples per candidate in the first, coarse pass.
+const COARSE_SAMPLES: usize = 200;
+/// Candidates carried into the full pass.
+const REFINED_CANDIDATES: usize = 8;
+pub const DEFAULT_SAMPLES: usize = 2_000;
+pub const MAX_SAMPLES: usize = 10_000;
+
+/// Expected strokes to hole out from a distance and lie.
+pub trait StrokesModel {
+    fn expected_strokes(&self, distance_yards: f64, lie: Lie) -> f64;
+}
+
+/// Distance a club keeps from each lie.
+pub fn lie_factor(lie: Lie) -> f64 {
+    match lie {
+        Lie::Tee | Lie::Fairway | Lie::Green => 1.0,
+        Lie::Rough => 0.93,
+        Lie::Sand => 0.85,
+        Lie::Recovery | Lie::Water => 0.6,
+    }
+}
+
+/// Clubs that can't sensibly be played from a lie.
+pub fn playable(club_type: &str, lie: Lie) -> bool {
+    match (club_type, lie) {
+        ("putter", Lie::Green) => true,
+        ("putter", _) | (_, Lie::Green) => false,
+        ("driver", lie) => lie == Lie::Tee,
+        ("wood", Lie::Sand | Lie::Recovery | Lie::Rough) => false,
+        ("hybrid", Lie::Recovery) => false,
+        _ => true,
+    }
+}
+
+/// Typical sideways spread for a club type when the player has no shots to
+/// measure, as a share of the distance hit.
+fn default_spread(club_type: &str) -> f64 {
+    match club_type {
+        "driver" => 0.09,
+        "wood" => 0.08,
+        "hybrid" => 0.07,
+        "iron" => 0.06,
+        _ => 0.05,
+    }
+}
+
+/// A club's shot pattern: from the player's own shots when there are enough,
+/// otherwise from what they entered in the bag.
+#[derive(Debug, Clone, Copy)]
+pub struct ShotPattern {
+    pub total: f64,
+    pub carry: f64,
+    pub depth_sd: f64,
+    pub lateral_sd_per_100: f64,
+    /// Average miss right (negative is left) per 100 yards.
+    pub bias_per_100: f64,
+    pub source: &'static str,
+}
+
+impl ShotPattern {
+    pub fn from_statistics(stats: &ClubStatistics) -> Option<ShotPattern> {
+        let spread = default_spread(&stats.club_type);
+
+        if let Some(clean) = stats.clean.as_ref().filter(|clean| clean.total.shots >= MIN_STATS_SHOTS) {
+            let total = clean.total.median;
+            let per_100 = |yards: f64| yards * 100.0 / total.max(1.0);
+            return Some(ShotPattern {
+                total,
+                carry: clean
+                    .carry
+                    .map(|carry| carry.median)
+                    .or(stats.bag_carry_yards)
+                    .unwrap_or(total * 0.92),
+                // p10 to p90 spans about 2.56 standard deviations.
+                depth_sd: ((clean.total.p90 - clean.total.p10) / 2.56).max(total * 0.03),
+                lateral_sd_per_100: clean
+                    .dispersion
+                    .map_or(spread * 100.0, |d| per_100(d.std_dev.max(1.0))),
+                bias_per_100: clean.dispersion.map_or(0.0, |d| per_100(d.average_offset)),
+                source: "shots",
+            });
+        }
+
+        let total = stats.bag_total_yards.or(stats.bag_carry_yards)?;
+        Some(ShotPattern {
+            total,
+            carry: stats.bag_carry_yards.unwrap_or(total * 0.92),
+            depth_sd: total * 0.06,
+            lateral_sd_per_100: spread * 100.0,
+            bias_per_100: 0.0,
+            source: "bag",
+        })
+    }
+}
+
+/// Where a simulated ball finished.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Landing {
+    InPlay(Lie),
+    Water,
+    OutOfBounds,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, Default)]
+pub struct OutcomeShares {
+    pub fairway: f64,
+    pub rough: f64,
+    pub sand: f64,
+    pub recovery: f64,
+    pub green: f64,
+    pub water: f64,
+    pub out_of_bounds: f64,
+}
+
+impl OutcomeShares {
+    fn add(&mut self, landing: Landing) {
+        match landing {
+            Landing::InPlay(Lie::Green) => self.green += 1.0,
+            Landing::InPlay(Lie::Tee | Lie::Fairway) => self.fairway += 1.0,
+            Landing::InPlay(Lie::Sand) => self.sand += 1.0,
+            Landing::InPlay(Lie::Recovery) => self.recovery += 1.0,
+            Landing::InPlay(Lie::Rough | Lie::Water) => self.rough += 1.0,
+            Landing::Water => self.water += 1.0,
+            Landing::OutOfBounds => self.out_of_bounds += 1.0,
+        }
+    }
+
+    fn scale(&mut self, samples: usize) {
+        let n = samples.max(1) as f64;
+        for share in [
+            &mut self.fairway,
+            &mut self.rough,
+            &mut self.sand,
+            &mut self.recovery,
+            &mut self.green,
+            &mut self.water,
+            &mut self.out_of_bounds,
+        ] {
+            *share /= n;
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct StrategyOption {
+    pub club_id: i32,
+    pub club: String,
+    pub aim: LatLong,
+    /// Degrees right (negative is left) of the line to the flag.
+    pub aim_offset_deg: f64,
+    pub expected_strokes: f64,
+    pub outcomes: OutcomeShares,
+    pub samples: usize,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SimulationResult {
+    pub seed: u64,
+    pub start_lie: Lie,
+    pub distance_yards: f64,
+    /// Expected strokes from here before choosing a club, for comparison.
+    pub baseline_strokes: f64,
+    /// The best aim for each club, best first.
+    pub options: Vec<StrategyOption>,
+}
+
+/// The mapped hole's extent in local yards around the start, grown by the
+/// out-of-bounds margin.
+struct Bounds {
+    min: (f64, f64),
+    max: (f64, f64),
+}
+
+impl Bounds {
+    fn around(context: &HoleContext, origin: LatLong) -> Bounds {
+        let points = context
+            .shapes
+            .iter()
+            .flat_map(|shape| shape.points.iter().copied())
+            .chain(context.tees.iter().copied())
+            .chain(context.flag())
+            .chain(Some(origin))
+            .map(|p| to_local(origin, p));
+
+        let (mut min, mut max) = ((f64::MAX, f64::MAX), (f64::MIN, f64::MIN));
+        for (x, y) in points {
+            min = (min.0.min(x), min.1.min(y));
+            max = (max.0.max(x), max.1.max(y));
+        }
+        Bounds {
+            min: (min.0 - OB_MARGIN_YARDS, min.1 - OB_MARGIN_YARDS),
+            max: (max.0 + OB_MARGIN_YARDS, max.1 + OB_MARGIN_YARDS),
+        }
+    }
+
+    fn contains(&self, (x, y): (f64, f64)) -> bool {
+        (self.min.0..=self.max.0).contains(&x) && (self.min.1..=self.max.1).contains(&y)
+    }
+}
+
+struct Simulator<'a> {
+    context: &'a HoleContext,
+    model: &'a dyn StrokesModel,
+    start: LatLong,
+    start_lie: Lie,
+    flag: LatLong,
+    bounds: Bounds,
+}
+
+impl Simulator<'_> {
+    fn classify(&self, local: (f64, f64)) -> Landing {
+        if !self.bounds.contains(local) {
+            return Landing::OutOfBounds;
+        }
+        match lie_at(&self.context.shapes, from_local(self.start, local)) {
+            Lie::Water => Landing::Water,
+            lie => Landing::InPlay(lie),
+        }
+    }
+
+    /// Strokes for this shot plus the expected strokes to hole out after it.
+    fn strokes_after(&self, local: (f64, f64), landing: Landing) -> f64 {
+        match landing {
+            Landing::InPlay(lie) => {
+                1.0 + self.model.expected_strokes(distance_yards(from_local(self.start, local), self.flag), lie)
+            }
+            // Stroke and distance: the shot, the penalty, and the same shot again.
+            Landing::OutOfBounds => {
+                2.0 + self.model.expected_strokes(distance_yards(self.start, self.flag), self.start_lie)
+            }
+            // Penalty drop back along the line of flight at the first dry
+            // spot, or from where the shot was played if there's none short
+            // of it.
+            Landing::Water => {
+                let length = local.0.hypot(local.1).max(1.0);
+                let mut back = length - DROP_STEP_YARDS;
+                let mut drop = (0.0, 0.0);
+                while back > 0.0 {
+                    let spot = (local.0 * back / length, local.1 * back / length);
+                    if self.classify(spot) != Landing::Water {
+                        drop = spot;
+                        break;
+                    }
+                    back -= DROP_STEP_YARDS;
+                }
+                let lie = match self.classify(drop) {
+                    Landing::InPlay(lie) if lie != Lie::Green => lie,
+                    _ => Lie::Rough,
+                };
+                2.0 + self.model.expected_strokes(distance_yards(from_local(self.start, drop), self.flag), lie)
+            }
+        }
+    }
+
+    fn run(&self, pattern: &ShotPattern, aim_offset_deg: f64, samples: usize, rng: &mut ChaCha8Rng) -> (f64, OutcomeShares) {
+        let (fx, fy) = to_local(self.start, self.flag);
+        let heading = fx.atan2(fy) + aim_offset_deg.to_radians();
+        let unit = (heading.sin(), heading.cos());
+        let right = (unit.1, -unit.0);
+
+        let flight = pattern.total * lie_factor(self.start_lie);
+        let lateral_sd = pattern.lateral_sd_per_100 * flight / 100.0;
+        let bias = pattern.bias_per_100 * flight / 100.0;
+
+        let mut total = 0.0;
+        let mut outcomes = OutcomeShares::default();
+        for _ in 0..samples {
+            let depth: f64 = StandardNormal.sample(rng);
+            let side: f64 = StandardNormal.sample(rng);
+            let along = (flight + depth * pattern.depth_sd).max(0.0);
+            let across = bias + side * lateral_sd;
+            let local = (
+                unit.0 * along + right.0 * across,
+                unit.1 * along + right.1 * across,
+            );
+            let landing = self.classify(local);
+            outcomes.add(landing);
+            total += self.strokes_after(local, landing);
+        }
+        outcomes.scale(samples);
+        (total / samples.max(1) as f64, outcomes)
+    }
+}
+
+/// A seed for one candidate, so each candidate's samples don't depend on the
+/// order candidates are run in.
+fn candidate_seed(seed: u64, club_id: i32, aim_index: usize) -> u64 {
+    seed ^ (club_id as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15) ^ (aim_index as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F)
+}
+
+/// Simulates every playable club at a fan of aim points from `start` and
+/// ranks them by expected strokes to hole out. The same seed always gives
+/// the same result.
+pub fn simulate(
+    context: &HoleContext,
+    model: &dyn StrokesModel,
+    bag: &[ClubStatistics],
+    start: LatLong,
+    seed: u64,
+    samples: usize,
+) -> Option<SimulationResult> {
+    let flag = context.flag()?;
+    let start_lie = context.lie_at(start);
+    let simulator = Simulator {
+        context,
+        model,
+        start,
+        start_lie,
+        flag,
+        bounds: Bounds::around(context, start),
+    };
+
+    let clubs: Vec<(&ClubStatistics, ShotPattern)> = bag
+        .iter()
+        .filter(|stats| playable(&stats.club_type, start_lie))
+        .filter_map(|stats| Some((stats, ShotPattern::from_statistics(stats)?)))
+        .collect();
+
+    let mut candidates: Vec<(usize, usize, f64, OutcomeShares)> = Vec::new();
+    for (club_index, (stats, pattern)) in clubs.iter().enumerate() {
+        for (aim_index, offset) in AIM_OFFSETS_DEG.iter().enumerate() {
+            let mut rng = ChaCha8Rng::seed_from_u64(candidate_seed(seed, stats.club_id, aim_index));
+            let (strokes, outcomes) = simulator.run(pattern, *offset, COARSE_SAMPLES.min(samples), &mut rng);
+            candidates.push((club_index, aim_index, strokes, outcomes));
+        }
+    }
+    candidates.sort_by(|a, b| a.2.total_cmp(&b.2));
+
+    // Re-run the most promising candidates with the full sample count, then
+    // keep the best aim for each club.
+    let mut options: Vec<StrategyOption> = Vec::new();
+    for (i, (club_index, aim_index, strokes, outcomes)) in candidates.into_iter().enumerate() {
+        let (stats, pattern) = &clubs[club_index];
+        if options.iter().any(|option| option.club_id == stats.club_id) {
+            continue;
+        }
+        let offset = AIM_OFFSETS_DEG[aim_index];
+        let (strokes, outcomes, used) = if i < REFINED_CANDIDATES && samples > COARSE_SAMPLES {
+            let mut rng = ChaCha8Rng::seed_from_u64(candidate_seed(seed, stats.club_id, aim_index).wrapping_add(1));
+            let (strokes, outcomes) = simulator.run(pattern, offset, samples, &mut rng);
+            (strokes, outcomes, samples)
+        } else {
+            (strokes, outcomes, COARSE_SAMPLES.min(samples))
+        };
+
+        let (fx, fy) = to_local(start, flag);
+        let heading = fx.atan2(fy) + offset.to_radians();
+        let along = pattern.total * lie_factor(start_lie);
+        options.push(StrategyOption {
+            club_id: stats.club_id,
+            club: stats.club.clone(),
+            aim: from_local(start, (heading.sin() * along, heading.cos() * along)),
+            aim_offset_deg: offset,
+            expected_strokes: strokes,
+            outcomes,
+            samples: used,
+        });
+    }
+    options.sort_by(|a, b| a.expected_strokes.total_cmp(&b.expected_strokes));
+
+    Some(SimulationResult {
+        seed,
+        start_lie,
+        distance_yards: distance_yards(start, flag),
+        baseline_strokes: model.expected_strokes(distance_yards(start, flag), start_lie),
+        options,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::Shape;
+    use crate::models::Hole;
+    use crate::strokes_gained::Baseline;
+
+    const TEE: LatLong = LatLong { lat: 41.0, long: -93.0 };
+
+    /// A rectangle in local yards around the tee.
+    fn shape(surface_type: &str, (west, south): (f64, f64), (east, north): (f64, f64)) -> Shape {
+        Shape {
+            surface_type: surface_type.to_string(),
+            points: [(west, south), (east, south), (east, north), (west, north)]
+                .into_iter()
+                .map(|corner| from_local(TEE, corner))
+                .collect(),
+        }
+    }
+
+    /// A 400-yard hole straight north of the tee with water across it.
+    fn context(water: ((f64, f64), (f64, f64))) -> HoleContext {
+        let flag = from_local(TEE, (0.0, 400.0));
+        HoleContext {
+            hole: Hole {
+                id: 1,
+                hole_id: 1,
+                number: Some(1),
+                course_id: Some(1),
+                rotation: None,
+                range_x_min: None,
+                range_x_max: None,
+                range_y_min: None,
+                range_y_max: None,
+                dimensions_width: None,
+                dimensions_height: None,
+                flag_lat: Some(flag.lat),
+                flag_long: Some(flag.long),
+                par: Some(4),
+                flag_elevation_ft: None,
+                name: None,
+                stroke_index: None,
+                stroke_index_women: None,
+            },
+            shapes: vec![
+                shape("tee box", (-5.0, -5.0), (5.0, 5.0)),
+                shape("fairway", (-20.0, 20.0), (20.0, 370.0)),
+                shape("green", (-15.0, 380.0), (15.0, 415.0)),
+                shape("water", water.0, water.1),
+            ],
+            tees: vec![TEE],
+            pin: None,
+            elevation: None,
+        }
+    }
+
+    fn club(club_id: i32, club_type: &str, total: f64) -> ClubStatistics {
+        ClubStatistics {
+            club_id,
+            club: club_type.to_string(),
+            club_type: club_type.to_string(),
+            bag_carry_yards: None,
+            bag_total_yards: Some(total),
+            shots: 0,
+            clean: None,
+            by_lie: Vec::new(),
+            trend: Vec::new(),
+        }
+    }
+
+    fn bag() -> Vec<ClubStatistics> {
+        vec![club(1, "driver", 250.0), club(2, "hybrid", 200.0), club(3, "iron", 150.0)]
+    }
+
+    fn ranking(result: &SimulationResult) -> Vec<(i32, f64, f64)> {
+        result
+            .options
+            .iter()
+            .map(|option| (option.club_id, option.aim_offset_deg, option.expected_strokes))
+            .collect()
+    }
+
+    #[test]
+    fn same_seed_gives_same_ranking() {
+        let baseline = Baseline::load(None).unwrap();
+        let context = context(((-60.0, 230.0), (60.0, 260.0)));
+
+        let first = simulate(&context, &baseline, &bag(), TEE, 7, 500).unwrap();
+        let again = simulate(&context, &baseline, &bag(), TEE, 7, 500).unwrap();
+        assert_eq!(ranking(&first), ranking(&again));
+        assert_eq!(first.start_lie, Lie::Tee);
+        assert_eq!(first.options.len(), 3);
+
+        let other = simulate(&context, &baseline, &bag(), TEE, 8, 500).unwrap();
+        assert_eq!(other.seed, 8);
+        assert_eq!(other.options.len(), 3);
+        assert!(other.options.iter().all(|option| option.expected_strokes.is_finite()));
+    }
+
+    #[test]
+    fn water_with_no_dry_ground_short_of_it_drops_at_the_start() {
+        let baseline = Baseline::load(None).unwrap();
+        let context = context(((-60.0, 1.0), (60.0, 200.0)));
+        let simulator = Simulator {
+            context: &context,
+            model: &baseline,
+            start: TEE,
+            start_lie: Lie::Tee,
+            flag: context.flag().unwrap(),
+            bounds: Bounds::around(&context, TEE),
+        };
+
+        // Walking back from 103 yards in 5-yard steps ends 2 yards behind
+        // the tee without finding dry ground.
+        let strokes = simulator.strokes_after((0.0, 103.0), Landing::Water);
+        let replayed = 2.0 + baseline.expected_strokes(distance_yards(TEE, simulator.flag), Lie::Tee);
+        assert!((strokes - replayed).abs() < 1e-9);
+    }
+}
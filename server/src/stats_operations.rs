@@ -0,0 +1,647 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use chrono::{Datelike, NaiveDate, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::bag_operations;
+use crate::error::ApiError;
+use crate::geometry::{to_local, LatLong, Lie};
+use crate::models::{Club, RoundPlayer, Shot};
+use crate::pin_operations;
+use crate::round_operations::authorize_round;
+use crate::schema::{hole_scores, holes, round_players, rounds, shots};
+use crate::simulation::StrokesModel;
+use crate::strokes_gained::{self, ShotGained, StrokesGainedSummary};
+
+/// A shot's weight halves for every this many days since it was played.
+const HALF_LIFE_DAYS: f64 = 90.0;
+/// Targets closer than this give too short a line to measure offsets against.
+const MIN_TARGET_YARDS: f64 = 20.0;
+/// Offsets within this many yards of the target line count as on line.
+const ON_LINE_YARDS: f64 = 10.0;
+/// Finishing within this many yards of the target's distance counts as pin high.
+const PIN_HIGH_YARDS: f64 = 10.0;
+
+/// Lies grouped the way they change a club's numbers. `Clean` lies are the
+/// player's stock distances; the others are reported separately.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum LieGroup {
+    Clean,
+    Rough,
+    Sand,
+    Recovery,
+}
+
+impl LieGroup {
+    /// Putts and shots out of water have no place in club distances.
+    fn from_lie(lie: Lie) -> Option<LieGroup> {
+        match lie {
+            Lie::Tee | Lie::Fairway => Some(LieGroup::Clean),
+            Lie::Rough => Some(LieGroup::Rough),
+            Lie::Sand => Some(LieGroup::Sand),
+            Lie::Recovery => Some(LieGroup::Recovery),
+            Lie::Green | Lie::Water => None,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Copy)]
+pub struct DistanceStats {
+    pub shots: usize,
+    pub average: f64,
+    pub median: f64,
+    pub p10: f64,
+    pub p90: f64,
+}
+
+/// Offsets from the target line in yards; negative is left, positive right.
+#[derive(Serialize, Debug, Clone, Copy)]
+pub struct DispersionStats {
+    pub shots: usize,
+    pub average_offset: f64,
+    pub std_dev: f64,
+    /// Nine in ten shots finish right of this.
+    pub left: f64,
+    /// Nine in ten shots finish left of this.
+    pub right: f64,
+}
+
+/// Share of shots missing each way, weighted like the other figures.
+#[derive(Serialize, Debug, Clone, Copy)]
+pub struct MissTendency {
+    pub left: f64,
+    pub on_line: f64,
+    pub right: f64,
+    /// Only shots with an explicit target count towards short and long.
+    pub short: Option<f64>,
+    pub pin_high: Option<f64>,
+    pub long: Option<f64>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct LieStatistics {
+    pub lie: LieGroup,
+    pub total: DistanceStats,
+    pub carry: Option<DistanceStats>,
+    pub dispersion: Option<DispersionStats>,
+    pub misses: Option<MissTendency>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct MonthlyTrend {
+    /// `YYYY-MM`.
+    pub month: String,
+    pub shots: usize,
+    pub average_total: f64,
+    pub average_carry: Option<f64>,
+    pub average_offset: Option<f64>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ClubStatistics {
+    pub club_id: i32,
+    pub club: String,
+    pub club_type: String,
+    /// The distances the player entered for the club, for comparison.
+    pub bag_carry_yards: Option<f64>,
+    pub bag_total_yards: Option<f64>,
+    pub shots: usize,
+    /// Stock numbers from the tee and fairway.
+    pub clean: Option<LieStatistics>,
+    pub by_lie: Vec<LieStatistics>,
+    /// Clean-lie shots by month, oldest first.
+    pub trend: Vec<MonthlyTrend>,
+}
+
+/// One measured shot, reduced to the numbers the statistics need.
+struct Sample {
+    lie: LieGroup,
+    played_on: NaiveDate,
+    weight: f64,
+    total: f64,
+    carry: Option<f64>,
+    offset: Option<f64>,
+    /// Distance past (positive) or short of (negative) an explicit target.
+    depth: Option<f64>,
+}
+
+/// Splits the shot into distance along and offset across the line from its
+/// start to `target`, in yards.
+fn along_and_offset(start: LatLong, end: LatLong, target: LatLong) -> Option<(f64, f64, f64)> {
+    let (tx, ty) = to_local(start, target);
+    let length = tx.hypot(ty);
+    if length < MIN_TARGET_YARDS {
+        return None;
+    }
+    let (ux, uy) = (tx / length, ty / length);
+    let (ex, ey) = to_local(start, end);
+    Some((ex * ux + ey * uy, ex * uy - ey * ux, length))
+}
+
+fn sample(shot: &Shot, played_on: NaiveDate, flag: Option<LatLong>, today: NaiveDate) -> Option<Sample> {
+    if shot.penalty_strokes > 0 {
+        return None;
+    }
+    let end = shot.end()?;
+    let total = shot.distance_yards?;
+    let lie = shot
+        .start_lie
+        .as_deref()
+        .and_then(Lie::parse)
+        .and_then(LieGroup::from_lie)?;
+
+    let explicit = shot.target();
+    let line = explicit
+        .or(flag)
+        .and_then(|target| along_and_offset(shot.start(), end, target));
+    let age_days = (today - played_on).num_days().max(0) as f64;
+
+    Some(Sample {
+        lie,
+        played_on,
+        weight: 0.5_f64.powf(age_days / HALF_LIFE_DAYS),
+        total,
+        carry: shot.carry_yards,
+        offset: line.map(|(_, offset, _)| offset),
+        depth: explicit
+            .and(line)
+            .map(|(along, _, length)| along - length),
+    })
+}
+
+fn weighted_percentile(values: &[(f64, f64)], q: f64) -> f64 {
+    let total_weight: f64 = values.iter().map(|(_, w)| w).sum();
+    let mut cumulative = 0.0;
+    for (value, weight) in values {
+        cumulative += weight;
+        if cumulative >= q * total_weight {
+            return *value;
+        }
+    }
+    values.last().map_or(0.0, |(value, _)| *value)
+}
+
+fn weighted_mean(values: &[(f64, f64)]) -> f64 {
+    let total_weight: f64 = values.iter().map(|(_, w)| w).sum();
+    values.iter().map(|(v, w)| v * w).sum::<f64>() / total_weight
+}
+
+/// Distance figures over (value, weight) pairs.
+fn distance_stats(mut values: Vec<(f64, f64)>) -> Option<DistanceStats> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_by(|a, b| a.0.total_cmp(&b.0));
+    Some(DistanceStats {
+        shots: values.len(),
+        average: weighted_mean(&values),
+        median: weighted_percentile(&values, 0.5),
+        p10: weighted_percentile(&values, 0.1),
+        p90: weighted_percentile(&values, 0.9),
+    })
+}
+
+fn dispersion_stats(mut offsets: Vec<(f64, f64)>) -> Option<DispersionStats> {
+    if offsets.is_empty() {
+        return None;
+    }
+    offsets.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let mean = weighted_mean(&offsets);
+    let variance = weighted_mean(&offsets.iter().map(|(v, w)| ((v - mean).powi(2), *w)).collect::<Vec<_>>());
+    Some(DispersionStats {
+        shots: offsets.len(),
+        average_offset: mean,
+        std_dev: variance.sqrt(),
+        left: weighted_percentile(&offsets, 0.1),
+        right: weighted_percentile(&offsets, 0.9),
+    })
+}
+
+fn share(values: &[(f64, f64)], predicate: impl Fn(f64) -> bool) -> f64 {
+    let total_weight: f64 = values.iter().map(|(_, w)| w).sum();
+    let matching = values
+        .iter()
+        .filter(|(v, _)| predicate(*v))
+        .fold(0.0, |sum, (_, w)| sum + w);
+    matching / total_weight
+}
+
+fn miss_tendency(offsets: &[(f64, f64)], depths: &[(f64, f64)]) -> Option<MissTendency> {
+    if offsets.is_empty() {
+        return None;
+    }
+    let depth_share = |predicate: fn(f64) -> bool| (!depths.is_empty()).then(|| share(depths, predicate));
+    Some(MissTendency {
+        left: share(offsets, |v| v < -ON_LINE_YARDS),
+        on_line: share(offsets, |v| v.abs() <= ON_LINE_YARDS),
+        right: share(offsets, |v| v > ON_LINE_YARDS),
+        short: depth_share(|v| v < -PIN_HIGH_YARDS),
+        pin_high: depth_share(|v| v.abs() <= PIN_HIGH_YARDS),
+        long: depth_share(|v| v > PIN_HIGH_YARDS),
+    })
+}
+
+fn lie_statistics(lie: LieGroup, samples: &[&Sample]) -> Option<LieStatistics> {
+    let weighted = |value: fn(&Sample) -> Option<f64>| -> Vec<(f64, f64)> {
+        samples.iter().filter_map(|s| value(s).map(|v| (v, s.weight))).collect()
+    };
+    let offsets = weighted(|s| s.offset);
+    let depths = weighted(|s| s.depth);
+
+    Some(LieStatistics {
+        lie,
+        total: distance_stats(weighted(|s| Some(s.total)))?,
+        carry: distance_stats(weighted(|s| s.carry)),
+        misses: miss_tendency(&offsets, &depths),
+        dispersion: dispersion_stats(offsets),
+    })
+}
+
+fn average(values: impl Iterator<Item = f64>) -> Option<f64> {
+    let (sum, count) = values.fold((0.0, 0), |(sum, count), v| (sum + v, count + 1));
+    (count > 0).then(|| sum / count as f64)
+}
+
+fn monthly_trend(samples: &[&Sample]) -> Vec<MonthlyTrend> {
+    let mut months: BTreeMap<(i32, u32), Vec<&Sample>> = BTreeMap::new();
+    for sample in samples {
+        months
+            .entry((sample.played_on.year(), sample.played_on.month()))
+            .or_default()
+            .push(sample);
+    }
+
+    months
+        .into_iter()
+        .map(|((year, month), samples)| MonthlyTrend {
+            month: format!("{:04}-{:02}", year, month),
+            shots: samples.len(),
+            average_total: average(samples.iter().map(|s| s.total)).unwrap_or(0.0),
+            average_carry: average(samples.iter().filter_map(|s| s.carry)),
+            average_offset: average(samples.iter().filter_map(|s| s.offset)),
+        })
+        .collect()
+}
+
+fn club_statistics_from(club: &Club, samples: &[Sample]) -> ClubStatistics {
+    let mut by_group: BTreeMap<LieGroup, Vec<&Sample>> = BTreeMap::new();
+    for sample in samples {
+        by_group.entry(sample.lie).or_default().push(sample);
+    }
+
+    let mut by_lie: Vec<LieStatistics> = by_group
+        .iter()
+        .filter_map(|(lie, samples)| lie_statistics(*lie, samples))
+        .collect();
+    let clean = by_lie
+        .iter()
+        .position(|stats| stats.lie == LieGroup::Clean)
+        .map(|i| by_lie.remove(i));
+
+    ClubStatistics {
+        club_id: club.id,
+        club: club.name.clone(),
+        club_type: club.club_type.clone(),
+        bag_carry_yards: club.carry_yards,
+        bag_total_yards: club.total_yards,
+        shots: samples.len(),
+        clean,
+        by_lie,
+        trend: by_group
+            .get(&LieGroup::Clean)
+            .map(|samples| monthly_trend(samples))
+            .unwrap_or_default(),
+    }
+}
+
+/// Statistics for every club in the user's bag from the shots they have
+/// played with it, in bag order. Clubs without shots are included with empty
+/// figures.
+pub fn club_statistics(conn: &mut PgConnection, user_id: i32) -> Result<Vec<ClubStatistics>, ApiError> {
+    let bag = bag_operations::fetch_bag(conn, user_id)?;
+
+    let played: Vec<(Shot, NaiveDate)> = shots::table
+        .inner_join(round_players::table)
+        .inner_join(rounds::table)
+        .filter(round_players::user_id.eq(user_id))
+        .filter(shots::club_id.is_not_null())
+        .select((Shot::as_select(), rounds::played_on))
+        .load(conn)?;
+
+    let hole_ids: Vec<i32> = played.iter().map(|(shot, _)| shot.hole_id).collect();
+    let flags: HashMap<i32, LatLong> = holes::table
+        .filter(holes::hole_id.eq_any(&hole_ids))
+        .select((holes::hole_id, holes::flag_lat, holes::flag_long))
+        .load::<(i32, Option<f64>, Option<f64>)>(conn)?
+        .into_iter()
+        .filter_map(|(hole_id, lat, long)| Some((hole_id, LatLong::new(lat?, long?))))
+        .collect();
+    let pins = pin_operations::pins_for_holes(conn, &hole_ids)?;
+
+    let today = Utc::now().date_naive();
+    let mut samples: HashMap<i32, Vec<Sample>> = HashMap::new();
+    for (shot, played_on) in &played {
+        let flag = pins
+            .get(&(shot.hole_id, *played_on))
+            .or(flags.get(&shot.hole_id))
+            .copied();
+        if let (Some(club_id), Some(sample)) = (shot.club_id, sample(shot, *played_on, flag, today)) {
+            samples.entry(club_id).or_default().push(sample);
+        }
+    }
+
+    Ok(bag
+        .iter()
+        .map(|club| club_statistics_from(club, samples.get(&club.id).map_or(&[], Vec::as_slice)))
+        .collect())
+}
+
+pub fn club_statistics_for(conn: &mut PgConnection, user_id: i32, club_id: i32) -> Result<ClubStatistics, ApiError> {
+    club_statistics(conn, user_id)?
+        .into_iter()
+        .find(|stats| stats.club_id == club_id)
+        .ok_or(ApiError::NotFound)
+}
+
+#[derive(Deserialize, Debug)]
+pub struct StrokesGainedQuery {
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    /// `week`, `month` (the default) or `year`.
+    pub period: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct HoleStrokesGained {
+    pub hole_id: i32,
+    pub number: Option<i32>,
+    pub summary: StrokesGainedSummary,
+    pub shots: Vec<ShotGained>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct PlayerStrokesGained {
+    pub player_id: i32,
+    pub name: String,
+    pub summary: StrokesGainedSummary,
+    pub holes: Vec<HoleStrokesGained>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct RoundStrokesGained {
+    pub round_id: i32,
+    pub baseline: String,
+    pub players: Vec<PlayerStrokesGained>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct PeriodStrokesGained {
+    pub period: String,
+    pub rounds: usize,
+    pub summary: StrokesGainedSummary,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ClubStrokesGained {
+    pub club_id: i32,
+    pub club: String,
+    pub summary: StrokesGainedSummary,
+    pub per_shot: f64,
+}
+
+#[derive(Serialize, Debug)]
+pub struct StrokesGainedReport {
+    pub baseline: String,
+    pub rounds: usize,
+    pub summary: StrokesGainedSummary,
+    pub periods: Vec<PeriodStrokesGained>,
+    pub clubs: Vec<ClubStrokesGained>,
+}
+
+/// A shot's strokes gained with where and when it was played.
+struct GainedShot {
+    gained: ShotGained,
+    club: Option<String>,
+    round_id: i32,
+    player_id: i32,
+    hole_id: i32,
+    played_on: NaiveDate,
+}
+
+/// Strokes gained for the given shots. Only holes the player has a score for
+/// and whose shots account for it count, since otherwise the last logged
+/// shot hasn't holed out.
+fn strokes_gained_for(
+    conn: &mut PgConnection,
+    model: &dyn StrokesModel,
+    played: Vec<(Shot, NaiveDate)>,
+) -> Result<Vec<GainedShot>, ApiError> {
+    let player_ids: HashSet<i32> = played.iter().map(|(shot, _)| shot.player_id).collect();
+    let hole_ids: HashSet<i32> = played.iter().map(|(shot, _)| shot.hole_id).collect();
+    let pins = pin_operations::pins_for_holes(conn, &hole_ids.iter().copied().collect::<Vec<_>>())?;
+
+    let finished: HashMap<(i32, i32), i32> = hole_scores::table
+        .filter(hole_scores::player_id.eq_any(player_ids))
+        .select(((hole_scores::player_id, hole_scores::hole_id), hole_scores::strokes))
+        .load::<((i32, i32), i32)>(conn)?
+        .into_iter()
+        .collect();
+    let hole_info: HashMap<i32, (Option<LatLong>, Option<i32>)> = holes::table
+        .filter(holes::hole_id.eq_any(hole_ids))
+        .select((holes::hole_id, holes::flag_lat, holes::flag_long, holes::par))
+        .load::<(i32, Option<f64>, Option<f64>, Option<i32>)>(conn)?
+        .into_iter()
+        .map(|(hole_id, lat, long, par)| (hole_id, (lat.zip(long).map(|(lat, long)| LatLong::new(lat, long)), par)))
+        .collect();
+
+    let mut by_hole: BTreeMap<(i32, i32, i32), Vec<(Shot, NaiveDate)>> = BTreeMap::new();
+    for (shot, played_on) in played {
+        by_hole
+            .entry((shot.round_id, shot.player_id, shot.hole_id))
+            .or_default()
+            .push((shot, played_on));
+    }
+
+    let mut gained = Vec::new();
+    for ((round_id, player_id, hole_id), mut hole_shots) in by_hole {
+        let Some((imported_flag, par)) = hole_info.get(&hole_id).copied() else {
+            continue;
+        };
+        let Some(strokes) = finished.get(&(player_id, hole_id)).copied() else {
+            continue;
+        };
+        hole_shots.sort_by_key(|(shot, _)| shot.shot_number);
+        let played_on = hole_shots[0].1;
+        let Some(flag) = pins.get(&(hole_id, played_on)).copied().or(imported_flag) else {
+            continue;
+        };
+        let shots: Vec<Shot> = hole_shots.into_iter().map(|(shot, _)| shot).collect();
+        if !strokes_gained::is_fully_logged(&shots, strokes, flag) {
+            continue;
+        }
+
+        for (shot, shot_gained) in shots
+            .iter()
+            .zip(strokes_gained::hole_strokes_gained(model, &shots, flag, par))
+        {
+            gained.push(GainedShot {
+                gained: shot_gained,
+                club: shot.club.clone(),
+                round_id,
+                player_id,
+                hole_id,
+                played_on,
+            });
+        }
+    }
+    Ok(gained)
+}
+
+/// Strokes gained for every player in a round, hole by hole.
+pub fn round_strokes_gained(
+    conn: &mut PgConnection,
+    baseline: &strokes_gained::Baseline,
+    round_id: i32,
+    user_id: i32,
+) -> Result<RoundStrokesGained, ApiError> {
+    let round = authorize_round(conn, round_id, user_id)?;
+    let players: Vec<RoundPlayer> = RoundPlayer::belonging_to(&round)
+        .select(RoundPlayer::as_select())
+        .order(round_players::id.asc())
+        .load(conn)?;
+    let played: Vec<(Shot, NaiveDate)> = Shot::belonging_to(&round)
+        .select(Shot::as_select())
+        .load(conn)?
+        .into_iter()
+        .map(|shot| (shot, round.played_on))
+        .collect();
+    let numbers: HashMap<i32, Option<i32>> = holes::table
+        .filter(holes::course_id.eq(Some(round.course_id)))
+        .select((holes::hole_id, holes::number))
+        .load::<(i32, Option<i32>)>(conn)?
+        .into_iter()
+        .collect();
+
+    let gained = strokes_gained_for(conn, baseline, played)?;
+
+    let players = players
+        .into_iter()
+        .map(|player| {
+            let mut holes: BTreeMap<i32, Vec<ShotGained>> = BTreeMap::new();
+            for shot in gained.iter().filter(|shot| shot.player_id == player.id) {
+                holes.entry(shot.hole_id).or_default().push(shot.gained);
+            }
+            let mut holes: Vec<HoleStrokesGained> = holes
+                .into_iter()
+                .map(|(hole_id, shots)| HoleStrokesGained {
+                    hole_id,
+                    number: numbers.get(&hole_id).copied().flatten(),
+                    summary: StrokesGainedSummary::from_shots(&shots),
+                    shots,
+                })
+                .collect();
+            holes.sort_by_key(|hole| hole.number);
+
+            PlayerStrokesGained {
+                player_id: player.id,
+                name: player.name,
+                summary: StrokesGainedSummary::from_shots(holes.iter().flat_map(|hole| &hole.shots)),
+                holes,
+            }
+        })
+        .collect();
+
+    Ok(RoundStrokesGained {
+        round_id: round.id,
+        baseline: baseline.name.clone(),
+        players,
+    })
+}
+
+fn period_label(date: NaiveDate, period: &str) -> String {
+    match period {
+        "week" => {
+            let week = date.iso_week();
+            format!("{:04}-W{:02}", week.year(), week.week())
+        }
+        "year" => format!("{:04}", date.year()),
+        _ => format!("{:04}-{:02}", date.year(), date.month()),
+    }
+}
+
+/// The user's strokes gained across their rounds, by period and by club.
+pub fn strokes_gained_report(
+    conn: &mut PgConnection,
+    baseline: &strokes_gained::Baseline,
+    user_id: i32,
+    query: StrokesGainedQuery,
+) -> Result<StrokesGainedReport, ApiError> {
+    let period = query.period.as_deref().unwrap_or("month");
+    if !["week", "month", "year"].contains(&period) {
+        return Err(ApiError::BadRequest("period must be week, month or year".to_string()));
+    }
+
+    let mut played_query = shots::table
+        .inner_join(round_players::table)
+        .inner_join(rounds::table)
+        .filter(round_players::user_id.eq(user_id))
+        .select((Shot::as_select(), rounds::played_on))
+        .into_boxed();
+    if let Some(from) = query.from {
+        played_query = played_query.filter(rounds::played_on.ge(from));
+    }
+    if let Some(to) = query.to {
+        played_query = played_query.filter(rounds::played_on.le(to));
+    }
+    let played: Vec<(Shot, NaiveDate)> = played_query.load(conn)?;
+
+    let gained = strokes_gained_for(conn, baseline, played)?;
+
+    let mut periods: BTreeMap<String, (HashSet<i32>, StrokesGainedSummary)> = BTreeMap::new();
+    let mut clubs: BTreeMap<i32, (String, StrokesGainedSummary)> = BTreeMap::new();
+    let mut rounds = HashSet::new();
+    let mut summary = StrokesGainedSummary::default();
+    for shot in &gained {
+        summary.add(&shot.gained);
+        rounds.insert(shot.round_id);
+
+        let (period_rounds, period_summary) = periods.entry(period_label(shot.played_on, period)).or_default();
+        period_rounds.insert(shot.round_id);
+        period_summary.add(&shot.gained);
+
+        if let Some(club_id) = shot.gained.club_id {
+            let (_, club_summary) = clubs
+                .entry(club_id)
+                .or_insert_with(|| (shot.club.clone().unwrap_or_default(), StrokesGainedSummary::default()));
+            club_summary.add(&shot.gained);
+        }
+    }
+
+    let mut clubs: Vec<ClubStrokesGained> = clubs
+        .into_iter()
+        .map(|(club_id, (club, summary))| ClubStrokesGained {
+            club_id,
+            club,
+            per_shot: summary.total / summary.shots.max(1) as f64,
+            summary,
+        })
+        .collect();
+    clubs.sort_by(|a, b| b.summary.total.total_cmp(&a.summary.total));
+
+    Ok(StrokesGainedReport {
+        baseline: baseline.name.clone(),
+        rounds: rounds.len(),
+        summary,
+        periods: periods
+            .into_iter()
+            .map(|(period, (rounds, summary))| PeriodStrokesGained {
+                period,
+                rounds: rounds.len(),
+                summary,
+            })
+            .collect(),
+        clubs,
+    })
+}
@@ -0,0 +1,272 @@
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::geometry::{distance_yards, LatLong, Lie};
+use crate::models::Shot;
+use crate::simulation::StrokesModel;
+
+/// The baseline shipped with the crate, used unless the configuration names
+/// another file in the same format.
+const DEFAULT_BASELINE: &str = include_str!("../baselines/pga_tour.json");
+/// Shots from inside this distance, off the green, are around the green.
+const AROUND_GREEN_YARDS: f64 = 30.0;
+/// Par 3 tee shots are approaches; without a par, tee shots shorter than
+/// this are treated as par 3s.
+const PAR_THREE_MAX_YARDS: f64 = 250.0;
+/// A shot ending this close to the flag is taken to have holed out.
+const HOLED_YARDS: f64 = 1.0;
+
+/// Expected strokes to hole out, by lie, as `[distance_yards, strokes]` rows
+/// sorted by distance.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+struct BaselineTables {
+    tee: Vec<(f64, f64)>,
+    fairway: Vec<(f64, f64)>,
+    rough: Vec<(f64, f64)>,
+    sand: Vec<(f64, f64)>,
+    recovery: Vec<(f64, f64)>,
+    green: Vec<(f64, f64)>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Baseline {
+    pub name: String,
+    #[serde(default)]
+    pub source: Option<String>,
+    tables: BaselineTables,
+}
+
+impl Baseline {
+    /// Loads the baseline from `path`, or the shipped one if there is none.
+    pub fn load(path: Option<&Path>) -> Result<Baseline, String> {
+        match path {
+            Some(path) => {
+                let contents =
+                    fs::read_to_string(path).map_err(|e| format!("could not read {}: {}", path.display(), e))?;
+                Baseline::parse(&contents).map_err(|e| format!("{}: {}", path.display(), e))
+            }
+            None => Baseline::parse(DEFAULT_BASELINE),
+        }
+    }
+
+    fn parse(contents: &str) -> Result<Baseline, String> {
+        let baseline: Baseline = serde_json::from_str(contents).map_err(|e| format!("invalid baseline: {}", e))?;
+        let tables = &baseline.tables;
+        for (lie, table) in [
+            ("tee", &tables.tee),
+            ("fairway", &tables.fairway),
+            ("rough", &tables.rough),
+            ("sand", &tables.sand),
+            ("recovery", &tables.recovery),
+            ("green", &tables.green),
+        ] {
+            if table.is_empty() {
+                return Err(format!("the {} table is empty", lie));
+            }
+            if table.iter().any(|(d, s)| !d.is_finite() || !s.is_finite() || *d < 0.0) {
+                return Err(format!("the {} table has an invalid row", lie));
+            }
+            if table.windows(2).any(|rows| rows[0].0 >= rows[1].0) {
+                return Err(format!("the {} table must be sorted by distance", lie));
+            }
+        }
+        Ok(baseline)
+    }
+
+    fn table(&self, lie: Lie) -> &[(f64, f64)] {
+        match lie {
+            Lie::Tee => &self.tables.tee,
+            Lie::Fairway => &self.tables.fairway,
+            Lie::Rough => &self.tables.rough,
+            Lie::Sand => &self.tables.sand,
+            Lie::Recovery | Lie::Water => &self.tables.recovery,
+            Lie::Green => &self.tables.green,
+        }
+    }
+}
+
+impl StrokesModel for Baseline {
+    /// Interpolates linearly between rows, holding the end values beyond the
+    /// table.
+    fn expected_strokes(&self, distance_yards: f64, lie: Lie) -> f64 {
+        let table = self.table(lie);
+        let upper = table.partition_point(|(d, _)| *d < distance_yards);
+        match (upper.checked_sub(1).map(|i| table[i]), table.get(upper)) {
+            (Some((d0, s0)), Some((d1, s1))) => s0 + (s1 - s0) * (distance_yards - d0) / (d1 - d0),
+            (None, Some(&(_, s))) | (Some((_, s)), None) => s,
+            (None, None) => 0.0,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum ShotCategory {
+    OffTheTee,
+    Approach,
+    AroundTheGreen,
+    Putting,
+}
+
+fn categorize(start_lie: Lie, start_yards: f64, shot_number: i32, par: Option<i32>) -> ShotCategory {
+    if start_lie == Lie::Green {
+        return ShotCategory::Putting;
+    }
+    let par_three = par.map_or(start_yards < PAR_THREE_MAX_YARDS, |par| par <= 3);
+    if shot_number == 1 && !par_three {
+        ShotCategory::OffTheTee
+    } else if start_yards <= AROUND_GREEN_YARDS {
+        ShotCategory::AroundTheGreen
+    } else {
+        ShotCategory::Approach
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Copy)]
+pub struct ShotGained {
+    pub shot_id: i32,
+    pub club_id: Option<i32>,
+    pub category: ShotCategory,
+    pub strokes_gained: f64,
+}
+
+fn start_lie(shot: &Shot) -> Lie {
+    shot.start_lie.as_deref().and_then(Lie::parse).unwrap_or(if shot.shot_number == 1 {
+        Lie::Tee
+    } else {
+        Lie::Rough
+    })
+}
+
+/// Whether a hole's shots, in order, account for the player's score on it:
+/// every stroke was logged, or the last shot ends in the hole. Holes with
+/// shots missing would credit the last logged shot with holing out.
+pub fn is_fully_logged(shots: &[Shot], strokes: i32, flag: LatLong) -> bool {
+    let logged = shots.len() as i32 + shots.iter().map(|shot| shot.penalty_strokes).sum::<i32>();
+    logged == strokes
+        || shots
+            .last()
+            .and_then(Shot::end)
+            .is_some_and(|end| distance_yards(end, flag) <= HOLED_YARDS)
+}
+
+/// Strokes gained for each shot on a finished hole, given its shots in order.
+/// The last shot is taken to have holed out, so only call it for holes that
+/// are [`is_fully_logged`].
+pub fn hole_strokes_gained(
+    model: &dyn StrokesModel,
+    shots: &[Shot],
+    flag: LatLong,
+    par: Option<i32>,
+) -> Vec<ShotGained> {
+    shots
+        .iter()
+        .enumerate()
+        .map(|(i, shot)| {
+            let start_yards = distance_yards(shot.start(), flag);
+            let lie = start_lie(shot);
+            let after = shots
+                .get(i + 1)
+                .map_or(0.0, |next| model.expected_strokes(distance_yards(next.start(), flag), start_lie(next)));
+
+            ShotGained {
+                shot_id: shot.id,
+                club_id: shot.club_id,
+                category: categorize(lie, start_yards, shot.shot_number, par),
+                strokes_gained: model.expected_strokes(start_yards, lie)
+                    - after
+                    - 1.0
+                    - f64::from(shot.penalty_strokes),
+            }
+        })
+        .collect()
+}
+
+#[derive(Serialize, Debug, Clone, Copy, Default)]
+pub struct StrokesGainedSummary {
+    pub shots: usize,
+    pub total: f64,
+    pub off_the_tee: f64,
+    pub approach: f64,
+    pub around_the_green: f64,
+    pub putting: f64,
+}
+
+impl StrokesGainedSummary {
+    pub fn add(&mut self, shot: &ShotGained) {
+        self.shots += 1;
+        self.total += shot.strokes_gained;
+        *match shot.category {
+            ShotCategory::OffTheTee => &mut self.off_the_tee,
+            ShotCategory::Approach => &mut self.approach,
+            ShotCategory::AroundTheGreen => &mut self.around_the_green,
+            ShotCategory::Putting => &mut self.putting,
+        } += shot.strokes_gained;
+    }
+
+    pub fn from_shots<'a>(shots: impl IntoIterator<Item = &'a ShotGained>) -> StrokesGainedSummary {
+        let mut summary = StrokesGainedSummary::default();
+        for shot in shots {
+            summary.add(shot);
+        }
+        summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDateTime;
+
+    use super::*;
+
+    const FLAG: LatLong = LatLong { lat: 40.0, long: -93.0 };
+
+    fn shot(shot_number: i32, start_lat: f64, end_lat: Option<f64>, penalty_strokes: i32) -> Shot {
+        Shot {
+            id: shot_number,
+            round_id: 1,
+            player_id: 1,
+            hole_id: 1,
+            shot_number,
+            start_lat,
+            start_long: FLAG.long,
+            end_lat,
+            end_long: end_lat.map(|_| FLAG.long),
+            club_id: None,
+            club: None,
+            lie: None,
+            penalty_strokes,
+            distance_yards: None,
+            remaining_yards: None,
+            start_lie: None,
+            end_lie: None,
+            created_at: NaiveDateTime::default(),
+            carry_yards: None,
+            target_lat: None,
+            target_long: None,
+        }
+    }
+
+    #[test]
+    fn every_stroke_logged_counts_penalties() {
+        let shots = [shot(1, 40.004, None, 1), shot(2, 40.001, None, 0), shot(3, 40.0001, None, 0)];
+        assert!(is_fully_logged(&shots, 4, FLAG));
+        assert!(!is_fully_logged(&shots, 5, FLAG));
+    }
+
+    #[test]
+    fn last_shot_ending_in_the_hole_counts() {
+        let shots = [shot(1, 40.004, Some(40.001), 0), shot(2, 40.001, Some(40.0), 0)];
+        assert!(is_fully_logged(&shots, 4, FLAG));
+    }
+
+    #[test]
+    fn missing_shots_are_not_scored() {
+        let shots = [shot(1, 40.004, Some(40.001), 0), shot(2, 40.001, Some(40.0003), 0)];
+        assert!(!is_fully_logged(&shots, 4, FLAG));
+    }
+}
@@ -0,0 +1,292 @@
+use std::collections::HashMap;
+
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::course_operations;
+use crate::error::ApiError;
+use crate::geometry::{green_centre, group_shapes, route_yards, LatLong};
+use crate::models::{HoleData, Round, TeeSet, TeeSetHole};
+use crate::round_operations::fetch_course_holes;
+use crate::schema::{tee_set_holes, tee_sets};
+
+/// Slope ratings run from 55 to 155 under the World Handicap System.
+const SLOPE_RANGE: std::ops::RangeInclusive<i32> = 55..=155;
+
+pub const GENDERS: &[&str] = &["men", "women", "mixed"];
+
+fn default_gender() -> String {
+    "mixed".to_string()
+}
+
+#[derive(Deserialize, Insertable, AsChangeset, Debug)]
+#[diesel(table_name = tee_sets, treat_none_as_null = true)]
+pub struct TeeSetInput {
+    pub name: String,
+    /// Matched against the tee markers on each hole; defaults to the name.
+    pub colour: Option<String>,
+    #[serde(default = "default_gender")]
+    pub gender: String,
+    pub course_rating: f64,
+    pub slope_rating: i32,
+    pub par: i32,
+}
+
+impl TeeSetInput {
+    fn validate(&mut self) -> Result<(), ApiError> {
+        self.name = self.name.trim().to_string();
+        if self.name.is_empty() || self.name.len() > 20 {
+            return Err(ApiError::BadRequest("name must be 1 to 20 characters".to_string()));
+        }
+        self.colour = self.colour.take().map(|colour| colour.trim().to_string()).filter(|c| !c.is_empty());
+        if self.colour.as_ref().is_some_and(|colour| colour.len() > 20) {
+            return Err(ApiError::BadRequest("colour must be at most 20 characters".to_string()));
+        }
+        if !GENDERS.contains(&self.gender.as_str()) {
+            return Err(ApiError::BadRequest(format!("gender must be one of {}", GENDERS.join(", "))));
+        }
+        if !SLOPE_RANGE.contains(&self.slope_rating) {
+            return Err(ApiError::BadRequest(format!(
+                "slope_rating must be between {} and {}",
+                SLOPE_RANGE.start(),
+                SLOPE_RANGE.end()
+            )));
+        }
+        if !self.course_rating.is_finite() || self.course_rating <= 0.0 {
+            return Err(ApiError::BadRequest("course_rating must be positive".to_string()));
+        }
+        if self.par <= 0 {
+            return Err(ApiError::BadRequest("par must be positive".to_string()));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct TeeSetDetails {
+    #[serde(flatten)]
+    pub tee_set: TeeSet,
+    /// Holes with a tee marker for this set, in course order.
+    pub holes: Vec<TeeSetHole>,
+    pub total_yards: i32,
+}
+
+/// Where this tee set plays a hole from and how long the hole is from there,
+/// measured from the tee marker along the route to the centre of the green.
+fn measure_hole(tee_set: &TeeSet, hole_data: &HoleData) -> Option<TeeSetHole> {
+    let tee = hole_data
+        .vectors
+        .iter()
+        .filter(|v| v.vector_type.as_deref().is_some_and(|marker| tee_set.uses_marker(marker)))
+        .find_map(|v| Some(LatLong::new(v.lat?, v.long?)))?;
+    let line = match &hole_data.route {
+        Some(route) => route.playing_line_from(tee),
+        None => {
+            let shapes = group_shapes(&hole_data.polygons);
+            vec![tee, green_centre(&shapes, hole_data.hole.flag()).or(hole_data.hole.flag())?]
+        }
+    };
+
+    Some(TeeSetHole {
+        tee_set_id: tee_set.id,
+        hole_id: hole_data.hole.hole_id,
+        tee_lat: tee.lat,
+        tee_long: tee.long,
+        yards: route_yards(&line).round() as i32,
+    })
+}
+
+/// Recomputes the per-hole yardages of a tee set from the course geometry.
+pub fn measure_tee_set(conn: &mut PgConnection, tee_set: &TeeSet) -> Result<Vec<TeeSetHole>, ApiError> {
+    let holes: Vec<TeeSetHole> = course_operations::fetch_course_hole_data(conn, tee_set.course_id)?
+        .iter()
+        .filter_map(|hole_data| measure_hole(tee_set, hole_data))
+        .collect();
+
+    conn.transaction(|conn| -> Result<(), ApiError> {
+        diesel::delete(tee_set_holes::table.filter(tee_set_holes::tee_set_id.eq(tee_set.id))).execute(conn)?;
+        diesel::insert_into(tee_set_holes::table)
+            .values(&holes)
+            .execute(conn)?;
+        Ok(())
+    })?;
+    Ok(holes)
+}
+
+/// Measures every tee set on a course again, e.g. after its holes change.
+pub fn measure_course_tee_sets(conn: &mut PgConnection, course_id: i32) -> Result<usize, ApiError> {
+    let sets = tee_sets::table
+        .filter(tee_sets::course_id.eq(course_id))
+        .select(TeeSet::as_select())
+        .load(conn)?;
+    for tee_set in &sets {
+        measure_tee_set(conn, tee_set)?;
+    }
+    Ok(sets.len())
+}
+
+fn with_holes(tee_set: TeeSet, holes: Vec<TeeSetHole>) -> TeeSetDetails {
+    TeeSetDetails {
+        total_yards: holes.iter().map(|hole| hole.yards).sum(),
+        tee_set,
+        holes,
+    }
+}
+
+pub fn fetch_tee_sets(conn: &mut PgConnection, course_id: i32) -> Result<Vec<TeeSetDetails>, ApiError> {
+    let sets = tee_sets::table
+        .filter(tee_sets::course_id.eq(course_id))
+        .order((tee_sets::course_rating.desc(), tee_sets::name.asc(), tee_sets::gender.asc()))
+        .select(TeeSet::as_select())
+        .load(conn)?;
+
+    let mut order: HashMap<i32, i32> = HashMap::new();
+    for (i, hole) in fetch_course_holes(conn, course_id)?.iter().enumerate() {
+        order.insert(hole.hole_id, hole.number.unwrap_or(i as i32 + 1));
+    }
+    let holes = TeeSetHole::belonging_to(&sets)
+        .select(TeeSetHole::as_select())
+        .load(conn)?
+        .grouped_by(&sets);
+
+    Ok(sets
+        .into_iter()
+        .zip(holes)
+        .map(|(tee_set, mut holes)| {
+            holes.sort_by_key(|hole| order.get(&hole.hole_id).copied());
+            with_holes(tee_set, holes)
+        })
+        .collect())
+}
+
+/// Loads a tee set, making sure it belongs to the course.
+pub fn fetch_tee_set(conn: &mut PgConnection, course_id: i32, tee_set_id: i32) -> Result<TeeSet, ApiError> {
+    Ok(tee_sets::table
+        .find(tee_set_id)
+        .filter(tee_sets::course_id.eq(course_id))
+        .select(TeeSet::as_select())
+        .first(conn)?)
+}
+
+fn tee_sets_named(
+    conn: &mut PgConnection,
+    course_id: i32,
+    name: &str,
+    gender: Option<&str>,
+) -> Result<Vec<TeeSet>, ApiError> {
+    let mut query = tee_sets::table
+        .filter(tee_sets::course_id.eq(course_id))
+        .filter(tee_sets::name.eq(name.trim()))
+        .order(tee_sets::id.asc())
+        .select(TeeSet::as_select())
+        .into_boxed();
+    if let Some(gender) = gender {
+        query = query.filter(tee_sets::gender.eq(gender));
+    }
+    Ok(query.load(conn)?)
+}
+
+/// A course's tee set by name, and gender when given, for clients that
+/// don't send tee set ids. Men's and women's sets often share a name, so
+/// a name that matches more than one set is rejected rather than guessed.
+pub fn find_tee_set(
+    conn: &mut PgConnection,
+    course_id: i32,
+    name: &str,
+    gender: Option<&str>,
+) -> Result<Option<TeeSet>, ApiError> {
+    if gender.is_some_and(|gender| !GENDERS.contains(&gender)) {
+        return Err(ApiError::BadRequest(format!("gender must be one of {}", GENDERS.join(", "))));
+    }
+    let mut sets = tee_sets_named(conn, course_id, name, gender)?;
+    if sets.len() > 1 {
+        let genders: Vec<&str> = sets.iter().map(|tee_set| tee_set.gender.as_str()).collect();
+        return Err(ApiError::BadRequest(format!(
+            "more than one tee set is called `{}` ({}); choose one by tee_set_id or tee_set_gender",
+            name.trim(),
+            genders.join(", ")
+        )));
+    }
+    Ok(sets.pop())
+}
+
+/// The tee set a round is played from. A round kept only by name, from
+/// before tee sets had ids, has none if that name is now ambiguous.
+pub fn round_tee_set(conn: &mut PgConnection, round: &Round) -> Result<Option<TeeSet>, ApiError> {
+    match (round.tee_set_id, round.tee_set.as_deref()) {
+        (Some(tee_set_id), _) => Ok(Some(fetch_tee_set(conn, round.course_id, tee_set_id)?)),
+        (None, Some(name)) => {
+            let mut sets = tee_sets_named(conn, round.course_id, name, None)?;
+            Ok(if sets.len() == 1 { sets.pop() } else { None })
+        }
+        (None, None) => Ok(None),
+    }
+}
+
+pub fn fetch_tee_set_holes(conn: &mut PgConnection, tee_set_id: i32) -> Result<Vec<TeeSetHole>, ApiError> {
+    Ok(tee_set_holes::table
+        .filter(tee_set_holes::tee_set_id.eq(tee_set_id))
+        .select(TeeSetHole::as_select())
+        .load(conn)?)
+}
+
+/// Where a tee set plays a hole from.
+pub fn tee_position(conn: &mut PgConnection, tee_set_id: i32, hole_id: i32) -> Result<LatLong, ApiError> {
+    let hole = tee_set_holes::table
+        .find((tee_set_id, hole_id))
+        .select(TeeSetHole::as_select())
+        .first(conn)
+        .optional()?
+        .ok_or_else(|| ApiError::BadRequest(format!("tee set {} has no tee on hole {}", tee_set_id, hole_id)))?;
+    Ok(LatLong::new(hole.tee_lat, hole.tee_long))
+}
+
+pub fn create_tee_set(conn: &mut PgConnection, course_id: i32, mut input: TeeSetInput) -> Result<TeeSetDetails, ApiError> {
+    input.validate()?;
+    course_operations::fetch_course(conn, course_id)?;
+
+    // Measured in the same transaction, so a tee set is never left without
+    // its holes if measuring fails.
+    conn.transaction(|conn| {
+        let tee_set = diesel::insert_into(tee_sets::table)
+            .values((tee_sets::course_id.eq(course_id), &input))
+            .returning(TeeSet::as_returning())
+            .get_result(conn)?;
+        let holes = measure_tee_set(conn, &tee_set)?;
+        Ok(with_holes(tee_set, holes))
+    })
+}
+
+/// Replaces a tee set's details and measures its holes again.
+pub fn update_tee_set(
+    conn: &mut PgConnection,
+    course_id: i32,
+    tee_set_id: i32,
+    mut input: TeeSetInput,
+) -> Result<TeeSetDetails, ApiError> {
+    input.validate()?;
+    fetch_tee_set(conn, course_id, tee_set_id)?;
+
+    conn.transaction(|conn| {
+        let tee_set = diesel::update(tee_sets::table.find(tee_set_id))
+            .set(&input)
+            .returning(TeeSet::as_returning())
+            .get_result(conn)?;
+        let holes = measure_tee_set(conn, &tee_set)?;
+        Ok(with_holes(tee_set, holes))
+    })
+}
+
+pub fn delete_tee_set(conn: &mut PgConnection, course_id: i32, tee_set_id: i32) -> Result<(), ApiError> {
+    let deleted = diesel::delete(
+        tee_sets::table
+            .find(tee_set_id)
+            .filter(tee_sets::course_id.eq(course_id)),
+    )
+    .execute(conn)?;
+
+    if deleted == 0 {
+        return Err(ApiError::NotFound);
+    }
+    Ok(())
+}
@@ -0,0 +1,132 @@
+use std::collections::{BTreeSet, HashMap};
+
+use diesel::dsl::{max, min};
+use diesel::prelude::*;
+
+use crate::error::ApiError;
+use crate::geometry::{group_shapes, LatLong};
+use crate::models::{Hole, Polygon, Vector};
+use crate::schema::{holes, polygons, vectors};
+use crate::tiles::{self, TileFeature, TileGeometry, TileId, TileValue};
+
+/// Holes with a polygon, tee marker or flag in the box between `south_west`
+/// and `north_east`.
+fn holes_in(conn: &mut PgConnection, south_west: LatLong, north_east: LatLong) -> Result<Vec<i32>, ApiError> {
+    let shaped = polygons::table
+        .group_by(polygons::hole_id)
+        .having(
+            max(polygons::lat)
+                .ge(south_west.lat)
+                .and(min(polygons::lat).le(north_east.lat))
+                .and(max(polygons::long).ge(south_west.long))
+                .and(min(polygons::long).le(north_east.long)),
+        )
+        .select(polygons::hole_id)
+        .load::<Option<i32>>(conn)?;
+    let marked = vectors::table
+        .filter(vectors::lat.between(south_west.lat, north_east.lat))
+        .filter(vectors::long.between(south_west.long, north_east.long))
+        .select(vectors::hole_id)
+        .distinct()
+        .load::<Option<i32>>(conn)?;
+    let flagged = holes::table
+        .filter(holes::flag_lat.between(south_west.lat, north_east.lat))
+        .filter(holes::flag_long.between(south_west.long, north_east.long))
+        .select(holes::hole_id)
+        .load::<i32>(conn)?;
+
+    let hole_ids: BTreeSet<i32> = shaped.into_iter().chain(marked).flatten().chain(flagged).collect();
+    Ok(hole_ids.into_iter().collect())
+}
+
+fn hole_attributes(hole: &Hole) -> Vec<(&'static str, TileValue)> {
+    let mut attributes = vec![("hole_id", TileValue::Int(i64::from(hole.hole_id)))];
+    if let Some(course_id) = hole.course_id {
+        attributes.push(("course_id", TileValue::Int(i64::from(course_id))));
+    }
+    if let Some(number) = hole.number {
+        attributes.push(("hole_number", TileValue::Int(i64::from(number))));
+    }
+    attributes
+}
+
+/// Builds the vector tile covering `tile` from the stored course geometry.
+pub fn build_tile(conn: &mut PgConnection, tile: TileId) -> Result<Vec<u8>, ApiError> {
+    if tile.z < tiles::MIN_ZOOM {
+        return Ok(Vec::new());
+    }
+    let (south_west, north_east) = tile.bounds();
+    let hole_ids = holes_in(conn, south_west, north_east)?;
+    if hole_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let holes = holes::table.filter(holes::hole_id.eq_any(&hole_ids)).load::<Hole>(conn)?;
+    let mut polygons_by_hole: HashMap<i32, Vec<Polygon>> = HashMap::new();
+    for polygon in polygons::table
+        .filter(polygons::hole_id.eq_any(&hole_ids))
+        .load::<Polygon>(conn)?
+    {
+        if let Some(hole_id) = polygon.hole_id {
+            polygons_by_hole.entry(hole_id).or_default().push(polygon);
+        }
+    }
+    let mut vectors_by_hole: HashMap<i32, Vec<Vector>> = HashMap::new();
+    for vector in vectors::table
+        .filter(vectors::hole_id.eq_any(&hole_ids))
+        .order(vectors::id.asc())
+        .load::<Vector>(conn)?
+    {
+        if let Some(hole_id) = vector.hole_id {
+            vectors_by_hole.entry(hole_id).or_default().push(vector);
+        }
+    }
+
+    let mut layers: HashMap<&'static str, Vec<TileFeature>> = HashMap::new();
+    for hole in &holes {
+        let shapes = group_shapes(polygons_by_hole.get(&hole.hole_id).map_or(&[], Vec::as_slice));
+        for (index, shape) in shapes.iter().enumerate() {
+            let Some(layer) = tiles::layer_for(&shape.surface_type) else {
+                continue;
+            };
+            let mut attributes = hole_attributes(hole);
+            attributes.push(("surface", TileValue::String(shape.surface_type.clone())));
+            attributes.push(("area_sq_yards", TileValue::Double(shape.area_sq_yards().round())));
+            // Unique across tiles, so renderers can match up a shape split
+            // between them.
+            let id = (hole.hole_id as u64) << 16 | index as u64;
+            layers.entry(layer).or_default().push(TileFeature::polygon(id, shape, attributes));
+        }
+
+        if let Some(flag) = hole.flag() {
+            let mut attributes = hole_attributes(hole);
+            if let Some(par) = hole.par {
+                attributes.push(("par", TileValue::Int(i64::from(par))));
+            }
+            layers.entry("flags").or_default().push(TileFeature {
+                id: hole.hole_id as u64,
+                geometry: TileGeometry::Point(flag),
+                attributes,
+            });
+        }
+
+        // The flag is stored with the hole; every other vector is a tee marker.
+        for vector in vectors_by_hole.get(&hole.hole_id).into_iter().flatten() {
+            if vector.vector_type.as_deref() == Some("Flag") {
+                continue;
+            }
+            let (Some(lat), Some(long)) = (vector.lat, vector.long) else {
+                continue;
+            };
+            let mut attributes = hole_attributes(hole);
+            attributes.push(("marker", TileValue::String(vector.vector_type.clone().unwrap_or_default())));
+            layers.entry("tees").or_default().push(TileFeature {
+                id: vector.id as u64,
+                geometry: TileGeometry::Point(LatLong::new(lat, long)),
+                attributes,
+            });
+        }
+    }
+
+    Ok(tiles::encode_tile(tile, &layers))
+}
@@ -0,0 +1,443 @@
+use std::collections::HashMap;
+use std::f64::consts::PI;
+
+use crate::cache::BuiltCache;
+use crate::geometry::{LatLong, Lie, Shape};
+
+/// Tile coordinates run 0..EXTENT across a tile.
+pub const EXTENT: u32 = 4096;
+/// Geometry is kept this far past the tile edge so shapes join up without
+/// seams when neighbouring tiles are drawn.
+const BUFFER: f64 = 64.0;
+pub const MAX_ZOOM: u8 = 24;
+/// Below this zoom a course is a few pixels across, so tiles are left empty
+/// rather than filled with every hole in view.
+pub const MIN_ZOOM: u8 = 12;
+
+/// Layers in drawing order, bottom first.
+pub const LAYERS: [&str; 7] = ["woods", "fairways", "water", "bunkers", "greens", "tees", "flags"];
+
+/// The layer a surface is drawn in, `None` for rough, which is left to the
+/// map's background.
+pub fn layer_for(surface_type: &str) -> Option<&'static str> {
+    match Lie::from_surface_type(surface_type) {
+        Lie::Green => Some("greens"),
+        Lie::Fairway => Some("fairways"),
+        Lie::Sand => Some("bunkers"),
+        Lie::Water => Some("water"),
+        Lie::Recovery => Some("woods"),
+        Lie::Tee => Some("tees"),
+        Lie::Rough => None,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TileId {
+    pub z: u8,
+    pub x: u32,
+    pub y: u32,
+}
+
+impl TileId {
+    pub fn new(z: u8, x: u32, y: u32) -> Option<TileId> {
+        let tiles = 1u64 << z.min(MAX_ZOOM);
+        (z <= MAX_ZOOM && u64::from(x) < tiles && u64::from(y) < tiles).then_some(TileId { z, x, y })
+    }
+
+    fn tiles(&self) -> f64 {
+        f64::from(1u32 << self.z)
+    }
+
+    /// Where `point` falls in this tile, in tile coordinates.
+    pub fn project(&self, point: LatLong) -> (f64, f64) {
+        let n = self.tiles();
+        let lat = point.lat.to_radians();
+        let x = (point.long + 180.0) / 360.0 * n;
+        let y = (1.0 - (lat.tan() + 1.0 / lat.cos()).ln() / PI) / 2.0 * n;
+        (
+            (x - f64::from(self.x)) * f64::from(EXTENT),
+            (y - f64::from(self.y)) * f64::from(EXTENT),
+        )
+    }
+
+    fn unproject(&self, x: f64, y: f64) -> LatLong {
+        let n = self.tiles();
+        let (x, y) = (f64::from(self.x) + x / f64::from(EXTENT), f64::from(self.y) + y / f64::from(EXTENT));
+        LatLong::new((PI * (1.0 - 2.0 * y / n)).sinh().atan().to_degrees(), x / n * 360.0 - 180.0)
+    }
+
+    /// South-west and north-east corners, including the buffer.
+    pub fn bounds(&self) -> (LatLong, LatLong) {
+        let (low, high) = (-BUFFER, f64::from(EXTENT) + BUFFER);
+        (self.unproject(low, high), self.unproject(high, low))
+    }
+}
+
+/// An attribute value on a feature.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TileValue {
+    String(String),
+    Int(i64),
+    Double(f64),
+}
+
+#[derive(Debug, Clone)]
+pub enum TileGeometry {
+    Point(LatLong),
+    Polygon(Vec<LatLong>),
+}
+
+#[derive(Debug, Clone)]
+pub struct TileFeature {
+    pub id: u64,
+    pub geometry: TileGeometry,
+    pub attributes: Vec<(&'static str, TileValue)>,
+}
+
+impl TileFeature {
+    pub fn polygon(id: u64, shape: &Shape, attributes: Vec<(&'static str, TileValue)>) -> TileFeature {
+        TileFeature {
+            id,
+            geometry: TileGeometry::Polygon(shape.points.clone()),
+            attributes,
+        }
+    }
+}
+
+/// Minimal protobuf writer for the handful of field types a tile uses.
+#[derive(Default)]
+struct Message(Vec<u8>);
+
+impl Message {
+    fn varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.0.push((value as u8) | 0x80);
+            value >>= 7;
+        }
+        self.0.push(value as u8);
+    }
+
+    fn key(&mut self, field: u32, wire_type: u8) {
+        self.varint(u64::from(field << 3 | u32::from(wire_type)));
+    }
+
+    fn uint(&mut self, field: u32, value: u64) {
+        self.key(field, 0);
+        self.varint(value);
+    }
+
+    fn bytes(&mut self, field: u32, bytes: &[u8]) {
+        self.key(field, 2);
+        self.varint(bytes.len() as u64);
+        self.0.extend_from_slice(bytes);
+    }
+
+    fn packed(&mut self, field: u32, values: &[u32]) {
+        let mut packed = Message::default();
+        for value in values {
+            packed.varint(u64::from(*value));
+        }
+        self.bytes(field, &packed.0);
+    }
+}
+
+fn zigzag(value: i32) -> u32 {
+    ((value << 1) ^ (value >> 31)) as u32
+}
+
+fn command(id: u32, count: usize) -> u32 {
+    (id & 0x7) | ((count as u32) << 3)
+}
+
+/// Clips a ring to a rectangle, one edge at a time.
+fn clip_ring(ring: &[(f64, f64)], low: f64, high: f64) -> Vec<(f64, f64)> {
+    type Edge = (fn(&(f64, f64)) -> f64, f64, bool);
+    let edges: [Edge; 4] = [
+        (|p| p.0, low, true),
+        (|p| p.0, high, false),
+        (|p| p.1, low, true),
+        (|p| p.1, high, false),
+    ];
+    let mut output = ring.to_vec();
+    for (axis, limit, keep_above) in edges {
+        let inside = |p: &(f64, f64)| if keep_above { axis(p) >= limit } else { axis(p) <= limit };
+        let input = std::mem::take(&mut output);
+        let Some(mut previous) = input.last().copied() else {
+            break;
+        };
+        for point in input {
+            if inside(&point) != inside(&previous) {
+                let t = (limit - axis(&previous)) / (axis(&point) - axis(&previous));
+                output.push((
+                    previous.0 + (point.0 - previous.0) * t,
+                    previous.1 + (point.1 - previous.1) * t,
+                ));
+            }
+            if inside(&point) {
+                output.push(point);
+            }
+            previous = point;
+        }
+    }
+    output
+}
+
+/// Encodes a feature's geometry, or `None` when nothing of it is left in the
+/// tile.
+fn encode_geometry(tile: TileId, geometry: &TileGeometry) -> Option<(u32, Vec<u32>)> {
+    let (low, high) = (-BUFFER, f64::from(EXTENT) + BUFFER);
+    match geometry {
+        TileGeometry::Point(point) => {
+            let (x, y) = tile.project(*point);
+            if !(low..=high).contains(&x) || !(low..=high).contains(&y) {
+                return None;
+            }
+            Some((1, vec![command(1, 1), zigzag(x.round() as i32), zigzag(y.round() as i32)]))
+        }
+        TileGeometry::Polygon(points) => {
+            let projected: Vec<(f64, f64)> = points.iter().map(|p| tile.project(*p)).collect();
+            let mut ring: Vec<(i32, i32)> = clip_ring(&projected, low, high)
+                .into_iter()
+                .map(|(x, y)| (x.round() as i32, y.round() as i32))
+                .collect();
+            ring.dedup();
+            while ring.len() > 1 && ring.first() == ring.last() {
+                ring.pop();
+            }
+            if ring.len() < 3 {
+                return None;
+            }
+            // Exterior rings wind clockwise on screen, where y runs down.
+            let area: i64 = ring
+                .iter()
+                .zip(ring.iter().cycle().skip(1))
+                .map(|(a, b)| i64::from(a.0) * i64::from(b.1) - i64::from(b.0) * i64::from(a.1))
+                .sum();
+            if area == 0 {
+                return None;
+            }
+            if area < 0 {
+                ring.reverse();
+            }
+
+            let mut commands = Vec::with_capacity(ring.len() * 2 + 3);
+            commands.extend([command(1, 1), zigzag(ring[0].0), zigzag(ring[0].1)]);
+            commands.push(command(2, ring.len() - 1));
+            for pair in ring.windows(2) {
+                commands.extend([zigzag(pair[1].0 - pair[0].0), zigzag(pair[1].1 - pair[0].1)]);
+            }
+            commands.push(command(7, 1));
+            Some((3, commands))
+        }
+    }
+}
+
+fn encode_layer(tile: TileId, name: &str, features: &[TileFeature]) -> Option<Vec<u8>> {
+    let mut keys: Vec<&str> = Vec::new();
+    let mut values: Vec<TileValue> = Vec::new();
+    let mut layer = Message::default();
+    layer.uint(15, 2);
+    layer.bytes(1, name.as_bytes());
+
+    let mut encoded = 0;
+    for feature in features {
+        let Some((geometry_type, geometry)) = encode_geometry(tile, &feature.geometry) else {
+            continue;
+        };
+        let mut tags = Vec::with_capacity(feature.attributes.len() * 2);
+        for (key, value) in &feature.attributes {
+            let key_index = keys.iter().position(|k| k == key).unwrap_or_else(|| {
+                keys.push(key);
+                keys.len() - 1
+            });
+            let value_index = values.iter().position(|v| v == value).unwrap_or_else(|| {
+                values.push(value.clone());
+                values.len() - 1
+            });
+            tags.extend([key_index as u32, value_index as u32]);
+        }
+
+        let mut message = Message::default();
+        message.uint(1, feature.id);
+        message.packed(2, &tags);
+        message.uint(3, u64::from(geometry_type));
+        message.packed(4, &geometry);
+        layer.bytes(2, &message.0);
+        encoded += 1;
+    }
+    if encoded == 0 {
+        return None;
+    }
+
+    for key in keys {
+        layer.bytes(3, key.as_bytes());
+    }
+    for value in values {
+        let mut message = Message::default();
+        match value {
+            TileValue::String(value) => message.bytes(1, value.as_bytes()),
+            TileValue::Double(value) => {
+                message.key(3, 1);
+                message.0.extend_from_slice(&value.to_le_bytes());
+            }
+            TileValue::Int(value) => message.uint(4, value as u64),
+        }
+        layer.bytes(4, &message.0);
+    }
+    layer.uint(5, u64::from(EXTENT));
+    Some(layer.0)
+}
+
+/// Encodes a Mapbox Vector Tile with a layer for each name in `layers` that
+/// has something in the tile.
+pub fn encode_tile(tile: TileId, layers: &HashMap<&'static str, Vec<TileFeature>>) -> Vec<u8> {
+    let mut message = Message::default();
+    for name in LAYERS {
+        if let Some(layer) = layers.get(name).and_then(|features| encode_layer(tile, name, features)) {
+            message.bytes(3, &layer);
+        }
+    }
+    message.0
+}
+
+/// Recently built tiles. Course geometry only changes on import, so tiles
+/// are reused until they expire.
+pub type TileCache = BuiltCache<TileId>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tile() -> TileId {
+        TileId::new(0, 0, 0).unwrap()
+    }
+
+    /// A 16 unit square north-east of the middle of the zoom 0 tile, wound
+    /// counter-clockwise on screen.
+    fn square() -> Vec<LatLong> {
+        vec![
+            LatLong::new(0.0, 0.0),
+            LatLong::new(0.0, 1.40625),
+            LatLong::new(1.40625, 1.40625),
+            LatLong::new(1.40625, 0.0),
+        ]
+    }
+
+    fn unzigzag(value: u32) -> i32 {
+        (value >> 1) as i32 ^ -((value & 1) as i32)
+    }
+
+    /// The ring a polygon's commands draw, in tile coordinates.
+    fn decode_ring(commands: &[u32]) -> Vec<(i32, i32)> {
+        let mut ring = vec![(unzigzag(commands[1]), unzigzag(commands[2]))];
+        let line_to = commands[3] >> 3;
+        for delta in commands[4..4 + 2 * line_to as usize].chunks(2) {
+            let (x, y) = *ring.last().unwrap();
+            ring.push((x + unzigzag(delta[0]), y + unzigzag(delta[1])));
+        }
+        ring
+    }
+
+    fn area(ring: &[(f64, f64)]) -> f64 {
+        ring.iter()
+            .zip(ring.iter().cycle().skip(1))
+            .map(|(a, b)| a.0 * b.1 - b.0 * a.1)
+            .sum::<f64>()
+            / 2.0
+    }
+
+    #[test]
+    fn zigzag_interleaves_negative_values() {
+        assert_eq!(zigzag(0), 0);
+        assert_eq!(zigzag(-1), 1);
+        assert_eq!(zigzag(1), 2);
+        assert_eq!(zigzag(-64), 127);
+        assert_eq!(zigzag(-2048), 4095);
+        assert_eq!(zigzag(i32::MIN), u32::MAX);
+    }
+
+    #[test]
+    fn encodes_a_point_feature() {
+        let feature = TileFeature {
+            id: 1,
+            geometry: TileGeometry::Point(LatLong::new(0.0, 0.0)),
+            attributes: vec![("hole", TileValue::Int(7))],
+        };
+        let layers = HashMap::from([("flags", vec![feature])]);
+
+        let mut expected = vec![0x1a, 0x27, 0x78, 0x02, 0x0a, 0x05];
+        expected.extend(b"flags");
+        // The feature: id, tags, type and a move to (2048, 2048).
+        expected.extend([0x12, 0x0f, 0x08, 0x01, 0x12, 0x02, 0x00, 0x00, 0x18, 0x01]);
+        expected.extend([0x22, 0x05, 0x09, 0x80, 0x20, 0x80, 0x20]);
+        expected.extend([0x1a, 0x04]);
+        expected.extend(b"hole");
+        expected.extend([0x22, 0x02, 0x20, 0x07, 0x28, 0x80, 0x20]);
+        assert_eq!(encode_tile(tile(), &layers), expected);
+    }
+
+    #[test]
+    fn encodes_a_polygon_feature() {
+        let feature = TileFeature {
+            id: 1,
+            geometry: TileGeometry::Polygon(square()),
+            attributes: Vec::new(),
+        };
+        let layers = HashMap::from([("greens", vec![feature])]);
+
+        let mut expected = vec![0x1a, 0x24, 0x78, 0x02, 0x0a, 0x06];
+        expected.extend(b"greens");
+        expected.extend([0x12, 0x15, 0x08, 0x01, 0x12, 0x00, 0x18, 0x03, 0x22, 0x0d]);
+        // Move to (2048, 2032), three lines clockwise round the square, close.
+        expected.extend([0x09, 0x80, 0x20, 0xe0, 0x1f, 0x1a, 0x20, 0x00, 0x00, 0x20, 0x1f, 0x00, 0x0f]);
+        expected.extend([0x28, 0x80, 0x20]);
+        assert_eq!(encode_tile(tile(), &layers), expected);
+    }
+
+    #[test]
+    fn exterior_rings_have_positive_area() {
+        let mut clockwise = square();
+        clockwise.reverse();
+        for points in [square(), clockwise] {
+            let (geometry_type, commands) = encode_geometry(tile(), &TileGeometry::Polygon(points)).unwrap();
+            assert_eq!(geometry_type, 3);
+            assert_eq!(commands.last(), Some(&command(7, 1)));
+            let ring: Vec<(f64, f64)> = decode_ring(&commands)
+                .into_iter()
+                .map(|(x, y)| (f64::from(x), f64::from(y)))
+                .collect();
+            assert_eq!(area(&ring), 256.0);
+        }
+    }
+
+    #[test]
+    fn rings_clipped_at_the_edge_stay_closed() {
+        let ring = [(-200.0, -200.0), (100.0, -200.0), (100.0, 100.0), (-200.0, 100.0)];
+        let clipped = clip_ring(&ring, -BUFFER, f64::from(EXTENT) + BUFFER);
+        assert_eq!(clipped.len(), 4);
+        assert!(clipped.iter().all(|&(x, y)| x >= -BUFFER && y >= -BUFFER));
+        assert_eq!(area(&clipped).abs(), 164.0 * 164.0);
+
+        // Cut across a corner, the ring gains a point but still closes on
+        // the edge it left from.
+        let ring = [(-200.0, 0.0), (100.0, 0.0), (100.0, 100.0)];
+        let clipped = clip_ring(&ring, -BUFFER, f64::from(EXTENT) + BUFFER);
+        assert_eq!(clipped.len(), 4);
+        let (first, last) = (clipped[0], clipped[clipped.len() - 1]);
+        assert!(first.0 == -BUFFER || last.0 == -BUFFER);
+        let whole = area(&[(-200.0, 0.0), (100.0, 0.0), (100.0, 100.0)]);
+        let cut_off = area(&[(-200.0, 0.0), (-BUFFER, 0.0), (-BUFFER, 100.0 * (200.0 - BUFFER) / 300.0)]);
+        assert!((area(&clipped) - (whole - cut_off)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn empty_layers_are_left_out() {
+        let feature = TileFeature {
+            id: 1,
+            geometry: TileGeometry::Point(LatLong::new(60.0, 100.0)),
+            attributes: Vec::new(),
+        };
+        let layers = HashMap::from([("flags", vec![feature])]);
+        assert!(encode_tile(TileId::new(10, 0, 0).unwrap(), &layers).is_empty());
+    }
+}
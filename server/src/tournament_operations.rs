@@ -0,0 +1,793 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::{NaiveDate, NaiveDateTime};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::auth;
+use crate::error::ApiError;
+use crate::handicap::{self, MAX_INDEX};
+use crate::handicap_operations::{self, DEFAULT_ALLOWANCE_PERCENT};
+use crate::hole_operations;
+use crate::leaderboard::{self, CardHole, Division, EntrantCards, Leaderboard, RoundCard};
+use crate::models::{
+    HoleScore, TeeSet, Tournament, TournamentEntrant, TournamentFlight, TournamentGroup, TournamentGroupEntrant,
+    TournamentRound,
+};
+use crate::round_operations::{self, fetch_course_holes, NewPlayerInput, Scorecard};
+use crate::schema::{
+    hole_scores, round_players, tournament_entrants, tournament_flights, tournament_group_entrants, tournament_groups,
+    tournament_rounds, tournaments,
+};
+use crate::tee_set_operations;
+
+const MAX_ROUNDS: usize = 8;
+const MAX_FLIGHTS: usize = 10;
+const MAX_GROUP_SIZE: usize = 4;
+
+#[derive(Deserialize, Debug)]
+pub struct TournamentRoundInput {
+    pub course_id: i32,
+    pub tee_set_id: Option<i32>,
+    pub played_on: NaiveDate,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct FlightInput {
+    pub name: String,
+    /// Highest Handicap Index in the flight; leave out for an open flight.
+    pub max_index: Option<f64>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct NewTournamentInput {
+    pub name: String,
+    /// Share of each course handicap used for the net division. Defaults to
+    /// the individual stroke play allowance.
+    pub allowance: Option<f64>,
+    pub rounds: Vec<TournamentRoundInput>,
+    #[serde(default)]
+    pub flights: Vec<FlightInput>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct NewEntrantInput {
+    /// Only the organiser's own account; anyone else is entered by name and
+    /// invited to link theirs.
+    pub user_id: Option<i32>,
+    /// Defaults to the user's display name.
+    pub name: Option<String>,
+    /// Defaults to the user's current Handicap Index, or to the index of
+    /// whoever accepts the invite.
+    pub handicap_index: Option<f64>,
+    /// Defaults to the flight the index falls in.
+    pub flight_id: Option<i32>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct JoinTournamentInput {
+    pub invite_code: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct NewGroupInput {
+    pub tee_time: NaiveDateTime,
+    pub starting_hole: Option<i32>,
+    pub entrant_ids: Vec<i32>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct LeaderboardQuery {
+    #[serde(default)]
+    pub division: Division,
+    pub flight_id: Option<i32>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = tournaments)]
+struct NewTournament {
+    name: String,
+    organiser_id: i32,
+    allowance_percent: f64,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = tournament_rounds)]
+struct NewTournamentRound {
+    tournament_id: i32,
+    round_number: i32,
+    course_id: i32,
+    tee_set_id: Option<i32>,
+    played_on: NaiveDate,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = tournament_flights)]
+struct NewTournamentFlight {
+    tournament_id: i32,
+    name: String,
+    max_index: Option<f64>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = tournament_entrants)]
+struct NewTournamentEntrant {
+    tournament_id: i32,
+    user_id: Option<i32>,
+    name: String,
+    handicap_index: Option<f64>,
+    flight_id: Option<i32>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = tournament_groups)]
+struct NewTournamentGroup {
+    tournament_round_id: i32,
+    tee_time: NaiveDateTime,
+    starting_hole: i32,
+}
+
+/// Shown once; only its hash is kept.
+#[derive(Serialize, Debug)]
+pub struct EntrantInvite {
+    pub entrant_id: i32,
+    pub invite_code: String,
+}
+
+#[derive(Serialize, Debug)]
+pub struct GroupDetails {
+    pub group: TournamentGroup,
+    pub entrant_ids: Vec<i32>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct TournamentRoundDetails {
+    pub round: TournamentRound,
+    /// Tee times in order.
+    pub groups: Vec<GroupDetails>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct TournamentDetails {
+    pub tournament: Tournament,
+    pub rounds: Vec<TournamentRoundDetails>,
+    pub flights: Vec<TournamentFlight>,
+    pub entrants: Vec<TournamentEntrant>,
+}
+
+fn fetch_tournament(conn: &mut PgConnection, tournament_id: i32) -> Result<Tournament, ApiError> {
+    Ok(tournaments::table
+        .find(tournament_id)
+        .select(Tournament::as_select())
+        .first(conn)?)
+}
+
+/// Loads a tournament the user organises or is entered in.
+fn authorize_tournament(conn: &mut PgConnection, tournament_id: i32, user_id: i32) -> Result<Tournament, ApiError> {
+    let tournament = fetch_tournament(conn, tournament_id)?;
+    if tournament.organiser_id == user_id {
+        return Ok(tournament);
+    }
+    let is_entrant: bool = diesel::select(diesel::dsl::exists(
+        TournamentEntrant::belonging_to(&tournament).filter(tournament_entrants::user_id.eq(user_id)),
+    ))
+    .get_result(conn)?;
+
+    if is_entrant {
+        Ok(tournament)
+    } else {
+        Err(ApiError::NotFound)
+    }
+}
+
+/// Loads a tournament only its organiser may change.
+fn authorize_organiser(conn: &mut PgConnection, tournament_id: i32, user_id: i32) -> Result<Tournament, ApiError> {
+    let tournament = authorize_tournament(conn, tournament_id, user_id)?;
+    if tournament.organiser_id != user_id {
+        return Err(ApiError::Forbidden);
+    }
+    Ok(tournament)
+}
+
+fn fetch_stages(conn: &mut PgConnection, tournament: &Tournament) -> Result<Vec<TournamentRound>, ApiError> {
+    Ok(TournamentRound::belonging_to(tournament)
+        .order(tournament_rounds::round_number.asc())
+        .select(TournamentRound::as_select())
+        .load(conn)?)
+}
+
+fn fetch_flights(conn: &mut PgConnection, tournament: &Tournament) -> Result<Vec<TournamentFlight>, ApiError> {
+    Ok(TournamentFlight::belonging_to(tournament)
+        .order(tournament_flights::id.asc())
+        .select(TournamentFlight::as_select())
+        .load(conn)?)
+}
+
+fn fetch_entrants(conn: &mut PgConnection, tournament: &Tournament) -> Result<Vec<TournamentEntrant>, ApiError> {
+    Ok(TournamentEntrant::belonging_to(tournament)
+        .order(tournament_entrants::id.asc())
+        .select(TournamentEntrant::as_select())
+        .load(conn)?)
+}
+
+/// Groups of the given rounds with their entrant links, by tee time.
+fn fetch_groups(
+    conn: &mut PgConnection,
+    stages: &[TournamentRound],
+) -> Result<Vec<(TournamentGroup, Vec<TournamentGroupEntrant>)>, ApiError> {
+    let groups = TournamentGroup::belonging_to(stages)
+        .order((tournament_groups::tee_time.asc(), tournament_groups::id.asc()))
+        .select(TournamentGroup::as_select())
+        .load(conn)?;
+    let members = TournamentGroupEntrant::belonging_to(&groups)
+        .order(tournament_group_entrants::entrant_id.asc())
+        .select(TournamentGroupEntrant::as_select())
+        .load(conn)?
+        .grouped_by(&groups);
+    Ok(groups.into_iter().zip(members).collect())
+}
+
+fn stage_tee_set(conn: &mut PgConnection, stage: &TournamentRound) -> Result<Option<TeeSet>, ApiError> {
+    stage
+        .tee_set_id
+        .map(|tee_set_id| tee_set_operations::fetch_tee_set(conn, stage.course_id, tee_set_id))
+        .transpose()
+}
+
+/// The first flight, lowest limit first, whose limit covers the index. Open
+/// flights take everyone, including entrants without an index.
+fn flight_for(flights: &[TournamentFlight], index: Option<f64>) -> Option<i32> {
+    let mut ordered: Vec<&TournamentFlight> = flights.iter().collect();
+    ordered.sort_by(|a, b| match (a.max_index, b.max_index) {
+        (Some(a), Some(b)) => a.total_cmp(&b),
+        (a, b) => a.is_none().cmp(&b.is_none()),
+    });
+    ordered
+        .into_iter()
+        .find(|flight| match (flight.max_index, index) {
+            (None, _) => true,
+            (Some(max_index), Some(index)) => index <= max_index,
+            (Some(_), None) => false,
+        })
+        .map(|flight| flight.id)
+}
+
+pub fn create_tournament(
+    conn: &mut PgConnection,
+    user_id: i32,
+    input: NewTournamentInput,
+) -> Result<TournamentDetails, ApiError> {
+    let name = input.name.trim().to_string();
+    if name.is_empty() {
+        return Err(ApiError::BadRequest("a tournament needs a name".to_string()));
+    }
+    let allowance = input.allowance.unwrap_or(DEFAULT_ALLOWANCE_PERCENT);
+    if !(0.0..=100.0).contains(&allowance) {
+        return Err(ApiError::BadRequest("allowance must be between 0 and 100".to_string()));
+    }
+    if input.rounds.is_empty() || input.rounds.len() > MAX_ROUNDS {
+        return Err(ApiError::BadRequest(format!(
+            "a tournament has between 1 and {} rounds",
+            MAX_ROUNDS
+        )));
+    }
+    if input.flights.len() > MAX_FLIGHTS {
+        return Err(ApiError::BadRequest(format!(
+            "a tournament can have at most {} flights",
+            MAX_FLIGHTS
+        )));
+    }
+    for flight in &input.flights {
+        if flight.name.trim().is_empty() {
+            return Err(ApiError::BadRequest("every flight needs a name".to_string()));
+        }
+        if flight
+            .max_index
+            .is_some_and(|max_index| !(-10.0..=MAX_INDEX).contains(&max_index))
+        {
+            return Err(ApiError::BadRequest(format!(
+                "max_index must be between -10 and {}",
+                MAX_INDEX
+            )));
+        }
+    }
+    for round in &input.rounds {
+        if fetch_course_holes(conn, round.course_id)?.is_empty() {
+            return Err(ApiError::BadRequest(format!("course {} has no holes", round.course_id)));
+        }
+        if let Some(tee_set_id) = round.tee_set_id {
+            tee_set_operations::fetch_tee_set(conn, round.course_id, tee_set_id).map_err(|e| match e {
+                ApiError::NotFound => {
+                    ApiError::BadRequest(format!("tee set {} is not on course {}", tee_set_id, round.course_id))
+                }
+                e => e,
+            })?;
+        }
+    }
+
+    let tournament_id = conn.transaction(|conn| -> Result<i32, ApiError> {
+        let tournament_id = diesel::insert_into(tournaments::table)
+            .values(&NewTournament {
+                name,
+                organiser_id: user_id,
+                allowance_percent: allowance,
+            })
+            .returning(tournaments::id)
+            .get_result::<i32>(conn)?;
+
+        let rounds: Vec<NewTournamentRound> = input
+            .rounds
+            .into_iter()
+            .enumerate()
+            .map(|(i, round)| NewTournamentRound {
+                tournament_id,
+                round_number: i as i32 + 1,
+                course_id: round.course_id,
+                tee_set_id: round.tee_set_id,
+                played_on: round.played_on,
+            })
+            .collect();
+        diesel::insert_into(tournament_rounds::table)
+            .values(&rounds)
+            .execute(conn)?;
+
+        let flights: Vec<NewTournamentFlight> = input
+            .flights
+            .into_iter()
+            .map(|flight| NewTournamentFlight {
+                tournament_id,
+                name: flight.name.trim().to_string(),
+                max_index: flight.max_index,
+            })
+            .collect();
+        diesel::insert_into(tournament_flights::table)
+            .values(&flights)
+            .execute(conn)?;
+
+        Ok(tournament_id)
+    })?;
+
+    tournament(conn, tournament_id, user_id)
+}
+
+/// Tournaments the user organises or is entered in, newest first.
+pub fn list_tournaments(conn: &mut PgConnection, user_id: i32) -> Result<Vec<Tournament>, ApiError> {
+    let entered_in = tournament_entrants::table
+        .filter(tournament_entrants::user_id.eq(user_id))
+        .select(tournament_entrants::tournament_id);
+    Ok(tournaments::table
+        .filter(
+            tournaments::organiser_id
+                .eq(user_id)
+                .or(tournaments::id.eq_any(entered_in)),
+        )
+        .order(tournaments::created_at.desc())
+        .select(Tournament::as_select())
+        .load(conn)?)
+}
+
+pub fn tournament(conn: &mut PgConnection, tournament_id: i32, user_id: i32) -> Result<TournamentDetails, ApiError> {
+    let tournament = authorize_tournament(conn, tournament_id, user_id)?;
+    let stages = fetch_stages(conn, &tournament)?;
+    let groups = fetch_groups(conn, &stages)?;
+
+    let rounds = stages
+        .into_iter()
+        .map(|round| TournamentRoundDetails {
+            groups: groups
+                .iter()
+                .filter(|(group, _)| group.tournament_round_id == round.id)
+                .map(|(group, members)| GroupDetails {
+                    group: group.clone(),
+                    entrant_ids: members.iter().map(|member| member.entrant_id).collect(),
+                })
+                .collect(),
+            round,
+        })
+        .collect();
+
+    Ok(TournamentDetails {
+        flights: fetch_flights(conn, &tournament)?,
+        entrants: fetch_entrants(conn, &tournament)?,
+        rounds,
+        tournament,
+    })
+}
+
+pub fn add_entrant(
+    conn: &mut PgConnection,
+    tournament_id: i32,
+    user_id: i32,
+    input: NewEntrantInput,
+) -> Result<TournamentEntrant, ApiError> {
+    let tournament = authorize_organiser(conn, tournament_id, user_id)?;
+
+    // A linked entrant's scores count towards their handicap record, so
+    // anyone else has to accept an invite first.
+    if input.user_id.is_some_and(|id| id != user_id) {
+        return Err(ApiError::BadRequest(
+            "entrants can only be linked to your own account; invite anyone else".to_string(),
+        ));
+    }
+    let (name, handicap_index) = match input.user_id {
+        Some(entrant_user_id) => {
+            let user = auth::fetch_user(conn, entrant_user_id)?;
+            let handicap_index = match input.handicap_index {
+                Some(index) => Some(index),
+                None => handicap_operations::handicap_for_user(conn, entrant_user_id)?.handicap_index,
+            };
+            (input.name.or(user.display_name).unwrap_or_default(), handicap_index)
+        }
+        None => (input.name.unwrap_or_default(), input.handicap_index),
+    };
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err(ApiError::BadRequest("an entrant needs a name".to_string()));
+    }
+    if handicap_index.is_some_and(|index| !(-10.0..=MAX_INDEX).contains(&index)) {
+        return Err(ApiError::BadRequest(format!(
+            "handicap_index must be between -10 and {}",
+            MAX_INDEX
+        )));
+    }
+
+    let flights = fetch_flights(conn, &tournament)?;
+    let flight_id = match input.flight_id {
+        Some(flight_id) if flights.iter().any(|flight| flight.id == flight_id) => Some(flight_id),
+        Some(flight_id) => {
+            return Err(ApiError::BadRequest(format!(
+                "flight {} is not in this tournament",
+                flight_id
+            )));
+        }
+        None => flight_for(&flights, handicap_index),
+    };
+
+    Ok(diesel::insert_into(tournament_entrants::table)
+        .values(&NewTournamentEntrant {
+            tournament_id,
+            user_id: input.user_id,
+            name,
+            handicap_index,
+            flight_id,
+        })
+        .returning(TournamentEntrant::as_returning())
+        .get_result(conn)?)
+}
+
+/// Issues a code the organiser can hand to an entrant, replacing any earlier
+/// one, so they can link their own account to the entry.
+pub fn invite_entrant(
+    conn: &mut PgConnection,
+    tournament_id: i32,
+    entrant_id: i32,
+    user_id: i32,
+) -> Result<EntrantInvite, ApiError> {
+    let tournament = authorize_organiser(conn, tournament_id, user_id)?;
+    let entrant = TournamentEntrant::belonging_to(&tournament)
+        .filter(tournament_entrants::id.eq(entrant_id))
+        .select(TournamentEntrant::as_select())
+        .first(conn)?;
+    if entrant.user_id.is_some() {
+        return Err(ApiError::Conflict("entrant is already linked to an account".to_string()));
+    }
+
+    let invite_code = auth::random_token();
+    diesel::update(tournament_entrants::table.find(entrant.id))
+        .set(tournament_entrants::invite_hash.eq(auth::hash_token(&invite_code)))
+        .execute(conn)?;
+    Ok(EntrantInvite { entrant_id, invite_code })
+}
+
+/// Links the user to the entry an invite was issued for, and to its players
+/// in any groups already started. A missing index or flight is filled in
+/// from the user's handicap record. The code only works once.
+pub fn join_tournament(
+    conn: &mut PgConnection,
+    user_id: i32,
+    input: JoinTournamentInput,
+) -> Result<TournamentDetails, ApiError> {
+    let tournament_id = conn.transaction(|conn| {
+        let entrant = tournament_entrants::table
+            .filter(tournament_entrants::invite_hash.eq(auth::hash_token(input.invite_code.trim())))
+            .select(TournamentEntrant::as_select())
+            .for_update()
+            .first(conn)
+            .optional()?
+            .ok_or(ApiError::NotFound)?;
+        let tournament = fetch_tournament(conn, entrant.tournament_id)?;
+
+        let already_entered: bool = diesel::select(diesel::dsl::exists(
+            TournamentEntrant::belonging_to(&tournament).filter(tournament_entrants::user_id.eq(user_id)),
+        ))
+        .get_result(conn)?;
+        if already_entered {
+            return Err(ApiError::Conflict("you are already entered in this tournament".to_string()));
+        }
+
+        let handicap_index = match entrant.handicap_index {
+            Some(index) => Some(index),
+            None => handicap_operations::handicap_for_user(conn, user_id)?.handicap_index,
+        };
+        let flight_id = match entrant.flight_id {
+            Some(flight_id) => Some(flight_id),
+            None => flight_for(&fetch_flights(conn, &tournament)?, handicap_index),
+        };
+        diesel::update(tournament_entrants::table.find(entrant.id))
+            .set((
+                tournament_entrants::user_id.eq(Some(user_id)),
+                tournament_entrants::handicap_index.eq(handicap_index),
+                tournament_entrants::flight_id.eq(flight_id),
+                tournament_entrants::invite_hash.eq(None::<String>),
+            ))
+            .execute(conn)?;
+
+        let started_players = tournament_group_entrants::table
+            .filter(tournament_group_entrants::entrant_id.eq(entrant.id))
+            .filter(tournament_group_entrants::round_player_id.is_not_null())
+            .select(tournament_group_entrants::round_player_id.assume_not_null());
+        diesel::update(round_players::table)
+            .filter(round_players::id.eq_any(started_players))
+            .filter(round_players::user_id.is_null())
+            .set(round_players::user_id.eq(Some(user_id)))
+            .execute(conn)?;
+        Ok(tournament.id)
+    })?;
+    tournament(conn, tournament_id, user_id)
+}
+
+/// Adds a tee time to one round of the tournament. An entrant plays in one
+/// group per round.
+pub fn add_group(
+    conn: &mut PgConnection,
+    tournament_id: i32,
+    round_number: i32,
+    user_id: i32,
+    input: NewGroupInput,
+) -> Result<GroupDetails, ApiError> {
+    let tournament = authorize_organiser(conn, tournament_id, user_id)?;
+    let stage = TournamentRound::belonging_to(&tournament)
+        .filter(tournament_rounds::round_number.eq(round_number))
+        .select(TournamentRound::as_select())
+        .first(conn)?;
+
+    if input.entrant_ids.is_empty() || input.entrant_ids.len() > MAX_GROUP_SIZE {
+        return Err(ApiError::BadRequest(format!(
+            "a group has between 1 and {} entrants",
+            MAX_GROUP_SIZE
+        )));
+    }
+    let holes = fetch_course_holes(conn, stage.course_id)?.len() as i32;
+    let starting_hole = input.starting_hole.unwrap_or(1);
+    if !(1..=holes).contains(&starting_hole) {
+        return Err(ApiError::BadRequest(format!(
+            "starting_hole must be between 1 and {}",
+            holes
+        )));
+    }
+
+    let entrants = fetch_entrants(conn, &tournament)?;
+    for (i, entrant_id) in input.entrant_ids.iter().enumerate() {
+        if !entrants.iter().any(|entrant| entrant.id == *entrant_id) {
+            return Err(ApiError::BadRequest(format!(
+                "entrant {} is not in this tournament",
+                entrant_id
+            )));
+        }
+        if input.entrant_ids[..i].contains(entrant_id) {
+            return Err(ApiError::BadRequest(format!("entrant {} is listed twice", entrant_id)));
+        }
+    }
+
+    conn.transaction(|conn| {
+        // Holding the stage row keeps two tee times for the same entrant
+        // from being added at once.
+        tournament_rounds::table
+            .find(stage.id)
+            .select(tournament_rounds::id)
+            .for_update()
+            .first::<i32>(conn)?;
+        let grouped: HashSet<i32> = fetch_groups(conn, std::slice::from_ref(&stage))?
+            .into_iter()
+            .flat_map(|(_, members)| members.into_iter().map(|member| member.entrant_id))
+            .collect();
+        if let Some(entrant_id) = input.entrant_ids.iter().find(|entrant_id| grouped.contains(entrant_id)) {
+            return Err(ApiError::Conflict(format!(
+                "entrant {} already has a tee time in round {}",
+                entrant_id, round_number
+            )));
+        }
+
+        let group = diesel::insert_into(tournament_groups::table)
+            .values(&NewTournamentGroup {
+                tournament_round_id: stage.id,
+                tee_time: input.tee_time,
+                starting_hole,
+            })
+            .returning(TournamentGroup::as_returning())
+            .get_result(conn)?;
+
+        let members: Vec<TournamentGroupEntrant> = input
+            .entrant_ids
+            .iter()
+            .map(|entrant_id| TournamentGroupEntrant {
+                group_id: group.id,
+                entrant_id: *entrant_id,
+                round_player_id: None,
+            })
+            .collect();
+        diesel::insert_into(tournament_group_entrants::table)
+            .values(&members)
+            .execute(conn)?;
+
+        Ok(GroupDetails {
+            group,
+            entrant_ids: input.entrant_ids,
+        })
+    })
+}
+
+/// Creates the stored round a group keeps its scores in, with one player per
+/// entrant. The organiser or anyone in the group can start it, and owns the
+/// round.
+pub fn start_group(
+    conn: &mut PgConnection,
+    tournament_id: i32,
+    group_id: i32,
+    user_id: i32,
+) -> Result<Scorecard, ApiError> {
+    let tournament = authorize_tournament(conn, tournament_id, user_id)?;
+    let stages = fetch_stages(conn, &tournament)?;
+    let (group, members) = fetch_groups(conn, &stages)?
+        .into_iter()
+        .find(|(group, _)| group.id == group_id)
+        .ok_or(ApiError::NotFound)?;
+    if group.round_id.is_some() {
+        return Err(ApiError::Conflict("this group has already started".to_string()));
+    }
+    let stage = stages
+        .iter()
+        .find(|stage| stage.id == group.tournament_round_id)
+        .ok_or(ApiError::NotFound)?;
+
+    let entrant_ids: Vec<i32> = members.iter().map(|member| member.entrant_id).collect();
+    let entrants = TournamentEntrant::belonging_to(&tournament)
+        .filter(tournament_entrants::id.eq_any(&entrant_ids))
+        .order(tournament_entrants::id.asc())
+        .select(TournamentEntrant::as_select())
+        .load(conn)?;
+    if tournament.organiser_id != user_id && !entrants.iter().any(|entrant| entrant.user_id == Some(user_id)) {
+        return Err(ApiError::Forbidden);
+    }
+
+    let tee_set = stage_tee_set(conn, stage)?;
+    let round_id = conn.transaction(|conn| -> Result<i32, ApiError> {
+        // Checked again under the lock, so a group started twice at once
+        // gets one round.
+        let started = tournament_groups::table
+            .find(group.id)
+            .select(tournament_groups::round_id)
+            .for_update()
+            .first::<Option<i32>>(conn)?;
+        if started.is_some() {
+            return Err(ApiError::Conflict("this group has already started".to_string()));
+        }
+
+        let players = entrants
+            .iter()
+            .map(|entrant| NewPlayerInput {
+                name: entrant.name.clone(),
+                user_id: entrant.user_id,
+            })
+            .collect();
+        let players = round_operations::create_round(
+            conn,
+            user_id,
+            stage.course_id,
+            tee_set.as_ref(),
+            tee_set.as_ref().map(|tee_set| tee_set.name.clone()),
+            stage.played_on,
+            players,
+        )?;
+        let round_id = players[0].round_id;
+
+        diesel::update(tournament_groups::table.find(group.id))
+            .set(tournament_groups::round_id.eq(round_id))
+            .execute(conn)?;
+        for (entrant, player) in entrants.iter().zip(&players) {
+            diesel::update(tournament_group_entrants::table.find((group.id, entrant.id)))
+                .set(tournament_group_entrants::round_player_id.eq(player.id))
+                .execute(conn)?;
+        }
+        Ok(round_id)
+    })?;
+
+    round_operations::fetch_scorecard(conn, round_id, user_id)
+}
+
+/// Standings across every round played so far, read from the groups'
+/// stored rounds.
+pub fn leaderboard(
+    conn: &mut PgConnection,
+    tournament_id: i32,
+    user_id: i32,
+    query: LeaderboardQuery,
+) -> Result<Leaderboard, ApiError> {
+    let tournament = authorize_tournament(conn, tournament_id, user_id)?;
+    let stages = fetch_stages(conn, &tournament)?;
+    let entrants: Vec<TournamentEntrant> = fetch_entrants(conn, &tournament)?
+        .into_iter()
+        .filter(|entrant| query.flight_id.is_none() || entrant.flight_id == query.flight_id)
+        .collect();
+
+    // The round player each entrant scored as, by tournament round.
+    let mut players: HashMap<(i32, i32), i32> = HashMap::new();
+    for (group, members) in fetch_groups(conn, &stages)? {
+        for member in members {
+            if let Some(player_id) = member.round_player_id {
+                players.insert((group.tournament_round_id, member.entrant_id), player_id);
+            }
+        }
+    }
+    let player_ids: Vec<i32> = players.values().copied().collect();
+    let strokes: HashMap<(i32, i32), i32> = hole_scores::table
+        .filter(hole_scores::player_id.eq_any(&player_ids))
+        .select(HoleScore::as_select())
+        .load(conn)?
+        .into_iter()
+        .map(|score| ((score.player_id, score.hole_id), score.strokes))
+        .collect();
+
+    let mut courses = Vec::with_capacity(stages.len());
+    for stage in &stages {
+        let tee_set = stage_tee_set(conn, stage)?;
+        // Handicaps need the tee set's rating; without one net would
+        // quietly be gross.
+        if query.division == Division::Net && tee_set.is_none() {
+            return Err(ApiError::BadRequest(format!(
+                "round {} has no tee set, so there is no net leaderboard",
+                stage.round_number
+            )));
+        }
+        courses.push((hole_operations::played_holes(conn, stage.course_id, tee_set.as_ref())?, tee_set));
+    }
+
+    let cards = entrants
+        .into_iter()
+        .map(|entrant| {
+            let cards = stages
+                .iter()
+                .zip(&courses)
+                .filter_map(|(stage, (holes, tee_set))| {
+                    let player_id = *players.get(&(stage.id, entrant.id))?;
+                    let playing_handicap = entrant.handicap_index.zip(tee_set.as_ref()).map(|(index, tee)| {
+                        handicap::playing_handicap(handicap::course_handicap(index, tee), tournament.allowance_percent)
+                    });
+                    let holes = holes
+                        .iter()
+                        .enumerate()
+                        .map(|(i, hole)| CardHole {
+                            number: hole.number.unwrap_or(i as i32 + 1),
+                            par: hole.par,
+                            rank: hole.rank().unwrap_or(i as i32 + 1),
+                            strokes: strokes.get(&(player_id, hole.hole_id)).copied(),
+                        })
+                        .collect();
+                    Some(RoundCard {
+                        round_number: stage.round_number,
+                        playing_handicap,
+                        holes,
+                    })
+                })
+                .collect();
+            EntrantCards {
+                entrant_id: entrant.id,
+                name: entrant.name,
+                flight_id: entrant.flight_id,
+                cards,
+            }
+        })
+        .collect();
+
+    Ok(leaderboard::build(cards, stages.len(), query.division))
+}
@@ -0,0 +1,333 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use chrono::{NaiveDateTime, Timelike};
+use serde::{Deserialize, Serialize};
+
+use crate::config::WeatherConfig;
+use crate::geometry::{distance_yards, local_time, LatLong};
+
+/// The fixture shipped with the crate, used unless the configuration names
+/// another file in the same format.
+const DEFAULT_FIXTURE: &str = include_str!("../weather/fixture.json");
+/// Cached readings are shared by requests within this many degrees of
+/// latitude and longitude, about a kilometre.
+const CACHE_CELL_DEGREES: f64 = 0.01;
+/// Cached readings are shared by requests within the same hour.
+const CACHE_BUCKET_SECS: i64 = 60 * 60;
+
+/// Conditions at a place and time. Wind direction is where the wind blows
+/// from, in degrees clockwise from north.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Weather {
+    pub at: NaiveDateTime,
+    pub wind_mph: f64,
+    pub wind_from_deg: f64,
+    pub temperature_f: f64,
+    pub pressure_hpa: f64,
+    pub humidity_percent: f64,
+    /// Which provider the reading came from.
+    pub source: String,
+}
+
+/// Anything that can report the weather for a location and (UTC) time.
+pub trait WeatherProvider: Send + Sync {
+    fn conditions(&self, location: LatLong, at: NaiveDateTime) -> Result<Weather, String>;
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(deny_unknown_fields)]
+struct FixtureHour {
+    /// Hour of the day at the station, by the sun.
+    hour: u32,
+    wind_mph: f64,
+    wind_from_deg: f64,
+    temperature_f: f64,
+    pressure_hpa: f64,
+    humidity_percent: f64,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+struct FixtureStation {
+    lat: f64,
+    long: f64,
+    hours: Vec<FixtureHour>,
+}
+
+/// Weather read from a file describing a typical day at one or more
+/// stations. Every day is the same; readings between the listed hours are
+/// interpolated, so it works offline and gives the same answer every run.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct FixtureWeather {
+    pub name: String,
+    stations: Vec<FixtureStation>,
+}
+
+impl FixtureWeather {
+    /// Loads the fixture from `path`, or the shipped one if there is none.
+    pub fn load(path: Option<&Path>) -> Result<FixtureWeather, String> {
+        match path {
+            Some(path) => {
+                let contents =
+                    fs::read_to_string(path).map_err(|e| format!("could not read {}: {}", path.display(), e))?;
+                FixtureWeather::parse(&contents).map_err(|e| format!("{}: {}", path.display(), e))
+            }
+            None => FixtureWeather::parse(DEFAULT_FIXTURE),
+        }
+    }
+
+    fn parse(contents: &str) -> Result<FixtureWeather, String> {
+        let mut fixture: FixtureWeather =
+            serde_json::from_str(contents).map_err(|e| format!("invalid weather fixture: {}", e))?;
+        if fixture.stations.is_empty() {
+            return Err("the fixture has no stations".to_string());
+        }
+        for station in &mut fixture.stations {
+            if !LatLong::new(station.lat, station.long).is_valid() {
+                return Err(format!("station at {}, {} is not a valid position", station.lat, station.long));
+            }
+            if station.hours.is_empty() {
+                return Err(format!("station at {}, {} has no hours", station.lat, station.long));
+            }
+            if station.hours.iter().any(|hour| hour.hour > 23) {
+                return Err(format!("station at {}, {} has an hour past 23", station.lat, station.long));
+            }
+            station.hours.sort_by_key(|hour| hour.hour);
+            if station.hours.windows(2).any(|pair| pair[0].hour == pair[1].hour) {
+                return Err(format!("station at {}, {} repeats an hour", station.lat, station.long));
+            }
+        }
+        Ok(fixture)
+    }
+}
+
+/// Angle-aware interpolation, so 350° to 10° passes through north.
+fn lerp_degrees(a: f64, b: f64, t: f64) -> f64 {
+    let delta = (b - a + 540.0).rem_euclid(360.0) - 180.0;
+    (a + delta * t).rem_euclid(360.0)
+}
+
+impl WeatherProvider for FixtureWeather {
+    fn conditions(&self, location: LatLong, at: NaiveDateTime) -> Result<Weather, String> {
+        let station = self
+            .stations
+            .iter()
+            .min_by(|a, b| {
+                distance_yards(location, LatLong::new(a.lat, a.long))
+                    .total_cmp(&distance_yards(location, LatLong::new(b.lat, b.long)))
+            })
+            .ok_or_else(|| "the fixture has no stations".to_string())?;
+
+        // The readings either side of the station's time, wrapping round
+        // midnight.
+        let local = local_time(LatLong::new(station.lat, station.long), at);
+        let hour = f64::from(local.num_seconds_from_midnight()) / 3600.0;
+        let after = station
+            .hours
+            .iter()
+            .position(|reading| f64::from(reading.hour) > hour)
+            .unwrap_or(0);
+        let before = (after + station.hours.len() - 1) % station.hours.len();
+        let (a, b) = (station.hours[before], station.hours[after]);
+        let span = (f64::from(b.hour) - f64::from(a.hour)).rem_euclid(24.0);
+        let t = if span > 0.0 {
+            (hour - f64::from(a.hour)).rem_euclid(24.0) / span
+        } else {
+            0.0
+        };
+        let lerp = |x: f64, y: f64| x + (y - x) * t;
+
+        Ok(Weather {
+            at,
+            wind_mph: lerp(a.wind_mph, b.wind_mph),
+            wind_from_deg: lerp_degrees(a.wind_from_deg, b.wind_from_deg, t),
+            temperature_f: lerp(a.temperature_f, b.temperature_f),
+            pressure_hpa: lerp(a.pressure_hpa, b.pressure_hpa),
+            humidity_percent: lerp(a.humidity_percent, b.humidity_percent),
+            source: self.name.clone(),
+        })
+    }
+}
+
+type CacheKey = (i64, i64, i64);
+
+/// Remembers another provider's readings for a while, so a course full of
+/// players asking for the wind makes one upstream request an hour.
+pub struct CachedWeather {
+    inner: Box<dyn WeatherProvider>,
+    ttl: Duration,
+    entries: Mutex<HashMap<CacheKey, (Instant, Weather)>>,
+}
+
+impl CachedWeather {
+    pub fn new(inner: Box<dyn WeatherProvider>, ttl: Duration) -> CachedWeather {
+        CachedWeather {
+            inner,
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn key(location: LatLong, at: NaiveDateTime) -> CacheKey {
+        (
+            (location.lat / CACHE_CELL_DEGREES).round() as i64,
+            (location.long / CACHE_CELL_DEGREES).round() as i64,
+            at.and_utc().timestamp().div_euclid(CACHE_BUCKET_SECS),
+        )
+    }
+}
+
+impl WeatherProvider for CachedWeather {
+    fn conditions(&self, location: LatLong, at: NaiveDateTime) -> Result<Weather, String> {
+        let key = CachedWeather::key(location, at);
+        let now = Instant::now();
+        if let Some((fetched, weather)) = self.entries.lock().map_err(|e| e.to_string())?.get(&key) {
+            if now.duration_since(*fetched) < self.ttl {
+                // The reading is shared across the hour, but it's reported
+                // for the time this caller asked about.
+                return Ok(Weather { at, ..weather.clone() });
+            }
+        }
+
+        // Not held across the upstream request, so one slow lookup doesn't
+        // block every other course.
+        let weather = self.inner.conditions(location, at)?;
+        let mut entries = self.entries.lock().map_err(|e| e.to_string())?;
+        entries.retain(|_, (fetched, _)| now.duration_since(*fetched) < self.ttl);
+        entries.insert(key, (now, weather.clone()));
+        Ok(weather)
+    }
+}
+
+/// The configured provider, behind a cache unless caching is turned off.
+pub fn load_provider(config: &WeatherConfig) -> Result<Arc<dyn WeatherProvider>, String> {
+    let fixture = FixtureWeather::load(config.fixture_path.as_deref())?;
+    if config.cache_ttl_secs == 0 {
+        return Ok(Arc::new(fixture));
+    }
+    Ok(Arc::new(CachedWeather::new(
+        Box::new(fixture),
+        Duration::from_secs(config.cache_ttl_secs),
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    /// A station six hours behind UTC by the sun, and another far away.
+    const FIXTURE: &str = r#"{
+        "name": "Test",
+        "stations": [
+            {
+                "lat": 40.0,
+                "long": -90.0,
+                "hours": [
+                    { "hour": 12, "wind_mph": 20, "wind_from_deg": 10, "temperature_f": 70, "pressure_hpa": 1010, "humidity_percent": 50 },
+                    { "hour": 6, "wind_mph": 10, "wind_from_deg": 350, "temperature_f": 50, "pressure_hpa": 1020, "humidity_percent": 90 },
+                    { "hour": 22, "wind_mph": 4, "wind_from_deg": 180, "temperature_f": 40, "pressure_hpa": 1020, "humidity_percent": 90 }
+                ]
+            },
+            {
+                "lat": 51.5,
+                "long": 0.0,
+                "hours": [
+                    { "hour": 0, "wind_mph": 30, "wind_from_deg": 270, "temperature_f": 55, "pressure_hpa": 1000, "humidity_percent": 80 }
+                ]
+            }
+        ]
+    }"#;
+
+    fn utc(day: u32, hour: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2026, 10, day).unwrap().and_hms_opt(hour, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn hours_are_the_station_local_time() {
+        let fixture = FixtureWeather::parse(FIXTURE).unwrap();
+        let course = LatLong::new(40.1, -90.1);
+
+        // 3pm UTC is 9am at the station, halfway between its 6am and noon.
+        let weather = fixture.conditions(course, utc(19, 15)).unwrap();
+        assert_eq!(weather.at, utc(19, 15));
+        assert_eq!(weather.wind_mph, 15.0);
+        assert_eq!(weather.temperature_f, 60.0);
+        assert!(weather.wind_from_deg.abs() < 1e-9);
+        assert_eq!(weather.source, "Test");
+
+        // 6am UTC is midnight there, a quarter of the way from 10pm to 6am.
+        let weather = fixture.conditions(course, utc(19, 6)).unwrap();
+        assert_eq!(weather.wind_mph, 5.5);
+    }
+
+    #[test]
+    fn nearest_station_reports() {
+        let fixture = FixtureWeather::parse(FIXTURE).unwrap();
+        let weather = fixture.conditions(LatLong::new(51.4, -0.3), utc(19, 15)).unwrap();
+        assert_eq!(weather.wind_mph, 30.0);
+    }
+
+    #[test]
+    fn malformed_fixtures_are_rejected() {
+        let station = |hours: &str| {
+            format!(
+                r#"{{ "name": "Bad", "stations": [{{ "lat": 40, "long": -90, "hours": [{}] }}] }}"#,
+                hours
+            )
+        };
+        let hour = |hour: u32| {
+            format!(
+                r#"{{ "hour": {}, "wind_mph": 1, "wind_from_deg": 1, "temperature_f": 1, "pressure_hpa": 1, "humidity_percent": 1 }}"#,
+                hour
+            )
+        };
+        assert!(FixtureWeather::parse(r#"{ "name": "Bad", "stations": [] }"#).is_err());
+        assert!(FixtureWeather::parse(&station("")).is_err());
+        assert!(FixtureWeather::parse(&station(&hour(24))).is_err());
+        assert!(FixtureWeather::parse(&station(&format!("{}, {}", hour(3), hour(3)))).is_err());
+        assert!(FixtureWeather::parse(&station(&hour(3))).is_ok());
+        assert!(FixtureWeather::load(None).is_ok());
+    }
+
+    struct Counting {
+        calls: Arc<AtomicUsize>,
+        fixture: FixtureWeather,
+    }
+
+    impl WeatherProvider for Counting {
+        fn conditions(&self, location: LatLong, at: NaiveDateTime) -> Result<Weather, String> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            self.fixture.conditions(location, at)
+        }
+    }
+
+    #[test]
+    fn cache_shares_readings_nearby_within_the_hour() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let cached = CachedWeather::new(
+            Box::new(Counting {
+                calls: calls.clone(),
+                fixture: FixtureWeather::parse(FIXTURE).unwrap(),
+            }),
+            Duration::from_secs(600),
+        );
+        let first = cached.conditions(LatLong::new(40.0, -90.0), utc(19, 15)).unwrap();
+        let later = utc(19, 15) + chrono::Duration::minutes(20);
+        let nearby = cached.conditions(LatLong::new(40.001, -90.001), later).unwrap();
+        assert_eq!(nearby.at, later);
+        assert_eq!(Weather { at: first.at, ..nearby }, first);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        cached.conditions(LatLong::new(40.0, -90.0), utc(19, 16)).unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+}
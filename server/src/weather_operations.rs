@@ -0,0 +1,65 @@
+use chrono::{NaiveDateTime, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::course_operations;
+use crate::error::ApiError;
+use crate::geometry::LatLong;
+use crate::schema::holes;
+use crate::weather::{Weather, WeatherProvider};
+
+#[derive(Deserialize, Debug)]
+pub struct ConditionsQuery {
+    /// UTC time to report for; defaults to now.
+    pub at: Option<NaiveDateTime>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct CourseConditions {
+    pub course_id: i32,
+    /// Where the conditions were taken for: the middle of the course's flags.
+    pub location: LatLong,
+    #[serde(flatten)]
+    pub weather: Weather,
+}
+
+/// The middle of a course, as the average of its hole flags.
+pub fn course_location(conn: &mut PgConnection, course_id: i32) -> Result<Option<LatLong>, ApiError> {
+    let flags: Vec<LatLong> = holes::table
+        .filter(holes::course_id.eq(Some(course_id)))
+        .select((holes::flag_lat, holes::flag_long))
+        .load::<(Option<f64>, Option<f64>)>(conn)?
+        .into_iter()
+        .filter_map(|(lat, long)| Some(LatLong::new(lat?, long?)))
+        .collect();
+    if flags.is_empty() {
+        return Ok(None);
+    }
+
+    let n = flags.len() as f64;
+    let (lat, long) = flags
+        .iter()
+        .fold((0.0, 0.0), |(lat, long), flag| (lat + flag.lat, long + flag.long));
+    Ok(Some(LatLong::new(lat / n, long / n)))
+}
+
+pub fn course_conditions(
+    conn: &mut PgConnection,
+    provider: &dyn WeatherProvider,
+    course_id: i32,
+    query: ConditionsQuery,
+) -> Result<CourseConditions, ApiError> {
+    course_operations::fetch_course(conn, course_id)?;
+    let location = course_location(conn, course_id)?
+        .ok_or_else(|| ApiError::BadRequest("course has no mapped holes to locate it by".to_string()))?;
+    let at = query.at.unwrap_or_else(|| Utc::now().naive_utc());
+
+    let weather = provider
+        .conditions(location, at)
+        .map_err(|e| ApiError::Internal(format!("Weather error: {}", e)))?;
+    Ok(CourseConditions {
+        course_id,
+        location,
+        weather,
+    })
+}